@@ -0,0 +1,162 @@
+use std::path::Path;
+use crate::client::HttpResponse;
+use crate::storage::{FolderConfig, RequestOptions, StatusSet};
+use crate::transform;
+
+/// A check of a response. Requests write their own in an `assert` option,
+/// separated by `;`, and name shared ones with `assert-set`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Assertion {
+    /// `status 2xx`: the response has one of these statuses.
+    Status(StatusSet),
+    /// `header Content-Type contains json`: the header is present and, when
+    /// given, its value contains the text, ignoring case.
+    Header { name: String, contains: Option<String> },
+    /// `.error == null`: the expression is true for the JSON body.
+    Body(String),
+}
+
+impl Assertion {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        if let Some(statuses) = s.strip_prefix("status ") {
+            return Ok(Assertion::Status(StatusSet::parse(statuses)?));
+        }
+        if let Some(rest) = s.strip_prefix("header ") {
+            let rest = rest.trim();
+            let (name, contains) = match rest.split_once(" contains ") {
+                Some((name, text)) => (name.trim(), Some(text.trim().to_string())),
+                None => (rest, None),
+            };
+            if name.is_empty() || name.contains(' ') {
+                return Err(format!("Invalid header name '{}'", name));
+            }
+            return Ok(Assertion::Header { name: name.to_string(), contains });
+        }
+        if s.starts_with('.') {
+            return Ok(Assertion::Body(s.to_string()));
+        }
+        Err(format!("Expected 'status CODES', 'header NAME [contains TEXT]' or an expression such as .ok == true, got '{}'", s))
+    }
+
+    /// Parses assertions separated by `;`.
+    pub fn parse_list(s: &str) -> Result<Vec<Assertion>, String> {
+        s.split(';').map(str::trim).filter(|part| !part.is_empty()).map(Assertion::parse).collect()
+    }
+
+    /// Why `res` fails the assertion, if it does.
+    pub fn check(&self, res: &HttpResponse) -> Result<(), String> {
+        match self {
+            Assertion::Status(statuses) if statuses.contains(res.status) => Ok(()),
+            Assertion::Status(_) => Err(format!("the status is {}", res.status)),
+            Assertion::Header { name, contains } => {
+                let values: Vec<&str> = res.headers.iter()
+                    .filter(|(header, _)| header.eq_ignore_ascii_case(name))
+                    .map(|(_, value)| value.as_str())
+                    .collect();
+                match contains {
+                    _ if values.is_empty() => Err("the header is missing".to_string()),
+                    Some(text) if !values.iter().any(|v| v.to_lowercase().contains(&text.to_lowercase())) => {
+                        Err(format!("the header is '{}'", values.join(", ")))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            Assertion::Body(expression) => {
+                let body: serde_json::Value = serde_json::from_str(&res.body).map_err(|_| "the response is not JSON".to_string())?;
+                match transform::apply(expression, &body).map_err(|e| e.to_string())? {
+                    serde_json::Value::Bool(true) => Ok(()),
+                    value => Err(format!("got {}", value)),
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Assertion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Assertion::Status(statuses) => write!(f, "status {}", statuses),
+            Assertion::Header { name, contains: None } => write!(f, "header {}", name),
+            Assertion::Header { name, contains: Some(text) } => write!(f, "header {} contains {}", name, text),
+            Assertion::Body(expression) => write!(f, "{}", expression),
+        }
+    }
+}
+
+/// An assertion of a request, with the set it came from; `None` is the
+/// request's own `assert` option.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sourced {
+    pub set: Option<String>,
+    pub assertion: Result<Assertion, String>,
+}
+
+/// The outcome of one assertion against a response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub set: Option<String>,
+    /// The assertion as written.
+    pub text: String,
+    /// Why it failed; `None` when it passed.
+    pub failure: Option<String>,
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// The assertions of the set `name`, from the nearest `.folder.json` above
+/// the request at `path` that defines it; the one in the base directory
+/// holds the workspace's sets.
+pub fn find_set(path: Option<&Path>, name: &str) -> Option<Vec<String>> {
+    FolderConfig::nearest(path, |config| config.assert_sets.get(name).cloned()).map(|(_, lines)| lines)
+}
+
+/// Sets named by `options` that no folder above the request defines.
+pub fn missing_sets(path: Option<&Path>, options: &RequestOptions) -> Vec<String> {
+    options.assert_sets.iter().filter(|name| find_set(path, name).is_none()).cloned().collect()
+}
+
+/// Everything the request at `path` asserts: the assertions of its sets, in
+/// the order they are named, then its own. A set no folder defines, or a
+/// line of a set that does not parse, is kept as an error so it fails the
+/// request instead of being skipped.
+pub fn collect(path: Option<&Path>, options: &RequestOptions) -> Vec<Sourced> {
+    let mut all = Vec::new();
+    for name in &options.assert_sets {
+        let set = Some(name.clone());
+        match find_set(path, name) {
+            Some(lines) => all.extend(lines.iter().filter(|l| !l.trim().is_empty()).map(|line| Sourced {
+                set: set.clone(),
+                assertion: Assertion::parse(line).map_err(|e| format!("{}: {}", line.trim(), e)),
+            })),
+            None => all.push(Sourced { set, assertion: Err(format!("No folder defines the assertion set '{}'", name)) }),
+        }
+    }
+    all.extend(options.assertions.iter().map(|a| Sourced { set: None, assertion: Ok(a.clone()) }));
+    all
+}
+
+/// Checks `res` against everything the request at `path` asserts.
+pub fn evaluate(path: Option<&Path>, options: &RequestOptions, res: &HttpResponse) -> Vec<Outcome> {
+    collect(path, options).into_iter()
+        .map(|sourced| match sourced.assertion {
+            Ok(assertion) => Outcome { set: sourced.set, text: assertion.to_string(), failure: assertion.check(res).err() },
+            Err(e) => Outcome { set: sourced.set, text: String::new(), failure: Some(e) },
+        })
+        .collect()
+}
+
+/// One line describing a failed outcome, for run warnings.
+pub fn describe_failure(outcome: &Outcome) -> String {
+    let failure = outcome.failure.as_deref().unwrap_or_default();
+    let source = outcome.set.as_ref().map(|set| format!(" ({})", set)).unwrap_or_default();
+    if outcome.text.is_empty() {
+        format!("Assertion failed{}: {}", source, failure)
+    } else {
+        format!("Assertion failed{}: {} — {}", source, outcome.text, failure)
+    }
+}
@@ -0,0 +1,194 @@
+use std::path::{Path, PathBuf};
+use base64::Engine;
+use crate::storage::{FolderConfig, HttpRequest};
+
+/// Credentials added to a request when it is sent. Set on a request with an
+/// `# auth:` line or on a folder in its `.folder.json`, where it applies to
+/// every request below that sets none of its own.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Auth {
+    /// Sends no credentials, even when a folder above sets some.
+    None,
+    Bearer { token: String },
+    Basic { username: String, password: String },
+    ApiKey {
+        #[serde(default)]
+        location: KeyLocation,
+        name: String,
+        value: String,
+    },
+}
+
+/// Where an API key is sent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyLocation {
+    #[default]
+    Header,
+    Query,
+}
+
+/// Names of the auth kinds, as written first in an `# auth:` line.
+pub const KINDS: &[&str] = &["none", "bearer", "basic", "api-key"];
+
+impl Auth {
+    /// Parses `none`, `bearer TOKEN`, `basic USER:PASSWORD` or
+    /// `api-key header|query NAME VALUE`.
+    pub fn parse(s: &str) -> Result<Auth, String> {
+        let s = s.trim();
+        let (kind, rest) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        let rest = rest.trim();
+        match kind {
+            "none" => Ok(Auth::None),
+            "bearer" => Ok(Auth::Bearer { token: rest.to_string() }),
+            "basic" => {
+                let (username, password) = rest.split_once(':').unwrap_or((rest, ""));
+                Ok(Auth::Basic { username: username.to_string(), password: password.to_string() })
+            }
+            "api-key" => {
+                let mut parts = rest.splitn(3, char::is_whitespace);
+                let location = match parts.next() {
+                    Some("header") => KeyLocation::Header,
+                    Some("query") => KeyLocation::Query,
+                    _ => return Err("api-key: expected 'header' or 'query' NAME VALUE".to_string()),
+                };
+                let name = parts.next().filter(|n| !n.is_empty()).ok_or("api-key: the key name is missing")?;
+                let value = parts.next().unwrap_or("").trim();
+                Ok(Auth::ApiKey { location, name: name.to_string(), value: value.to_string() })
+            }
+            _ => Err(format!("Unknown auth '{}'; expected one of {}", kind, KINDS.join(", "))),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Auth::None => "none",
+            Auth::Bearer { .. } => "bearer",
+            Auth::Basic { .. } => "basic",
+            Auth::ApiKey { .. } => "api-key",
+        }
+    }
+
+    /// An empty auth of the given kind, for switching kinds in the editor.
+    pub fn empty(kind: &str) -> Auth {
+        Auth::parse(if kind == "api-key" { "api-key header X-API-Key" } else { kind }).unwrap_or(Auth::None)
+    }
+
+    /// Short description that does not show the secret itself.
+    pub fn summary(&self) -> String {
+        match self {
+            Auth::None => "No auth".to_string(),
+            Auth::Bearer { token } => format!("Bearer token {}", masked(token)),
+            Auth::Basic { username, password } => format!("Basic auth as {} with password {}", username, masked(password)),
+            Auth::ApiKey { location: KeyLocation::Header, name, value } => format!("API key in header {}: {}", name, masked(value)),
+            Auth::ApiKey { location: KeyLocation::Query, name, value } => format!("API key in query parameter {}={}", name, masked(value)),
+        }
+    }
+
+    /// The same auth with `f` applied to every value, e.g. to expand placeholders.
+    pub fn map_values(&self, mut f: impl FnMut(&str) -> String) -> Auth {
+        match self {
+            Auth::None => Auth::None,
+            Auth::Bearer { token } => Auth::Bearer { token: f(token) },
+            Auth::Basic { username, password } => Auth::Basic { username: f(username), password: f(password) },
+            Auth::ApiKey { location, name, value } => Auth::ApiKey { location: *location, name: f(name), value: f(value) },
+        }
+    }
+
+    /// The header this auth adds, with its secret hidden, for previews.
+    /// `None` when it adds no header.
+    pub fn header_preview(&self) -> Option<(String, String)> {
+        match self {
+            Auth::None | Auth::ApiKey { location: KeyLocation::Query, .. } => None,
+            Auth::Bearer { token } => Some(("Authorization".to_string(), format!("Bearer {}", masked(token)))),
+            Auth::Basic { username, password } => {
+                Some(("Authorization".to_string(), format!("Basic base64({}:{})", username, masked(password))))
+            }
+            Auth::ApiKey { location: KeyLocation::Header, name, value } => Some((name.clone(), masked(value))),
+        }
+    }
+
+    /// Adds the credentials to `req`, whose values are already resolved. A
+    /// header the request sets itself is left alone.
+    pub fn apply(&self, req: &mut HttpRequest) {
+        let header = match self {
+            Auth::None => None,
+            Auth::Bearer { token } => Some(("Authorization".to_string(), format!("Bearer {}", token))),
+            Auth::Basic { username, password } => {
+                let credentials = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+                Some(("Authorization".to_string(), format!("Basic {}", credentials)))
+            }
+            Auth::ApiKey { location: KeyLocation::Header, name, value } => Some((name.clone(), value.clone())),
+            Auth::ApiKey { location: KeyLocation::Query, name, value } => {
+                match reqwest::Url::parse(&req.url) {
+                    Ok(mut url) => {
+                        url.query_pairs_mut().append_pair(name, value);
+                        req.url = url.into();
+                    }
+                    Err(_) => {
+                        let separator = if req.url.contains('?') { '&' } else { '?' };
+                        req.url = format!("{}{}{}={}", req.url, separator, name, value);
+                    }
+                }
+                None
+            }
+        };
+        if let Some((name, value)) = header
+            && !req.headers.iter().any(|(k, _)| k.eq_ignore_ascii_case(&name))
+        {
+            req.headers.push((name, value));
+        }
+    }
+}
+
+impl std::fmt::Display for Auth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Auth::None => write!(f, "none"),
+            Auth::Bearer { token } => write!(f, "bearer {}", token),
+            Auth::Basic { username, password } => write!(f, "basic {}:{}", username, password),
+            Auth::ApiKey { location, name, value } => {
+                let location = match location {
+                    KeyLocation::Header => "header",
+                    KeyLocation::Query => "query",
+                };
+                write!(f, "api-key {} {} {}", location, name, value)
+            }
+        }
+    }
+}
+
+/// Placeholders are shown as is; literal secrets are hidden.
+fn masked(value: &str) -> String {
+    if value.contains("{{") {
+        value.to_string()
+    } else {
+        "•".repeat(value.chars().count().min(8))
+    }
+}
+
+/// Which level supplied a request's auth.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthSource {
+    Request,
+    Folder(PathBuf),
+}
+
+/// The auth a request is sent with and where it came from: the request's own
+/// `auth` option, or else the auth of the nearest folder above `path`.
+pub fn effective(path: Option<&Path>, req: &HttpRequest) -> Option<(Auth, AuthSource)> {
+    if let Some(auth) = &req.options.auth {
+        return Some((auth.clone(), AuthSource::Request));
+    }
+    FolderConfig::nearest(path, |config| config.auth)
+        .map(|(folder, auth)| (auth, AuthSource::Folder(folder)))
+}
+
+/// `req` with the auth inherited from its folders filled in, ready to be
+/// resolved. A request with auth of its own is returned unchanged.
+pub fn inherit(path: Option<&Path>, req: &HttpRequest) -> HttpRequest {
+    let mut inherited = req.clone();
+    inherited.options.auth = effective(path, req).map(|(auth, _)| auth);
+    inherited
+}
@@ -0,0 +1,378 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use rusqlite::{Connection, OptionalExtension, params};
+use crate::settings::Settings;
+use crate::storage::{FileNode, get_base_dir};
+
+/// Where request files live. Paths are always absolute paths under the base
+/// dir, so the rest of the app addresses requests the same way whichever
+/// backend holds them.
+pub trait StorageBackend: Send + Sync {
+    /// Folders and `.req` files directly inside `folder`, unsorted. Files come
+    /// back without a title and folders without children.
+    fn scan(&self, folder: &Path) -> Vec<FileNode>;
+    fn load(&self, path: &Path) -> io::Result<String>;
+    /// Writes `content`, creating parent folders as needed.
+    fn save(&self, path: &Path, content: &str) -> io::Result<()>;
+    fn exists(&self, path: &Path) -> bool;
+    /// Removes the file, or the folder with everything in it.
+    fn delete(&self, path: &Path) -> io::Result<()>;
+    /// Moves the file, or the folder with everything in it, creating the
+    /// parent folders of `to` as needed. A folder already at `to` gets the
+    /// contents merged in; nothing moves when a file exists on both sides.
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    fn modified(&self, path: &Path) -> Option<SystemTime>;
+}
+
+/// Which backend a workspace uses, stored in its settings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// A directory tree of `.req` files.
+    #[default]
+    Files,
+    /// One SQLite file, `.requests.sqlite` in the base dir.
+    Sqlite,
+}
+
+pub fn sqlite_file() -> PathBuf {
+    get_base_dir().join(".requests.sqlite")
+}
+
+/// Opens the backend `kind` for the workspace in the base dir.
+pub fn open(kind: BackendKind) -> io::Result<Box<dyn StorageBackend>> {
+    Ok(match kind {
+        BackendKind::Files => Box::new(FsBackend),
+        BackendKind::Sqlite => Box::new(SqliteBackend::open(&sqlite_file(), &get_base_dir())?),
+    })
+}
+
+/// Copies the workspace's requests from the backend in use into `kind` and
+/// selects it for the next start. The old copy is left in place. Returns
+/// the number of request files copied.
+pub fn switch_to(kind: BackendKind) -> io::Result<usize> {
+    crate::workspace::ensure_writable()?;
+    let mut settings = Settings::load();
+    if settings.storage_backend == kind {
+        return Ok(0);
+    }
+    let from = open(settings.storage_backend)?;
+    let to = open(kind)?;
+    let copied = copy_all(from.as_ref(), to.as_ref(), &get_base_dir())?;
+    settings.storage_backend = kind;
+    settings.save()?;
+    Ok(copied)
+}
+
+fn is_hidden(name: &str) -> bool {
+    // Dot-directories hold app data such as environments
+    name.starts_with('.')
+}
+
+/// The current directory-tree layout.
+pub struct FsBackend;
+
+impl StorageBackend for FsBackend {
+    fn scan(&self, folder: &Path) -> Vec<FileNode> {
+        let mut children = Vec::new();
+        let Ok(entries) = fs::read_dir(folder) else { return children };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if is_hidden(&name) {
+                continue;
+            }
+            let path = entry.path();
+            if path.is_dir() {
+                children.push(FileNode::Folder { name, path, children: Vec::new() });
+            } else if path.extension().is_some_and(|ext| ext == "req") {
+                children.push(FileNode::File { name, path, title: None });
+            }
+        }
+        children
+    }
+
+    fn load(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn save(&self, path: &Path, content: &str) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn delete(&self, path: &Path) -> io::Result<()> {
+        if path.is_dir() { fs::remove_dir_all(path) } else { fs::remove_file(path) }
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        if let Some(clash) = clash(from, to) {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", clash.display())));
+        }
+        merge(from, to)
+    }
+
+    fn modified(&self, path: &Path) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+}
+
+/// The first file under `to` that moving `from` there would overwrite.
+fn clash(from: &Path, to: &Path) -> Option<PathBuf> {
+    if !to.exists() {
+        return None;
+    }
+    if !(from.is_dir() && to.is_dir()) {
+        return Some(to.to_path_buf());
+    }
+    fs::read_dir(from).ok()?.flatten().find_map(|entry| clash(&entry.path(), &to.join(entry.file_name())))
+}
+
+fn merge(from: &Path, to: &Path) -> io::Result<()> {
+    if !to.exists() {
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        return fs::rename(from, to);
+    }
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        merge(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    fs::remove_dir(from)
+}
+
+/// All request files in one SQLite database, keyed by their path relative to
+/// the base dir with `/` separators. Folders exist implicitly through the
+/// files inside them.
+pub struct SqliteBackend {
+    root: PathBuf,
+    conn: Mutex<Connection>,
+}
+
+impl SqliteBackend {
+    /// Opens (or creates) the database at `file`, mapping its rows under `root`.
+    pub fn open(file: &Path, root: &Path) -> io::Result<Self> {
+        let conn = Connection::open(file).map_err(io::Error::other)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS requests (
+                path TEXT PRIMARY KEY,
+                content TEXT NOT NULL,
+                modified_ms INTEGER NOT NULL
+            )",
+        ).map_err(io::Error::other)?;
+        Ok(SqliteBackend { root: root.to_path_buf(), conn: Mutex::new(conn) })
+    }
+
+    fn key(&self, path: &Path) -> io::Result<String> {
+        let relative = path.strip_prefix(&self.root).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not in the workspace", path.display()))
+        })?;
+        Ok(relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/"))
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Keys of every row at or under `key`.
+    fn keys_under(&self, key: &str) -> io::Result<Vec<String>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT path FROM requests").map_err(io::Error::other)?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0)).map_err(io::Error::other)?;
+        let prefix = format!("{}/", key);
+        Ok(rows.flatten().filter(|k| key.is_empty() || k == key || k.starts_with(&prefix)).collect())
+    }
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as i64)
+}
+
+impl StorageBackend for SqliteBackend {
+    fn scan(&self, folder: &Path) -> Vec<FileNode> {
+        let Ok(key) = self.key(folder) else { return Vec::new() };
+        let Ok(keys) = self.keys_under(&key) else { return Vec::new() };
+        let prefix = if key.is_empty() { String::new() } else { format!("{}/", key) };
+        let mut children: Vec<FileNode> = Vec::new();
+        for full in keys {
+            let Some(rest) = full.strip_prefix(&prefix) else { continue };
+            let (name, is_folder) = match rest.split_once('/') {
+                Some((folder_name, _)) => (folder_name, true),
+                None => (rest, false),
+            };
+            if is_hidden(name) || children.iter().any(|c| c.name() == name) {
+                continue;
+            }
+            let path = folder.join(name);
+            if is_folder {
+                children.push(FileNode::Folder { name: name.to_string(), path, children: Vec::new() });
+            } else if name.ends_with(".req") {
+                children.push(FileNode::File { name: name.to_string(), path, title: None });
+            }
+        }
+        children
+    }
+
+    fn load(&self, path: &Path) -> io::Result<String> {
+        let key = self.key(path)?;
+        self.conn()
+            .query_row("SELECT content FROM requests WHERE path = ?1", [&key], |row| row.get(0))
+            .optional()
+            .map_err(io::Error::other)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} not found", key)))
+    }
+
+    fn save(&self, path: &Path, content: &str) -> io::Result<()> {
+        let key = self.key(path)?;
+        self.conn()
+            .execute(
+                "INSERT INTO requests (path, content, modified_ms) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(path) DO UPDATE SET content = ?2, modified_ms = ?3",
+                params![key, content, now_ms()],
+            )
+            .map(|_| ())
+            .map_err(io::Error::other)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.key(path).and_then(|key| self.keys_under(&key)).is_ok_and(|keys| !keys.is_empty())
+    }
+
+    fn delete(&self, path: &Path) -> io::Result<()> {
+        let key = self.key(path)?;
+        self.conn()
+            .execute("DELETE FROM requests WHERE path = ?1 OR substr(path, 1, length(?1) + 1) = ?1 || '/'", [&key])
+            .map(|_| ())
+            .map_err(io::Error::other)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        // Folders are implicit, so only a file under both paths clashes,
+        // which the primary key refuses
+        let (from, to) = (self.key(from)?, self.key(to)?);
+        self.conn()
+            .execute(
+                "UPDATE requests SET path = ?2 || substr(path, length(?1) + 1)
+                 WHERE path = ?1 OR substr(path, 1, length(?1) + 1) = ?1 || '/'",
+                [&from, &to],
+            )
+            .map(|_| ())
+            .map_err(io::Error::other)
+    }
+
+    fn modified(&self, path: &Path) -> Option<SystemTime> {
+        let key = self.key(path).ok()?;
+        let ms: i64 = self.conn()
+            .query_row("SELECT modified_ms FROM requests WHERE path = ?1", [&key], |row| row.get(0))
+            .ok()?;
+        Some(UNIX_EPOCH + Duration::from_millis(ms.max(0) as u64))
+    }
+}
+
+/// Copies every request file, and each folder's `.folder.json`, from one
+/// backend to another, both rooted at `root`. Returns the number of request
+/// files copied.
+pub fn copy_all(from: &dyn StorageBackend, to: &dyn StorageBackend, root: &Path) -> io::Result<usize> {
+    let mut copied = 0;
+    let mut folders = vec![root.to_path_buf()];
+    while let Some(folder) = folders.pop() {
+        let config = folder.join(".folder.json");
+        if let Ok(content) = from.load(&config) {
+            to.save(&config, &content)?;
+        }
+        for child in from.scan(&folder) {
+            match child {
+                FileNode::Folder { path, .. } => folders.push(path),
+                FileNode::File { path, .. } => {
+                    to.save(&path, &from.load(&path)?)?;
+                    copied += 1;
+                }
+            }
+        }
+    }
+    Ok(copied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh empty folder under the system temp dir.
+    fn temp_root() -> PathBuf {
+        let root = std::env::temp_dir().join(format!("requester-backend-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    /// `(name, is_folder)` of the entries `scan` lists, sorted.
+    fn listing(backend: &dyn StorageBackend, folder: &Path) -> Vec<(String, bool)> {
+        let mut names: Vec<(String, bool)> = backend.scan(folder).iter()
+            .map(|node| (node.name().to_string(), matches!(node, FileNode::Folder { .. })))
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Runs the same operations against `backend` rooted at `root`.
+    fn exercise(backend: &dyn StorageBackend, root: &Path) {
+        backend.save(&root.join("top.req"), "GET https://example.com\n").unwrap();
+        backend.save(&root.join("api/users.req"), "GET https://example.com/users\n").unwrap();
+        backend.save(&root.join("api/admin/keys.req"), "DELETE https://example.com/keys\n").unwrap();
+        backend.save(&root.join(".environments/dev.env"), "a=1\n").unwrap();
+        assert_eq!(listing(backend, root), [("api".to_string(), true), ("top.req".to_string(), false)]);
+        assert_eq!(listing(backend, &root.join("api")), [("admin".to_string(), true), ("users.req".to_string(), false)]);
+        assert_eq!(backend.load(&root.join("api/users.req")).unwrap(), "GET https://example.com/users\n");
+        assert!(backend.load(&root.join("missing.req")).is_err());
+        assert!(backend.exists(&root.join("api/admin")));
+        assert!(backend.modified(&root.join("top.req")).is_some());
+
+        backend.save(&root.join("top.req"), "POST https://example.com\n").unwrap();
+        assert_eq!(backend.load(&root.join("top.req")).unwrap(), "POST https://example.com\n");
+
+        backend.rename(&root.join("api"), &root.join("v2")).unwrap();
+        assert!(!backend.exists(&root.join("api")));
+        assert_eq!(backend.load(&root.join("v2/admin/keys.req")).unwrap(), "DELETE https://example.com/keys\n");
+        backend.save(&root.join("other.req"), "GET https://example.com/other\n").unwrap();
+        assert!(backend.rename(&root.join("other.req"), &root.join("top.req")).is_err());
+        assert_eq!(backend.load(&root.join("top.req")).unwrap(), "POST https://example.com\n");
+
+        backend.delete(&root.join("v2")).unwrap();
+        assert!(!backend.exists(&root.join("v2/admin/keys.req")));
+        assert_eq!(listing(backend, root), [("other.req".to_string(), false), ("top.req".to_string(), false)]);
+    }
+
+    #[test]
+    fn file_and_sqlite_backends_behave_alike() {
+        let root = temp_root();
+        exercise(&FsBackend, &root);
+        fs::remove_dir_all(&root).unwrap();
+        let root = temp_root();
+        let sqlite = SqliteBackend::open(&root.join(".requests.sqlite"), &root).unwrap();
+        exercise(&sqlite, &root);
+        assert!(sqlite.save(Path::new("/elsewhere/x.req"), "").is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn copying_between_backends_keeps_files_and_folder_settings() {
+        let root = temp_root();
+        FsBackend.save(&root.join("a.req"), "GET https://example.com/a\n").unwrap();
+        FsBackend.save(&root.join("f/b.req"), "GET https://example.com/b\n").unwrap();
+        FsBackend.save(&root.join("f/.folder.json"), "{}").unwrap();
+        let sqlite = SqliteBackend::open(&root.join(".requests.sqlite"), &root).unwrap();
+        assert_eq!(copy_all(&FsBackend, &sqlite, &root).unwrap(), 2);
+        assert_eq!(listing(&sqlite, &root), listing(&FsBackend, &root));
+        assert_eq!(sqlite.load(&root.join("f/b.req")).unwrap(), "GET https://example.com/b\n");
+        assert_eq!(sqlite.load(&root.join("f/.folder.json")).unwrap(), "{}");
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,351 @@
+/// Body templates, for requests with the `template` option: blocks that
+/// repeat their content, expanded before placeholders are filled in so each
+/// copy gets its own `{{$timestamp}}` and the like.
+///
+/// - `{{#repeat 500}}...{{/repeat}}` writes the content 500 times, with
+///   `{{i}}` counting from 0.
+/// - `{{#range 1 10}}...{{/range}}` writes it for `{{i}}` from 1 to 10.
+///
+/// Either takes a separator to put between copies, in double quotes and with
+/// `\n` and `\t` escapes: `{{#repeat 3 ",\n"}}`. Blocks nest; `{{i}}` is the
+/// innermost one's. Any other `{{...}}` is left for the resolver. All blocks
+/// together make at most [`MAX_COPIES`] copies, however short.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    /// `{{i}}` inside a block.
+    Index,
+    Block(Block),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Block {
+    /// Line of the opening tag, for errors.
+    line: usize,
+    /// Values of `{{i}}`, the end excluded.
+    from: u64,
+    to: u64,
+    separator: String,
+    nodes: Vec<Node>,
+}
+
+/// Copies all blocks of a template may make together, nested ones counted
+/// once per copy of the blocks around them.
+pub const MAX_COPIES: u128 = 1_000_000;
+
+/// A problem with a template, on the line it is about.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Body template, line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// A block being parsed: its name and opening, and what it holds so far.
+struct Open {
+    name: &'static str,
+    block: Block,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Template, TemplateError> {
+        let mut stack: Vec<Open> = Vec::new();
+        let mut nodes = Vec::new();
+        let mut line = 1;
+        let mut rest = source;
+        let error = |line, message: String| Err(TemplateError { line, message });
+        while let Some(start) = rest.find("{{") {
+            let Some(end) = rest[start + 2..].find("}}").map(|end| start + 2 + end) else { break };
+            let text = &rest[..start];
+            let tag = &rest[start..end + 2];
+            let inner = rest[start + 2..end].trim();
+            line += text.matches('\n').count();
+            let in_block = !stack.is_empty();
+            let current = stack.last_mut().map_or(&mut nodes, |open| &mut open.block.nodes);
+            push_text(current, text);
+            if let Some(head) = inner.strip_prefix('#') {
+                let (name, block) = match parse_head(head, line) {
+                    Ok(parsed) => parsed,
+                    Err(message) => return error(line, message),
+                };
+                stack.push(Open { name, block });
+            } else if let Some(name) = inner.strip_prefix('/') {
+                let Some(open) = stack.pop() else {
+                    return error(line, format!("{{{{/{}}}}} closes no block", name.trim()));
+                };
+                if name.trim() != open.name {
+                    return error(line, format!("{{{{/{}}}}} cannot close {{{{#{}}}}} from line {}", name.trim(), open.name, open.block.line));
+                }
+                let current = stack.last_mut().map_or(&mut nodes, |open| &mut open.block.nodes);
+                current.push(Node::Block(open.block));
+            } else if inner == "i" && in_block {
+                current.push(Node::Index);
+            } else {
+                push_text(current, tag);
+            }
+            line += tag.matches('\n').count();
+            rest = &rest[end + 2..];
+        }
+        if let Some(open) = stack.pop() {
+            return error(open.block.line, format!("{{{{#{}}}}} is never closed with {{{{/{}}}}}", open.name, open.name));
+        }
+        push_text(&mut nodes, rest);
+        Ok(Template { nodes })
+    }
+
+    /// Length in bytes of the expanded body, worked out without expanding it.
+    pub fn expanded_size(&self) -> u128 {
+        measure(&self.nodes).0
+    }
+
+    /// The expanded body, or an error on the line of the largest outer block
+    /// when it would be longer than `max_bytes`.
+    pub fn render(&self, max_bytes: usize) -> Result<String, TemplateError> {
+        let copies = copies(&self.nodes);
+        if copies > MAX_COPIES {
+            let line = self.nodes.iter()
+                .filter_map(|node| match node {
+                    Node::Block(block) => Some((block_copies(block), block.line)),
+                    _ => None,
+                })
+                .max()
+                .map_or(1, |(_, line)| line);
+            return Err(TemplateError {
+                line,
+                message: format!("makes {} copies, over the limit of {}", copies, MAX_COPIES),
+            });
+        }
+        let size = self.expanded_size();
+        if size > max_bytes as u128 {
+            let line = self.nodes.iter()
+                .filter_map(|node| match node {
+                    Node::Block(block) => Some((block_size(block), block.line)),
+                    _ => None,
+                })
+                .max()
+                .map_or(1, |(_, line)| line);
+            return Err(TemplateError {
+                line,
+                message: format!("expands to {} bytes, over the limit of {} bytes; raise template-max if this is intended", size, max_bytes),
+            });
+        }
+        let mut out = String::with_capacity(size as usize);
+        render(&self.nodes, None, &mut out);
+        Ok(out)
+    }
+}
+
+/// The limit in bytes for a `template-max` of `max_mb` megabytes; unset
+/// means none.
+pub fn limit_bytes(max_mb: Option<u64>) -> usize {
+    max_mb.map_or(usize::MAX, |mb| usize::try_from(mb.saturating_mul(1024 * 1024)).unwrap_or(usize::MAX))
+}
+
+/// Parses templates and expands them in one go.
+pub fn expand(source: &str, max_bytes: usize) -> Result<String, TemplateError> {
+    Template::parse(source)?.render(max_bytes)
+}
+
+fn push_text(nodes: &mut Vec<Node>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    match nodes.last_mut() {
+        Some(Node::Text(last)) => last.push_str(text),
+        _ => nodes.push(Node::Text(text.to_string())),
+    }
+}
+
+/// Parses `repeat N ["sep"]` or `range FROM TO ["sep"]`.
+fn parse_head(head: &str, line: usize) -> Result<(&'static str, Block), String> {
+    let (words, separator) = match head.split_once('"') {
+        Some((words, quoted)) => {
+            let quoted = quoted.strip_suffix('"').ok_or("the separator needs a closing \"")?;
+            (words, quoted.replace("\\n", "\n").replace("\\t", "\t"))
+        }
+        None => (head, String::new()),
+    };
+    let words: Vec<&str> = words.split_whitespace().collect();
+    let number = |word: &str| word.parse::<u64>().map_err(|_| format!("'{}' is not a whole number", word));
+    let (name, from, to) = match words.as_slice() {
+        ["repeat", count] => ("repeat", 0, number(count)?),
+        ["range", from, to] => {
+            let (from, to) = (number(from)?, number(to)?);
+            if from > to {
+                return Err(format!("the range {} to {} is empty", from, to));
+            }
+            ("range", from, to.checked_add(1).ok_or("the range is too large")?)
+        }
+        ["repeat", ..] => return Err("expected {{#repeat COUNT}}".to_string()),
+        ["range", ..] => return Err("expected {{#range FROM TO}}".to_string()),
+        _ => return Err(format!("unknown block '#{}'; expected #repeat or #range", head.trim())),
+    };
+    if (to - from) as u128 > MAX_COPIES {
+        return Err(format!("{} copies is over the limit of {}", to - from, MAX_COPIES));
+    }
+    Ok((name, Block { line, from, to, separator, nodes: Vec::new() }))
+}
+
+/// Bytes the nodes take apart from their own `{{i}}`, and how many of those
+/// they hold; blocks inside have their own.
+fn measure(nodes: &[Node]) -> (u128, u128) {
+    nodes.iter().fold((0u128, 0u128), |(fixed, indexes), node| match node {
+        Node::Text(text) => (fixed.saturating_add(text.len() as u128), indexes),
+        Node::Index => (fixed, indexes + 1),
+        Node::Block(block) => (fixed.saturating_add(block_size(block)), indexes),
+    })
+}
+
+fn block_size(block: &Block) -> u128 {
+    let (fixed, indexes) = measure(&block.nodes);
+    let count = (block.to - block.from) as u128;
+    let separators = (block.separator.len() as u128).saturating_mul(count.saturating_sub(1));
+    count.saturating_mul(fixed)
+        .saturating_add(indexes.saturating_mul(digit_sum(block.from, block.to)))
+        .saturating_add(separators)
+}
+
+/// Copies the blocks among `nodes` make, those nested in them included.
+fn copies(nodes: &[Node]) -> u128 {
+    nodes.iter()
+        .map(|node| match node {
+            Node::Block(block) => block_copies(block),
+            _ => 0,
+        })
+        .fold(0, u128::saturating_add)
+}
+
+fn block_copies(block: &Block) -> u128 {
+    let count = (block.to - block.from) as u128;
+    count.saturating_mul(copies(&block.nodes).saturating_add(1))
+}
+
+/// Total number of digits of the numbers from `from` up to, not including, `to`.
+fn digit_sum(from: u64, to: u64) -> u128 {
+    let mut total = 0u128;
+    let mut low = 0u64;
+    for digits in 1..=20u128 {
+        let high = 10u64.checked_pow(digits as u32).unwrap_or(u64::MAX);
+        let (start, end) = (from.max(low), to.min(high));
+        if start < end {
+            total += (end - start) as u128 * digits;
+        }
+        low = high;
+    }
+    total
+}
+
+fn render(nodes: &[Node], index: Option<u64>, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Index => out.push_str(&index.unwrap_or_default().to_string()),
+            Node::Block(block) => {
+                for i in block.from..block.to {
+                    if i > block.from {
+                        out.push_str(&block.separator);
+                    }
+                    render(&block.nodes, Some(i), out);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(source: &str) -> TemplateError {
+        expand(source, usize::MAX).unwrap_err()
+    }
+
+    #[test]
+    fn digit_sums_cross_powers_of_ten() {
+        assert_eq!(digit_sum(0, 0), 0);
+        assert_eq!(digit_sum(0, 10), 10);
+        assert_eq!(digit_sum(5, 12), 5 + 2 * 2);
+        assert_eq!(digit_sum(0, 1000), 10 + 90 * 2 + 900 * 3);
+        assert_eq!(digit_sum(99, 101), 2 + 3);
+        assert_eq!(digit_sum(u64::MAX - 1, u64::MAX), 20);
+        for (from, to) in [(0, 1), (3, 250), (95, 1005), (9_999, 10_001)] {
+            let counted: usize = (from..to).map(|i: u64| i.to_string().len()).sum();
+            assert_eq!(digit_sum(from, to), counted as u128, "{}..{}", from, to);
+        }
+    }
+
+    #[test]
+    fn blocks_expand_with_separators_and_indexes() {
+        assert_eq!(expand("[{{#repeat 3 \", \"}}{{i}}{{/repeat}}]", usize::MAX).unwrap(), "[0, 1, 2]");
+        assert_eq!(expand("{{#range 8 11 \"\\n\"}}n{{i}}{{/range}}", usize::MAX).unwrap(), "n8\nn9\nn10\nn11");
+        assert_eq!(expand("{{#repeat 0 \",\"}}x{{/repeat}}", usize::MAX).unwrap(), "");
+        // Placeholders and a stray {{i}} are left for the resolver
+        assert_eq!(expand("{{i}} {{#repeat 2}}{{$uuid}}{{/repeat}}", usize::MAX).unwrap(), "{{i}} {{$uuid}}{{$uuid}}");
+    }
+
+    #[test]
+    fn nested_blocks_use_the_innermost_index() {
+        let source = "{{#range 1 2 \";\"}}{{i}}:{{#repeat 3 \",\"}}{{i}}{{/repeat}}/{{i}}{{/range}}";
+        let template = Template::parse(source).unwrap();
+        let out = template.render(usize::MAX).unwrap();
+        assert_eq!(out, "1:0,1,2/1;2:0,1,2/2");
+        assert_eq!(template.expanded_size(), out.len() as u128);
+    }
+
+    #[test]
+    fn sizes_are_worked_out_without_expanding() {
+        for source in [
+            "plain",
+            "{{#repeat 12 \",\\n\"}}{\"id\": {{i}}}{{/repeat}}",
+            "a{{#range 95 1005 \"-\"}}{{#repeat 2}}{{i}}{{/repeat}}{{i}}{{/range}}b",
+        ] {
+            let template = Template::parse(source).unwrap();
+            assert_eq!(template.expanded_size(), template.render(usize::MAX).unwrap().len() as u128, "{}", source);
+        }
+        let template = Template::parse("{{#repeat 10}}abc{{/repeat}}").unwrap();
+        assert_eq!(template.render(30).unwrap().len(), 30);
+        let too_big = template.render(29).unwrap_err();
+        assert_eq!(too_big.message, "expands to 30 bytes, over the limit of 29 bytes; raise template-max if this is intended");
+    }
+
+    #[test]
+    fn errors_name_the_line_of_the_tag() {
+        let unclosed = error("{\n  \"items\": [\n{{#repeat 3}}\n  x\n]");
+        assert_eq!(unclosed, TemplateError { line: 3, message: "{{#repeat}} is never closed with {{/repeat}}".to_string() });
+        assert_eq!(unclosed.to_string(), "Body template, line 3: {{#repeat}} is never closed with {{/repeat}}");
+
+        let mismatched = error("{{#range 1 2}}\n{{#repeat 2}}\n{{/range}}");
+        assert_eq!((mismatched.line, mismatched.message.as_str()), (3, "{{/range}} cannot close {{#repeat}} from line 2"));
+        assert_eq!(error("a\n\n{{/repeat}}").line, 3);
+        // Newlines inside a tag count towards the lines after it
+        assert_eq!(error("{{x\ny}}\n{{#repeat many}}{{/repeat}}"), TemplateError { line: 3, message: "'many' is not a whole number".to_string() });
+        assert_eq!(error("{{#range 5 1}}{{/range}}").message, "the range 5 to 1 is empty");
+        assert_eq!(error("{{#repeat 2 \",}}{{/repeat}}").message, "the separator needs a closing \"");
+        assert_eq!(error("{{#each items}}{{/each}}").message, "unknown block '#each items'; expected #repeat or #range");
+    }
+
+    #[test]
+    fn copies_are_limited_however_short() {
+        assert_eq!(
+            error("\n{{#repeat 18446744073709551615}}{{/repeat}}"),
+            TemplateError { line: 2, message: "18446744073709551615 copies is over the limit of 1000000".to_string() },
+        );
+        assert_eq!(error("{{#range 0 18446744073709551615}}{{/range}}").message, "the range is too large");
+        // Nested blocks multiply
+        let nested = error("x\n{{#repeat 1000}}\n{{#repeat 1000}}{{/repeat}}{{/repeat}}");
+        assert_eq!((nested.line, nested.message.as_str()), (2, "makes 1001000 copies, over the limit of 1000000"));
+        assert_eq!(expand("{{#repeat 1000}}{{#repeat 999}}{{/repeat}}{{/repeat}}", usize::MAX).unwrap(), "");
+    }
+}
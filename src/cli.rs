@@ -0,0 +1,297 @@
+use std::io::{IsTerminal, Read};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+use crate::assertions::{self, Outcome};
+use crate::auth;
+use crate::client::{self, execute_request};
+use crate::environment;
+use crate::hooks;
+use crate::jmx;
+use crate::poll::{self, PollConfig, PollEnd};
+use crate::runner::CancelFlag;
+use crate::settings::Settings;
+use crate::backend::{self, BackendKind};
+use crate::storage::{self, HttpRequest};
+use crate::workspace;
+
+/// `requester run FILE.req [--env NAME] [--allow-hooks] [--trust] [--ignore-pin]`:
+/// sends one request without the GUI and prints the response. A request
+/// pinned to other environments than `--env` is refused unless
+/// `--ignore-pin` is given. Hooks run external
+/// programs, so a request with one fails unless `--allow-hooks` is given. In a
+/// workspace that is not trusted, hooks and `{{env:NAME}}` stay blocked unless
+/// `--trust` is given for this run. Returns the process exit code: 0 when the status
+/// is accepted by the request, 1 when it is not or the request failed, and 2
+/// for usage errors.
+pub fn run_command(args: &[String]) -> i32 {
+    let mut file = None;
+    let mut env_name = None;
+    let mut allow_hooks = false;
+    let mut trust = false;
+    let mut ignore_pin = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--env" | "-e" => match args.next() {
+                Some(name) => env_name = Some(name.clone()),
+                None => return usage_error("--env needs an environment name"),
+            },
+            "--allow-hooks" => allow_hooks = true,
+            "--trust" => trust = true,
+            "--ignore-pin" => ignore_pin = true,
+            flag if flag.starts_with('-') => return usage_error(&format!("Unknown option: {}", flag)),
+            _ if file.is_none() => file = Some(arg.clone()),
+            _ => return usage_error(&format!("Unexpected argument: {}", arg)),
+        }
+    }
+    let Some(file) = file else {
+        return usage_error("Missing request file");
+    };
+
+    let request = match storage::load_request(Path::new(&file)) {
+        Ok(request) => request,
+        Err(e) => {
+            eprintln!("Cannot load {}: {}", file, e);
+            return 1;
+        }
+    };
+    if !ignore_pin && request.options.pinned_elsewhere(env_name.as_deref()) {
+        eprintln!(
+            "{} is pinned to {} but would be sent with {}; pass --env or --ignore-pin",
+            file,
+            request.options.environments.join(", "),
+            env_name.as_deref().unwrap_or("no environment"),
+        );
+        return 1;
+    }
+    let vars = match env_name.as_deref().map(environment::load_environment).transpose() {
+        Ok(vars) => vars.unwrap_or_default(),
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let trusted = trust || workspace::workspace_trusted();
+    let settings = Settings::load();
+    client::set_body_limits(settings.body_limits.clone());
+    let resolver = environment::Resolver {
+        allow_hooks: allow_hooks && trusted,
+        environment: env_name.clone(),
+        ..settings.resolver_with_trust(vars, trusted)
+    };
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Cannot start async runtime: {}", e);
+            return 1;
+        }
+    };
+    let resolved = runtime.block_on(hooks::prepare(&resolver, Some(Path::new(&file)), &request))
+        .and_then(|resolver| resolver.resolve_request(&auth::inherit(Some(Path::new(&file)), &request)));
+    let resolved = match resolved {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let response: Result<_, String> = runtime.block_on(async {
+        let first = execute_request(&resolved).await?;
+        let Some(config) = PollConfig::from_options(&resolved.options) else { return Ok((first, true)) };
+        let (last, end) = poll::poll(&config, &resolved, first, &CancelFlag::default(), |step| {
+            eprintln!("[{} ms] GET {}: {}", step.at_ms, step.url, step.outcome.unwrap_or_else(|e| e));
+        }).await;
+        if let Some(problem) = end.problem() {
+            eprintln!("{}", problem);
+        }
+        Ok((last, end == PollEnd::Met))
+    });
+    match response {
+        Ok((res, polled)) => {
+            println!("{}", res.status_text);
+            for (k, v) in &res.headers {
+                println!("{}: {}", k, v);
+            }
+            println!();
+            println!("{}", res.body);
+            if let Some(outcome) = res.expect_continue {
+                eprintln!("{}", outcome);
+            }
+            if let Some(truncation) = &res.truncated {
+                eprintln!("Body truncated to {} bytes by the body limit for {}", truncation.limit_bytes, truncation.rule);
+            }
+            let outcomes = assertions::evaluate(Some(Path::new(&file)), &request.options, &res);
+            for outcome in outcomes.iter().filter(|o| !o.passed()) {
+                eprintln!("{}", assertions::describe_failure(outcome));
+            }
+            let passed = if outcomes.is_empty() { request.options.accepts(res.status) } else { outcomes.iter().all(Outcome::passed) };
+            if polled && passed { 0 } else { 1 }
+        }
+        Err(e) => {
+            eprintln!("Request failed: {}", e);
+            1
+        }
+    }
+}
+
+fn usage_error(message: &str) -> i32 {
+    eprintln!("{}", message);
+    eprintln!("Usage: requester run FILE.req [--env NAME] [--allow-hooks] [--trust] [--ignore-pin]");
+    2
+}
+
+/// `requester import-jmx PLAN.jmx [--into FOLDER]`: converts the HTTP samplers
+/// of a JMeter plan into request files. FOLDER is relative to the workspace
+/// and defaults to the plan's file name.
+pub fn import_jmx_command(args: &[String]) -> i32 {
+    const USAGE: &str = "Usage: requester import-jmx PLAN.jmx [--into FOLDER]";
+    let mut file = None;
+    let mut into = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--into" => match args.next() {
+                Some(folder) => into = Some(folder.clone()),
+                None => return usage_error_with("--into needs a folder", USAGE),
+            },
+            flag if flag.starts_with('-') => return usage_error_with(&format!("Unknown option: {}", flag), USAGE),
+            _ if file.is_none() => file = Some(arg.clone()),
+            _ => return usage_error_with(&format!("Unexpected argument: {}", arg), USAGE),
+        }
+    }
+    let Some(file) = file else {
+        return usage_error_with("Missing .jmx file", USAGE);
+    };
+    let into = into.unwrap_or_else(|| {
+        Path::new(&file).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_else(|| "imported".to_string())
+    });
+    let into = storage::get_base_dir().join(into);
+
+    let content = match std::fs::read_to_string(&file) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Cannot read {}: {}", file, e);
+            return 1;
+        }
+    };
+    match jmx::import_jmx(&content, &into) {
+        Ok(report) => {
+            for path in &report.written {
+                println!("Wrote {}", path.display());
+            }
+            println!("Imported {} request(s) into {}", report.written.len(), into.display());
+            if !report.unsupported.is_empty() {
+                println!();
+                println!("Not imported:");
+                for note in &report.unsupported {
+                    println!("  - {}", note);
+                }
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("Import failed: {}", e);
+            1
+        }
+    }
+}
+
+/// `requester switch-storage files|sqlite`: copies the workspace's requests
+/// into the other backend and uses it from the next start.
+pub fn switch_storage_command(args: &[String]) -> i32 {
+    const USAGE: &str = "Usage: requester switch-storage files|sqlite";
+    let kind = match args {
+        [kind] if kind == "files" => BackendKind::Files,
+        [kind] if kind == "sqlite" => BackendKind::Sqlite,
+        [] => return usage_error_with("Missing backend", USAGE),
+        _ => return usage_error_with(&format!("Unexpected arguments: {}", args.join(" ")), USAGE),
+    };
+    match backend::switch_to(kind) {
+        Ok(copied) => {
+            println!("Copied {} request(s); the workspace now uses {}", copied, args[0]);
+            0
+        }
+        Err(e) => {
+            eprintln!("Switching storage failed: {}", e);
+            1
+        }
+    }
+}
+
+fn usage_error_with(message: &str, usage: &str) -> i32 {
+    eprintln!("{}", message);
+    eprintln!("{}", usage);
+    2
+}
+
+/// Request fields given on the command line when launching the GUI:
+///
+/// `requester [-X METHOD] [-H 'Name: value']... [URL] < body`
+#[derive(Clone, Default)]
+pub struct LaunchOptions {
+    pub method: Option<String>,
+    pub url: Option<String>,
+    pub headers: Vec<(String, String)>,
+    stdin: Option<Arc<Mutex<Option<oneshot::Receiver<String>>>>>,
+}
+
+impl LaunchOptions {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = LaunchOptions::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-X" | "--request" => {
+                    let method = args.next().ok_or("-X needs a method")?;
+                    options.method = Some(method.to_uppercase());
+                }
+                "-H" | "--header" => {
+                    let header = args.next().ok_or("-H needs a 'Name: value' argument")?;
+                    let (k, v) = header.split_once(':').ok_or_else(|| format!("Invalid header: {}", header))?;
+                    options.headers.push((k.trim().to_string(), v.trim().to_string()));
+                }
+                flag if flag.starts_with('-') => return Err(format!("Unknown option: {}", flag)),
+                _ if options.url.is_none() => options.url = Some(arg),
+                _ => return Err(format!("Unexpected argument: {}", arg)),
+            }
+        }
+        options.stdin = read_piped_stdin().map(|rx| Arc::new(Mutex::new(Some(rx))));
+        Ok(options)
+    }
+
+    /// The request the editor starts with: the defaults overridden by any arguments.
+    pub fn initial_request(&self) -> HttpRequest {
+        let mut req = HttpRequest::new();
+        if let Some(method) = &self.method {
+            req.method = method.clone();
+        }
+        if let Some(url) = &self.url {
+            req.url = url.clone();
+        }
+        req.headers = self.headers.clone();
+        req
+    }
+
+    /// Piped stdin, delivered once it has been read to the end.
+    pub fn take_stdin(&self) -> Option<oneshot::Receiver<String>> {
+        self.stdin.as_ref()?.lock().ok()?.take()
+    }
+}
+
+/// Reads stdin on a background thread when it is a pipe or file, so the
+/// window opens immediately even if the producer is slow.
+fn read_piped_stdin() -> Option<oneshot::Receiver<String>> {
+    if std::io::stdin().is_terminal() {
+        return None;
+    }
+    let (tx, rx) = oneshot::channel();
+    std::thread::spawn(move || {
+        let mut body = String::new();
+        if std::io::stdin().read_to_string(&mut body).is_ok() && !body.is_empty() {
+            let _ = tx.send(body);
+        }
+    });
+    Some(rx)
+}
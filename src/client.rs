@@ -1,39 +1,702 @@
-use crate::storage::HttpRequest;
-use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use crate::auth::{Auth, KeyLocation};
+use crate::body_template;
+use crate::cookies;
+use crate::dns::{self, DnsLookup, ServerResolver};
+use crate::runner::CancelFlag;
+use crate::storage::{HttpRequest, RequestOptions};
+use reqwest::Url;
+use reqwest::header::{
+    CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, EXPECT, HOST, HeaderMap, HeaderName, HeaderValue, LOCATION, SET_COOKIE,
+    TRANSFER_ENCODING,
+};
+use reqwest::redirect::Policy;
+use std::collections::{HashMap, VecDeque};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct HttpResponse {
     pub status: u16,
     pub status_text: String,
     pub headers: Vec<(String, String)>,
     pub body: String,
+    /// Bytes exactly as received, before any decoding.
+    #[serde(skip)]
+    pub raw_body: Vec<u8>,
+    /// Problems found while decoding the body, shown above it.
+    pub warnings: Vec<String>,
+    /// Time from sending the attempt that got a response until its body was
+    /// read, redirects followed on the way included. Failed attempts before
+    /// it and their retry delays are not counted.
+    #[serde(default)]
+    pub elapsed_ms: u128,
+    /// Length of the body as received, before any decoding.
+    #[serde(default)]
+    pub size_bytes: usize,
+    /// Where a redirect that was not followed pointed: following it would
+    /// have switched scheme and turned the request into a GET, losing its body.
+    #[serde(default)]
+    pub downgrading_redirect: Option<String>,
+    /// Sizes of the request body when it was compressed before sending.
+    #[serde(default)]
+    pub compressed_body: Option<CompressedBody>,
+    /// Address the response came from.
+    #[serde(default)]
+    pub remote_addr: Option<String>,
+    /// How its host name was resolved; `None` when the URL has an IP address.
+    #[serde(default)]
+    pub dns: Option<DnsLookup>,
+    /// Set when a body limit stopped reading the body early.
+    #[serde(default)]
+    pub truncated: Option<Truncation>,
+    /// How the server answered `Expect: 100-continue`, when it was sent.
+    #[serde(default)]
+    pub expect_continue: Option<ContinueOutcome>,
 }
 
+/// How a server answered `Expect: 100-continue`.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ContinueOutcome {
+    /// It sent 100 Continue, then got the body.
+    Continued,
+    /// It sent a final status first, so the body was not sent.
+    FinalStatus,
+    /// It said nothing within [`CONTINUE_WAIT`], so the body was sent anyway.
+    NoAnswer,
+}
+
+impl std::fmt::Display for ContinueOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContinueOutcome::Continued => write!(f, "The server sent 100 Continue; the body followed"),
+            ContinueOutcome::FinalStatus => write!(f, "The server answered before asking for the body; the body was not sent"),
+            ContinueOutcome::NoAnswer => {
+                write!(f, "No 100 Continue within {} ms; the body was sent anyway", CONTINUE_WAIT.as_millis())
+            }
+        }
+    }
+}
+
+/// Where a body limit cut a response body short.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Truncation {
+    /// Content type pattern of the limit that applied.
+    pub rule: String,
+    pub limit_bytes: usize,
+    /// Length the server announced, when it did.
+    pub total_bytes: Option<u64>,
+}
+
+/// `Content-Encoding` a request body can be compressed with before sending.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyCompression {
+    Gzip,
+    Deflate,
+}
+
+impl BodyCompression {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "gzip" => Ok(BodyCompression::Gzip),
+            "deflate" => Ok(BodyCompression::Deflate),
+            other => Err(format!("Expected gzip or deflate, got '{}'", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BodyCompression::Gzip => "gzip",
+            BodyCompression::Deflate => "deflate",
+        }
+    }
+
+    fn compress(&self, bytes: &[u8]) -> Vec<u8> {
+        use std::io::Write;
+        let level = flate2::Compression::default();
+        // Writing to a Vec cannot fail
+        match self {
+            BodyCompression::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), level);
+                let _ = encoder.write_all(bytes);
+                encoder.finish().unwrap_or_default()
+            }
+            BodyCompression::Deflate => {
+                // HTTP's "deflate" is the zlib format, not raw deflate
+                let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), level);
+                let _ = encoder.write_all(bytes);
+                encoder.finish().unwrap_or_default()
+            }
+        }
+    }
+}
+
+/// A request body before and after compression.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CompressedBody {
+    pub encoding: BodyCompression,
+    pub original: usize,
+    pub compressed: usize,
+}
+
+impl std::fmt::Display for CompressedBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let percent = (self.compressed * 100).checked_div(self.original).unwrap_or(100);
+        write!(f, "Body {}-compressed: {} → {} bytes ({}%)", self.encoding.as_str(), self.original, self.compressed, percent)
+    }
+}
+
+/// Response headers that describe this hop or this particular response and
+/// make no sense on a request built from it.
+const RESPONSE_ONLY_HEADERS: &[&str] = &[
+    "connection", "keep-alive", "proxy-authenticate", "proxy-connection", "te", "trailer",
+    "transfer-encoding", "upgrade", "content-length", "content-encoding", "date", "server",
+    "set-cookie", "age", "etag", "expires", "last-modified", "location", "retry-after",
+    "vary", "via", "www-authenticate", "alt-svc", "strict-transport-security",
+];
+
+impl HttpResponse {
+    /// A POST to `url` carrying this response's body and its content
+    /// headers, for sending a received payload on to another service. The
+    /// body is already decoded, so encoding and length headers are dropped.
+    pub fn to_request(&self, url: &str) -> HttpRequest {
+        let headers = self.headers.iter()
+            .filter(|(k, _)| !RESPONSE_ONLY_HEADERS.contains(&k.to_ascii_lowercase().as_str()))
+            .filter(|(k, _)| !k.to_ascii_lowercase().starts_with("access-control-"))
+            .cloned()
+            .collect();
+        HttpRequest {
+            name: Some(format!("From {} response", self.status_text)),
+            method: "POST".to_string(),
+            url: url.to_string(),
+            headers,
+            body: self.body.clone(),
+            ..HttpRequest::new()
+        }
+    }
+}
+
+/// Why a header name is not a valid HTTP token, with a corrected name when one is obvious.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeaderNameProblem {
+    pub message: String,
+    pub suggestion: Option<String>,
+}
+
+/// Checks `name` against the RFC 9110 token rules. Empty names are not
+/// reported since they belong to rows that are still being typed.
+pub fn header_name_problem(name: &str) -> Option<HeaderNameProblem> {
+    if name.is_empty() || name.bytes().all(is_token_char) {
+        return None;
+    }
+    let message = if name.trim() != name {
+        "Header names cannot start or end with whitespace"
+    } else if name.contains(':') {
+        "Header names cannot contain ':' (put the value in the Value column)"
+    } else if name.contains(char::is_whitespace) {
+        "Header names cannot contain spaces"
+    } else {
+        "Header names may only contain letters, digits and !#$%&'*+-.^_`|~"
+    };
+
+    let candidate = name.split(':').next().unwrap_or("").trim();
+    let candidate: String = candidate.split_whitespace().collect::<Vec<_>>().join("-")
+        .bytes()
+        .filter(|b| is_token_char(*b))
+        .map(char::from)
+        .collect();
+    let suggestion = (!candidate.is_empty() && candidate != name).then_some(candidate);
+
+    Some(HeaderNameProblem { message: message.to_string(), suggestion })
+}
+
+fn is_token_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+/// `url` with `scheme://` prepended when it has no scheme of its own, or
+/// `None` when it already has one.
+pub fn with_default_scheme(url: &str, scheme: &str) -> Option<String> {
+    let has_scheme = url.split_once("://").is_some_and(|(prefix, _)| {
+        !prefix.is_empty() && prefix.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    });
+    (!has_scheme && !url.trim().is_empty()).then(|| format!("{}://{}", scheme, url.trim_start_matches('/')))
+}
+
+/// The resolver asking the DNS server of the `dns` option; `None` for the
+/// system resolver.
+fn server_resolver(options: &RequestOptions) -> Result<Option<Arc<ServerResolver>>, String> {
+    match options.dns_server.as_deref() {
+        None | Some(dns::SYSTEM) => Ok(None),
+        Some(server) => Ok(Some(Arc::new(ServerResolver::new(dns::parse_server(server)?)))),
+    }
+}
+
+/// Redirects are followed by `send_request`, which decides what each hop
+/// keeps, so the client itself follows none.
+fn build_client(options: &RequestOptions, resolver: Option<Arc<ServerResolver>>) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(resolver) = resolver {
+        builder = builder.dns_resolver(resolver);
+    }
+    builder = builder.redirect(Policy::none());
+    if let Some(timeout) = options.connect_timeout_ms {
+        builder = builder.connect_timeout(Duration::from_millis(timeout));
+    }
+    if let Some(timeout) = options.read_timeout_ms {
+        builder = builder.read_timeout(Duration::from_millis(timeout));
+    }
+    if options.insecure_tls == Some(true) {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    match options.proxy.as_deref() {
+        Some("none") => builder = builder.no_proxy(),
+        Some(url) => {
+            let proxy = reqwest::Proxy::all(url).map_err(|e| format!("Invalid proxy '{}': {}", url, e))?;
+            builder = builder.proxy(proxy);
+        }
+        None => {}
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// Redirects followed when a request sets no limit.
+const DEFAULT_MAX_REDIRECTS: u32 = 10;
+
+/// Where a redirect response points, if it is one that is followed.
+fn redirect_target(response: &reqwest::Response) -> Option<Url> {
+    if !matches!(response.status().as_u16(), 301..=303 | 307 | 308) {
+        return None;
+    }
+    let location = response.headers().get(LOCATION)?.to_str().ok()?;
+    response.url().join(location).ok()
+}
+
+fn same_origin(a: &Url, b: &Url) -> bool {
+    a.scheme() == b.scheme() && a.host_str() == b.host_str() && a.port_or_known_default() == b.port_or_known_default()
+}
+
+/// Takes the credentials out of `headers` before they follow a redirect to
+/// another origin: `Authorization`, `Proxy-Authorization`, `Cookie` and the
+/// header of an API key auth. Returns the names of those that were there.
+fn strip_credentials(headers: &mut HeaderMap, auth: Option<&Auth>) -> Vec<String> {
+    let mut names = vec!["Authorization".to_string(), "Proxy-Authorization".to_string(), "Cookie".to_string()];
+    if let Some(Auth::ApiKey { location: KeyLocation::Header, name, .. }) = auth
+        && !names.iter().any(|n| n.eq_ignore_ascii_case(name))
+    {
+        names.push(name.clone());
+    }
+    names.into_iter()
+        .filter(|name| HeaderName::from_str(name).is_ok_and(|header| remove_header(headers, &header)))
+        .collect()
+}
+
+/// Appended to connection failures of https:// requests to this machine;
+/// dev servers there often speak plain HTTP.
+const PLAIN_HTTP_HINT: &str = "The server may be plain HTTP; retry as ";
+
+/// The http:// URL suggested by a connection error, if it carries the hint.
+pub fn plain_http_retry(error: &str) -> Option<String> {
+    let (_, url) = error.split_once(PLAIN_HTTP_HINT)?;
+    Some(url.lines().next().unwrap_or_default().trim().to_string())
+}
+
+/// Adds how long the request had been running to an error.
+fn failed_after(sent_at: Instant) -> impl Fn(String) -> String {
+    move |e| format!("{}\n\nFailed after {} ms", e, sent_at.elapsed().as_millis())
+}
+
+fn is_local_host(url: &reqwest::Url) -> bool {
+    let Some(host) = url.host_str() else { return false };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    host == "localhost"
+        || host.ends_with(".localhost")
+        || host.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_loopback() || ip.is_unspecified())
+}
+
+/// Requests in flight per host at most; 0 for no limit.
+static HOST_LIMIT: AtomicUsize = AtomicUsize::new(0);
+
+/// Limits how many requests go to the same host and port at once, counting
+/// every way of sending. Requests over the limit wait their turn in order;
+/// `None` lets any number through, as before.
+pub fn set_host_limit(limit: Option<usize>) {
+    HOST_LIMIT.store(limit.unwrap_or(0), Ordering::Relaxed);
+    // A higher limit may let waiting requests through
+    for slots in host_queue().lock().unwrap_or_else(|e| e.into_inner()).hosts.values() {
+        slots.changed.notify_waiters();
+    }
+}
+
+/// How much of a response body is read, for the content types matching
+/// `pattern`: `type/subtype`, `type/*` or `*` for any.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BodyLimit {
+    pub pattern: String,
+    /// Bytes read at most; 0 reads the whole body.
+    pub max_bytes: usize,
+}
+
+/// Text is kept whole up to a size worth reading; binary downloads are
+/// only previewed.
+pub fn default_body_limits() -> Vec<BodyLimit> {
+    const MB: usize = 1024 * 1024;
+    [
+        ("text/*", 10 * MB),
+        ("application/json", 20 * MB),
+        ("application/xml", 20 * MB),
+        ("application/octet-stream", 256 * 1024),
+        ("image/*", 2 * MB),
+        ("*", 10 * MB),
+    ]
+    .into_iter()
+    .map(|(pattern, max_bytes)| BodyLimit { pattern: pattern.to_string(), max_bytes })
+    .collect()
+}
+
+/// The limit for bodies of type `mime`: an exact media type wins over
+/// `type/*`, which wins over `*`. Among equals the first listed wins.
+pub fn body_limit<'a>(limits: &'a [BodyLimit], mime: &str) -> Option<&'a BodyLimit> {
+    let mime = mime.trim().to_ascii_lowercase();
+    let main_type = mime.split('/').next().unwrap_or_default();
+    let rank = |pattern: &str| {
+        let pattern = pattern.trim().to_ascii_lowercase();
+        if pattern == "*" {
+            Some(0)
+        } else if pattern.strip_suffix("/*").is_some_and(|t| t == main_type) {
+            Some(1)
+        } else if pattern == mime && !mime.is_empty() {
+            Some(2)
+        } else {
+            None
+        }
+    };
+    [2, 1, 0].into_iter().find_map(|wanted| limits.iter().find(|limit| rank(&limit.pattern) == Some(wanted)))
+}
+
+fn body_limits() -> &'static Mutex<Vec<BodyLimit>> {
+    static LIMITS: OnceLock<Mutex<Vec<BodyLimit>>> = OnceLock::new();
+    LIMITS.get_or_init(|| Mutex::new(default_body_limits()))
+}
+
+/// Replaces the body limits every way of sending reads responses with.
+pub fn set_body_limits(limits: Vec<BodyLimit>) {
+    *body_limits().lock().unwrap_or_else(|e| e.into_inner()) = limits;
+}
+
+#[derive(Default)]
+struct HostSlots {
+    in_flight: usize,
+    /// Tickets of the requests waiting, first in line at the front.
+    waiting: VecDeque<u64>,
+    /// Wakes the waiting requests when a slot is given back or the line
+    /// changes, for the first in line to check whether it may go.
+    changed: Arc<Notify>,
+}
+
+#[derive(Default)]
+struct HostQueue {
+    next_ticket: u64,
+    hosts: HashMap<String, HostSlots>,
+}
+
+fn host_queue() -> &'static Mutex<HostQueue> {
+    static QUEUE: OnceLock<Mutex<HostQueue>> = OnceLock::new();
+    QUEUE.get_or_init(Mutex::default)
+}
+
+/// One of a host's slots, given back when dropped.
+struct HostPermit(String);
+
+impl Drop for HostPermit {
+    fn drop(&mut self) {
+        let mut queue = host_queue().lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(slots) = queue.hosts.get_mut(&self.0) {
+            slots.in_flight = slots.in_flight.saturating_sub(1);
+            slots.changed.notify_waiters();
+            if slots.in_flight == 0 && slots.waiting.is_empty() {
+                queue.hosts.remove(&self.0);
+            }
+        }
+    }
+}
+
+/// A request's place in its host's line, left when dropped: a send whose
+/// future is dropped while it waits, as `tokio::select!` does on cancel,
+/// must not hold up the ones behind it.
+struct QueueTicket {
+    host: String,
+    ticket: u64,
+}
+
+impl Drop for QueueTicket {
+    fn drop(&mut self) {
+        let mut queue = host_queue().lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(slots) = queue.hosts.get_mut(&self.host) {
+            slots.waiting.retain(|&t| t != self.ticket);
+            slots.changed.notify_waiters();
+            if slots.in_flight == 0 && slots.waiting.is_empty() {
+                queue.hosts.remove(&self.host);
+            }
+        }
+    }
+}
+
+/// The host and port a request goes to, e.g. `localhost:8080`.
+fn host_key(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    Some(format!("{}:{}", url.host_str()?, url.port_or_known_default()?))
+}
+
+/// Waits for a free slot of `host`, which has `limit` of them (0 for no
+/// limit). `None` when `cancel` was set first, which takes the request out
+/// of the line.
+async fn acquire_host(host: String, limit: &AtomicUsize, cancel: &CancelFlag, on_queued: &mut impl FnMut(bool)) -> Option<HostPermit> {
+    let has_room = |slots: &HostSlots| {
+        let limit = limit.load(Ordering::Relaxed);
+        limit == 0 || slots.in_flight < limit
+    };
+    let (ticket, changed) = {
+        let mut queue = host_queue().lock().unwrap_or_else(|e| e.into_inner());
+        let ticket = queue.next_ticket;
+        queue.next_ticket += 1;
+        let slots = queue.hosts.entry(host.clone()).or_default();
+        if slots.waiting.is_empty() && has_room(slots) {
+            slots.in_flight += 1;
+            return Some(HostPermit(host));
+        }
+        slots.waiting.push_back(ticket);
+        (QueueTicket { host: host.clone(), ticket }, slots.changed.clone())
+    };
+    on_queued(true);
+    loop {
+        // Registered before checking, so a slot given back in between still wakes it
+        let mut notified = std::pin::pin!(changed.notified());
+        notified.as_mut().enable();
+        if cancel.is_cancelled() {
+            on_queued(false);
+            return None;
+        }
+        {
+            let mut queue = host_queue().lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(slots) = queue.hosts.get_mut(&host)
+                && slots.waiting.front() == Some(&ticket.ticket)
+                && has_room(slots)
+            {
+                slots.waiting.pop_front();
+                slots.in_flight += 1;
+                drop(queue);
+                on_queued(false);
+                return Some(HostPermit(host));
+            }
+        }
+        tokio::select! {
+            _ = notified => {}
+            _ = cancel.cancelled() => {}
+        }
+    }
+}
+
+/// The error of a request cancelled while it waited for its host.
+const QUEUE_CANCELLED: &str = "Cancelled before it was sent";
+
 pub async fn execute_request(req_data: &HttpRequest) -> Result<HttpResponse, String> {
-    let client = reqwest::Client::new();
-    
+    execute_queued(req_data, &CancelFlag::default(), |_| {}).await
+}
+
+/// Sends `req_data` once its host has a free slot (see `set_host_limit`).
+/// `on_queued` is told when the request starts and stops waiting for one;
+/// a request cancelled while waiting is never sent.
+pub async fn execute_queued(
+    req_data: &HttpRequest,
+    cancel: &CancelFlag,
+    mut on_queued: impl FnMut(bool),
+) -> Result<HttpResponse, String> {
+    let scheme = req_data.options.default_scheme.as_deref().unwrap_or("https");
+    let url = with_default_scheme(&req_data.url, scheme).unwrap_or_else(|| req_data.url.clone());
+    let _permit = match host_key(&url) {
+        Some(host) => match acquire_host(host, &HOST_LIMIT, cancel, &mut on_queued).await {
+            Some(permit) => Some(permit),
+            None => return Err(QUEUE_CANCELLED.to_string()),
+        },
+        // Malformed URLs fail in send_request without reaching any host
+        None => None,
+    };
+    send_request(req_data).await
+}
+
+/// The request's own headers; empty names are skipped. They go on the wire
+/// in file order, except that a name used more than once is sent with all
+/// its values together, at the position of its first occurrence.
+fn request_headers(pairs: &[(String, String)]) -> Result<HeaderMap, String> {
+    let mut headers = HeaderMap::new();
+    for (k, v) in pairs {
+        if k.is_empty() {
+            continue;
+        }
+        if let Some(problem) = header_name_problem(k) {
+            return Err(format!("Invalid header name '{}': {}", k, problem.message));
+        }
+        let name = HeaderName::from_str(k).map_err(|e| format!("Invalid header name '{}': {}", k, e))?;
+        let value = HeaderValue::from_str(v).map_err(|e| format!("Invalid value for header '{}': {}", k, e))?;
+        // HeaderMap keeps names in first-insertion order and hyper writes them
+        // in that order, one name with all its values at a time
+        headers.append(name, value);
+    }
+    Ok(headers)
+}
+
+/// Removes `name` from `headers`, keeping the others in their order;
+/// `HeaderMap::remove` moves the last name into the gap. Returns whether
+/// it was there.
+fn remove_header(headers: &mut HeaderMap, name: &HeaderName) -> bool {
+    if !headers.contains_key(name) {
+        return false;
+    }
+    let mut kept = HeaderMap::with_capacity(headers.len());
+    for (k, v) in headers.iter().filter(|(k, _)| *k != name) {
+        kept.append(k.clone(), v.clone());
+    }
+    *headers = kept;
+    true
+}
+
+async fn send_request(req_data: &HttpRequest) -> Result<HttpResponse, String> {
+    let options = &req_data.options;
     let method = reqwest::Method::from_str(&req_data.method)
         .map_err(|e| format!("Invalid method: {}", e))?;
+    let resolver = server_resolver(options)?;
+    let client = build_client(options, resolver.clone())?;
     
-    let mut headers = HeaderMap::new();
-    for (k, v) in &req_data.headers {
-        if let (Ok(name), Ok(value)) = (HeaderName::from_str(k), HeaderValue::from_str(v)) {
-            headers.insert(name, value);
+    let mut headers = request_headers(&req_data.headers)?;
+    let (body, compressed_body) = assemble_body(req_data)?;
+    // hyper sends the body right behind the head, so these requests go over
+    // a connection of their own that can hold it back
+    let expect_continue = options.expect_continue && !body.is_empty();
+    if expect_continue && !headers.contains_key(EXPECT) {
+        headers.insert(EXPECT, HeaderValue::from_static("100-continue"));
+    }
+    if let Some(compressed) = &compressed_body {
+        headers.insert(CONTENT_ENCODING, HeaderValue::from_static(compressed.encoding.as_str()));
+    }
+
+    let scheme = options.default_scheme.as_deref().unwrap_or("https");
+    let prefixed = with_default_scheme(&req_data.url, scheme);
+    let url = prefixed.as_deref().unwrap_or(&req_data.url);
+
+    let use_jar = options.cookie_jar != Some(false);
+    let now_ms = chrono::Local::now().timestamp_millis();
+    let jar_cookie = use_jar && !headers.contains_key(COOKIE);
+    if jar_cookie
+        && let Ok(parsed) = Url::parse(url)
+        && let Some(cookie) = cookies::with_jar(|jar| (jar.header_for(&parsed, now_ms), false))
+        && let Ok(value) = HeaderValue::from_str(&cookie)
+    {
+        headers.insert(COOKIE, value);
+    }
+
+    let mut current = Url::parse(url).map_err(|e| format!("Invalid URL '{}': {}", url, e))?;
+    // Failures say how long the request ran, retries and redirects included
+    let failed = failed_after(Instant::now());
+    if expect_continue {
+        let mut res = send_expecting_continue(&method, &current, &headers, &body, options).await.map_err(&failed)?;
+        if use_jar {
+            store_cookies(res.headers.iter().filter(|(k, _)| k == "set-cookie").map(|(_, v)| v.as_str()), &current, now_ms);
+        }
+        if prefixed.is_some() {
+            res.warnings.insert(0, format!("The URL has no scheme; it was sent as {}", url));
         }
+        res.compressed_body = compressed_body;
+        return Ok(res);
     }
+    let (mut response, started) = send_with_retries(&client, &method, &current, &headers, &body, options).await.map_err(&failed)?;
 
-    let response = client
-        .request(method, &req_data.url)
-        .headers(headers)
-        .body(req_data.body.clone())
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
+    // Redirects are followed here so credentials can be held back from
+    // other origins. A 301/302/303 turns anything but GET and HEAD into a
+    // GET without a body; when that would also switch scheme (typically
+    // http:// to https://) the redirect is reported instead.
+    let max_redirects = options.max_redirects.unwrap_or(DEFAULT_MAX_REDIRECTS);
+    let mut hop_method = method.clone();
+    let mut hop_body = body.clone();
+    let mut redirects = 0;
+    let mut redirect_notes = Vec::new();
+    while max_redirects > 0
+        && let Some(next) = redirect_target(&response)
+    {
+        let becomes_get = matches!(response.status().as_u16(), 301..=303)
+            && !matches!(hop_method, reqwest::Method::GET | reqwest::Method::HEAD);
+        if becomes_get && next.scheme() != current.scheme() {
+            break;
+        }
+        if redirects == max_redirects {
+            return Err(failed(format!("Stopped after {} redirects; the last one pointed to {}", max_redirects, next)));
+        }
+        redirects += 1;
+        if use_jar {
+            store_response_cookies(&response, now_ms);
+        }
+        if becomes_get {
+            hop_method = reqwest::Method::GET;
+            hop_body.clear();
+            for name in [CONTENT_TYPE, CONTENT_LENGTH, CONTENT_ENCODING] {
+                remove_header(&mut headers, &name);
+            }
+        }
+        // Cookies from the jar are picked again for each hop
+        if jar_cookie {
+            remove_header(&mut headers, &COOKIE);
+        }
+        if !same_origin(&current, &next) {
+            let origin = next.origin().ascii_serialization();
+            if options.redirect_credentials == Some(true) {
+                let kept: Vec<&str> = ["Authorization", "Proxy-Authorization", "Cookie"].into_iter()
+                    .filter(|name| headers.contains_key(*name))
+                    .collect();
+                if !kept.is_empty() {
+                    redirect_notes.push(format!("Redirected to {}, another origin; sent {} there as redirect-credentials is on", origin, kept.join(", ")));
+                }
+            } else {
+                let removed = strip_credentials(&mut headers, options.auth.as_ref());
+                if !removed.is_empty() {
+                    redirect_notes.push(format!("Redirected to {}, another origin; {} not sent there", origin, removed.join(", ")));
+                }
+            }
+        }
+        if jar_cookie
+            && let Some(cookie) = cookies::with_jar(|jar| (jar.header_for(&next, now_ms), false))
+            && let Ok(value) = HeaderValue::from_str(&cookie)
+        {
+            headers.insert(COOKIE, value);
+        }
+        current = next;
+        response = send_with_retries(&client, &hop_method, &current, &headers, &hop_body, options).await.map_err(&failed)?.0;
+    }
 
     let status = response.status().as_u16();
+    let remote_addr = response.remote_addr().map(|addr| addr.to_string());
+    let host = response.url().host_str().unwrap_or_default().to_string();
+    let dns = match &resolver {
+        _ if host.parse::<std::net::IpAddr>().is_ok() || host.starts_with('[') => None,
+        Some(resolver) => resolver.lookup(&host),
+        None => Some(DnsLookup { host, resolver: dns::SYSTEM.to_string(), ttl_s: None }),
+    };
     let status_text = response.status().to_string();
+    // Besides a limit of 0, following only stops at a redirect for the downgrade case
+    let downgrading_redirect = matches!(response.status().as_u16(), 301..=303)
+        .then(|| response.headers().get(reqwest::header::LOCATION)?.to_str().ok())
+        .flatten()
+        .and_then(|location| response.url().join(location).ok())
+        .filter(|location| location.scheme() != response.url().scheme() && !matches!(hop_method, reqwest::Method::GET | reqwest::Method::HEAD))
+        .map(String::from);
     
+    if use_jar {
+        store_response_cookies(&response, now_ms);
+    }
+
     let mut res_headers = Vec::new();
     for (name, value) in response.headers() {
         res_headers.push((
@@ -42,12 +705,1045 @@ pub async fn execute_request(req_data: &HttpRequest) -> Result<HttpResponse, Str
         ));
     }
 
-    let body = response.text().await.unwrap_or_default();
+    let content_type = response.headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let charset = content_type_charset(&content_type);
+    let mime = content_type.split(';').next().unwrap_or_default();
+    let limit = body_limit(&body_limits().lock().unwrap_or_else(|e| e.into_inner()), mime)
+        .filter(|limit| limit.max_bytes > 0)
+        .cloned();
+    let total_bytes = response.content_length();
+    let mut raw_body = Vec::new();
+    let mut truncated = None;
+    // Read in chunks so a limit stops the download rather than only the display
+    while let Some(chunk) = response.chunk().await.map_err(|e| failed(describe_error(&e, options)))? {
+        if let Some(limit) = &limit
+            && raw_body.len() + chunk.len() > limit.max_bytes
+        {
+            raw_body.extend_from_slice(&chunk[..limit.max_bytes - raw_body.len()]);
+            truncated = Some(Truncation { rule: limit.pattern.clone(), limit_bytes: limit.max_bytes, total_bytes });
+            break;
+        }
+        raw_body.extend_from_slice(&chunk);
+    }
+    let elapsed_ms = started.elapsed().as_millis();
+    let (body, mut warnings) = decode_body(&raw_body, charset.as_deref());
+    if prefixed.is_some() {
+        warnings.insert(0, format!("The URL has no scheme; it was sent as {}", url));
+    }
+    warnings.extend(redirect_notes);
+    if let Some(location) = &downgrading_redirect {
+        warnings.push(format!(
+            "{} redirects to {}; following it would resend the {} as a GET without its body, so it was not followed",
+            status_text, location, hop_method,
+        ));
+    }
+
+    Ok(HttpResponse {
+        status,
+        status_text,
+        headers: res_headers,
+        body,
+        size_bytes: raw_body.len(),
+        raw_body,
+        warnings,
+        elapsed_ms,
+        downgrading_redirect,
+        compressed_body,
+        remote_addr,
+        dns,
+        truncated,
+        expect_continue: None,
+    })
+}
+
+/// Sends one hop, trying again after transport errors as often as the
+/// `retries` option allows. Returns the response and when its attempt started.
+async fn send_with_retries(
+    client: &reqwest::Client,
+    method: &reqwest::Method,
+    url: &Url,
+    headers: &HeaderMap,
+    body: &[u8],
+    options: &RequestOptions,
+) -> Result<(reqwest::Response, Instant), String> {
+    let mut attempts_left = options.retries.unwrap_or(0);
+    loop {
+        let started = Instant::now();
+        let mut request = client
+            .request(method.clone(), url.clone())
+            .headers(headers.clone())
+            .body(body.to_vec());
+        if let Some(timeout) = options.timeout_ms {
+            request = request.timeout(Duration::from_millis(timeout));
+        }
+        match request.send().await {
+            Ok(response) => return Ok((response, started)),
+            Err(_) if attempts_left > 0 => attempts_left -= 1,
+            Err(e) => {
+                if let Some(dns) = dns::find_error(&e) {
+                    return Err(dns.to_string());
+                }
+                let mut message = describe_error(&e, options);
+                if e.is_connect()
+                    && let Some(mut http_url) = e.url().filter(|u| u.scheme() == "https" && is_local_host(u)).cloned()
+                    && http_url.set_scheme("http").is_ok()
+                {
+                    message = format!("{}\n{}{}", message, PLAIN_HTTP_HINT, http_url);
+                }
+                return Err(message);
+            }
+        }
+    }
+}
+
+/// Keeps the cookies `response` sets in the jar.
+fn store_response_cookies(response: &reqwest::Response, now_ms: i64) {
+    let set_cookies = response.headers().get_all(SET_COOKIE).iter().filter_map(|value| value.to_str().ok());
+    store_cookies(set_cookies, response.url(), now_ms);
+}
+
+/// Keeps the cookies of `Set-Cookie` values received from `url` in the jar.
+fn store_cookies<'a>(set_cookies: impl Iterator<Item = &'a str>, url: &Url, now_ms: i64) {
+    let received: Vec<cookies::Cookie> = set_cookies
+        .filter_map(|value| cookies::parse_set_cookie(value, url, now_ms))
+        .collect();
+    if !received.is_empty() {
+        cookies::with_jar(|jar| (jar.merge(received, now_ms), true));
+    }
+}
+
+/// Names the phase a timeout happened in, since reqwest reports them all alike.
+fn describe_error(e: &reqwest::Error, options: &RequestOptions) -> String {
+    if !e.is_timeout() {
+        return e.to_string();
+    }
+    let limits = [
+        ("connect timeout", options.connect_timeout_ms.filter(|_| e.is_connect())),
+        ("read timeout", options.read_timeout_ms.filter(|_| !e.is_connect())),
+        ("total timeout", options.timeout_ms),
+    ];
+    let limits: Vec<String> = limits.iter()
+        .filter_map(|(name, ms)| ms.map(|ms| format!("{} {} ms", name, ms)))
+        .collect();
+    let phase = if e.is_connect() { "connecting" } else if e.is_body() { "reading the response body" } else { "waiting for the response" };
+    match (limits.as_slice(), options.timeout_ms) {
+        ([], _) => format!("Timed out {}", phase),
+        // Only the total timeout was set, so it is the one that fired
+        ([_], Some(ms)) => format!("Request timed out after {} ms {}", ms, phase),
+        _ => format!("Timed out {} ({})", phase, limits.join(", ")),
+    }
+}
+
+/// Longest silence allowed from a raw-mode server when the message sets no
+/// timeout.
+const RAW_READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// Raw responses stop being read past this size.
+const RAW_MAX_BYTES: usize = 16 * 1024 * 1024;
+
+/// What came back for a message sent in raw mode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawExchange {
+    /// Every byte the server sent, as received.
+    pub received: Vec<u8>,
+    pub remote_addr: String,
+    pub elapsed_ms: u64,
+    /// Why reading stopped before the response was complete, if it did.
+    pub cut_short: Option<String>,
+}
+
+impl RawExchange {
+    /// The first line of the response, when there is one.
+    pub fn status_line(&self) -> Option<String> {
+        let end = self.received.iter().position(|&b| b == b'\n').unwrap_or(self.received.len());
+        let line = String::from_utf8_lossy(&self.received[..end]).trim_end().to_string();
+        (!line.is_empty()).then_some(line)
+    }
+}
+
+trait RawStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send {}
+
+impl<T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send> RawStream for T {}
+
+/// Host, port and whether to use TLS for an http(s) target. The path is
+/// ignored: the message carries its own request target.
+fn raw_endpoint(target: &str) -> Result<(String, u16, bool), String> {
+    let url = Url::parse(target.trim()).map_err(|e| format!("Invalid target '{}': {}", target.trim(), e))?;
+    let tls = match url.scheme() {
+        "http" => false,
+        "https" => true,
+        other => return Err(format!("Raw mode sends to http:// or https:// targets, not {}://", other)),
+    };
+    let host = url.host_str().ok_or("The target has no host")?;
+    let host = host.trim_start_matches('[').trim_end_matches(']').to_string();
+    let port = url.port_or_known_default().unwrap_or(if tls { 443 } else { 80 });
+    Ok((host, port, tls))
+}
+
+/// Opens a connection to `host`, wrapped in TLS when `tls` is set. Returns
+/// it with the address connected to.
+async fn raw_connect(host: &str, port: u16, tls: bool, insecure_tls: bool, wait: Duration) -> Result<(Box<dyn RawStream>, String), String> {
+    let tcp = tokio::time::timeout(wait, tokio::net::TcpStream::connect((host, port))).await
+        .map_err(|_| format!("Timed out connecting to {}:{}", host, port))?
+        .map_err(|e| format!("Cannot connect to {}:{}: {}", host, port, e))?;
+    let remote_addr = tcp.peer_addr().map(|addr| addr.to_string()).unwrap_or_default();
+    if !tls {
+        return Ok((Box::new(tcp), remote_addr));
+    }
+    let connector = tokio_native_tls::native_tls::TlsConnector::builder()
+        .danger_accept_invalid_certs(insecure_tls)
+        .danger_accept_invalid_hostnames(insecure_tls)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let stream = tokio_native_tls::TlsConnector::from(connector).connect(host, tcp).await
+        .map_err(|e| format!("TLS handshake with {} failed: {}", host, e))?;
+    Ok((Box::new(stream), remote_addr))
+}
+
+/// Reads a response into `received` until it is complete by its own
+/// framing, the server closes the connection, or `wait` passes without
+/// data. Returns why reading stopped before the response was complete, if
+/// it did.
+async fn read_raw_response(stream: &mut Box<dyn RawStream>, received: &mut Vec<u8>, head_only: bool, wait: Duration) -> Result<Option<String>, String> {
+    use tokio::io::AsyncReadExt;
+
+    let mut chunk = vec![0u8; 64 * 1024];
+    loop {
+        if raw_response_complete(received, head_only) {
+            return Ok(None);
+        }
+        if received.len() >= RAW_MAX_BYTES {
+            return Ok(Some(format!("Stopped reading after {} bytes", RAW_MAX_BYTES)));
+        }
+        match tokio::time::timeout(wait, stream.read(&mut chunk)).await {
+            Err(_) => return Ok(Some(format!("The server sent nothing more for {} ms", wait.as_millis()))),
+            // The server closed the connection
+            Ok(Ok(0)) => return Ok(None),
+            Ok(Ok(n)) => received.extend_from_slice(&chunk[..n]),
+            Ok(Err(e)) if received.is_empty() => return Err(format!("Reading the response failed: {}", e)),
+            Ok(Err(e)) => return Ok(Some(format!("Reading stopped: {}", e))),
+        }
+    }
+}
+
+/// Sends `message` byte for byte over a new connection to the host of
+/// `target`, wrapped in TLS for https. Nothing is validated or added: no
+/// cookies, credentials, redirects or retries. Reading stops once the
+/// response is complete by its own framing, when the server closes the
+/// connection, or after `timeout_ms` of silence.
+pub async fn send_raw(target: &str, message: &[u8], insecure_tls: bool, timeout_ms: Option<u64>) -> Result<RawExchange, String> {
+    use tokio::io::AsyncWriteExt;
+
+    let (host, port, tls) = raw_endpoint(target)?;
+    let wait = timeout_ms.map_or(RAW_READ_TIMEOUT, Duration::from_millis);
+    let started = Instant::now();
+    let failed = failed_after(started);
+    let (mut stream, remote_addr) = raw_connect(&host, port, tls, insecure_tls, wait).await.map_err(&failed)?;
+    stream.write_all(message).await.map_err(|e| failed(format!("Sending failed: {}", e)))?;
+    stream.flush().await.map_err(|e| failed(format!("Sending failed: {}", e)))?;
+
+    let mut received = Vec::new();
+    let cut_short = read_raw_response(&mut stream, &mut received, message.starts_with(b"HEAD "), wait).await.map_err(&failed)?;
+    Ok(RawExchange { received, remote_addr, elapsed_ms: started.elapsed().as_millis() as u64, cut_short })
+}
+
+/// How long a request with `Expect: 100-continue` holds its body back
+/// waiting for the server to answer, as long as curl does.
+pub const CONTINUE_WAIT: Duration = Duration::from_secs(1);
+
+/// Sends a request with `Expect: 100-continue` over a connection of its
+/// own, holding the body back until the server answers 100 Continue or
+/// [`CONTINUE_WAIT`] passes. A final status that comes first ends the
+/// exchange without the body. Redirects are not followed, and the proxy and
+/// DNS server options cannot be used.
+async fn send_expecting_continue(
+    method: &reqwest::Method,
+    url: &Url,
+    headers: &HeaderMap,
+    body: &[u8],
+    options: &RequestOptions,
+) -> Result<HttpResponse, String> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    if options.proxy.as_deref().is_some_and(|proxy| proxy != "none") {
+        return Err("Requests with expect-continue go over a direct connection; they cannot use a proxy".to_string());
+    }
+    if options.dns_server.as_deref().is_some_and(|server| server != dns::SYSTEM) {
+        return Err("Requests with expect-continue use the system resolver; they cannot use a DNS server option".to_string());
+    }
+    let (host, port, tls) = raw_endpoint(url.as_str())?;
+    let wait = options.read_timeout_ms.or(options.timeout_ms).map_or(RAW_READ_TIMEOUT, Duration::from_millis);
+    let connect_wait = options.connect_timeout_ms.map_or(wait, Duration::from_millis);
+    let (mut stream, remote_addr) = raw_connect(&host, port, tls, options.insecure_tls == Some(true), connect_wait).await?;
+
+    let target = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    let mut head = format!("{} {} HTTP/1.1\r\n", method, target).into_bytes();
+    if !headers.contains_key(HOST) {
+        let host = url.host_str().unwrap_or_default();
+        let authority = url.port().map_or_else(|| host.to_string(), |port| format!("{}:{}", host, port));
+        head.extend_from_slice(format!("Host: {}\r\n", authority).as_bytes());
+    }
+    for (name, value) in headers {
+        head.extend_from_slice(name.as_str().as_bytes());
+        head.extend_from_slice(b": ");
+        head.extend_from_slice(value.as_bytes());
+        head.extend_from_slice(b"\r\n");
+    }
+    if !headers.contains_key(CONTENT_LENGTH) && !headers.contains_key(TRANSFER_ENCODING) {
+        head.extend_from_slice(format!("Content-Length: {}\r\n", body.len()).as_bytes());
+    }
+    head.extend_from_slice(b"\r\n");
+
+    let started = Instant::now();
+    let sending = |e: std::io::Error| format!("Sending failed: {}", e);
+    stream.write_all(&head).await.map_err(sending)?;
+    stream.flush().await.map_err(sending)?;
+
+    let mut received = Vec::new();
+    let mut chunk = vec![0u8; 16 * 1024];
+    let deadline = Instant::now() + CONTINUE_WAIT;
+    let outcome = loop {
+        match first_answer(&received) {
+            Some(100) => break ContinueOutcome::Continued,
+            Some(_) => break ContinueOutcome::FinalStatus,
+            None => {}
+        }
+        match tokio::time::timeout(deadline.saturating_duration_since(Instant::now()), stream.read(&mut chunk)).await {
+            Err(_) => break ContinueOutcome::NoAnswer,
+            Ok(Ok(0)) => return Err("The server closed the connection without answering".to_string()),
+            Ok(Ok(n)) => received.extend_from_slice(&chunk[..n]),
+            Ok(Err(e)) => return Err(format!("Reading the response failed: {}", e)),
+        }
+    };
+    if outcome != ContinueOutcome::FinalStatus {
+        stream.write_all(body).await.map_err(sending)?;
+        stream.flush().await.map_err(sending)?;
+    }
+    if let Some(problem) = read_raw_response(&mut stream, &mut received, *method == reqwest::Method::HEAD, wait).await? {
+        return Err(problem);
+    }
+    let elapsed_ms = started.elapsed().as_millis();
+    let RawResponse { status, reason, headers: res_headers, body: raw_body } = parse_raw_response(&received, *method == reqwest::Method::HEAD)?;
+
+    let status_text = reqwest::StatusCode::from_u16(status).ok()
+        .filter(|code| code.canonical_reason().is_some())
+        .map_or_else(|| format!("{} {}", status, reason).trim_end().to_string(), |code| code.to_string());
+    let header = |name: &str| res_headers.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone());
+    let content_type = header("content-type").unwrap_or_default();
+    let mime = content_type.split(';').next().unwrap_or_default();
+    let limit = body_limit(&body_limits().lock().unwrap_or_else(|e| e.into_inner()), mime)
+        .filter(|limit| limit.max_bytes > 0 && raw_body.len() > limit.max_bytes)
+        .cloned();
+    let total_bytes = raw_body.len() as u64;
+    let mut raw_body = raw_body;
+    let truncated = limit.map(|limit| {
+        raw_body.truncate(limit.max_bytes);
+        Truncation { rule: limit.pattern, limit_bytes: limit.max_bytes, total_bytes: Some(total_bytes) }
+    });
+    let (body, mut warnings) = decode_body(&raw_body, content_type_charset(&content_type).as_deref());
+    if (300..400).contains(&status)
+        && let Some(location) = header("location")
+    {
+        warnings.push(format!("{} points to {}; redirects are not followed for requests with expect-continue", status_text, location));
+    }
+    let dns = (url.host_str().is_some_and(|h| h.parse::<std::net::IpAddr>().is_err() && !h.starts_with('[')))
+        .then(|| DnsLookup { host, resolver: dns::SYSTEM.to_string(), ttl_s: None });
 
     Ok(HttpResponse {
         status,
         status_text,
         headers: res_headers,
         body,
+        size_bytes: raw_body.len(),
+        raw_body,
+        warnings,
+        elapsed_ms,
+        downgrading_redirect: None,
+        compressed_body: None,
+        remote_addr: Some(remote_addr),
+        dns,
+        truncated,
+        expect_continue: Some(outcome),
     })
 }
+
+/// The status of the first answer in `received` that is either 100
+/// Continue or final, once its head is complete. Other interim answers are
+/// passed over.
+fn first_answer(received: &[u8]) -> Option<u16> {
+    let mut rest = received;
+    loop {
+        let end = rest.windows(4).position(|w| w == b"\r\n\r\n")?;
+        let status = String::from_utf8_lossy(&rest[..end]).split_whitespace().nth(1)?.parse::<u16>().ok()?;
+        if status == 100 || !(101..200).contains(&status) {
+            return Some(status);
+        }
+        rest = &rest[end + 4..];
+    }
+}
+
+/// The final response of a raw exchange.
+struct RawResponse {
+    status: u16,
+    reason: String,
+    /// Names lowercased, in the order received.
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+/// The final response in `received`, after any interim ones. Chunked
+/// bodies are joined.
+fn parse_raw_response(received: &[u8], head_only: bool) -> Result<RawResponse, String> {
+    let mut rest = received;
+    loop {
+        let end = rest.windows(4).position(|w| w == b"\r\n\r\n").ok_or("The response has no complete head")?;
+        let head = String::from_utf8_lossy(&rest[..end]).into_owned();
+        let body = &rest[end + 4..];
+        let mut lines = head.lines();
+        let status_line = lines.next().unwrap_or_default();
+        let mut parts = status_line.splitn(3, ' ');
+        let status = parts.nth(1).and_then(|s| s.parse::<u16>().ok())
+            .ok_or_else(|| format!("Invalid status line: {}", status_line))?;
+        if (100..200).contains(&status) && status != 101 {
+            rest = body;
+            continue;
+        }
+        let reason = parts.next().unwrap_or_default().to_string();
+        let headers: Vec<(String, String)> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+            .collect();
+        let header = |name: &str| headers.iter().find(|(k, _)| k == name).map(|(_, v)| v.to_ascii_lowercase());
+        let body = if head_only || matches!(status, 101 | 204 | 304) {
+            Vec::new()
+        } else if header("transfer-encoding").is_some_and(|te| te.contains("chunked")) {
+            dechunk(body)?
+        } else if let Some(length) = header("content-length").and_then(|n| n.parse::<usize>().ok()) {
+            body[..length.min(body.len())].to_vec()
+        } else {
+            body.to_vec()
+        };
+        return Ok(RawResponse { status, reason, headers, body });
+    }
+}
+
+/// Joins the chunks of a chunked body; trailers are dropped.
+fn dechunk(mut rest: &[u8]) -> Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = rest.windows(2).position(|w| w == b"\r\n").ok_or("A chunk size line is cut short")?;
+        let line = String::from_utf8_lossy(&rest[..line_end]);
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16).map_err(|_| format!("Invalid chunk size '{}'", size))?;
+        rest = &rest[line_end + 2..];
+        if size == 0 {
+            return Ok(body);
+        }
+        let chunk = rest.get(..size).ok_or("A chunk is cut short")?;
+        body.extend_from_slice(chunk);
+        rest = rest.get(size + 2..).unwrap_or_default();
+    }
+}
+
+/// The bytes raw mode sends for the message typed as `text`. With `crlf`,
+/// line breaks in the head, up to and including the blank line ending it,
+/// are sent as CRLF; the body always goes byte for byte as typed.
+/// `generated_length` is the `Content-Length` raw mode wrote itself: while
+/// the head still carries exactly that value it is replaced by the length
+/// of the body as sent, so edits to the body keep it right. A length the
+/// user typed or changed is sent as it is.
+pub fn raw_message_bytes(text: &str, crlf: bool, generated_length: Option<usize>) -> Vec<u8> {
+    // The blank line ends at the first "\n\n" or "\n\r\n"
+    let blank = [text.find("\n\n").map(|i| (i + 1, 1)), text.find("\n\r\n").map(|i| (i + 1, 2))]
+        .into_iter().flatten().min();
+    let (head, separator, body) = match blank {
+        Some((end, len)) => (&text[..end], &text[end..end + len], &text[end + len..]),
+        None => (text, "", ""),
+    };
+    let mut lines: Vec<&str> = head.split_inclusive('\n').collect();
+    let length_line;
+    if let Some(generated) = generated_length {
+        let is_length = |line: &&str| line.split_once(':').is_some_and(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"));
+        let found: Vec<usize> = lines.iter().enumerate().filter(|(_, line)| is_length(line)).map(|(i, _)| i).collect();
+        if let [i] = found[..]
+            && let Some((name, value)) = lines[i].split_once(':')
+            && value.trim() == generated.to_string()
+        {
+            let ending = &lines[i][lines[i].trim_end_matches(['\r', '\n']).len()..];
+            length_line = format!("{}: {}{}", name, body.len(), ending);
+            lines[i] = &length_line;
+        }
+    }
+    let mut bytes = Vec::with_capacity(text.len() + lines.len() + 1);
+    for line in lines.iter().copied().chain((!separator.is_empty()).then_some(separator)) {
+        match line.strip_suffix('\n').filter(|_| crlf) {
+            Some(content) => {
+                bytes.extend_from_slice(content.strip_suffix('\r').unwrap_or(content).as_bytes());
+                bytes.extend_from_slice(b"\r\n");
+            }
+            None => bytes.extend_from_slice(line.as_bytes()),
+        }
+    }
+    bytes.extend_from_slice(body.as_bytes());
+    bytes
+}
+
+/// Whether `received` holds a whole response by its own framing, after any
+/// interim 1xx responses. Responses framed by neither `Content-Length` nor
+/// chunked encoding end when the connection closes.
+fn raw_response_complete(received: &[u8], head_only: bool) -> bool {
+    let mut rest = received;
+    loop {
+        let Some(end) = rest.windows(4).position(|w| w == b"\r\n\r\n") else { return false };
+        let head = String::from_utf8_lossy(&rest[..end]);
+        let body = &rest[end + 4..];
+        let status = head.split_whitespace().nth(1).and_then(|s| s.parse::<u16>().ok());
+        match status {
+            Some(101) | Some(204) | Some(304) => return true,
+            Some(100..=199) => {
+                rest = body;
+                continue;
+            }
+            _ if head_only => return true,
+            _ => {}
+        }
+        let header = |name: &str| head.lines().skip(1)
+            .filter_map(|line| line.split_once(':'))
+            .find(|(n, _)| n.trim().eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.trim().to_ascii_lowercase());
+        if header("transfer-encoding").is_some_and(|te| te.contains("chunked")) {
+            return body.starts_with(b"0\r\n\r\n") || body.ends_with(b"\r\n0\r\n\r\n");
+        }
+        return header("content-length").and_then(|n| n.parse::<usize>().ok()).is_some_and(|n| body.len() >= n);
+    }
+}
+
+/// The body bytes as sent: encoded in the request's charset, then compressed
+/// when its `compress` option asks for it. The sizes are returned when it was
+/// compressed; an empty body is sent as is.
+pub fn assemble_body(req: &HttpRequest) -> Result<(Vec<u8>, Option<CompressedBody>), String> {
+    let content_type = req.headers.iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+        .map(|(_, v)| v.as_str());
+    let charset = req.options.body_charset.clone().or_else(|| content_type.and_then(content_type_charset));
+    let body = encode_body(&req.body, charset.as_deref())?;
+    let Some(encoding) = req.options.compress.filter(|_| !body.is_empty()) else {
+        return Ok((body, None));
+    };
+    if content_type.is_some_and(|ct| ct.trim_start().to_ascii_lowercase().starts_with("multipart/")) {
+        return Err("Multipart bodies cannot be compressed; remove the compress option".to_string());
+    }
+    let compressed = encoding.compress(&body);
+    let sizes = CompressedBody { encoding, original: body.len(), compressed: compressed.len() };
+    Ok((compressed, Some(sizes)))
+}
+
+/// What a request body is, from its `Content-Type` or, without one, its
+/// first character.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BodyKind {
+    Empty,
+    /// With its number of values, when it parses; placeholders not yet
+    /// filled in can keep it from parsing.
+    Json(Option<usize>),
+    Xml,
+    Form,
+    Multipart,
+    Text,
+}
+
+impl BodyKind {
+    fn detect(content_type: Option<&str>, body: &str) -> BodyKind {
+        let trimmed = body.trim_start();
+        if trimmed.is_empty() {
+            return BodyKind::Empty;
+        }
+        let mime = content_type.map(|ct| ct.split(';').next().unwrap_or_default().trim().to_ascii_lowercase());
+        let json = || BodyKind::Json(serde_json::from_str::<serde_json::Value>(body).ok().map(|value| count_values(&value)));
+        match mime.as_deref() {
+            Some(mime) if mime.contains("json") => json(),
+            Some(mime) if mime.contains("xml") => BodyKind::Xml,
+            Some("application/x-www-form-urlencoded") => BodyKind::Form,
+            Some(mime) if mime.starts_with("multipart/") => BodyKind::Multipart,
+            Some(_) => BodyKind::Text,
+            None if trimmed.starts_with(['{', '[']) => json(),
+            None if trimmed.starts_with('<') => BodyKind::Xml,
+            None => BodyKind::Text,
+        }
+    }
+}
+
+impl std::fmt::Display for BodyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BodyKind::Empty => write!(f, "empty"),
+            BodyKind::Json(Some(1)) => write!(f, "JSON, 1 value"),
+            BodyKind::Json(Some(values)) => write!(f, "JSON, {} values", values),
+            BodyKind::Json(None) => write!(f, "JSON, not parsable as written"),
+            BodyKind::Xml => write!(f, "XML"),
+            BodyKind::Form => write!(f, "form data"),
+            BodyKind::Multipart => write!(f, "multipart"),
+            BodyKind::Text => write!(f, "text"),
+        }
+    }
+}
+
+/// Values in `value`, itself included.
+fn count_values(value: &serde_json::Value) -> usize {
+    1 + match value {
+        serde_json::Value::Array(items) => items.iter().map(count_values).sum(),
+        serde_json::Value::Object(map) => map.values().map(count_values).sum(),
+        _ => 0,
+    }
+}
+
+/// The body of a request as it would be sent, found without sending it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BodyPreview {
+    /// Length on the wire.
+    pub bytes: usize,
+    pub kind: BodyKind,
+    pub compressed: Option<CompressedBody>,
+}
+
+/// Dry run of the steps that finish the body of `req`: its SOAP envelope,
+/// its body template, then [`assemble_body`]. Placeholders are left as they
+/// are, so hooks and environments play no part. Returns the error sending
+/// would stop at, if any.
+pub fn preview_body(req: &HttpRequest, defaults: &RequestOptions) -> Result<BodyPreview, String> {
+    let mut req = crate::soap::wrap(req);
+    req.options = req.options.inherit(defaults);
+    if req.options.body_template {
+        let max_bytes = body_template::limit_bytes(req.options.template_max_mb);
+        req.body = body_template::expand(&req.body, max_bytes).map_err(|e| e.to_string())?;
+    }
+    let (body, compressed) = assemble_body(&req)?;
+    let content_type = req.headers.iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+        .map(|(_, v)| v.as_str());
+    Ok(BodyPreview { bytes: body.len(), kind: BodyKind::detect(content_type, &req.body), compressed })
+}
+
+/// Encodes the body for the wire. Characters the charset cannot represent are
+/// an error rather than being silently replaced.
+fn encode_body(body: &str, charset: Option<&str>) -> Result<Vec<u8>, String> {
+    let Some(label) = charset else {
+        return Ok(body.as_bytes().to_vec());
+    };
+    let encoding = encoding_rs::Encoding::for_label(label.as_bytes())
+        .ok_or_else(|| format!("Unknown body charset '{}'", label))?;
+    if encoding == encoding_rs::UTF_8 {
+        return Ok(body.as_bytes().to_vec());
+    }
+    // encode() falls back to UTF-8 for UTF-16 since browsers never send it
+    if encoding == encoding_rs::UTF_16LE || encoding == encoding_rs::UTF_16BE {
+        let units = body.encode_utf16();
+        return Ok(if encoding == encoding_rs::UTF_16LE {
+            units.flat_map(u16::to_le_bytes).collect()
+        } else {
+            units.flat_map(u16::to_be_bytes).collect()
+        });
+    }
+    let (bytes, _, had_errors) = encoding.encode(body);
+    if had_errors {
+        let bad: String = body.chars().filter(|c| {
+            let mut buf = [0; 4];
+            encoding.encode(c.encode_utf8(&mut buf)).2
+        }).take(5).collect();
+        return Err(format!("The body contains characters that cannot be encoded as {}: {}", encoding.name(), bad));
+    }
+    Ok(bytes.into_owned())
+}
+
+fn content_type_charset(content_type: &str) -> Option<String> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim().eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+/// Decodes a response body, stripping byte order marks and replacing
+/// invalid sequences instead of failing, and reports what was repaired.
+fn decode_body(bytes: &[u8], charset: Option<&str>) -> (String, Vec<String>) {
+    let mut warnings = Vec::new();
+
+    let utf8_label = charset.is_none_or(|c| c.eq_ignore_ascii_case("utf-8") || c.eq_ignore_ascii_case("utf8"));
+    if !utf8_label || bytes.starts_with(&[0xFF, 0xFE]) || bytes.starts_with(&[0xFE, 0xFF]) {
+        let encoding = charset
+            .and_then(|c| encoding_rs::Encoding::for_label(c.as_bytes()))
+            .unwrap_or(encoding_rs::UTF_8);
+        // decode() sniffs a BOM and lets it override the declared charset
+        let (text, used, had_errors) = encoding.decode(bytes);
+        if encoding_rs::Encoding::for_bom(bytes).is_some() {
+            warnings.push(format!("Removed {} byte order mark", used.name()));
+        }
+        if had_errors {
+            warnings.push(format!("Body is not valid {}; invalid bytes are shown as \u{FFFD}", used.name()));
+        }
+        return (text.into_owned(), warnings);
+    }
+
+    let bytes = match bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        Some(rest) => {
+            warnings.push("Removed UTF-8 byte order mark".to_string());
+            rest
+        }
+        None => bytes,
+    };
+    let invalid = bytes.utf8_chunks().filter(|chunk| !chunk.invalid().is_empty()).count();
+    if invalid > 0 {
+        warnings.push(format!(
+            "Body contained {} invalid UTF-8 byte sequence{}; they are shown as \u{FFFD}",
+            invalid,
+            if invalid == 1 { "" } else { "s" }
+        ));
+    }
+    (String::from_utf8_lossy(bytes).into_owned(), warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Answers every connection to a local port with `respond`, given the
+    /// port and the head of the request. Returns the port.
+    async fn serve(respond: impl Fn(u16, &str) -> String + Send + Sync + 'static) -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let respond = Arc::new(respond);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let respond = respond.clone();
+                tokio::spawn(async move {
+                    let mut received = Vec::new();
+                    let mut buf = [0; 4096];
+                    // A TLS handshake never ends its head; answer it as it is
+                    while !received.windows(4).any(|w| w == b"\r\n\r\n") && received.first().is_none_or(u8::is_ascii_alphabetic) {
+                        match stream.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => received.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let response = respond(port, &String::from_utf8_lossy(&received));
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        port
+    }
+
+    fn request(method: &str, url: &str) -> HttpRequest {
+        HttpRequest { method: method.to_string(), url: url.to_string(), ..HttpRequest::new() }
+    }
+
+    #[tokio::test]
+    async fn cancelled_queued_sends_leave_the_line() {
+        let limit = AtomicUsize::new(1);
+        let host = "queue-test.invalid:1".to_string();
+        let keep = CancelFlag::default();
+        let mut ignore = |_| {};
+        let first = acquire_host(host.clone(), &limit, &keep, &mut ignore).await;
+        assert!(first.is_some());
+
+        // Dropped while waiting, as tokio::select! does when polling is cancelled
+        let dropped = tokio::time::timeout(Duration::from_millis(100), acquire_host(host.clone(), &limit, &keep, &mut ignore)).await;
+        assert!(dropped.is_err());
+
+        // Cancelled through its flag while waiting
+        let cancel = CancelFlag::default();
+        let flagged = acquire_host(host.clone(), &limit, &cancel, &mut ignore);
+        let flag = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            cancel.cancel();
+        };
+        let (flagged, _) = tokio::join!(flagged, flag);
+        assert!(flagged.is_none());
+
+        drop(first);
+        let next = tokio::time::timeout(Duration::from_secs(1), acquire_host(host.clone(), &limit, &keep, &mut ignore)).await;
+        assert!(next.is_ok_and(|permit| permit.is_some()));
+    }
+
+    #[tokio::test]
+    async fn queued_sends_go_in_order_as_slots_free_up() {
+        let limit = AtomicUsize::new(1);
+        let host = "queue-order.invalid:1".to_string();
+        let keep = CancelFlag::default();
+        let first = acquire_host(host.clone(), &limit, &keep, &mut |_| {}).await.unwrap();
+        let order = Mutex::new(Vec::new());
+        let wait = |n: usize| {
+            let (host, limit, keep, order) = (host.clone(), &limit, &keep, &order);
+            async move {
+                let permit = acquire_host(host, limit, keep, &mut |_| {}).await;
+                order.lock().unwrap().push(n);
+                // Held a moment, so the next in line has to be woken again
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                drop(permit);
+            }
+        };
+        let release = async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            drop(first);
+        };
+        tokio::time::timeout(Duration::from_secs(1), async { tokio::join!(wait(1), wait(2), wait(3), release) }).await.unwrap();
+        assert_eq!(*order.lock().unwrap(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn raw_messages_normalise_only_the_head() {
+        let text = "POST / HTTP/1.1\nHost: a\r\n\nline 1\nline 2\r\n";
+        assert_eq!(raw_message_bytes(text, true, None), b"POST / HTTP/1.1\r\nHost: a\r\n\r\nline 1\nline 2\r\n");
+        assert_eq!(raw_message_bytes(text, false, None), text.as_bytes());
+        assert_eq!(raw_message_bytes("GET / HTTP/1.1\nHost: a", true, None), b"GET / HTTP/1.1\r\nHost: a");
+    }
+
+    #[test]
+    fn raw_messages_recount_only_the_generated_length() {
+        let text = "POST / HTTP/1.1\nContent-Length: 2\n\nabcd";
+        assert_eq!(raw_message_bytes(text, true, Some(2)), b"POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nabcd");
+        // Typed by the user, changed, or repeated: sent as written
+        assert_eq!(raw_message_bytes(text, false, None), text.as_bytes());
+        assert_eq!(raw_message_bytes(text, false, Some(3)), text.as_bytes());
+        let twice = "POST / HTTP/1.1\nContent-Length: 2\nContent-Length: 2\n\nabcd";
+        assert_eq!(raw_message_bytes(twice, false, Some(2)), twice.as_bytes());
+    }
+
+    /// Answers with the head of the request as the body.
+    async fn echo() -> u16 {
+        serve(|_, head| format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}", head.len(), head)).await
+    }
+
+    /// Header lines of an echoed head, names lowercased, leaving out those
+    /// the client adds by itself.
+    fn echoed_headers(head: &str) -> Vec<(String, String)> {
+        head.lines().skip(1)
+            .filter_map(|line| line.split_once(':'))
+            .map(|(k, v)| (k.to_ascii_lowercase(), v.trim().to_string()))
+            .filter(|(k, v)| !["host", "accept-encoding", "content-length"].contains(&k.as_str()) && (k.as_str(), v.as_str()) != ("accept", "*/*"))
+            .collect()
+    }
+
+    fn pairs(list: &[(&str, &str)]) -> Vec<(String, String)> {
+        list.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[tokio::test]
+    async fn headers_go_on_the_wire_in_file_order_with_repeated_names_grouped() {
+        let port = echo().await;
+        let req = HttpRequest::from_http_string(&format!(
+            "GET http://127.0.0.1:{}/\nX-First: 1\nAccept: text/html\nX-Tag: one\nAccept: application/json\nX-Last: 2\nX-Tag: two\n",
+            port,
+        )).unwrap();
+        let res = execute_request(&req).await.unwrap();
+        assert_eq!(echoed_headers(&res.body), pairs(&[
+            ("x-first", "1"),
+            ("accept", "text/html"),
+            ("accept", "application/json"),
+            ("x-tag", "one"),
+            ("x-tag", "two"),
+            ("x-last", "2"),
+        ]));
+    }
+
+    #[tokio::test]
+    async fn headers_left_out_on_a_redirect_keep_the_rest_in_order() {
+        let echo = echo().await;
+        let port = serve(move |_, _| format!(
+            "HTTP/1.1 307 Temporary Redirect\r\nLocation: http://127.0.0.1:{}/\r\nContent-Length: 0\r\n\r\n",
+            echo,
+        )).await;
+        let mut req = request("GET", &format!("http://127.0.0.1:{}/", port));
+        req.headers = pairs(&[("X-A", "1"), ("Authorization", "Bearer t"), ("X-B", "2"), ("Cookie", "c=1"), ("X-C", "3"), ("X-D", "4")]);
+        let res = execute_request(&req).await.unwrap();
+        assert_eq!(echoed_headers(&res.body), pairs(&[("x-a", "1"), ("x-b", "2"), ("x-c", "3"), ("x-d", "4")]));
+    }
+
+    #[tokio::test]
+    async fn https_to_a_local_plain_server_suggests_http() {
+        let port = serve(|_, _| "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n".to_string()).await;
+        let error = execute_request(&request("GET", &format!("https://127.0.0.1:{}/items?a=1", port))).await.unwrap_err();
+        assert_eq!(plain_http_retry(&error), Some(format!("http://127.0.0.1:{}/items?a=1", port)));
+
+        // Nothing listening at all
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+        let error = execute_request(&request("GET", &format!("https://localhost:{}/", closed))).await.unwrap_err();
+        assert_eq!(plain_http_retry(&error), Some(format!("http://localhost:{}/", closed)));
+        assert_eq!(plain_http_retry("Connection refused"), None);
+    }
+
+    #[tokio::test]
+    async fn redirects_that_would_drop_a_post_body_are_reported() {
+        let port = serve(|port, _| format!(
+            "HTTP/1.1 301 Moved Permanently\r\nLocation: https://127.0.0.1:{}/new\r\nContent-Length: 0\r\n\r\n",
+            port,
+        )).await;
+        let mut post = request("POST", &format!("http://127.0.0.1:{}/old", port));
+        post.body = "{\"a\": 1}".to_string();
+        let res = execute_request(&post).await.unwrap();
+        assert_eq!(res.status, 301);
+        assert_eq!(res.downgrading_redirect, Some(format!("https://127.0.0.1:{}/new", port)));
+
+        // A GET loses nothing, so the redirect is followed (and fails here)
+        assert!(execute_request(&request("GET", &format!("http://127.0.0.1:{}/old", port))).await.is_err());
+
+        // A 307 keeps the method and body, so it is not reported even where
+        // it is not followed
+        let port = serve(|port, _| format!(
+            "HTTP/1.1 307 Temporary Redirect\r\nLocation: https://127.0.0.1:{}/new\r\nContent-Length: 0\r\n\r\n",
+            port,
+        )).await;
+        post.url = format!("http://127.0.0.1:{}/old", port);
+        assert!(execute_request(&post).await.is_err());
+        post.options.max_redirects = Some(0);
+        let res = execute_request(&post).await.unwrap();
+        assert_eq!(res.status, 307);
+        assert_eq!(res.downgrading_redirect, None);
+    }
+
+    #[tokio::test]
+    async fn credentials_stay_behind_on_redirects_to_another_origin() {
+        let echo = echo().await;
+        let port = serve(move |_, _| format!(
+            "HTTP/1.1 302 Found\r\nLocation: http://127.0.0.1:{}/landing\r\nContent-Length: 0\r\n\r\n",
+            echo,
+        )).await;
+        let mut req = request("GET", &format!("http://127.0.0.1:{}/start", port));
+        req.headers = vec![
+            ("Authorization".to_string(), "Bearer secret".to_string()),
+            ("Cookie".to_string(), "session=1".to_string()),
+            ("X-Trace".to_string(), "abc".to_string()),
+        ];
+        let res = execute_request(&req).await.unwrap();
+        let head = res.body.to_ascii_lowercase();
+        assert!(head.starts_with("get /landing "));
+        assert!(head.contains("x-trace: abc"));
+        assert!(!head.contains("authorization") && !head.contains("session=1"));
+        let origin = format!("http://127.0.0.1:{}", echo);
+        assert!(res.warnings.contains(&format!("Redirected to {}, another origin; Authorization, Cookie not sent there", origin)));
+
+        req.options.redirect_credentials = Some(true);
+        let res = execute_request(&req).await.unwrap();
+        let head = res.body.to_ascii_lowercase();
+        assert!(head.contains("authorization: bearer secret") && head.contains("cookie: session=1"));
+        assert!(res.warnings.contains(&format!("Redirected to {}, another origin; sent Authorization, Cookie there as redirect-credentials is on", origin)));
+    }
+
+    #[test]
+    fn body_limits_prefer_the_most_specific_pattern() {
+        let limits: Vec<BodyLimit> = [("*", 10), ("image/*", 20), ("text/html", 30), ("Image/PNG", 40), ("text/html", 50)]
+            .into_iter()
+            .map(|(pattern, max_bytes)| BodyLimit { pattern: pattern.to_string(), max_bytes })
+            .collect();
+        let limit = |mime| body_limit(&limits, mime).map(|limit| limit.max_bytes);
+        assert_eq!(limit("text/html"), Some(30));
+        assert_eq!(limit(" IMAGE/png "), Some(40));
+        assert_eq!(limit("image/gif"), Some(20));
+        assert_eq!(limit("text/plain"), Some(10));
+        assert_eq!(limit(""), Some(10));
+        assert_eq!(body_limit(&limits[1..], "application/json"), None);
+        assert_eq!(body_limit(&default_body_limits(), "application/octet-stream").map(|l| l.pattern.as_str()), Some("application/octet-stream"));
+    }
+
+    /// A server reading uploads: after the head it sends 100 Continue when
+    /// `send_continue` is set, then reads the body and answers with it.
+    async fn upload_server(send_continue: bool) -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut received = Vec::new();
+                    let mut buf = [0; 4096];
+                    let head_end = loop {
+                        if let Some(end) = received.windows(4).position(|w| w == b"\r\n\r\n") {
+                            break end + 4;
+                        }
+                        match stream.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => received.extend_from_slice(&buf[..n]),
+                        }
+                    };
+                    let head = String::from_utf8_lossy(&received[..head_end]).to_ascii_lowercase();
+                    let length: usize = head.lines()
+                        .find_map(|line| line.strip_prefix("content-length:"))
+                        .map_or(0, |n| n.trim().parse().unwrap());
+                    if send_continue {
+                        stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").await.unwrap();
+                    }
+                    while received.len() < head_end + length {
+                        match stream.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => received.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let body = &received[head_end..];
+                    let response = format!("HTTP/1.1 201 Created\r\nContent-Length: {}\r\n\r\n", body.len());
+                    stream.write_all(&[response.as_bytes(), body].concat()).await.unwrap();
+                });
+            }
+        });
+        port
+    }
+
+    fn upload(port: u16) -> HttpRequest {
+        let mut req = request("PUT", &format!("http://127.0.0.1:{}/files/a.txt?v=2", port));
+        req.body = "file contents".to_string();
+        req.options.expect_continue = true;
+        req
+    }
+
+    #[tokio::test]
+    async fn expect_continue_waits_for_the_100_before_the_body() {
+        let res = execute_request(&upload(upload_server(true).await)).await.unwrap();
+        assert_eq!(res.expect_continue, Some(ContinueOutcome::Continued));
+        assert_eq!((res.status, res.status_text.as_str(), res.body.as_str()), (201, "201 Created", "file contents"));
+        assert!(res.elapsed_ms < CONTINUE_WAIT.as_millis());
+    }
+
+    #[tokio::test]
+    async fn expect_continue_keeps_the_body_when_the_server_answers_first() {
+        let port = serve(|_, head| {
+            assert!(head.starts_with("PUT /files/a.txt?v=2 HTTP/1.1\r\n"));
+            assert!(head.contains("\r\nexpect: 100-continue\r\n") && head.contains("\r\nContent-Length: 13\r\n"));
+            "HTTP/1.1 417 Expectation Failed\r\nContent-Length: 4\r\n\r\nnope".to_string()
+        }).await;
+        let res = execute_request(&upload(port)).await.unwrap();
+        assert_eq!(res.expect_continue, Some(ContinueOutcome::FinalStatus));
+        assert_eq!((res.status, res.body.as_str()), (417, "nope"));
+    }
+
+    #[tokio::test]
+    async fn expect_continue_sends_the_body_after_waiting_in_vain() {
+        let res = execute_request(&upload(upload_server(false).await)).await.unwrap();
+        assert_eq!(res.expect_continue, Some(ContinueOutcome::NoAnswer));
+        assert_eq!(res.body, "file contents");
+        assert!(res.elapsed_ms >= CONTINUE_WAIT.as_millis());
+
+        // Without a body there is nothing to hold back
+        let mut empty = upload(upload_server(false).await);
+        empty.body.clear();
+        assert_eq!(execute_request(&empty).await.unwrap().expect_continue, None);
+    }
+
+    #[test]
+    fn raw_responses_skip_interim_answers_and_join_chunks() {
+        let received = b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nX-A: 1\r\n\r\n5;ext=1\r\nhello\r\n7\r\n, world\r\n0\r\n\r\n";
+        assert_eq!(first_answer(received), Some(100));
+        assert_eq!(first_answer(&received[25..]), Some(200));
+        assert_eq!(first_answer(b"HTTP/1.1 102 Processing\r\n\r\nHTTP/1.1 20"), None);
+        let res = parse_raw_response(received, false).unwrap();
+        assert_eq!((res.status, res.reason.as_str()), (200, "OK"));
+        assert_eq!(res.headers, vec![("transfer-encoding".to_string(), "chunked".to_string()), ("x-a".to_string(), "1".to_string())]);
+        assert_eq!(res.body, b"hello, world");
+        assert_eq!(dechunk(b"zz\r\n").unwrap_err(), "Invalid chunk size 'zz'");
+        assert_eq!(dechunk(b"5\r\nab").unwrap_err(), "A chunk is cut short");
+    }
+}
@@ -0,0 +1,389 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use reqwest::Url;
+use crate::storage::get_base_dir;
+
+/// A cookie kept between requests.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// Lowercase, without a leading dot.
+    pub domain: String,
+    /// Sent only to `domain` itself, not its subdomains.
+    pub host_only: bool,
+    pub path: String,
+    pub secure: bool,
+    #[serde(default)]
+    pub http_only: bool,
+    /// Milliseconds since the Unix epoch; `None` lasts for the session,
+    /// which here means until the jar is cleared.
+    pub expires_ms: Option<i64>,
+}
+
+impl Cookie {
+    fn expired(&self, now_ms: i64) -> bool {
+        self.expires_ms.is_some_and(|expires| expires <= now_ms)
+    }
+
+    /// The same cookie as far as replacing is concerned.
+    fn same_slot(&self, other: &Cookie) -> bool {
+        self.name == other.name && self.domain == other.domain && self.path == other.path
+    }
+
+    fn matches(&self, url: &Url, now_ms: i64) -> bool {
+        let Some(host) = url.host_str().map(str::to_ascii_lowercase) else { return false };
+        let domain_matches = host == self.domain
+            || (!self.host_only && host.ends_with(&format!(".{}", self.domain)));
+        !self.expired(now_ms) && domain_matches && path_matches(url.path(), &self.path) && (!self.secure || url.scheme() == "https")
+    }
+}
+
+/// Whether `request_path` is within the cookie path `path` (RFC 6265 5.1.4).
+fn path_matches(request_path: &str, path: &str) -> bool {
+    request_path == path
+        || (request_path.starts_with(path) && (path.ends_with('/') || request_path[path.len()..].starts_with('/')))
+}
+
+/// The default cookie path for a request path: up to its last `/`.
+fn default_path(request_path: &str) -> String {
+    match request_path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(i) => request_path[..i].to_string(),
+    }
+}
+
+/// Cookies stored from responses and imports, in `cookies.json` in the
+/// base dir. Requests send the matching ones unless they set a `Cookie`
+/// header themselves.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CookieJar {
+    pub cookies: Vec<Cookie>,
+}
+
+/// How adding cookies to the jar went.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MergeCounts {
+    pub added: usize,
+    pub replaced: usize,
+    /// Expired cookies, which only remove a stored one in their place.
+    pub expired: usize,
+}
+
+impl CookieJar {
+    fn path() -> PathBuf {
+        get_base_dir().join("cookies.json")
+    }
+
+    pub fn load() -> CookieJar {
+        fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        crate::workspace::ensure_writable()?;
+        let content = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        fs::write(Self::path(), content)
+    }
+
+    /// Adds `cookies`, each replacing a stored one with the same name,
+    /// domain and path. An expired cookie deletes its stored counterpart.
+    pub fn merge(&mut self, cookies: Vec<Cookie>, now_ms: i64) -> MergeCounts {
+        let mut counts = MergeCounts::default();
+        for cookie in cookies {
+            let existing = self.cookies.iter().position(|c| c.same_slot(&cookie));
+            if cookie.expired(now_ms) {
+                counts.expired += 1;
+                if let Some(i) = existing {
+                    self.cookies.remove(i);
+                }
+                continue;
+            }
+            match existing {
+                Some(i) => {
+                    self.cookies[i] = cookie;
+                    counts.replaced += 1;
+                }
+                None => {
+                    self.cookies.push(cookie);
+                    counts.added += 1;
+                }
+            }
+        }
+        counts
+    }
+
+    /// The `Cookie` header value for a request to `url`: longer paths first,
+    /// as browsers send them.
+    pub fn header_for(&self, url: &Url, now_ms: i64) -> Option<String> {
+        let mut matching: Vec<&Cookie> = self.cookies.iter().filter(|c| c.matches(url, now_ms)).collect();
+        if matching.is_empty() {
+            return None;
+        }
+        matching.sort_by_key(|c| std::cmp::Reverse(c.path.len()));
+        Some(matching.iter().map(|c| format!("{}={}", c.name, c.value)).collect::<Vec<_>>().join("; "))
+    }
+
+    /// Domains with cookies, sorted, with how many each has.
+    pub fn domains(&self) -> Vec<(String, usize)> {
+        let mut domains: Vec<(String, usize)> = Vec::new();
+        for cookie in &self.cookies {
+            match domains.iter_mut().find(|(d, _)| *d == cookie.domain) {
+                Some((_, count)) => *count += 1,
+                None => domains.push((cookie.domain.clone(), 1)),
+            }
+        }
+        domains.sort();
+        domains
+    }
+}
+
+fn shared_jar() -> &'static Mutex<CookieJar> {
+    static JAR: OnceLock<Mutex<CookieJar>> = OnceLock::new();
+    JAR.get_or_init(|| Mutex::new(CookieJar::load()))
+}
+
+/// Runs `f` on the jar every request uses and saves it when `f` says it
+/// changed something.
+pub fn with_jar<T>(f: impl FnOnce(&mut CookieJar) -> (T, bool)) -> T {
+    let mut jar = shared_jar().lock().unwrap_or_else(|e| e.into_inner());
+    let (result, changed) = f(&mut jar);
+    if changed {
+        let _ = jar.save();
+    }
+    result
+}
+
+/// The `Cookie` header the jar has for `url` now, if it parses.
+pub fn cookie_for(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    with_jar(|jar| (jar.header_for(&url, chrono::Local::now().timestamp_millis()), false))
+}
+
+/// Parses a `Set-Cookie` header received from `url`. Cookies for a domain
+/// the host does not belong to are refused.
+pub fn parse_set_cookie(header: &str, url: &Url, now_ms: i64) -> Option<Cookie> {
+    let host = url.host_str()?.to_ascii_lowercase();
+    let mut parts = header.split(';');
+    let (name, value) = parts.next()?.split_once('=')?;
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    let mut cookie = Cookie {
+        name: name.to_string(),
+        value: value.trim().trim_matches('"').to_string(),
+        domain: host.clone(),
+        host_only: true,
+        path: default_path(url.path()),
+        secure: false,
+        http_only: false,
+        expires_ms: None,
+    };
+    let mut max_age = None;
+    for attribute in parts {
+        let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+        let value = value.trim();
+        match key.trim().to_ascii_lowercase().as_str() {
+            "domain" if !value.is_empty() => {
+                let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                if host != domain && !host.ends_with(&format!(".{}", domain)) {
+                    return None;
+                }
+                cookie.domain = domain;
+                cookie.host_only = false;
+            }
+            "path" if value.starts_with('/') => cookie.path = value.to_string(),
+            "secure" => cookie.secure = true,
+            "httponly" => cookie.http_only = true,
+            "max-age" => max_age = value.parse::<i64>().ok(),
+            "expires" => {
+                if let Ok(time) = chrono::DateTime::parse_from_rfc2822(&value.replace('-', " ")) {
+                    cookie.expires_ms = Some(time.timestamp_millis());
+                }
+            }
+            _ => {}
+        }
+    }
+    // Max-Age wins over Expires
+    if let Some(seconds) = max_age {
+        cookie.expires_ms = Some(now_ms + seconds.saturating_mul(1000));
+    }
+    Some(cookie)
+}
+
+/// Parses the Netscape `cookies.txt` format browser extensions export:
+/// tab-separated domain, include-subdomains flag, path, secure flag, expiry
+/// in Unix seconds (0 for session cookies), name and value. Lines starting
+/// with `#HttpOnly_` hold HTTP-only cookies; other `#` lines are comments.
+/// Returns the cookies and the problems with lines that were not read.
+pub fn parse_cookies_txt(content: &str) -> (Vec<Cookie>, Vec<String>) {
+    let mut cookies = Vec::new();
+    let mut problems = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+            Some(rest) => (rest, true),
+            None => (line, false),
+        };
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 7 {
+            problems.push(format!("Line {}: expected 7 tab-separated fields, found {}", i + 1, fields.len()));
+            continue;
+        }
+        let flag = |value: &str| value.eq_ignore_ascii_case("TRUE");
+        let expires = match fields[4].trim().parse::<i64>() {
+            Ok(0) => None,
+            Ok(seconds) => Some(seconds.saturating_mul(1000)),
+            Err(_) => {
+                problems.push(format!("Line {}: the expiry '{}' is not a number", i + 1, fields[4]));
+                continue;
+            }
+        };
+        let domain = fields[0].trim();
+        cookies.push(Cookie {
+            name: fields[5].to_string(),
+            value: fields[6..].join("\t"),
+            domain: domain.trim_start_matches('.').to_ascii_lowercase(),
+            host_only: !flag(fields[1]) && !domain.starts_with('.'),
+            path: if fields[2].is_empty() { "/".to_string() } else { fields[2].to_string() },
+            secure: flag(fields[3]),
+            http_only,
+            expires_ms: expires,
+        });
+    }
+    (cookies, problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000_000;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    fn cookie(name: &str, value: &str, domain: &str, path: &str) -> Cookie {
+        Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            domain: domain.to_string(),
+            host_only: true,
+            path: path.to_string(),
+            secure: false,
+            http_only: false,
+            expires_ms: None,
+        }
+    }
+
+    #[test]
+    fn set_cookie_headers_are_parsed_with_their_attributes() {
+        let from = url("https://api.example.com/v1/users/me");
+        let parsed = parse_set_cookie("sid=\"abc\"; Domain=.Example.com; Path=/v1; Secure; HttpOnly; Max-Age=60", &from, NOW).unwrap();
+        assert_eq!(parsed, Cookie {
+            name: "sid".to_string(),
+            value: "abc".to_string(),
+            domain: "example.com".to_string(),
+            host_only: false,
+            path: "/v1".to_string(),
+            secure: true,
+            http_only: true,
+            expires_ms: Some(NOW + 60_000),
+        });
+
+        // Defaults: the host only, and the path up to the last slash
+        let plain = parse_set_cookie("theme=dark", &from, NOW).unwrap();
+        assert_eq!(plain, cookie("theme", "dark", "api.example.com", "/v1/users"));
+        assert_eq!(parse_set_cookie("a=1", &url("https://example.com/login"), NOW).unwrap().path, "/");
+
+        // Max-Age wins over Expires, in either order
+        let expires = parse_set_cookie("a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT", &from, NOW).unwrap();
+        assert_eq!(expires.expires_ms, Some(1_445_412_480_000));
+        assert_eq!(parse_set_cookie("a=1; Max-Age=0; Expires=Wed, 21 Oct 2035 07:28:00 GMT", &from, NOW).unwrap().expires_ms, Some(NOW));
+        assert_eq!(parse_set_cookie("a=1; Path=relative", &from, NOW).unwrap().path, "/v1/users");
+
+        // Another site's domain, or no name, is refused
+        assert_eq!(parse_set_cookie("a=1; Domain=other.com", &from, NOW), None);
+        assert_eq!(parse_set_cookie("a=1; Domain=ample.com", &from, NOW), None);
+        assert_eq!(parse_set_cookie("=1", &from, NOW), None);
+        assert_eq!(parse_set_cookie("novalue", &from, NOW), None);
+    }
+
+    #[test]
+    fn paths_match_on_segment_boundaries() {
+        assert!(path_matches("/docs", "/docs"));
+        assert!(path_matches("/docs/", "/docs"));
+        assert!(path_matches("/docs/web", "/docs"));
+        assert!(path_matches("/docs/web", "/docs/"));
+        assert!(path_matches("/anything", "/"));
+        assert!(!path_matches("/docsets", "/docs"));
+        assert!(!path_matches("/doc", "/docs"));
+        assert!(!path_matches("/", "/docs"));
+    }
+
+    #[test]
+    fn requests_get_matching_cookies_longest_path_first() {
+        let mut wide = cookie("wide", "1", "example.com", "/");
+        wide.host_only = false;
+        let mut secure = cookie("secure", "2", "example.com", "/");
+        secure.secure = true;
+        let mut gone = cookie("gone", "3", "example.com", "/");
+        gone.expires_ms = Some(NOW);
+        let jar = CookieJar { cookies: vec![wide, secure, gone, cookie("deep", "4", "example.com", "/api/v1"), cookie("other", "5", "example.org", "/")] };
+        assert_eq!(jar.header_for(&url("http://example.com/api/v1/x"), NOW).as_deref(), Some("deep=4; wide=1"));
+        assert_eq!(jar.header_for(&url("https://example.com/"), NOW).as_deref(), Some("wide=1; secure=2"));
+        // Only `wide` is for subdomains too
+        assert_eq!(jar.header_for(&url("https://api.example.com/api/v1"), NOW).as_deref(), Some("wide=1"));
+        assert_eq!(jar.header_for(&url("https://example.net/"), NOW), None);
+    }
+
+    #[test]
+    fn merging_replaces_by_name_domain_and_path() {
+        let mut jar = CookieJar { cookies: vec![cookie("a", "old", "example.com", "/"), cookie("b", "1", "example.com", "/")] };
+        let mut expired = cookie("b", "", "example.com", "/");
+        expired.expires_ms = Some(NOW - 1);
+        let counts = jar.merge(vec![
+            cookie("a", "new", "example.com", "/"),
+            // Another path is another cookie
+            cookie("a", "api", "example.com", "/api"),
+            expired.clone(),
+            // Expired with nothing to remove
+            Cookie { name: "c".to_string(), ..expired },
+        ], NOW);
+        assert_eq!(counts, MergeCounts { added: 1, replaced: 1, expired: 2 });
+        assert_eq!(jar.cookies, vec![cookie("a", "new", "example.com", "/"), cookie("a", "api", "example.com", "/api")]);
+        assert_eq!(jar.domains(), vec![("example.com".to_string(), 2)]);
+    }
+
+    #[test]
+    fn cookies_txt_lines_are_read_or_reported() {
+        let content = [
+            "# Netscape HTTP Cookie File",
+            "",
+            ".example.com\tTRUE\t/\tTRUE\t0\tsid\tabc",
+            "#HttpOnly_shop.example.com\tFALSE\t/cart\tFALSE\t1700000000\tcart\ta\tb",
+            "example.org\tFALSE\t\tfalse\t0\tx\t1",
+            "too\tfew\tfields",
+            "example.net\tFALSE\t/\tFALSE\tsoon\tn\tv",
+        ].join("\n");
+        let (cookies, problems) = parse_cookies_txt(&content);
+        let mut sid = cookie("sid", "abc", "example.com", "/");
+        sid.host_only = false;
+        sid.secure = true;
+        let mut cart = cookie("cart", "a\tb", "shop.example.com", "/cart");
+        cart.http_only = true;
+        cart.expires_ms = Some(1_700_000_000_000);
+        assert_eq!(cookies, vec![sid, cart, cookie("x", "1", "example.org", "/")]);
+        assert_eq!(problems, [
+            "Line 6: expected 7 tab-separated fields, found 3",
+            "Line 7: the expiry 'soon' is not a number",
+        ]);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::client::HttpResponse;
+use crate::storage::HttpRequest;
+
+/// Methods a browser sends without a preflight.
+const SIMPLE_METHODS: &[&str] = &["GET", "HEAD", "POST"];
+
+/// Headers the browser sets itself; scripts cannot send them, so they never
+/// appear in `Access-Control-Request-Headers`.
+const FORBIDDEN_HEADERS: &[&str] = &[
+    "accept-charset", "accept-encoding", "access-control-request-headers", "access-control-request-method",
+    "connection", "content-length", "cookie", "cookie2", "date", "dnt", "expect", "host", "keep-alive",
+    "origin", "referer", "te", "trailer", "transfer-encoding", "upgrade", "via",
+];
+
+/// One rule a browser applies to the preflight response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorsCheck {
+    pub passed: bool,
+    pub message: String,
+}
+
+/// Header names, lowercased and sorted, that are not CORS-safelisted and
+/// therefore need the server's permission.
+pub fn non_simple_headers(req: &HttpRequest) -> Vec<String> {
+    let mut names: Vec<String> = req.headers.iter()
+        .filter(|(k, v)| !k.is_empty() && !is_safelisted(k, v))
+        .map(|(k, _)| k.to_lowercase())
+        .filter(|k| !FORBIDDEN_HEADERS.contains(&k.as_str()) && !k.starts_with("proxy-") && !k.starts_with("sec-"))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn is_safelisted(name: &str, value: &str) -> bool {
+    match name.to_lowercase().as_str() {
+        "accept" | "accept-language" | "content-language" | "range" => true,
+        "content-type" => {
+            let mime = value.split(';').next().unwrap_or("").trim().to_lowercase();
+            ["application/x-www-form-urlencoded", "multipart/form-data", "text/plain"].contains(&mime.as_str())
+        }
+        _ => false,
+    }
+}
+
+/// Whether a browser would send a preflight before `req`.
+pub fn needs_preflight(req: &HttpRequest) -> bool {
+    !SIMPLE_METHODS.contains(&req.method.as_str()) || !non_simple_headers(req).is_empty()
+}
+
+/// The OPTIONS request a browser on `origin` would send before `req`.
+pub fn preflight_request(req: &HttpRequest, origin: &str) -> HttpRequest {
+    let mut preflight = req.clone();
+    preflight.method = "OPTIONS".to_string();
+    preflight.body = String::new();
+    preflight.headers = vec![
+        ("Origin".to_string(), origin.to_string()),
+        ("Access-Control-Request-Method".to_string(), req.method.clone()),
+    ];
+    let headers = non_simple_headers(req);
+    if !headers.is_empty() {
+        preflight.headers.push(("Access-Control-Request-Headers".to_string(), headers.join(",")));
+    }
+    preflight
+}
+
+/// Evaluates a preflight response the way a browser would for `req` sent
+/// from `origin`. `credentials` means the request is sent with cookies or
+/// HTTP auth (`credentials: "include"`), which disables the `*` wildcards.
+pub fn evaluate(req: &HttpRequest, origin: &str, credentials: bool, res: &HttpResponse) -> Vec<CorsCheck> {
+    let header = |name: &str| {
+        res.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.trim().to_string())
+    };
+    let list = |name: &str| -> Vec<String> {
+        header(name).map(|v| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect()).unwrap_or_default()
+    };
+    let mut checks = Vec::new();
+    let mut check = |passed: bool, message: String| checks.push(CorsCheck { passed, message });
+
+    check(
+        (200..300).contains(&res.status),
+        format!("Preflight status {} {}", res.status, if (200..300).contains(&res.status) { "is OK" } else { "is not 2xx, so the browser treats it as a failure" }),
+    );
+
+    match header("Access-Control-Allow-Origin") {
+        None => check(false, "Access-Control-Allow-Origin is missing".to_string()),
+        Some(allowed) if allowed == "*" && credentials => {
+            check(false, "Access-Control-Allow-Origin is '*', which is not allowed for credentialed requests".to_string())
+        }
+        Some(allowed) if allowed == "*" || allowed == origin => {
+            check(true, format!("Origin {} is allowed ({})", origin, allowed))
+        }
+        Some(allowed) => check(false, format!("Access-Control-Allow-Origin is '{}', not '{}'", allowed, origin)),
+    }
+
+    if credentials {
+        let allowed = header("Access-Control-Allow-Credentials").is_some_and(|v| v == "true");
+        check(allowed, if allowed {
+            "Credentials are allowed".to_string()
+        } else {
+            "Access-Control-Allow-Credentials must be 'true' for credentialed requests".to_string()
+        });
+    }
+
+    let methods = list("Access-Control-Allow-Methods");
+    let method_allowed = SIMPLE_METHODS.contains(&req.method.as_str())
+        || methods.contains(&req.method)
+        || (!credentials && methods.iter().any(|m| m == "*"));
+    check(method_allowed, if method_allowed {
+        format!("Method {} is allowed", req.method)
+    } else if methods.is_empty() {
+        format!("Method {} is not simple and Access-Control-Allow-Methods is missing", req.method)
+    } else {
+        format!("Method {} is not in Access-Control-Allow-Methods: {}", req.method, methods.join(", "))
+    });
+
+    let allowed_headers: Vec<String> = list("Access-Control-Allow-Headers").iter().map(|h| h.to_lowercase()).collect();
+    let wildcard = !credentials && allowed_headers.iter().any(|h| h == "*");
+    for name in non_simple_headers(req) {
+        // The wildcard never covers Authorization
+        let allowed = allowed_headers.contains(&name) || (wildcard && name != "authorization");
+        check(allowed, if allowed {
+            format!("Header {} is allowed", name)
+        } else {
+            format!("Header {} is not in Access-Control-Allow-Headers", name)
+        });
+    }
+
+    checks
+}
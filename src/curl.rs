@@ -0,0 +1,207 @@
+use base64::Engine;
+use crate::shell::split_words;
+use crate::storage::HttpRequest;
+
+/// Flags whose value is not used but has to be skipped.
+const IGNORED_WITH_VALUE: &[&str] = &[
+    "-o", "--output", "-w", "--write-out", "--retry", "--retry-delay", "--cacert", "--cert", "-E",
+    "--key", "-T", "--upload-file", "-F", "--form", "-r", "--range", "-c", "--cookie-jar",
+    "--resolve", "-K", "--config", "--interface", "-U", "--proxy-user", "--limit-rate",
+];
+
+/// Flags that only change curl's own output, or ask for what requests do
+/// anyway, so ignoring them loses nothing.
+const NO_EFFECT: &[&str] = &[
+    "-s", "--silent", "-S", "--show-error", "-v", "--verbose", "-i", "--include", "-L", "--location",
+    "--compressed", "-#", "--progress-bar", "--no-progress-meter", "-f", "--fail", "-o", "--output",
+    "-w", "--write-out",
+];
+
+/// Builds a request from a `curl ...` command line as copied from docs or a
+/// browser's "Copy as cURL". Line continuations and shell quoting are
+/// understood. Flags that do not map onto a request are ignored, and
+/// returned second, once each, unless they would change nothing.
+pub fn from_curl(command: &str) -> Result<(HttpRequest, Vec<String>), String> {
+    let words = split_words(command.trim())?;
+    let mut args = words.into_iter();
+    if args.next().as_deref() != Some("curl") {
+        return Err("Not a curl command".to_string());
+    }
+
+    let mut req = HttpRequest { url: String::new(), ..HttpRequest::new() };
+    let mut method = None;
+    let mut data: Vec<String> = Vec::new();
+    let mut get = false;
+    let mut head = false;
+    let mut ignored: Vec<String> = Vec::new();
+    let mut ignore = |flag: &str| {
+        // Bundled short flags such as -sSL
+        let bundled_no_effect = !flag.starts_with("--") && flag.len() > 2
+            && flag[1..].chars().all(|c| NO_EFFECT.contains(&format!("-{}", c).as_str()));
+        if !NO_EFFECT.contains(&flag) && !bundled_no_effect && !ignored.iter().any(|f| f == flag) {
+            ignored.push(flag.to_string());
+        }
+    };
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "-X" | "--request" => method = Some(value(&arg)?.to_uppercase()),
+            flag if flag.starts_with("-X") => method = Some(flag[2..].to_uppercase()),
+            "-H" | "--header" => {
+                let header = value(&arg)?;
+                if let Some((k, v)) = header.split_once(':') {
+                    req.headers.push((k.trim().to_string(), v.trim().to_string()));
+                }
+            }
+            "-d" | "--data" | "--data-raw" | "--data-binary" | "--data-ascii" => data.push(value(&arg)?),
+            "--data-urlencode" => data.push(urlencode_data(&value(&arg)?)),
+            "--json" => {
+                data.push(value(&arg)?);
+                for (name, json) in [("Content-Type", "application/json"), ("Accept", "application/json")] {
+                    if !req.headers.iter().any(|(k, _)| k.eq_ignore_ascii_case(name)) {
+                        req.headers.push((name.to_string(), json.to_string()));
+                    }
+                }
+            }
+            "-u" | "--user" => {
+                let credentials = base64::engine::general_purpose::STANDARD.encode(value(&arg)?);
+                req.headers.push(("Authorization".to_string(), format!("Basic {}", credentials)));
+            }
+            "-A" | "--user-agent" => req.headers.push(("User-Agent".to_string(), value(&arg)?)),
+            "-e" | "--referer" => req.headers.push(("Referer".to_string(), value(&arg)?)),
+            "-b" | "--cookie" => req.headers.push(("Cookie".to_string(), value(&arg)?)),
+            "-G" | "--get" => get = true,
+            "-I" | "--head" => head = true,
+            "-k" | "--insecure" => req.options.insecure_tls = Some(true),
+            "-m" | "--max-time" => set_option(&mut req, "timeout", &seconds_to_ms(&value(&arg)?))?,
+            "--connect-timeout" => set_option(&mut req, "connect-timeout", &seconds_to_ms(&value(&arg)?))?,
+            "-x" | "--proxy" => set_option(&mut req, "proxy", &value(&arg)?)?,
+            "--dns-servers" => {
+                // Only the first server is used
+                let servers = value(&arg)?;
+                set_option(&mut req, "dns", servers.split(',').next().unwrap_or_default())?;
+            }
+            "--max-redirs" => set_option(&mut req, "max-redirects", &value(&arg)?)?,
+            "--location-trusted" => req.options.redirect_credentials = Some(true),
+            "--url" => req.url = value(&arg)?,
+            flag if IGNORED_WITH_VALUE.contains(&flag) => {
+                value(flag)?;
+                ignore(flag);
+            }
+            flag if flag.starts_with('-') => ignore(flag),
+            _ if req.url.is_empty() => req.url = arg,
+            _ => return Err(format!("Unexpected argument: {}", arg)),
+        }
+    }
+    if req.url.is_empty() {
+        return Err("The command has no URL".to_string());
+    }
+
+    let body = data.join("&");
+    if get && !body.is_empty() {
+        let separator = if req.url.contains('?') { '&' } else { '?' };
+        req.url = format!("{}{}{}", req.url, separator, body);
+    } else {
+        req.body = body;
+    }
+    req.method = method.unwrap_or_else(|| {
+        if head {
+            "HEAD"
+        } else if !req.body.is_empty() {
+            "POST"
+        } else {
+            "GET"
+        }.to_string()
+    });
+    Ok((req, ignored))
+}
+
+/// A `--data-urlencode` value as curl sends it: `content` and `=content`
+/// are encoded whole, `name=content` after the `=`. The `@file` forms are
+/// kept as written, since files are not read.
+fn urlencode_data(value: &str) -> String {
+    match value.find(['=', '@']) {
+        None => percent_encode(value),
+        Some(0) if value.starts_with('=') => percent_encode(&value[1..]),
+        Some(i) if value[i..].starts_with('=') => format!("{}={}", &value[..i], percent_encode(&value[i + 1..])),
+        Some(_) => value.to_string(),
+    }
+}
+
+/// Percent-encodes everything but unreserved characters, as curl does.
+fn percent_encode(s: &str) -> String {
+    s.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+        b => format!("%{:02X}", b),
+    }).collect()
+}
+
+fn set_option(req: &mut HttpRequest, key: &str, value: &str) -> Result<(), String> {
+    req.options.set(key, value).map_err(|e| format!("{}: {}", key, e))
+}
+
+/// curl takes timeouts in (possibly fractional) seconds.
+fn seconds_to_ms(value: &str) -> String {
+    value.parse::<f64>().map_or(value.to_string(), |s| ((s * 1000.0).round() as u64).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_urlencode_encodes_the_content() {
+        let (req, _) = from_curl("curl https://example.com --data-urlencode 'q=a b&c' --data-urlencode =é --data-urlencode x/y").unwrap();
+        assert_eq!(req.body, "q=a%20b%26c&%C3%A9&x%2Fy");
+        assert_eq!(req.method, "POST");
+        let (req, _) = from_curl("curl https://example.com --data-urlencode name@file.txt -d 'raw=a b'").unwrap();
+        assert_eq!(req.body, "name@file.txt&raw=a b");
+    }
+
+    #[test]
+    fn ansi_c_quoted_hex_escapes_decode_as_utf8() {
+        let (req, _) = from_curl("curl https://example.com --data-raw $'caf\\xc3\\xa9\\n'").unwrap();
+        assert_eq!(req.body, "café\n");
+    }
+
+    #[test]
+    fn multi_line_command_with_headers_and_json() {
+        let command = "curl -X PUT 'https://api.example.com/items/7' \\\n  -H 'Content-Type: application/json' \\\n  -H \"Accept: application/json\" \\\n  --header 'X-Trace:abc' \\\n  --data '{\"name\": \"box\", \"tags\": [\"a b\"]}'\n";
+        let (req, ignored) = from_curl(command).unwrap();
+        assert_eq!(req.method, "PUT");
+        assert_eq!(req.url, "https://api.example.com/items/7");
+        assert_eq!(req.headers, vec![
+            ("Content-Type".to_string(), "application/json".to_string()),
+            ("Accept".to_string(), "application/json".to_string()),
+            ("X-Trace".to_string(), "abc".to_string()),
+        ]);
+        assert_eq!(req.body, r#"{"name": "box", "tags": ["a b"]}"#);
+        assert!(ignored.is_empty());
+    }
+
+    #[test]
+    fn unknown_flags_are_ignored_and_reported_once() {
+        let (req, ignored) = from_curl("curl -sSL --http2 -o out.json --http2 https://example.com -d a=1 --tlsv1.2").unwrap();
+        assert_eq!(req.method, "POST");
+        assert_eq!(req.url, "https://example.com");
+        assert_eq!(req.body, "a=1");
+        assert_eq!(ignored, ["--http2", "--tlsv1.2"]);
+    }
+
+    #[test]
+    fn get_moves_data_into_the_query() {
+        let (req, _) = from_curl("curl -G 'https://example.com/s?x=1' -d q=rust -d page=2").unwrap();
+        assert_eq!(req.method, "GET");
+        assert_eq!(req.url, "https://example.com/s?x=1&q=rust&page=2");
+        assert_eq!(req.body, "");
+        assert_eq!(from_curl("curl -I https://example.com").unwrap().0.method, "HEAD");
+    }
+
+    #[test]
+    fn malformed_commands_are_errors() {
+        assert_eq!(from_curl("wget https://example.com").unwrap_err(), "Not a curl command");
+        assert_eq!(from_curl("curl -H 'A: b'").unwrap_err(), "The command has no URL");
+        assert_eq!(from_curl("curl https://example.com -H").unwrap_err(), "-H needs a value");
+        assert_eq!(from_curl("curl https://a.example https://b.example").unwrap_err(), "Unexpected argument: https://b.example");
+    }
+}
@@ -0,0 +1,188 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, Local, SecondsFormat, TimeZone, Utc};
+
+/// Timezone of a generated or displayed time. Only offsets are supported
+/// besides local time and UTC, as there is no timezone database at hand.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zone {
+    Local,
+    Utc,
+    Fixed(FixedOffset),
+}
+
+impl Zone {
+    /// Parses `local`, `UTC` (or `Z`) or an offset such as `+02:00`, `-0530`
+    /// or `+3`.
+    pub fn parse(s: &str) -> Result<Zone, String> {
+        let s = s.trim();
+        if s.is_empty() || s.eq_ignore_ascii_case("local") {
+            return Ok(Zone::Local);
+        }
+        if s.eq_ignore_ascii_case("utc") || s.eq_ignore_ascii_case("z") {
+            return Ok(Zone::Utc);
+        }
+        let unknown = || format!("Unknown timezone '{}'; use local, UTC or an offset such as +02:00", s);
+        let (sign, digits) = match s.as_bytes()[0] {
+            b'+' => (1, &s[1..]),
+            b'-' => (-1, &s[1..]),
+            _ => return Err(unknown()),
+        };
+        let (hours, minutes) = match digits.split_once(':') {
+            Some((h, m)) => (h, m),
+            None if digits.len() == 4 => digits.split_at(2),
+            None => (digits, "0"),
+        };
+        let hours: i32 = hours.parse().map_err(|_| unknown())?;
+        let minutes: i32 = minutes.parse().map_err(|_| unknown())?;
+        if !(0..60).contains(&minutes) {
+            return Err(unknown());
+        }
+        FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+            .filter(|_| hours <= 18)
+            .map(Zone::Fixed)
+            .ok_or_else(unknown)
+    }
+
+    fn at(&self, ms: i64) -> Option<DateTime<FixedOffset>> {
+        let utc = Utc.timestamp_millis_opt(ms).single()?;
+        Some(match self {
+            // Converting from UTC is never ambiguous, even across DST changes
+            Zone::Local => utc.with_timezone(&Local).fixed_offset(),
+            Zone::Utc => utc.fixed_offset(),
+            Zone::Fixed(offset) => utc.with_timezone(offset),
+        })
+    }
+}
+
+/// Checks a strftime-style format, naming the first pattern chrono does not
+/// know. Formatting with such a pattern would fail instead.
+pub fn check_format(format: &str) -> Result<(), String> {
+    if !StrftimeItems::new(format).any(|item| item == Item::Error) {
+        return Ok(());
+    }
+    let mut rest = format;
+    while let Some(start) = rest.find('%') {
+        rest = &rest[start..];
+        // A pattern is % plus up to three characters, e.g. %d, %-d, %.3f or %:z
+        let ends: Vec<usize> = rest.char_indices().map(|(i, c)| i + c.len_utf8()).take(4).collect();
+        let known = ends.iter().skip(1).find(|&&end| !StrftimeItems::new(&rest[..end]).any(|item| item == Item::Error));
+        match known {
+            Some(&end) => rest = &rest[end..],
+            None => {
+                let bad: String = rest.chars().take(2).collect();
+                return Err(format!("Invalid date format '{}': unknown pattern {}", format, bad));
+            }
+        }
+    }
+    Err(format!("Invalid date format '{}'", format))
+}
+
+/// Formats the Unix time `ms` in `zone`.
+pub fn format_ms(ms: i64, format: &str, zone: Zone) -> Result<String, String> {
+    check_format(format)?;
+    let time = zone.at(ms).ok_or_else(|| format!("Time {} ms is out of range", ms))?;
+    Ok(time.format(format).to_string())
+}
+
+/// A time for display, in local time or in UTC marked as such.
+pub fn display_ms(ms: i64, format: &str, utc: bool) -> String {
+    let zone = if utc { Zone::Utc } else { Zone::Local };
+    match format_ms(ms, format, zone) {
+        Ok(text) if utc => format!("{} UTC", text),
+        Ok(text) => text,
+        Err(_) => String::new(),
+    }
+}
+
+/// Value of a `{{$...}}` placeholder at the Unix time `now_ms`, or `None` for
+/// names that are not generators:
+/// - `$timestamp`: Unix time in seconds
+/// - `$isoTimestamp` or `$isoTimestamp(+02:00)`: ISO 8601 time, UTC by default
+/// - `$date(%Y-%m-%d)` or `$date(%d.%m.%Y %H:%M, UTC)`: strftime-style
+///   format, local time by default
+pub fn generate(name: &str, now_ms: i64) -> Option<Result<String, String>> {
+    if name == "$timestamp" {
+        return Some(Ok(now_ms.div_euclid(1000).to_string()));
+    }
+    if name == "$isoTimestamp" {
+        return Some(iso(now_ms, Zone::Utc));
+    }
+    if let Some(args) = arguments(name, "$isoTimestamp") {
+        return Some(Zone::parse(args).and_then(|zone| iso(now_ms, zone)));
+    }
+    let args = arguments(name, "$date")?;
+    // The zone is optional and the format may contain commas itself
+    let split = args.rsplit_once(',')
+        .filter(|(_, zone)| !zone.trim().is_empty())
+        .map(|(format, zone)| (format, Zone::parse(zone)));
+    let (format, zone) = match split {
+        Some((format, Ok(zone))) => (format, zone),
+        _ => (args, Zone::Local),
+    };
+    let format = format.trim();
+    let format = format.strip_prefix('"').and_then(|f| f.strip_suffix('"')).unwrap_or(format);
+    if format.is_empty() {
+        return Some(Err("$date needs a format such as $date(%Y-%m-%d)".to_string()));
+    }
+    Some(format_ms(now_ms, format, zone))
+}
+
+/// The text between the parentheses of `name(...)`.
+fn arguments<'a>(name: &'a str, function: &str) -> Option<&'a str> {
+    name.strip_prefix(function)?.trim_start().strip_prefix('(')?.strip_suffix(')')
+}
+
+fn iso(ms: i64, zone: Zone) -> Result<String, String> {
+    let time = zone.at(ms).ok_or_else(|| format!("Time {} ms is out of range", ms))?;
+    Ok(time.to_rfc3339_opts(SecondsFormat::Millis, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-03-31 00:30:00.250 UTC, the night European clocks went forward.
+    const NOW_MS: i64 = 1_711_845_000_250;
+
+    fn offset(seconds: i32) -> Zone {
+        Zone::Fixed(FixedOffset::east_opt(seconds).unwrap())
+    }
+
+    #[test]
+    fn zones_parse_names_and_offsets() {
+        assert_eq!(Zone::parse(""), Ok(Zone::Local));
+        assert_eq!(Zone::parse("Local"), Ok(Zone::Local));
+        assert_eq!(Zone::parse(" utc "), Ok(Zone::Utc));
+        assert_eq!(Zone::parse("Z"), Ok(Zone::Utc));
+        assert_eq!(Zone::parse("+02:00"), Ok(offset(7200)));
+        assert_eq!(Zone::parse("-0530"), Ok(offset(-19800)));
+        assert_eq!(Zone::parse("+3"), Ok(offset(10800)));
+        for bad in ["Europe/Kyiv", "+19", "+02:60", "+ab"] {
+            assert_eq!(Zone::parse(bad), Err(format!("Unknown timezone '{}'; use local, UTC or an offset such as +02:00", bad)));
+        }
+    }
+
+    #[test]
+    fn generators_format_in_the_given_zone() {
+        assert_eq!(generate("$timestamp", NOW_MS), Some(Ok("1711845000".to_string())));
+        assert_eq!(generate("$isoTimestamp", NOW_MS), Some(Ok("2024-03-31T00:30:00.250Z".to_string())));
+        // Offsets move the date across midnight both ways
+        assert_eq!(generate("$isoTimestamp(+02:00)", NOW_MS), Some(Ok("2024-03-31T02:30:00.250+02:00".to_string())));
+        assert_eq!(generate("$date(%Y-%m-%d %H:%M, -05:00)", NOW_MS), Some(Ok("2024-03-30 19:30".to_string())));
+        // A comma inside the format is not taken for the zone
+        assert_eq!(generate("$date(\"%d %b, %Y\", UTC)", NOW_MS), Some(Ok("31 Mar, 2024".to_string())));
+        assert_eq!(generate("$timestamp", -1500), Some(Ok("-2".to_string())));
+        assert_eq!(generate("$randomInt", NOW_MS), None);
+        assert_eq!(generate("name", NOW_MS), None);
+    }
+
+    #[test]
+    fn bad_formats_and_zones_name_the_problem() {
+        assert_eq!(check_format("%Y-%m-%d"), Ok(()));
+        assert_eq!(check_format("%Y-%Q"), Err("Invalid date format '%Y-%Q': unknown pattern %Q".to_string()));
+        assert_eq!(generate("$date(%Y-%Q)", NOW_MS), Some(Err("Invalid date format '%Y-%Q': unknown pattern %Q".to_string())));
+        assert_eq!(generate("$date()", NOW_MS), Some(Err("$date needs a format such as $date(%Y-%m-%d)".to_string())));
+        assert!(generate("$isoTimestamp(Mars)", NOW_MS).is_some_and(|r| r.is_err()));
+        assert_eq!(display_ms(NOW_MS, "%H:%M", true), "00:30 UTC");
+    }
+}
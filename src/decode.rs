@@ -0,0 +1,66 @@
+use std::io::Read;
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+use flate2::read::GzDecoder;
+use serde_json::Value;
+use crate::transform;
+
+/// The decoded content of an encoded field in a JSON response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedField {
+    /// Whether the base64 payload was gzip data and was decompressed.
+    pub gunzipped: bool,
+    pub bytes: Vec<u8>,
+}
+
+impl DecodedField {
+    /// The content as text: pretty-printed when it is JSON, `None` when it is
+    /// not valid UTF-8.
+    pub fn text(&self) -> Option<String> {
+        let text = std::str::from_utf8(&self.bytes).ok()?;
+        Some(match serde_json::from_str::<Value>(text) {
+            Ok(json) if json.is_object() || json.is_array() => serde_json::to_string_pretty(&json).unwrap_or_default(),
+            _ => text.to_string(),
+        })
+    }
+}
+
+/// Selects the string at `path` (a transform path such as `.data.blob`),
+/// base64-decodes it and gunzips the result when it starts with the gzip
+/// magic bytes.
+pub fn decode_field(body: &Value, path: &str) -> Result<DecodedField, String> {
+    let value = transform::apply(path, body).map_err(|e| e.to_string())?;
+    let encoded = match &value {
+        Value::String(s) => s,
+        Value::Null => return Err("No value at this path".to_string()),
+        other => return Err(format!("Expected a string, found {}", kind(other))),
+    };
+    let compact: String = encoded.chars().filter(|c| !c.is_whitespace()).collect();
+    let unpadded = compact.trim_end_matches('=');
+    let bytes = STANDARD.decode(&compact)
+        .or_else(|_| URL_SAFE.decode(&compact))
+        .or_else(|_| STANDARD_NO_PAD.decode(unpadded))
+        .or_else(|_| URL_SAFE_NO_PAD.decode(unpadded))
+        .map_err(|e| format!("Not base64: {}", e))?;
+
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut out = Vec::new();
+        GzDecoder::new(bytes.as_slice())
+            .read_to_end(&mut out)
+            .map_err(|e| format!("Invalid gzip data: {}", e))?;
+        Ok(DecodedField { gunzipped: true, bytes: out })
+    } else {
+        Ok(DecodedField { gunzipped: false, bytes })
+    }
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
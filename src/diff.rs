@@ -0,0 +1,289 @@
+use serde_json::Value;
+
+/// One line of a line-based diff between an old and a new text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffLine {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+/// Texts with more lines than this on both sides are compared line by line
+/// at equal positions instead, to keep the LCS table small.
+const MAX_LCS_LINES: usize = 2000;
+
+/// Line diff of `old` against `new` using a longest common subsequence.
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    if a.len() > MAX_LCS_LINES && b.len() > MAX_LCS_LINES {
+        return positional_diff(&a, &b);
+    }
+
+    // lcs[i][j] is the LCS length of a[i..] and b[j..]
+    let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+
+    let mut out = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            out.push(DiffLine::Same(a[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            out.push(DiffLine::Removed(a[i].to_string()));
+            i += 1;
+        } else {
+            out.push(DiffLine::Added(b[j].to_string()));
+            j += 1;
+        }
+    }
+    out.extend(a[i..].iter().map(|l| DiffLine::Removed(l.to_string())));
+    out.extend(b[j..].iter().map(|l| DiffLine::Added(l.to_string())));
+    out
+}
+
+fn positional_diff(a: &[&str], b: &[&str]) -> Vec<DiffLine> {
+    let mut out = Vec::new();
+    for i in 0..a.len().max(b.len()) {
+        match (a.get(i), b.get(i)) {
+            (Some(x), Some(y)) if x == y => out.push(DiffLine::Same(x.to_string())),
+            (x, y) => {
+                out.extend(x.map(|l| DiffLine::Removed(l.to_string())));
+                out.extend(y.map(|l| DiffLine::Added(l.to_string())));
+            }
+        }
+    }
+    out
+}
+
+
+/// `text` as a diff without changes, for showing it in the same layout.
+pub fn unchanged(text: &str) -> Vec<DiffLine> {
+    text.lines().map(|l| DiffLine::Same(l.to_string())).collect()
+}
+
+/// A run of diff lines that are either all unchanged or all part of one
+/// change. Changes are numbered in order through `hunk`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub hunk: Option<usize>,
+    pub lines: Vec<DiffLine>,
+}
+
+/// Groups `lines` into unchanged runs and numbered hunks.
+pub fn chunks(lines: Vec<DiffLine>) -> Vec<Chunk> {
+    let mut out: Vec<Chunk> = Vec::new();
+    let mut hunks = 0;
+    for line in lines {
+        let same = matches!(line, DiffLine::Same(_));
+        match out.last_mut() {
+            Some(chunk) if chunk.hunk.is_none() == same => chunk.lines.push(line),
+            _ => {
+                let hunk = (!same).then(|| {
+                    hunks += 1;
+                    hunks - 1
+                });
+                out.push(Chunk { hunk, lines: vec![line] });
+            }
+        }
+    }
+    out
+}
+
+/// `new` with hunk number `hunk` of its diff against `old` put back to the
+/// old lines.
+pub fn revert_hunk(old: &str, new: &str, hunk: usize) -> String {
+    let mut out = Vec::new();
+    for chunk in chunks(diff_lines(old, new)) {
+        let reverting = chunk.hunk == Some(hunk);
+        for line in chunk.lines {
+            match line {
+                DiffLine::Same(text) => out.push(text),
+                DiffLine::Removed(text) if reverting => out.push(text),
+                DiffLine::Added(text) if !reverting => out.push(text),
+                _ => {}
+            }
+        }
+    }
+    out.join("\n")
+}
+
+/// Structural difference between two JSON values.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonDiff {
+    Same(Value),
+    Added(Value),
+    Removed(Value),
+    /// A different scalar, or a different type of value.
+    Changed { old: Value, new: Value },
+    /// An array element found unchanged at another index.
+    Moved { from: usize, value: Value },
+    /// Two objects or two arrays with differences inside, labelled `.key`
+    /// or `[index]`; removed array elements keep their old index.
+    Children(Vec<(String, JsonDiff)>),
+}
+
+/// Arrays whose LCS table would exceed this many cells are compared
+/// position by position.
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+impl JsonDiff {
+    /// Every change with its path, e.g. `.items[2].price`, in document order.
+    pub fn changes(&self) -> Vec<(String, &JsonDiff)> {
+        fn walk<'a>(diff: &'a JsonDiff, path: String, out: &mut Vec<(String, &'a JsonDiff)>) {
+            match diff {
+                JsonDiff::Same(_) => {}
+                JsonDiff::Children(children) => {
+                    for (label, child) in children {
+                        walk(child, format!("{}{}", path, label), out);
+                    }
+                }
+                change => out.push((if path.is_empty() { ".".to_string() } else { path }, change)),
+            }
+        }
+        let mut out = Vec::new();
+        walk(self, String::new(), &mut out);
+        out
+    }
+}
+
+/// Compares `old` with `new` key by key and element by element. Array
+/// elements are matched with a longest common subsequence, so an inserted
+/// element does not show every later one as changed and a reordered one
+/// shows as moved.
+pub fn diff_json(old: &Value, new: &Value) -> JsonDiff {
+    if old == new {
+        return JsonDiff::Same(new.clone());
+    }
+    match (old, new) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut children: Vec<(String, JsonDiff)> = b.iter()
+                .map(|(key, value)| {
+                    let diff = a.get(key).map_or_else(|| JsonDiff::Added(value.clone()), |old| diff_json(old, value));
+                    (format!(".{}", key), diff)
+                })
+                .collect();
+            children.extend(a.iter()
+                .filter(|(key, _)| !b.contains_key(*key))
+                .map(|(key, value)| (format!(".{}", key), JsonDiff::Removed(value.clone()))));
+            JsonDiff::Children(children)
+        }
+        (Value::Array(a), Value::Array(b)) => JsonDiff::Children(diff_arrays(a, b)),
+        _ => JsonDiff::Changed { old: old.clone(), new: new.clone() },
+    }
+}
+
+fn diff_arrays(a: &[Value], b: &[Value]) -> Vec<(String, JsonDiff)> {
+    // Pairs of matched indices, in order
+    let matched: Vec<(usize, usize)> = if a.len().saturating_mul(b.len()) > MAX_LCS_CELLS {
+        (0..a.len().min(b.len())).filter(|&i| a[i] == b[i]).map(|i| (i, i)).collect()
+    } else {
+        let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i][j] = if a[i] == b[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+            }
+        }
+        let mut pairs = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() && j < b.len() {
+            if a[i] == b[j] {
+                pairs.push((i, j));
+                i += 1;
+                j += 1;
+            } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        pairs
+    };
+
+    // Unmatched elements equal to one on the other side were moved
+    let mut old_used: Vec<bool> = vec![false; a.len()];
+    let mut new_used: Vec<Option<JsonDiff>> = vec![None; b.len()];
+    for &(i, j) in &matched {
+        old_used[i] = true;
+        new_used[j] = Some(JsonDiff::Same(b[j].clone()));
+    }
+    for j in 0..b.len() {
+        if new_used[j].is_none()
+            && let Some(i) = (0..a.len()).find(|&i| !old_used[i] && a[i] == b[j])
+        {
+            old_used[i] = true;
+            new_used[j] = Some(JsonDiff::Moved { from: i, value: b[j].clone() });
+        }
+    }
+
+    // What is left between two matches is paired up in order and compared;
+    // the rest was added or removed
+    let mut out = Vec::new();
+    let bounds = matched.iter().copied().chain(std::iter::once((a.len(), b.len())));
+    let (mut i, mut j) = (0, 0);
+    for (next_i, next_j) in bounds {
+        let mut removed = (i..next_i).filter(|&k| !old_used[k]);
+        for k in j..next_j {
+            let diff = match new_used[k].take() {
+                Some(diff) => diff,
+                None => match removed.next() {
+                    Some(old) => diff_json(&a[old], &b[k]),
+                    None => JsonDiff::Added(b[k].clone()),
+                },
+            };
+            out.push((format!("[{}]", k), diff));
+        }
+        out.extend(removed.map(|k| (format!("[{}]", k), JsonDiff::Removed(a[k].clone()))));
+        if let Some(diff) = new_used.get_mut(next_j).and_then(Option::take) {
+            out.push((format!("[{}]", next_j), diff));
+        }
+        (i, j) = (next_i + 1, next_j + 1);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn paths(old: Value, new: Value) -> Vec<(String, JsonDiff)> {
+        diff_json(&old, &new).changes().into_iter().map(|(path, diff)| (path, diff.clone())).collect()
+    }
+
+    #[test]
+    fn reordered_array_elements_show_as_moved() {
+        assert_eq!(paths(json!([1, 2, 3]), json!([3, 1, 2])), [("[0]".to_string(), JsonDiff::Moved { from: 2, value: json!(3) })]);
+        assert_eq!(paths(json!(["a", "b"]), json!(["x", "a", "b"])), [("[0]".to_string(), JsonDiff::Added(json!("x")))]);
+        assert_eq!(paths(json!(["a", "b", "c"]), json!(["a", "c"])), [("[1]".to_string(), JsonDiff::Removed(json!("b")))]);
+    }
+
+    #[test]
+    fn type_changes_replace_the_whole_value() {
+        assert_eq!(paths(json!({"id": 1}), json!({"id": "1"})), [
+            (".id".to_string(), JsonDiff::Changed { old: json!(1), new: json!("1") }),
+        ]);
+        assert_eq!(paths(json!({"tags": ["a"]}), json!({"tags": {"a": true}})), [
+            (".tags".to_string(), JsonDiff::Changed { old: json!(["a"]), new: json!({"a": true}) }),
+        ]);
+        assert_eq!(paths(json!(null), json!(0)), [(".".to_string(), JsonDiff::Changed { old: json!(null), new: json!(0) })]);
+    }
+
+    #[test]
+    fn nested_additions_and_removals_have_full_paths() {
+        let old = json!({"user": {"name": "a", "roles": [{"id": 1}]}, "gone": true});
+        let new = json!({"user": {"name": "a", "roles": [{"id": 1, "scope": "x"}], "email": "a@x"}});
+        assert_eq!(paths(old, new), [
+            (".user.roles[0].scope".to_string(), JsonDiff::Added(json!("x"))),
+            (".user.email".to_string(), JsonDiff::Added(json!("a@x"))),
+            (".gone".to_string(), JsonDiff::Removed(json!(true))),
+        ]);
+        assert!(diff_json(&json!({"a": [1]}), &json!({"a": [1]})).changes().is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use hickory_resolver::TokioAsyncResolver;
+use hickory_resolver::config::{NameServerConfig, NameServerConfigGroup, Protocol, ResolverConfig, ResolverOpts};
+use hickory_resolver::error::{ResolveError, ResolveErrorKind};
+use hickory_resolver::proto::error::ProtoErrorKind;
+use hickory_resolver::proto::op::ResponseCode;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+
+/// Value of the `dns` option that keeps the system resolver.
+pub const SYSTEM: &str = "system";
+
+/// Time a custom DNS server has to answer, per attempt.
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How the host of a request was resolved.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct DnsLookup {
+    pub host: String,
+    /// `system`, or the `ip:port` of the DNS server asked.
+    pub resolver: String,
+    /// Seconds the answer was still valid for; the system resolver does
+    /// not say.
+    pub ttl_s: Option<u64>,
+}
+
+/// Parses a DNS server address, `ip` or `ip:port`; the port defaults to 53.
+pub fn parse_server(value: &str) -> Result<SocketAddr, String> {
+    let value = value.trim();
+    value.parse::<SocketAddr>()
+        .or_else(|_| value.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, 53)))
+        .map_err(|_| format!("DNS server must be '{}' or an IP address with an optional port, e.g. 10.0.0.2:53", SYSTEM))
+}
+
+/// Resolvers by DNS server, shared by all requests so their caches are.
+fn resolvers() -> &'static Mutex<HashMap<SocketAddr, TokioAsyncResolver>> {
+    static RESOLVERS: OnceLock<Mutex<HashMap<SocketAddr, TokioAsyncResolver>>> = OnceLock::new();
+    RESOLVERS.get_or_init(Mutex::default)
+}
+
+fn shared_resolver(server: SocketAddr) -> TokioAsyncResolver {
+    let mut resolvers = resolvers().lock().unwrap_or_else(|e| e.into_inner());
+    resolvers.entry(server)
+        .or_insert_with(|| {
+            let servers = NameServerConfigGroup::from(vec![NameServerConfig::new(server, Protocol::Udp)]);
+            let mut options = ResolverOpts::default();
+            options.timeout = TIMEOUT;
+            options.attempts = 1;
+            TokioAsyncResolver::tokio(ResolverConfig::from_parts(None, Vec::new(), servers), options)
+        })
+        .clone()
+}
+
+/// Forgets the answers cached for every custom DNS server. Returns how many
+/// servers had a resolver; the system resolver keeps its own cache.
+pub fn flush_cache() -> usize {
+    let resolvers = resolvers().lock().unwrap_or_else(|e| e.into_inner());
+    for resolver in resolvers.values() {
+        resolver.clear_cache();
+    }
+    resolvers.len()
+}
+
+/// Why a custom DNS server gave no address.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DnsFailure {
+    /// The name does not exist (NXDOMAIN).
+    NoSuchName,
+    /// The name exists but has no A or AAAA records.
+    NoAddress,
+    Timeout,
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DnsError {
+    pub server: SocketAddr,
+    pub host: String,
+    pub failure: DnsFailure,
+}
+
+impl std::fmt::Display for DnsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.failure {
+            DnsFailure::NoSuchName => write!(f, "DNS server {} says {} does not exist (NXDOMAIN)", self.server, self.host),
+            DnsFailure::NoAddress => write!(f, "DNS server {} has no address records for {}", self.server, self.host),
+            DnsFailure::Timeout => write!(f, "DNS server {} did not answer for {} within {} s", self.server, self.host, TIMEOUT.as_secs()),
+            DnsFailure::Other(e) => write!(f, "DNS server {} failed to resolve {}: {}", self.server, self.host, e),
+        }
+    }
+}
+
+impl std::error::Error for DnsError {}
+
+fn failure(e: &ResolveError) -> DnsFailure {
+    match e.kind() {
+        ResolveErrorKind::NoRecordsFound { response_code: ResponseCode::NXDomain, .. } => DnsFailure::NoSuchName,
+        ResolveErrorKind::NoRecordsFound { .. } => DnsFailure::NoAddress,
+        ResolveErrorKind::Timeout => DnsFailure::Timeout,
+        ResolveErrorKind::Proto(proto) if matches!(proto.kind(), ProtoErrorKind::Timeout) => DnsFailure::Timeout,
+        _ => DnsFailure::Other(e.to_string()),
+    }
+}
+
+/// Resolves the hosts of one request through `server`, noting the answers
+/// so the response can tell where its address came from.
+pub struct ServerResolver {
+    server: SocketAddr,
+    resolver: TokioAsyncResolver,
+    lookups: Arc<Mutex<Vec<DnsLookup>>>,
+}
+
+impl ServerResolver {
+    pub fn new(server: SocketAddr) -> Self {
+        ServerResolver { server, resolver: shared_resolver(server), lookups: Arc::default() }
+    }
+
+    /// The last answer for `host`.
+    pub fn lookup(&self, host: &str) -> Option<DnsLookup> {
+        let lookups = self.lookups.lock().unwrap_or_else(|e| e.into_inner());
+        lookups.iter().rev().find(|l| l.host.eq_ignore_ascii_case(host)).cloned()
+    }
+}
+
+impl Resolve for ServerResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let server = self.server;
+        let resolver = self.resolver.clone();
+        let lookups = self.lookups.clone();
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let answer = resolver.lookup_ip(host.as_str()).await
+                .map_err(|e| DnsError { server, host: host.clone(), failure: failure(&e) })?;
+            let ttl_s = answer.valid_until().saturating_duration_since(Instant::now()).as_secs();
+            lookups.lock().unwrap_or_else(|e| e.into_inner())
+                .push(DnsLookup { host, resolver: server.to_string(), ttl_s: Some(ttl_s) });
+            let addrs: Addrs = Box::new(answer.into_iter().map(|ip| SocketAddr::new(ip, 0)).collect::<Vec<_>>().into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// The DNS error behind a failed send, if resolving was what failed.
+pub fn find_error<'a>(e: &'a (dyn std::error::Error + 'static)) -> Option<&'a DnsError> {
+    let mut source = Some(e);
+    while let Some(error) = source {
+        if let Some(dns) = error.downcast_ref::<DnsError>() {
+            return Some(dns);
+        }
+        source = error.source();
+    }
+    None
+}
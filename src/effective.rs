@@ -0,0 +1,145 @@
+use crate::auth::{Auth, AuthSource};
+use crate::soap;
+use crate::storage::HttpRequest;
+
+/// Where a header that will be sent comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeaderOrigin {
+    /// A row of the request's header list, by index.
+    Request(usize),
+    /// The request's or a folder's auth.
+    Auth(AuthSource),
+    /// The SOAP envelope settings.
+    Soap,
+    /// A request option, by its key.
+    Option(&'static str),
+    /// Added by the HTTP client itself.
+    Automatic,
+}
+
+impl HeaderOrigin {
+    pub fn label(&self) -> String {
+        match self {
+            HeaderOrigin::Request(i) => format!("request row {}", i + 1),
+            HeaderOrigin::Auth(AuthSource::Request) => "request auth".to_string(),
+            HeaderOrigin::Auth(AuthSource::Folder(folder)) => {
+                let name = folder.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned());
+                format!("auth of folder {}", name)
+            }
+            HeaderOrigin::Soap => "SOAP envelope".to_string(),
+            HeaderOrigin::Option(key) => format!("option {}", key),
+            HeaderOrigin::Automatic => "automatic".to_string(),
+        }
+    }
+
+    /// CSS class suffix used to colour the rows of each origin.
+    pub fn class(&self) -> &'static str {
+        match self {
+            HeaderOrigin::Request(_) => "request",
+            HeaderOrigin::Auth(_) => "auth",
+            HeaderOrigin::Soap => "soap",
+            HeaderOrigin::Option(_) => "option",
+            HeaderOrigin::Automatic => "automatic",
+        }
+    }
+}
+
+/// One header the request will have, or would have had if another source
+/// did not win.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EffectiveHeader {
+    pub name: String,
+    pub value: String,
+    pub origin: HeaderOrigin,
+    /// Whether this value is sent; `note` says why not.
+    pub sent: bool,
+    pub note: Option<String>,
+}
+
+/// The headers `req` will be sent with, in the order they are added when
+/// sending: the request's rows, then the SOAP, auth and option headers, then
+/// the ones the client adds itself. `auth` is the request's effective auth
+/// with where it came from, and `jar_cookie` the `Cookie` header the cookie
+/// jar has for its URL. Values are shown as written, placeholders
+/// unexpanded and secrets hidden.
+pub fn effective_headers(req: &HttpRequest, auth: Option<&(Auth, AuthSource)>, jar_cookie: Option<String>) -> Vec<EffectiveHeader> {
+    let mut headers: Vec<EffectiveHeader> = req.headers.iter()
+        .enumerate()
+        .filter(|(_, (k, _))| !k.trim().is_empty())
+        .map(|(i, (k, v))| EffectiveHeader { name: k.clone(), value: v.clone(), origin: HeaderOrigin::Request(i), sent: true, note: None })
+        .collect();
+    let position = |headers: &[EffectiveHeader], name: &str| {
+        headers.iter().position(|h| h.sent && h.name.eq_ignore_ascii_case(name))
+    };
+    // A value that is only added when nothing before it set the header
+    let add_default = |headers: &mut Vec<EffectiveHeader>, name: &str, value: String, origin: HeaderOrigin| {
+        let winner = position(headers, name).map(|i| headers[i].origin.label());
+        headers.push(EffectiveHeader {
+            name: name.to_string(),
+            value,
+            sent: winner.is_none(),
+            note: winner.map(|winner| format!("Not sent: {} sets {} already", winner, name)),
+            origin,
+        });
+    };
+
+    if req.soap.is_some() {
+        let wrapped = soap::wrap(&HttpRequest { headers: Vec::new(), ..req.clone() });
+        for (name, value) in wrapped.headers {
+            add_default(&mut headers, &name, value, HeaderOrigin::Soap);
+        }
+    }
+    if let Some((auth, source)) = auth
+        && let Some((name, value)) = auth.header_preview()
+    {
+        add_default(&mut headers, &name, value, HeaderOrigin::Auth(source.clone()));
+    }
+    let has_body = !req.body.is_empty() || req.soap.is_some();
+    if req.options.expect_continue && has_body {
+        add_default(&mut headers, "Expect", "100-continue".to_string(), HeaderOrigin::Option("expect-continue"));
+    }
+    if let Some(compression) = req.options.compress.filter(|_| has_body) {
+        // The option replaces any Content-Encoding the request sets
+        for header in headers.iter_mut().filter(|h| h.sent && h.name.eq_ignore_ascii_case("content-encoding")) {
+            header.sent = false;
+            header.note = Some("Not sent: replaced by the compress option".to_string());
+        }
+        headers.push(EffectiveHeader {
+            name: "Content-Encoding".to_string(),
+            value: compression.as_str().to_string(),
+            origin: HeaderOrigin::Option("compress"),
+            sent: true,
+            note: None,
+        });
+    }
+    if let Some(cookie) = jar_cookie.filter(|_| req.options.cookie_jar != Some(false)) {
+        // Names only; the values are usually session secrets
+        let names = cookie.split("; ")
+            .map(|pair| format!("{}=•••", pair.split_once('=').map_or(pair, |(name, _)| name)))
+            .collect::<Vec<_>>()
+            .join("; ");
+        add_default(&mut headers, "Cookie", names, HeaderOrigin::Option("cookies"));
+    }
+
+    add_default(&mut headers, "accept", "*/*".to_string(), HeaderOrigin::Automatic);
+    let host = reqwest::Url::parse(&req.url).ok()
+        .and_then(|url| Some(match url.port() {
+            Some(port) => format!("{}:{}", url.host_str()?, port),
+            None => url.host_str()?.to_string(),
+        }))
+        .unwrap_or_else(|| "from the URL".to_string());
+    headers.push(EffectiveHeader { name: "host".to_string(), value: host, origin: HeaderOrigin::Automatic, sent: true, note: None });
+    if has_body {
+        add_default(&mut headers, "content-length", "size of the body as sent".to_string(), HeaderOrigin::Automatic);
+    }
+
+    // Repeated request rows are all sent; say so, since it is often a mistake
+    for i in 0..headers.len() {
+        let HeaderOrigin::Request(_) = headers[i].origin else { continue };
+        let first = headers.iter().position(|h| matches!(h.origin, HeaderOrigin::Request(_)) && h.sent && h.name.eq_ignore_ascii_case(&headers[i].name));
+        if headers[i].sent && first.is_some_and(|first| first < i) {
+            headers[i].note = Some("Also sent: the name repeats an earlier row".to_string());
+        }
+    }
+    headers
+}
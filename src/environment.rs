@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use chrono::Local;
+use crate::body_template;
+use crate::dates;
+use crate::storage::{HttpRequest, RequestOptions, get_base_dir};
+
+/// Directory holding one `<name>.env` or `<name>.json` file per environment.
+pub fn environments_dir() -> PathBuf {
+    get_base_dir().join(".environments")
+}
+
+/// Names of all environments, sorted.
+pub fn list_environments() -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(environments_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "env" || ext == "json"))
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+        .collect();
+    names.sort();
+    // An environment with both files is listed once
+    names.dedup();
+    names
+}
+
+/// Loads the variables of the environment called `name`, from `name.env`
+/// or else `name.json`.
+pub fn load_environment(name: &str) -> Result<HashMap<String, String>, String> {
+    let env_path = environments_dir().join(format!("{}.env", name));
+    let json_path = environments_dir().join(format!("{}.json", name));
+    let path = if !env_path.exists() && json_path.exists() { json_path } else { env_path };
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Cannot read environment '{}' ({}): {}", name, path.display(), e))?;
+    if path.extension().is_some_and(|ext| ext == "json") {
+        parse_json_env(&content).map_err(|e| format!("Cannot read environment '{}' ({}): {}", name, path.display(), e))
+    } else {
+        Ok(parse_env_file(&content))
+    }
+}
+
+/// Parses a JSON object of variables. Strings are taken as they are and
+/// numbers and booleans as their text; nested values are refused, as
+/// placeholders cannot reach into them.
+pub fn parse_json_env(content: &str) -> Result<HashMap<String, String>, String> {
+    let serde_json::Value::Object(map) = serde_json::from_str(content).map_err(|e| e.to_string())? else {
+        return Err("expected a JSON object of variables".to_string());
+    };
+    map.into_iter()
+        .map(|(key, value)| match value {
+            serde_json::Value::String(s) => Ok((key, s)),
+            serde_json::Value::Number(_) | serde_json::Value::Bool(_) => Ok((key, value.to_string())),
+            serde_json::Value::Null => Ok((key, String::new())),
+            _ => Err(format!("'{}' is not a string, number or boolean", key)),
+        })
+        .collect()
+}
+
+/// Parses `KEY=VALUE` lines, ignoring blank lines and `#` comments.
+/// Values may be wrapped in single or double quotes.
+pub fn parse_env_file(content: &str) -> HashMap<String, String> {
+    content.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(k, v)| {
+            let v = v.trim();
+            let unquoted = v.strip_prefix('"').and_then(|v| v.strip_suffix('"'))
+                .or_else(|| v.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                .unwrap_or(v);
+            (k.trim().to_string(), unquoted.to_string())
+        })
+        .collect()
+}
+
+/// Names of the `{{...}}` placeholders in `template`, trimmed, in order.
+pub fn placeholders(template: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else { break };
+        names.push(after[..end].trim());
+        rest = &after[end + 2..];
+    }
+    names
+}
+
+/// Prepares a request right before it is sent: expands `{{...}}`
+/// placeholders and fills unset options from the defaults.
+///
+/// Supported sources:
+/// - `{{name}}` looks `name` up in the active environment. Values may refer to
+///   other variables (`base_url=https://{{host}}`) and are expanded in turn.
+/// - `{{env:NAME}}` reads the OS environment variable `NAME` (unless disabled).
+/// - `{{$timestamp}}`, `{{$isoTimestamp(+02:00)}}` and `{{$date(FORMAT, ZONE)}}`
+///   generate the current time; see [`dates::generate`].
+#[derive(Clone, Default)]
+pub struct Resolver {
+    pub vars: HashMap<String, String>,
+    /// Environment `vars` were loaded from, named in errors; `None` when
+    /// requests are sent without one.
+    pub environment: Option<String>,
+    pub allow_os_env: bool,
+    /// Whether requests may run their external command hooks.
+    pub allow_hooks: bool,
+    /// Set when hooks and OS environment access are off because the
+    /// workspace is not trusted, so errors can say so.
+    pub untrusted: bool,
+    pub defaults: RequestOptions,
+}
+
+impl Resolver {
+    /// Returns a copy of `req` with all placeholders expanded, or an error
+    /// naming every placeholder that could not be resolved. Sending uses
+    /// this, so a request never goes out with literal braces in it.
+    pub fn resolve_request(&self, req: &HttpRequest) -> Result<HttpRequest, String> {
+        let (resolved, problems) = self.expand_request(req)?;
+        let errors = self.describe(problems);
+        if errors.is_empty() {
+            Ok(resolved)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    /// Like `resolve_request`, but placeholders that cannot be resolved are
+    /// left as written and reported second. For showing or copying a
+    /// request, where the user sees what is left over.
+    pub fn substitute_request(&self, req: &HttpRequest) -> Result<(HttpRequest, Vec<String>), String> {
+        let (resolved, problems) = self.expand_request(req)?;
+        Ok((resolved, self.describe(problems)))
+    }
+
+    fn expand_request(&self, req: &HttpRequest) -> Result<(HttpRequest, Problems), String> {
+        // The envelope may hold placeholders too, so wrap before expanding
+        let req = &crate::soap::wrap(req);
+        let mut problems = Problems::default();
+        let mut resolved = req.clone();
+        resolved.url = self.expand(&req.url, &mut Vec::new(), &mut problems);
+        resolved.headers = req.headers.iter()
+            .map(|(k, v)| (self.expand(k, &mut Vec::new(), &mut problems), self.expand(v, &mut Vec::new(), &mut problems)))
+            .collect();
+        resolved.options = req.options.inherit(&self.defaults);
+        resolved.body = if resolved.options.body_template {
+            let max_bytes = body_template::limit_bytes(resolved.options.template_max_mb);
+            let body = body_template::expand(&req.body, max_bytes).map_err(|e| e.to_string())?;
+            self.expand(&body, &mut Vec::new(), &mut problems)
+        } else {
+            self.expand(&req.body, &mut Vec::new(), &mut problems)
+        };
+        if let Some(auth) = &req.options.auth {
+            auth.map_values(|value| self.expand(value, &mut Vec::new(), &mut problems)).apply(&mut resolved);
+        }
+        Ok((resolved, problems))
+    }
+
+    /// One line per kind of problem, naming the placeholders involved.
+    fn describe(&self, problems: Problems) -> Vec<String> {
+        let mut errors = Vec::new();
+        if !problems.cycles.is_empty() {
+            errors.push(format!("Circular variable references: {}", problems.cycles.join("; ")));
+        }
+        if !problems.unresolved.is_empty() {
+            let place = match &self.environment {
+                Some(environment) => format!("not defined in environment {}", environment),
+                None => "no environment is selected".to_string(),
+            };
+            errors.push(format!("Unresolved variables: {} ({})", problems.unresolved.join(", "), place));
+        }
+        errors.extend(problems.invalid);
+        errors
+    }
+
+    /// Expands the placeholders in `template`. Environment values may contain
+    /// placeholders themselves; `stack` holds the variables currently being
+    /// expanded so that a variable reaching itself is reported instead of looping.
+    fn expand(&self, template: &str, stack: &mut Vec<String>, problems: &mut Problems) -> String {
+        let mut out = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let Some(end) = after.find("}}") else {
+                rest = &rest[start..];
+                break;
+            };
+            let placeholder = &rest[start..start + end + 4];
+            let name = after[..end].trim();
+            match self.lookup(name) {
+                Lookup::Value(value) => out.push_str(&value),
+                Lookup::Template(value) => {
+                    if let Some(pos) = stack.iter().position(|n| n == name) {
+                        let mut chain = stack[pos..].to_vec();
+                        chain.push(name.to_string());
+                        problems.cycle(chain.join(" -> "));
+                        out.push_str(placeholder);
+                    } else {
+                        stack.push(name.to_string());
+                        out.push_str(&self.expand(&value, stack, problems));
+                        stack.pop();
+                    }
+                }
+                Lookup::Missing(name) => {
+                    problems.missing(name);
+                    out.push_str(placeholder);
+                }
+                Lookup::Invalid(e) => {
+                    problems.invalid(format!("{}: {}", placeholder, e));
+                    out.push_str(placeholder);
+                }
+            }
+            rest = &after[end + 2..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    fn lookup(&self, name: &str) -> Lookup {
+        if name.starts_with('$')
+            && let Some(generated) = dates::generate(name, Local::now().timestamp_millis())
+        {
+            return generated.map_or_else(Lookup::Invalid, Lookup::Value);
+        }
+        match name.strip_prefix("env:") {
+            Some(var) if self.allow_os_env => match std::env::var(var.trim()) {
+                Ok(value) => Lookup::Value(value),
+                Err(_) => Lookup::Missing(name.to_string()),
+            },
+            Some(_) if self.untrusted => Lookup::Missing(format!("{} (blocked: the workspace is not trusted)", name)),
+            Some(_) => Lookup::Missing(format!("{} (OS environment access is disabled)", name)),
+            None => match self.vars.get(name) {
+                Some(value) => Lookup::Template(value.clone()),
+                None => Lookup::Missing(name.to_string()),
+            },
+        }
+    }
+}
+
+#[derive(Default)]
+struct Problems {
+    unresolved: Vec<String>,
+    cycles: Vec<String>,
+    /// Generator placeholders with bad arguments, with the reason.
+    invalid: Vec<String>,
+}
+
+impl Problems {
+    fn missing(&mut self, name: String) {
+        if !self.unresolved.contains(&name) {
+            self.unresolved.push(name);
+        }
+    }
+
+    fn invalid(&mut self, problem: String) {
+        if !self.invalid.contains(&problem) {
+            self.invalid.push(problem);
+        }
+    }
+
+    fn cycle(&mut self, chain: String) {
+        if !self.cycles.contains(&chain) {
+            self.cycles.push(chain);
+        }
+    }
+}
+
+enum Lookup {
+    Value(String),
+    /// An environment value, which may itself contain placeholders.
+    Template(String),
+    Missing(String),
+    /// A generator such as `$date(...)` whose arguments are wrong.
+    Invalid(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolver() -> Resolver {
+        Resolver {
+            vars: HashMap::from([("base".to_string(), "https://example.com".to_string())]),
+            environment: Some("dev".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn request(url: &str) -> HttpRequest {
+        HttpRequest { url: url.to_string(), ..HttpRequest::new() }
+    }
+
+    #[test]
+    fn sending_refuses_unresolved_placeholders() {
+        let req = request("{{base}}/users/{{id}}");
+        assert_eq!(resolver().resolve_request(&req).unwrap_err(), "Unresolved variables: id (not defined in environment dev)");
+        assert_eq!(resolver().resolve_request(&request("{{base}}/users")).unwrap().url, "https://example.com/users");
+    }
+
+    #[test]
+    fn substituting_leaves_unresolved_placeholders_and_reports_them() {
+        let (req, problems) = resolver().substitute_request(&request("{{base}}/users/{{id}}")).unwrap();
+        assert_eq!(req.url, "https://example.com/users/{{id}}");
+        assert_eq!(problems, vec!["Unresolved variables: id (not defined in environment dev)"]);
+    }
+}
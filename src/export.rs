@@ -0,0 +1,350 @@
+use serde_json::Value;
+use crate::client::HttpResponse;
+use crate::storage::HttpRequest;
+
+/// Flattens a JSON array of objects into rows of `(column, cell)` pairs,
+/// keeping the order of the fields. Nested objects become dotted column
+/// names (`address.city`); arrays and other values are written as their
+/// JSON text.
+pub fn flatten_records(value: &Value) -> Result<Vec<Vec<(String, String)>>, String> {
+    let Value::Array(items) = value else {
+        return Err("Response is not a JSON array".to_string());
+    };
+    items.iter().enumerate().map(|(i, item)| match item {
+        Value::Object(_) => {
+            let mut row = Vec::new();
+            flatten_value("", item, &mut row);
+            Ok(row)
+        }
+        Value::Array(_) => Err(format!("Item {} is an array; arrays of arrays have no column names to export", i)),
+        _ => Err(format!("Item {} is not an object", i)),
+    }).collect()
+}
+
+fn flatten_value(prefix: &str, value: &Value, row: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, child) in map {
+                let name = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten_value(&name, child, row);
+            }
+        }
+        Value::Null => row.push((prefix.to_string(), String::new())),
+        Value::String(s) => row.push((prefix.to_string(), s.clone())),
+        other => row.push((prefix.to_string(), other.to_string())),
+    }
+}
+
+/// Union of the columns of all rows, in the order they first appear.
+pub fn csv_columns(rows: &[Vec<(String, String)>]) -> Vec<String> {
+    let mut columns: Vec<String> = Vec::new();
+    for row in rows {
+        for (column, _) in row {
+            if !columns.contains(column) {
+                columns.push(column.clone());
+            }
+        }
+    }
+    columns
+}
+
+/// Writes `rows` as CSV with the given columns; missing cells are left empty.
+pub fn to_csv(rows: &[Vec<(String, String)>], columns: &[String]) -> String {
+    let mut out = String::new();
+    let header: Vec<String> = columns.iter().map(|c| csv_field(c)).collect();
+    out.push_str(&header.join(","));
+    out.push_str("\r\n");
+    for row in rows {
+        let cells: Vec<String> = columns.iter()
+            .map(|column| {
+                row.iter().find(|(c, _)| c == column).map(|(_, v)| csv_field(v)).unwrap_or_default()
+            })
+            .collect();
+        out.push_str(&cells.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// A `curl` command that sends `req` as written, placeholders included.
+/// GET and HEAD requests leave their body out, as curl would otherwise
+/// turn them into a POST-like upload.
+pub fn to_curl(req: &HttpRequest) -> String {
+    let method = req.method.to_uppercase();
+    let mut args = vec!["curl".to_string()];
+    // -X HEAD makes curl wait for a body that never comes; -I asks for HEAD
+    if method == "HEAD" {
+        args.push("-I".to_string());
+    } else if method != "GET" {
+        args.push(format!("-X {}", shell_quote(&method)));
+    }
+    if req.options.insecure_tls == Some(true) {
+        args.push("-k".to_string());
+    }
+    for (name, value) in req.headers.iter().filter(|(k, _)| !k.trim().is_empty()) {
+        args.push(format!("-H {}", shell_quote(&format!("{}: {}", name.trim(), value))));
+    }
+    if !req.body.is_empty() && method != "GET" && method != "HEAD" {
+        // --data-raw does not read a file for a body starting with @
+        args.push(format!("--data-raw {}", shell_quote(&req.body)));
+    }
+    args.push(shell_quote(&req.url));
+    args.join(" \\\n  ")
+}
+
+/// The HTTP/1.1 message raw mode starts from for `req`: its request line,
+/// a `Host` header unless it sets one, its headers, a `Content-Length` for
+/// the body unless it sets one, and `Connection: close` so the response
+/// ends with the connection. Lines end in `\n`; raw mode decides whether
+/// they go out as CRLF.
+pub fn to_raw_message(req: &HttpRequest) -> String {
+    let url = reqwest::Url::parse(&req.url).ok();
+    let target = url.as_ref().map_or_else(|| req.url.clone(), |url| match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    });
+    let has = |name: &str| req.headers.iter().any(|(k, _)| k.trim().eq_ignore_ascii_case(name));
+    let mut lines = vec![format!("{} {} HTTP/1.1", req.method.to_uppercase(), target)];
+    if !has("host")
+        && let Some(url) = &url
+        && let Some(host) = url.host_str()
+    {
+        lines.push(match url.port() {
+            Some(port) => format!("Host: {}:{}", host, port),
+            None => format!("Host: {}", host),
+        });
+    }
+    lines.extend(req.headers.iter().filter(|(k, _)| !k.trim().is_empty()).map(|(k, v)| format!("{}: {}", k.trim(), v)));
+    if let Some(length) = raw_content_length(req) {
+        lines.push(format!("Content-Length: {}", length));
+    }
+    if !has("connection") {
+        lines.push("Connection: close".to_string());
+    }
+    format!("{}\n\n{}", lines.join("\n"), req.body)
+}
+
+/// The `Content-Length` `to_raw_message` adds: the body's length, unless
+/// there is no body or the request frames it itself.
+pub fn raw_content_length(req: &HttpRequest) -> Option<usize> {
+    let has = |name: &str| req.headers.iter().any(|(k, _)| k.trim().eq_ignore_ascii_case(name));
+    (!req.body.is_empty() && !has("content-length") && !has("transfer-encoding")).then_some(req.body.len())
+}
+
+/// `value` quoted for a POSIX shell. Text with line breaks or other control
+/// characters uses `$'...'` so they survive copying as escapes.
+fn shell_quote(value: &str) -> String {
+    if value.chars().any(char::is_control) {
+        let mut quoted = String::from("$'");
+        for c in value.chars() {
+            match c {
+                '\\' => quoted.push_str("\\\\"),
+                '\'' => quoted.push_str("\\'"),
+                '\n' => quoted.push_str("\\n"),
+                '\r' => quoted.push_str("\\r"),
+                '\t' => quoted.push_str("\\t"),
+                c if c.is_control() => quoted.push_str(&format!("\\x{:02x}", c as u32)),
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('\'');
+        quoted
+    } else if !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c)) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// A Markdown report of a request and, when there is one, its response.
+/// Placeholders are left unresolved so secrets from environments stay out.
+pub fn to_markdown(req: &HttpRequest, response: Option<&Result<HttpResponse, String>>, file_name: Option<&str>) -> String {
+    let title = req.name.as_deref().or(file_name).unwrap_or("Request");
+    let mut md = format!("# {}\n\n## Request\n\n`{} {}`\n", title, req.method, req.url);
+    let headers: Vec<(String, String)> = req.headers.iter().filter(|(k, _)| !k.is_empty()).cloned().collect();
+    if !headers.is_empty() {
+        md.push('\n');
+        md.push_str(&markdown_headers(&headers));
+    }
+    if !req.body.is_empty() {
+        let content_type = header_value(&req.headers, "content-type");
+        md.push('\n');
+        md.push_str(&fenced(&req.body, fence_language(content_type, &req.body)));
+    }
+
+    match response {
+        Some(Ok(res)) => {
+            md.push_str(&format!("\n## Response\n\n**{}** in {} ms\n", res.status_text, res.elapsed_ms));
+            if !res.headers.is_empty() {
+                md.push('\n');
+                md.push_str(&markdown_headers(&res.headers));
+            }
+            if !res.body.is_empty() {
+                let content_type = header_value(&res.headers, "content-type");
+                md.push('\n');
+                md.push_str(&fenced(&res.body, fence_language(content_type, &res.body)));
+            }
+        }
+        Some(Err(e)) => md.push_str(&format!("\n## Response\n\nThe request failed:\n\n{}", fenced(e, ""))),
+        None => {}
+    }
+    md
+}
+
+fn header_value<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+}
+
+fn markdown_headers(headers: &[(String, String)]) -> String {
+    let mut table = String::from("| Header | Value |\n| --- | --- |\n");
+    for (k, v) in headers {
+        table.push_str(&format!("| {} | {} |\n", markdown_cell(k), markdown_cell(v)));
+    }
+    table
+}
+
+fn markdown_cell(s: &str) -> String {
+    s.replace('|', "\\|").replace('`', "\\`")
+}
+
+/// Info string for a fenced block, from the content type or the body itself.
+fn fence_language(content_type: Option<&str>, body: &str) -> &'static str {
+    let mime = content_type.unwrap_or("").split(';').next().unwrap_or("").trim().to_lowercase();
+    if mime.ends_with("json") || mime.ends_with("+json") {
+        "json"
+    } else if mime.ends_with("xml") || mime.ends_with("+xml") {
+        "xml"
+    } else if mime == "text/html" {
+        "html"
+    } else if mime.is_empty() && serde_json::from_str::<Value>(body).is_ok() {
+        "json"
+    } else {
+        ""
+    }
+}
+
+/// A fenced code block whose fence is longer than any backtick run in `text`.
+fn fenced(text: &str, language: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{}{}\n{}\n{}\n", fence, language, text.trim_end_matches('\n'), fence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, url: &str) -> HttpRequest {
+        let mut req = HttpRequest::new();
+        req.method = method.to_string();
+        req.url = url.to_string();
+        req
+    }
+
+    #[test]
+    fn curl_quotes_headers_and_body() {
+        let mut req = request("post", "https://example.com/items?a=1&b=2");
+        req.headers.push(("Content-Type".to_string(), "application/json".to_string()));
+        req.headers.push(("X-Note".to_string(), "it's".to_string()));
+        req.body = "{\"a\": 1}".to_string();
+        assert_eq!(to_curl(&req), [
+            "curl",
+            "-X POST",
+            "-H 'Content-Type: application/json'",
+            "-H 'X-Note: it'\\''s'",
+            "--data-raw '{\"a\": 1}'",
+            "'https://example.com/items?a=1&b=2'",
+        ].join(" \\\n  "));
+    }
+
+    #[test]
+    fn curl_escapes_line_breaks() {
+        let mut req = request("PUT", "https://example.com");
+        req.body = "line 1\nline 2\t'x'".to_string();
+        assert!(to_curl(&req).contains("--data-raw $'line 1\\nline 2\\t\\'x\\''"));
+    }
+
+    #[test]
+    fn curl_sends_head_with_dash_i() {
+        let mut req = request("HEAD", "https://example.com");
+        req.body = "ignored".to_string();
+        assert_eq!(to_curl(&req), "curl \\\n  -I \\\n  https://example.com");
+    }
+
+    #[test]
+    fn curl_leaves_get_without_method_or_body() {
+        let mut req = request("GET", "https://example.com");
+        req.body = "ignored".to_string();
+        assert_eq!(to_curl(&req), "curl \\\n  https://example.com");
+    }
+
+    #[test]
+    fn curl_copies_written_and_resolved_forms() {
+        let mut req = request("HEAD", "{{base}}/health");
+        req.headers.push(("Authorization".to_string(), "Bearer {{token}}".to_string()));
+        let written = to_curl(&req);
+        assert_eq!(written, "curl \\\n  -I \\\n  -H 'Authorization: Bearer {{token}}' \\\n  '{{base}}/health'");
+
+        let resolver = crate::environment::Resolver {
+            vars: [("base", "https://example.com"), ("token", "s3cret")]
+                .into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            ..Default::default()
+        };
+        let resolved = to_curl(&resolver.resolve_request(&req).unwrap());
+        assert_eq!(resolved, "curl \\\n  -I \\\n  -H 'Authorization: Bearer s3cret' \\\n  https://example.com/health");
+    }
+
+    fn cells(row: &[(String, String)]) -> Vec<(&str, &str)> {
+        row.iter().map(|(c, v)| (c.as_str(), v.as_str())).collect()
+    }
+
+    #[test]
+    fn flatten_uses_dotted_columns_for_nested_objects() {
+        let rows = flatten_records(&serde_json::json!([
+            {"id": 1, "user": {"name": "Ann", "address": {"city": "Kyiv"}}, "tags": ["a", "b"], "note": null, "ok": true, "extra": {}},
+        ])).unwrap();
+        assert_eq!(cells(&rows[0]), [
+            ("id", "1"),
+            ("user.name", "Ann"),
+            ("user.address.city", "Kyiv"),
+            ("tags", "[\"a\",\"b\"]"),
+            ("note", ""),
+            ("ok", "true"),
+            ("extra", "{}"),
+        ]);
+    }
+
+    #[test]
+    fn flatten_rejects_what_has_no_columns() {
+        assert_eq!(
+            flatten_records(&serde_json::json!([{"a": 1}, [1, 2]])).unwrap_err(),
+            "Item 1 is an array; arrays of arrays have no column names to export",
+        );
+        assert_eq!(flatten_records(&serde_json::json!([{"a": 1}, "text"])).unwrap_err(), "Item 1 is not an object");
+        assert_eq!(flatten_records(&serde_json::json!({"a": 1})).unwrap_err(), "Response is not a JSON array");
+        assert_eq!(flatten_records(&serde_json::json!([])).unwrap(), Vec::<Vec<(String, String)>>::new());
+    }
+
+    #[test]
+    fn csv_appends_later_columns_and_quotes_cells() {
+        let rows = flatten_records(&serde_json::json!([
+            {"id": 1, "name": "Smith, \"Jo\""},
+            {"name": "Multi\nline", "id": "2", "city": "Lviv"},
+            {"id": 3.5},
+        ])).unwrap();
+        let columns = csv_columns(&rows);
+        assert_eq!(columns, ["id", "name", "city"]);
+        assert_eq!(
+            to_csv(&rows, &columns),
+            "id,name,city\r\n1,\"Smith, \"\"Jo\"\"\",\r\n2,\"Multi\nline\",Lviv\r\n3.5,,\r\n",
+        );
+    }
+}
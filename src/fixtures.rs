@@ -0,0 +1,164 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::client::HttpResponse;
+use crate::share;
+use crate::storage::HttpRequest;
+
+/// Version of the fixture files, written into both as `schema`. Fields are
+/// only ever added, so readers should ignore ones they do not know.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A header as written in fixtures: `{"name": "...", "value": "..."}`.
+/// Kept as a list in the order sent or received, repeats included.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FixtureHeader {
+    pub name: String,
+    pub value: String,
+}
+
+/// `request.json` of a recorded fixture: the request as written in the
+/// editor, so `{{placeholders}}` are kept unresolved. Secret headers and
+/// passwords become placeholders too.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FixtureRequest {
+    pub schema: u32,
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<FixtureHeader>,
+    pub body: String,
+}
+
+/// `response.json` of a recorded fixture. The body is the decoded text.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FixtureResponse {
+    pub schema: u32,
+    pub status: u16,
+    pub headers: Vec<FixtureHeader>,
+    pub body: String,
+}
+
+fn headers(list: &[(String, String)]) -> Vec<FixtureHeader> {
+    list.iter()
+        .filter(|(name, _)| !name.is_empty())
+        .map(|(name, value)| FixtureHeader { name: name.clone(), value: value.clone() })
+        .collect()
+}
+
+impl FixtureRequest {
+    pub fn new(req: &HttpRequest) -> Self {
+        let req = share::without_secrets(req);
+        FixtureRequest {
+            schema: SCHEMA_VERSION,
+            method: req.method,
+            url: req.url,
+            headers: headers(&req.headers),
+            body: req.body,
+        }
+    }
+}
+
+impl FixtureResponse {
+    pub fn new(res: &HttpResponse) -> Self {
+        FixtureResponse { schema: SCHEMA_VERSION, status: res.status, headers: headers(&res.headers), body: res.body.clone() }
+    }
+}
+
+/// `Get users (v2)` -> `get-users-v2`.
+fn slug(title: &str) -> String {
+    let words: Vec<String> = title.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.is_empty() { "request".to_string() } else { words.join("-") }
+}
+
+/// The next free `slug-NNN` folder in `dir`: one past the highest number
+/// recorded for `slug`, so deleted fixtures do not get their names reused.
+fn next_folder(dir: &Path, slug: &str) -> PathBuf {
+    let prefix = format!("{}-", slug);
+    let highest = fs::read_dir(dir).into_iter().flatten().flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.strip_prefix(&prefix)?.parse::<u32>().ok())
+        .max()
+        .unwrap_or(0);
+    dir.join(format!("{}-{:03}", slug, highest + 1))
+}
+
+/// Writes `request.json` and `response.json` for an exchange into a new
+/// `title-NNN` folder of `dir`, and returns that folder.
+pub fn record(dir: &Path, title: &str, req: &HttpRequest, res: &HttpResponse) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let folder = next_folder(dir, &slug(title));
+    fs::create_dir(&folder)?;
+    let write = |name: &str, json: serde_json::Result<String>| {
+        fs::write(folder.join(name), json.map_err(io::Error::other)? + "\n")
+    };
+    write("request.json", serde_json::to_string_pretty(&FixtureRequest::new(req)))?;
+    write("response.json", serde_json::to_string_pretty(&FixtureResponse::new(res)))?;
+    Ok(folder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn exchange() -> (HttpRequest, HttpResponse) {
+        let mut req = HttpRequest::new();
+        req.method = "POST".to_string();
+        req.url = "{{base}}/users".to_string();
+        req.headers = vec![
+            ("Authorization".to_string(), "Bearer s3cret".to_string()),
+            ("X-Trace".to_string(), "{{trace}}".to_string()),
+        ];
+        req.body = "{\"name\": \"{{name}}\"}".to_string();
+        let res = serde_json::from_value(json!({
+            "status": 201,
+            "status_text": "201 Created",
+            "headers": [["Set-Cookie", "a=1"], ["Set-Cookie", "b=2"]],
+            "body": "{\"id\": 7}",
+            "warnings": [],
+        })).unwrap();
+        (req, res)
+    }
+
+    #[test]
+    fn fixtures_keep_placeholders_and_redact_secrets() {
+        let (req, res) = exchange();
+        let request = FixtureRequest::new(&req);
+        assert_eq!(serde_json::to_value(&request).unwrap(), json!({
+            "schema": 1,
+            "method": "POST",
+            "url": "{{base}}/users",
+            "headers": [
+                {"name": "Authorization", "value": "{{authorization}}"},
+                {"name": "X-Trace", "value": "{{trace}}"},
+            ],
+            "body": "{\"name\": \"{{name}}\"}",
+        }));
+        let response = FixtureResponse::new(&res);
+        assert_eq!(serde_json::to_value(&response).unwrap(), json!({
+            "schema": 1,
+            "status": 201,
+            "headers": [{"name": "Set-Cookie", "value": "a=1"}, {"name": "Set-Cookie", "value": "b=2"}],
+            "body": "{\"id\": 7}",
+        }));
+    }
+
+    #[test]
+    fn recorded_files_read_back_in_numbered_folders() {
+        let dir = std::env::temp_dir().join(format!("requester-fixtures-{}", uuid::Uuid::new_v4()));
+        let (req, res) = exchange();
+        let first = record(&dir, "Create user (v2)", &req, &res).unwrap();
+        let second = record(&dir, "Create user (v2)", &req, &res).unwrap();
+        assert_eq!(first, dir.join("create-user-v2-001"));
+        assert_eq!(second, dir.join("create-user-v2-002"));
+        let read = |name: &str| fs::read_to_string(first.join(name)).unwrap();
+        assert_eq!(serde_json::from_str::<FixtureRequest>(&read("request.json")).unwrap(), FixtureRequest::new(&req));
+        assert_eq!(serde_json::from_str::<FixtureResponse>(&read("response.json")).unwrap(), FixtureResponse::new(&res));
+        fs::remove_dir_all(&first).unwrap();
+        assert_eq!(record(&dir, "", &req, &res).unwrap(), dir.join("request-001"));
+        assert_eq!(record(&dir, "Create user (v2)", &req, &res).unwrap(), dir.join("create-user-v2-003"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,209 @@
+use std::time::Instant;
+use serde_json::Value;
+use crate::client::execute_request;
+use crate::export;
+use crate::runner::CancelFlag;
+use crate::storage::HttpRequest;
+
+/// Variants sent when no other limit is chosen.
+pub const DEFAULT_MAX_VARIANTS: usize = 100;
+/// Upper bound on the limit, so a typo cannot start a flood of requests.
+pub const MAX_VARIANTS: usize = 10_000;
+
+/// Which part of the request the values are put into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldKind {
+    Header,
+    Query,
+    /// A path such as `.user.name` or `.items[0].id` in a JSON body.
+    Body,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzField {
+    pub kind: FieldKind,
+    pub name: String,
+}
+
+impl FuzzField {
+    /// `req` with this field set to `value`. Headers and query parameters
+    /// that are missing are added. In the body, values that are JSON numbers
+    /// are written as numbers and everything else as a string.
+    pub fn apply(&self, req: &HttpRequest, value: &str) -> Result<HttpRequest, String> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("Choose the field to vary".to_string());
+        }
+        let mut variant = req.clone();
+        match self.kind {
+            FieldKind::Header => {
+                variant.headers.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+                variant.headers.push((name.to_string(), value.to_string()));
+            }
+            FieldKind::Query => {
+                let mut url = reqwest::Url::parse(&req.url).map_err(|e| format!("Invalid URL: {}", e))?;
+                let mut pairs: Vec<(String, String)> = url.query_pairs()
+                    .map(|(k, v)| (k.into_owned(), v.into_owned()))
+                    .collect();
+                match pairs.iter_mut().find(|(k, _)| k == name) {
+                    Some((_, v)) => *v = value.to_string(),
+                    None => pairs.push((name.to_string(), value.to_string())),
+                }
+                url.query_pairs_mut().clear().extend_pairs(pairs);
+                variant.url = url.into();
+            }
+            FieldKind::Body => {
+                let mut body: Value = serde_json::from_str(&req.body).map_err(|_| "The body is not JSON".to_string())?;
+                let new = match value.parse::<serde_json::Number>() {
+                    Ok(number) => Value::Number(number),
+                    Err(_) => Value::String(value.to_string()),
+                };
+                *path_target(&mut body, name)? = new;
+                variant.body = serde_json::to_string_pretty(&body).unwrap_or_default();
+            }
+        }
+        Ok(variant)
+    }
+}
+
+/// The value at `path` in `body`. A missing key at the end is created; a
+/// missing index is an error.
+fn path_target<'a>(body: &'a mut Value, path: &str) -> Result<&'a mut Value, String> {
+    let mut target = body;
+    for segment in path.trim_start_matches('.').split('.') {
+        let (key, indexes) = segment.split_once('[').unwrap_or((segment, ""));
+        if !key.is_empty() {
+            target = target.as_object_mut()
+                .ok_or_else(|| format!("{}: '{}' is not inside an object", path, key))?
+                .entry(key.to_string())
+                .or_insert(Value::Null);
+        }
+        for index in indexes.split('[').filter(|i| !i.is_empty()) {
+            let index: usize = index.trim_end_matches(']').parse()
+                .map_err(|_| format!("{}: invalid index [{}", path, index))?;
+            target = target.as_array_mut()
+                .and_then(|items| items.get_mut(index))
+                .ok_or_else(|| format!("{}: no item [{}]", path, index))?;
+        }
+    }
+    Ok(target)
+}
+
+/// Built-in lists of awkward values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueSet {
+    Empty,
+    Long,
+    Quotes,
+    Unicode,
+    Negative,
+}
+
+impl ValueSet {
+    pub const ALL: [ValueSet; 5] = [ValueSet::Empty, ValueSet::Long, ValueSet::Quotes, ValueSet::Unicode, ValueSet::Negative];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ValueSet::Empty => "Empty string",
+            ValueSet::Long => "Very long strings",
+            ValueSet::Quotes => "SQL-ish quotes",
+            ValueSet::Unicode => "Unicode",
+            ValueSet::Negative => "Negative numbers",
+        }
+    }
+
+    pub fn values(&self) -> Vec<String> {
+        match self {
+            ValueSet::Empty => vec![String::new()],
+            ValueSet::Long => vec!["a".repeat(1_000), "a".repeat(10_000), "a".repeat(100_000)],
+            ValueSet::Quotes => ["'", "\"", "' OR '1'='1", "\" OR \"1\"=\"1", "'; DROP TABLE users; --", "\\", "`"]
+                .map(String::from)
+                .to_vec(),
+            ValueSet::Unicode => ["héllo", "日本語", "😀", "\u{202e}txt.exe", "\u{0}", "Ω≈ç√∫", "\u{feff}bom"]
+                .map(String::from)
+                .to_vec(),
+            ValueSet::Negative => ["-1", "-0", "-0.5", "-2147483649", "-9223372036854775809", "-1e308"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
+}
+
+/// The values to try: one per line of `custom`, then the chosen sets, without
+/// repeats, capped at `max`.
+pub fn values(custom: &str, sets: &[ValueSet], max: usize) -> Vec<String> {
+    let mut values: Vec<String> = Vec::new();
+    let candidates = custom.lines().map(str::to_string).filter(|line| !line.is_empty())
+        .chain(sets.iter().flat_map(ValueSet::values));
+    for value in candidates {
+        if values.len() >= max.min(MAX_VARIANTS) {
+            break;
+        }
+        if !values.contains(&value) {
+            values.push(value);
+        }
+    }
+    values
+}
+
+/// One variant and how the server answered it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzResult {
+    pub value: String,
+    /// The variant as sent.
+    pub request: HttpRequest,
+    /// Status, or why the request failed.
+    pub outcome: Result<String, String>,
+    pub elapsed_ms: u64,
+}
+
+/// Builds every variant of `req` (already resolved) up front, so a field
+/// that cannot be set fails before anything is sent.
+pub fn variants(req: &HttpRequest, field: &FuzzField, values: &[String]) -> Result<Vec<(String, HttpRequest)>, String> {
+    values.iter().map(|value| Ok((value.clone(), field.apply(req, value)?))).collect()
+}
+
+/// Sends the variants one after another until done or `cancel` is set,
+/// reporting each through `on_result`.
+pub async fn run(variants: Vec<(String, HttpRequest)>, cancel: &CancelFlag, mut on_result: impl FnMut(FuzzResult)) {
+    for (value, request) in variants {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let started = Instant::now();
+        let outcome = execute_request(&request).await.map(|res| res.status_text);
+        on_result(FuzzResult { value, request, outcome, elapsed_ms: started.elapsed().as_millis() as u64 });
+    }
+}
+
+/// The value as shown in the results: quoted and escaped, long ones cut.
+pub fn preview(value: &str) -> String {
+    const SHOWN: usize = 40;
+    let count = value.chars().count();
+    if count <= SHOWN {
+        format!("{:?}", value)
+    } else {
+        let start: String = value.chars().take(SHOWN).collect();
+        format!("{:?}… ({} chars)", start, count)
+    }
+}
+
+/// The results as CSV, one row per variant.
+pub fn to_csv(results: &[FuzzResult]) -> String {
+    let columns: Vec<String> = ["value", "status", "error", "elapsed_ms"].map(String::from).to_vec();
+    let rows: Vec<Vec<(String, String)>> = results.iter()
+        .map(|result| {
+            let (status, error) = match &result.outcome {
+                Ok(status) => (status.clone(), String::new()),
+                Err(e) => (String::new(), e.clone()),
+            };
+            vec![
+                ("value".to_string(), result.value.clone()),
+                ("status".to_string(), status),
+                ("error".to_string(), error),
+                ("elapsed_ms".to_string(), result.elapsed_ms.to_string()),
+            ]
+        })
+        .collect();
+    export::to_csv(&rows, &columns)
+}
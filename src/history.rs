@@ -0,0 +1,145 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use crate::client::HttpResponse;
+use crate::storage::{HttpRequest, IdIndex, get_base_dir};
+
+/// One sent request, stored as a line of `.history/history.jsonl`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HistoryEntry {
+    /// Unix time in milliseconds.
+    pub timestamp_ms: i64,
+    /// The request file it was sent from, if any.
+    pub path: Option<PathBuf>,
+    /// ID of that request file; preferred over `path`, which breaks on rename.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub method: String,
+    /// The URL actually requested, with placeholders resolved.
+    pub url: String,
+    /// The request as it was in the editor, in `.req` format.
+    pub request: String,
+    pub response: Result<HttpResponse, String>,
+}
+
+impl HistoryEntry {
+    pub fn new(path: Option<PathBuf>, request: &HttpRequest, url: &str, response: &Result<HttpResponse, String>) -> Self {
+        HistoryEntry {
+            timestamp_ms: Local::now().timestamp_millis(),
+            path,
+            request_id: request.id.clone(),
+            method: request.method.clone(),
+            url: url.to_string(),
+            request: request.to_http_string(),
+            response: response.clone(),
+        }
+    }
+
+    /// Where the request file is now: found by ID, or the recorded path for
+    /// entries written before IDs existed or whose file is gone.
+    pub fn resolved_path(&self, ids: &IdIndex) -> Option<PathBuf> {
+        self.request_id.as_deref()
+            .and_then(|id| ids.path_of(id))
+            .cloned()
+            .or_else(|| self.path.clone())
+    }
+
+    pub fn status(&self) -> Option<u16> {
+        self.response.as_ref().ok().map(|r| r.status)
+    }
+
+    pub fn local_time(&self) -> Option<DateTime<Local>> {
+        Local.timestamp_millis_opt(self.timestamp_ms).single()
+    }
+
+    fn host(&self) -> &str {
+        let rest = self.url.split_once("://").map_or(self.url.as_str(), |(_, rest)| rest);
+        rest.split(['/', '?', '#']).next().unwrap_or("")
+    }
+}
+
+fn history_file() -> PathBuf {
+    get_base_dir().join(".history").join("history.jsonl")
+}
+
+/// Reads the whole history, oldest first. Lines that cannot be parsed
+/// (e.g. from a crash mid-write) are skipped.
+pub fn load() -> Vec<HistoryEntry> {
+    fs::read_to_string(history_file())
+        .map(|content| content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Appends an entry to the history file; call it off the UI thread.
+pub fn append(entry: &HistoryEntry) -> std::io::Result<()> {
+    crate::workspace::ensure_writable()?;
+    let path = history_file();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut line = serde_json::to_string(entry).map_err(std::io::Error::other)?;
+    line.push('\n');
+    OpenOptions::new().create(true).append(true).open(path)?.write_all(line.as_bytes())
+}
+
+/// Filters for the history view; empty fields match everything.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HistoryQuery {
+    pub method: String,
+    /// First digit of the status (2 for 2xx); 0 matches requests that failed without a response.
+    pub status_class: Option<u16>,
+    pub host: String,
+    /// Inclusive local dates, `YYYY-MM-DD`.
+    pub from: String,
+    pub to: String,
+    /// Case-insensitive substring of the URL.
+    pub text: String,
+}
+
+impl HistoryQuery {
+    pub fn matches(&self, entry: &HistoryEntry) -> bool {
+        if !self.method.is_empty() && !entry.method.eq_ignore_ascii_case(&self.method) {
+            return false;
+        }
+        if let Some(class) = self.status_class {
+            let entry_class = entry.status().map_or(0, |s| s / 100);
+            if entry_class != class {
+                return false;
+            }
+        }
+        if !self.host.is_empty() && !contains_ignore_case(entry.host(), &self.host) {
+            return false;
+        }
+        if !self.text.is_empty() && !contains_ignore_case(&entry.url, &self.text) {
+            return false;
+        }
+        let date = entry.local_time().map(|t| t.date_naive());
+        if let Some(from) = parse_date(&self.from)
+            && date.is_none_or(|d| d < from)
+        {
+            return false;
+        }
+        if let Some(to) = parse_date(&self.to)
+            && date.is_none_or(|d| d > to)
+        {
+            return false;
+        }
+        true
+    }
+
+    /// Matching entries newest first, `page_size` per page, plus the total match count.
+    pub fn run<'a>(&self, entries: &'a [HistoryEntry], page: usize, page_size: usize) -> (Vec<&'a HistoryEntry>, usize) {
+        let matching: Vec<&HistoryEntry> = entries.iter().rev().filter(|e| self.matches(e)).collect();
+        let total = matching.len();
+        (matching.into_iter().skip(page * page_size).take(page_size).collect(), total)
+    }
+}
+
+fn parse_date(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d").ok()
+}
+
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use crate::environment::{Resolver, parse_env_file};
+use crate::shell::split_words;
+use crate::storage::{FolderConfig, HttpRequest, get_base_dir};
+
+/// Time a hook may run when it sets no timeout of its own.
+pub const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+
+/// An external command run before a request is prepared, e.g. to refresh a
+/// token. Lines of its stdout in `KEY=VALUE` form become variables for the
+/// request.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Hook {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+impl Hook {
+    /// Parses a command line such as `aws sso login --profile "dev account"`.
+    pub fn parse(line: &str, timeout_ms: Option<u64>) -> Result<Option<Hook>, String> {
+        let mut words = split_words(line).map_err(|e| format!("Hook: {}", e))?.into_iter();
+        Ok(words.next().map(|command| Hook { command, args: words.collect(), timeout_ms }))
+    }
+
+    /// The hook of a request: its own `hook` option, or else the `hook` of the
+    /// nearest folder above `path` that sets one.
+    pub fn for_request(path: Option<&Path>, req: &HttpRequest) -> Result<Option<Hook>, String> {
+        if let Some(line) = &req.options.hook {
+            return Hook::parse(line, req.options.hook_timeout_ms);
+        }
+        Ok(FolderConfig::nearest(path, |config| config.hook).map(|(_, hook)| hook))
+    }
+
+    pub fn display(&self) -> String {
+        std::iter::once(&self.command).chain(&self.args).cloned().collect::<Vec<_>>().join(" ")
+    }
+
+    /// Runs the command and returns the variables it printed. A non-zero
+    /// exit or running past the timeout is an error carrying its stderr.
+    pub async fn run(&self) -> Result<HashMap<String, String>, String> {
+        let child = tokio::process::Command::new(&self.command)
+            .args(&self.args)
+            .current_dir(get_base_dir())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Cannot start hook `{}`: {}", self.display(), e))?;
+        let timeout = Duration::from_millis(self.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS));
+        let output = tokio::time::timeout(timeout, child.wait_with_output())
+            .await
+            .map_err(|_| format!("Hook `{}` timed out after {} ms", self.display(), timeout.as_millis()))?
+            .map_err(|e| format!("Hook `{}` failed: {}", self.display(), e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Hook `{}` failed ({}):\n{}", self.display(), output.status, stderr.trim_end()));
+        }
+        Ok(parse_env_file(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+/// The resolver to send `req` (stored at `path`, if saved) with: `resolver`
+/// plus the variables printed by the request's hook, which win over the
+/// environment's. Without a hook `resolver` is returned as is.
+pub async fn prepare(resolver: &Resolver, path: Option<&Path>, req: &HttpRequest) -> Result<Resolver, String> {
+    let Some(hook) = Hook::for_request(path, req)? else {
+        return Ok(resolver.clone());
+    };
+    if resolver.untrusted {
+        return Err(format!("The request runs the hook `{}`, which is blocked because the workspace is not trusted", hook.display()));
+    }
+    if !resolver.allow_hooks {
+        return Err(format!("The request runs the hook `{}`; pass --allow-hooks to run it", hook.display()));
+    }
+    let mut prepared = resolver.clone();
+    prepared.vars.extend(hook.run().await?);
+    Ok(prepared)
+}
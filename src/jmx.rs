@@ -0,0 +1,499 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use crate::storage::{self, HttpRequest};
+
+/// What an import wrote and what it had to leave out.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub written: Vec<PathBuf>,
+    pub unsupported: Vec<String>,
+}
+
+/// Converts the HTTP samplers of a JMeter test plan into `.req` files under
+/// `into`, with one folder per thread group or controller.
+///
+/// Header managers apply to the samplers in their scope like in JMeter, and
+/// `${var}` references become `{{var}}` placeholders. Anything else the plan
+/// does (timers, assertions, file uploads, ...) is listed in the report.
+pub fn import_jmx(content: &str, into: &Path) -> Result<ImportReport, String> {
+    let root = parse_xml(content)?;
+    if root.name != "jmeterTestPlan" {
+        return Err(format!("Not a JMeter test plan: root element is <{}>", root.name));
+    }
+    let tree = root.child("hashTree").ok_or("Test plan has no elements")?;
+    let mut importer = Importer { report: ImportReport::default(), used: HashSet::new() };
+    importer.walk(tree, into, &[])?;
+    Ok(importer.report)
+}
+
+struct Importer {
+    report: ImportReport,
+    used: HashSet<PathBuf>,
+}
+
+impl Importer {
+    /// Imports the elements of one `hashTree`. JMeter stores each element's
+    /// children in the `hashTree` that directly follows it.
+    fn walk(&mut self, tree: &Element, folder: &Path, inherited: &[(String, String)]) -> Result<(), String> {
+        let elements: Vec<&Element> = tree.elements().collect();
+        let mut headers = inherited.to_vec();
+        for el in elements.iter().filter(|el| el.name == "HeaderManager" && el.enabled()) {
+            merge_headers(&mut headers, header_manager(el));
+        }
+
+        let mut i = 0;
+        while i < elements.len() {
+            let el = elements[i];
+            let subtree = elements.get(i + 1).filter(|next| next.name == "hashTree").copied();
+            i += if subtree.is_some() { 2 } else { 1 };
+
+            if !el.enabled() {
+                self.unsupported(format!("Skipped disabled {} '{}'", el.name, el.test_name()));
+                continue;
+            }
+            match el.name.as_str() {
+                "HeaderManager" => {}
+                "HTTPSamplerProxy" | "HTTPSampler" => self.sampler(el, subtree, folder, &headers)?,
+                "TestPlan" => {
+                    if let Some(subtree) = subtree {
+                        self.walk(subtree, folder, &headers)?;
+                    }
+                }
+                kind if kind.ends_with("ThreadGroup") || kind.ends_with("Controller") => {
+                    if let Some(subtree) = subtree {
+                        self.walk(subtree, &folder.join(file_name(el.test_name(), "group")), &headers)?;
+                    }
+                }
+                kind => self.unsupported(format!("{} '{}' in {}", kind, el.test_name(), folder.display())),
+            }
+        }
+        Ok(())
+    }
+
+    fn sampler(&mut self, el: &Element, subtree: Option<&Element>, folder: &Path, inherited: &[(String, String)]) -> Result<(), String> {
+        let name = el.test_name();
+        let mut request = HttpRequest::new();
+        request.method = el.prop("HTTPSampler.method").unwrap_or("GET").to_uppercase();
+
+        let path = el.prop("HTTPSampler.path").unwrap_or("");
+        let mut url = if path.starts_with("http://") || path.starts_with("https://") {
+            path.to_string()
+        } else {
+            let port = el.prop("HTTPSampler.port").unwrap_or("");
+            let protocol = match el.prop("HTTPSampler.protocol") {
+                Some(protocol) if !protocol.is_empty() => protocol.to_lowercase(),
+                _ if port == "443" => "https".to_string(),
+                _ => "http".to_string(),
+            };
+            let mut url = format!("{}://{}", protocol, el.prop("HTTPSampler.domain").unwrap_or(""));
+            if !port.is_empty() {
+                url.push(':');
+                url.push_str(port);
+            }
+            if !path.starts_with('/') {
+                url.push('/');
+            }
+            url.push_str(path);
+            url
+        };
+
+        let arguments = el.prop_element("HTTPsampler.Arguments").map(arguments).unwrap_or_default();
+        let mut headers = inherited.to_vec();
+        if el.prop("HTTPSampler.postBodyRaw") == Some("true") {
+            request.body = arguments.iter().map(|(_, value, _)| value.as_str()).collect();
+        } else if !arguments.is_empty() {
+            let encoded: Vec<String> = arguments.iter()
+                .map(|(name, value, encode)| {
+                    let (name, value) = if *encode { (percent_encode(name), percent_encode(value)) } else { (name.clone(), value.clone()) };
+                    format!("{}={}", name, value)
+                })
+                .collect();
+            let encoded = encoded.join("&");
+            if matches!(request.method.as_str(), "POST" | "PUT" | "PATCH") {
+                request.body = encoded;
+                merge_headers(&mut headers, vec![("Content-Type".to_string(), "application/x-www-form-urlencoded".to_string())]);
+            } else {
+                url.push(if url.contains('?') { '&' } else { '?' });
+                url.push_str(&encoded);
+            }
+        }
+        if el.prop_element("HTTPsampler.Files").is_some_and(|files| files.descendants().any(|e| e.name == "elementProp")) {
+            self.unsupported(format!("File uploads of '{}'", name));
+        }
+
+        if el.prop("HTTPSampler.follow_redirects") == Some("false") && el.prop("HTTPSampler.auto_redirects") != Some("true") {
+            request.options.max_redirects = Some(0);
+        }
+        for (prop, key) in [("HTTPSampler.connect_timeout", "connect-timeout"), ("HTTPSampler.response_timeout", "read-timeout")] {
+            if let Some(value) = el.prop(prop).filter(|v| !v.is_empty())
+                && let Err(e) = request.options.set(key, value)
+            {
+                self.unsupported(format!("{} of '{}': {}", key, name, e));
+            }
+        }
+
+        for child in subtree.iter().flat_map(|tree| tree.elements()) {
+            match child.name.as_str() {
+                "hashTree" => {}
+                "HeaderManager" if child.enabled() => merge_headers(&mut headers, header_manager(child)),
+                kind => self.unsupported(format!("{} '{}' of '{}'", kind, child.test_name(), name)),
+            }
+        }
+
+        let mut functions = Vec::new();
+        request.url = convert_variables(&url, &mut functions);
+        request.headers = headers.iter()
+            .map(|(k, v)| (convert_variables(k, &mut functions), convert_variables(v, &mut functions)))
+            .collect();
+        request.body = convert_variables(&request.body, &mut functions);
+        for function in functions {
+            self.unsupported(format!("JMeter function {} in '{}' was left as is", function, name));
+        }
+
+        let path = self.unique_path(folder, name);
+        storage::save_request(&path, &request).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+        self.report.written.push(path);
+        Ok(())
+    }
+
+    fn unique_path(&mut self, folder: &Path, name: &str) -> PathBuf {
+        let base = file_name(name, "request");
+        let mut path = folder.join(format!("{}.req", base));
+        let mut n = 2;
+        while self.used.contains(&path) || path.exists() {
+            path = folder.join(format!("{} ({}).req", base, n));
+            n += 1;
+        }
+        self.used.insert(path.clone());
+        path
+    }
+
+    fn unsupported(&mut self, note: String) {
+        self.report.unsupported.push(note);
+    }
+}
+
+/// `Header.name`/`Header.value` pairs of a HeaderManager.
+fn header_manager(el: &Element) -> Vec<(String, String)> {
+    el.descendants()
+        .filter(|e| e.name == "elementProp")
+        .map(|e| (e.prop("Header.name").unwrap_or("").to_string(), e.prop("Header.value").unwrap_or("").to_string()))
+        .filter(|(name, _)| !name.is_empty())
+        .collect()
+}
+
+/// Adds `headers`, replacing any inherited header with the same name.
+fn merge_headers(into: &mut Vec<(String, String)>, headers: Vec<(String, String)>) {
+    let names: Vec<String> = headers.iter().map(|(k, _)| k.to_lowercase()).collect();
+    into.retain(|(k, _)| !names.contains(&k.to_lowercase()));
+    into.extend(headers);
+}
+
+/// Name, value and whether JMeter would URL-encode them, for each sampler argument.
+fn arguments(el: &Element) -> Vec<(String, String, bool)> {
+    el.descendants()
+        .filter(|e| e.name == "elementProp")
+        .map(|e| (
+            e.prop("Argument.name").unwrap_or("").to_string(),
+            e.prop("Argument.value").unwrap_or("").to_string(),
+            e.prop("HTTPArgument.always_encode") == Some("true"),
+        ))
+        .collect()
+}
+
+/// Rewrites `${var}` as `{{var}}`. JMeter functions (`${__name(...)}`) have
+/// no equivalent; they are kept and collected into `functions`.
+fn convert_variables(s: &str, functions: &mut Vec<String>) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+        let name = &after[..end];
+        if name.starts_with("__") {
+            let function = format!("${{{}}}", name);
+            out.push_str(&function);
+            if !functions.contains(&function) {
+                functions.push(function);
+            }
+        } else {
+            out.push_str(&format!("{{{{{}}}}}", name));
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Percent-encodes everything but unreserved characters, leaving `${...}`
+/// references intact so they can still be converted afterwards.
+fn percent_encode(s: &str) -> String {
+    let mut out = String::new();
+    let mut rest = s;
+    while !rest.is_empty() {
+        if rest.starts_with("${")
+            && let Some(end) = rest.find('}')
+        {
+            out.push_str(&rest[..=end]);
+            rest = &rest[end + 1..];
+            continue;
+        }
+        let c = rest.chars().next().unwrap_or_default();
+        if c.is_ascii_alphanumeric() || "-_.~".contains(c) {
+            out.push(c);
+        } else {
+            let mut buf = [0; 4];
+            for b in c.encode_utf8(&mut buf).bytes() {
+                out.push_str(&format!("%{:02X}", b));
+            }
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// A test element name made safe to use as a file or folder name.
+fn file_name(name: &str, fallback: &str) -> String {
+    let cleaned: String = name.chars()
+        .map(|c| if c.is_alphanumeric() || " -_.()".contains(c) { c } else { '_' })
+        .collect();
+    let cleaned = cleaned.trim().trim_start_matches('.');
+    if cleaned.is_empty() { fallback.to_string() } else { cleaned.to_string() }
+}
+
+/// The subset of XML that `.jmx` files use: elements, attributes, text,
+/// comments, CDATA and the standard entities.
+#[derive(Debug)]
+struct Element {
+    name: String,
+    attrs: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+#[derive(Debug)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+
+    fn enabled(&self) -> bool {
+        self.attr("enabled") != Some("false")
+    }
+
+    fn test_name(&self) -> &str {
+        self.attr("testname").unwrap_or("")
+    }
+
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(el) => Some(el),
+            Node::Text(_) => None,
+        })
+    }
+
+    fn descendants(&self) -> Box<dyn Iterator<Item = &Element> + '_> {
+        Box::new(self.elements().flat_map(|el| std::iter::once(el).chain(el.descendants())))
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|el| el.name == name)
+    }
+
+    /// A direct `*Prop` child element with the given `name` attribute.
+    fn prop_element(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|el| el.name.ends_with("Prop") && el.attr("name") == Some(name))
+    }
+
+    fn prop(&self, name: &str) -> Option<&str> {
+        let el = self.prop_element(name)?;
+        match el.children.as_slice() {
+            [] => Some(""),
+            [Node::Text(text)] => Some(text.as_str()),
+            _ => None,
+        }
+    }
+}
+
+fn parse_xml(input: &str) -> Result<Element, String> {
+    let mut parser = XmlParser { input, pos: 0 };
+    parser.skip_misc()?;
+    let root = parser.element()?;
+    parser.skip_misc()?;
+    if parser.pos < input.len() {
+        return Err(parser.error("content after the root element"));
+    }
+    Ok(root)
+}
+
+struct XmlParser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> XmlParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn error(&self, what: &str) -> String {
+        let line = self.input[..self.pos].lines().count().max(1);
+        format!("Invalid XML at line {}: {}", line, what)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn skip_past(&mut self, end: &str) -> Result<&'a str, String> {
+        let found = self.rest().find(end).ok_or_else(|| self.error(&format!("missing '{}'", end)))?;
+        let skipped = &self.input[self.pos..self.pos + found];
+        self.pos += found + end.len();
+        Ok(skipped)
+    }
+
+    /// Skips whitespace, comments, processing instructions and the doctype.
+    fn skip_misc(&mut self) -> Result<(), String> {
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with("<!DOCTYPE") {
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        let rest = self.rest();
+        let len = rest.find(|c: char| c.is_whitespace() || "/>=".contains(c)).unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error("expected a name"));
+        }
+        self.pos += len;
+        Ok(rest[..len].to_string())
+    }
+
+    fn expect(&mut self, s: &str) -> Result<(), String> {
+        if !self.rest().starts_with(s) {
+            return Err(self.error(&format!("expected '{}'", s)));
+        }
+        self.pos += s.len();
+        Ok(())
+    }
+
+    fn element(&mut self) -> Result<Element, String> {
+        self.expect("<")?;
+        let name = self.name()?;
+        let mut attrs = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.pos += 2;
+                return Ok(Element { name, attrs, children: Vec::new() });
+            }
+            if self.rest().starts_with('>') {
+                self.pos += 1;
+                break;
+            }
+            let key = self.name()?;
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let quote = if self.rest().starts_with('\'') { "'" } else { "\"" };
+            self.expect(quote)?;
+            let value = unescape(self.skip_past(quote)?);
+            attrs.push((key, value));
+        }
+
+        let mut children = Vec::new();
+        loop {
+            let rest = self.rest();
+            if rest.is_empty() {
+                return Err(self.error(&format!("<{}> is not closed", name)));
+            } else if rest.starts_with("</") {
+                self.pos += 2;
+                let closing = self.name()?;
+                if closing != name {
+                    return Err(self.error(&format!("</{}> closes <{}>", closing, name)));
+                }
+                self.skip_whitespace();
+                self.expect(">")?;
+                return Ok(Element { name, attrs, children });
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.pos += "<![CDATA[".len();
+                let text = self.skip_past("]]>")?.to_string();
+                push_text(&mut children, text);
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with('<') {
+                children.push(Node::Element(self.element()?));
+            } else {
+                let len = rest.find('<').unwrap_or(rest.len());
+                let text = unescape(&rest[..len]);
+                self.pos += len;
+                push_text(&mut children, text);
+            }
+        }
+    }
+}
+
+/// Appends text, joining it with preceding text so CDATA sections and
+/// entities do not split a value into several nodes. Indentation between
+/// elements is dropped.
+fn push_text(children: &mut Vec<Node>, text: String) {
+    if let Some(Node::Text(last)) = children.last_mut() {
+        last.push_str(&text);
+    } else if !text.trim().is_empty() || children.is_empty() {
+        children.push(Node::Text(text));
+    }
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let entity = after.find(';').map(|end| &after[..end]);
+        let decoded = entity.and_then(|entity| match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X"))
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32),
+        });
+        match (entity, decoded) {
+            (Some(entity), Some(c)) => {
+                out.push(c);
+                rest = &after[entity.len() + 1..];
+            }
+            _ => {
+                out.push('&');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
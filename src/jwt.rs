@@ -0,0 +1,196 @@
+use base64::Engine;
+use serde_json::Value;
+use crate::dates;
+
+/// A decoded JSON Web Token. The signature is kept as is and never checked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Jwt {
+    pub header: Value,
+    pub payload: Value,
+    pub signature: String,
+}
+
+/// A standard claim of the payload, readable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Claim {
+    pub key: &'static str,
+    pub name: &'static str,
+    pub value: String,
+    /// A warning, e.g. that the token has expired.
+    pub problem: Option<String>,
+}
+
+/// Registered claims, with what they mean.
+const CLAIMS: [(&str, &str); 7] = [
+    ("iss", "Issuer"),
+    ("sub", "Subject"),
+    ("aud", "Audience"),
+    ("exp", "Expires"),
+    ("nbf", "Not before"),
+    ("iat", "Issued at"),
+    ("jti", "Token ID"),
+];
+
+impl Jwt {
+    /// The signature algorithm named in the header.
+    pub fn algorithm(&self) -> &str {
+        self.header.get("alg").and_then(Value::as_str).unwrap_or("none")
+    }
+
+    /// The registered claims present in the payload; times are shown in local
+    /// time and checked against `now_ms`.
+    pub fn claims(&self, now_ms: i64) -> Vec<Claim> {
+        CLAIMS.iter()
+            .filter_map(|&(key, name)| {
+                let value = self.payload.get(key)?;
+                let Some(seconds) = value.as_i64().filter(|_| matches!(key, "exp" | "nbf" | "iat")) else {
+                    let value = value.as_str().map_or_else(|| value.to_string(), str::to_string);
+                    return Some(Claim { key, name, value, problem: None });
+                };
+                let ms = seconds.saturating_mul(1000);
+                let problem = match key {
+                    "exp" if ms <= now_ms => Some(format!("Expired {} ago", ago(now_ms - ms))),
+                    "nbf" if ms > now_ms => Some(format!("Not valid for another {}", ago(ms - now_ms))),
+                    _ => None,
+                };
+                let value = format!("{} ({})", dates::display_ms(ms, "%Y-%m-%d %H:%M:%S", false), seconds);
+                Some(Claim { key, name, value, problem })
+            })
+            .collect()
+    }
+}
+
+/// A duration in its largest whole unit, e.g. `3 h`.
+fn ago(ms: i64) -> String {
+    let seconds = ms / 1000;
+    match seconds {
+        s if s >= 86_400 => format!("{} d", s / 86_400),
+        s if s >= 3_600 => format!("{} h", s / 3_600),
+        s if s >= 60 => format!("{} min", s / 60),
+        s => format!("{} s", s),
+    }
+}
+
+fn decode_part(part: &str, what: &str) -> Result<Value, String> {
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(part.trim_end_matches('='))
+        .map_err(|e| format!("The {} is not base64url: {}", what, e))?;
+    serde_json::from_slice(&bytes).map_err(|e| format!("The {} is not JSON: {}", what, e))
+}
+
+/// Decodes `token`, which may start with `Bearer `.
+pub fn decode(token: &str) -> Result<Jwt, String> {
+    let token = token.trim();
+    let token = token.strip_prefix("Bearer ").or_else(|| token.strip_prefix("bearer ")).unwrap_or(token).trim();
+    let parts: Vec<&str> = token.split('.').collect();
+    if parts.len() != 3 {
+        return Err(format!("A JWT has 3 parts separated by dots, this has {}", parts.len()));
+    }
+    let header = decode_part(parts[0], "header")?;
+    if !header.is_object() {
+        return Err("The header is not a JSON object".to_string());
+    }
+    Ok(Jwt { header, payload: decode_part(parts[1], "payload")?, signature: parts[2].to_string() })
+}
+
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+}
+
+/// JWT-shaped strings in `text`, in order and without repeats. A candidate
+/// must start with `eyJ` (base64 of `{"`) and decode.
+pub fn find_tokens(text: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("eyJ") {
+        let preceded = text.len() - rest.len() + start;
+        let candidate = &rest[start..];
+        let end = candidate.find(|c: char| !is_token_char(c) && c != '.').unwrap_or(candidate.len());
+        let token = candidate[..end].trim_end_matches('.');
+        let standalone = !text[..preceded].ends_with(is_token_char);
+        if standalone && decode(token).is_ok() && !tokens.iter().any(|t| t == token) {
+            tokens.push(token.to_string());
+        }
+        rest = &candidate[end.max(3)..];
+    }
+    tokens
+}
+
+/// JWTs among the string values of a JSON document, with their paths in
+/// the form used elsewhere, e.g. `.data.access_token` or `.items[0].jwt`.
+pub fn find_in_json(value: &Value) -> Vec<(String, String)> {
+    fn walk(value: &Value, path: String, found: &mut Vec<(String, String)>) {
+        match value {
+            Value::Object(map) => {
+                for (key, child) in map {
+                    walk(child, format!("{}.{}", path, key), found);
+                }
+            }
+            Value::Array(items) => {
+                for (i, child) in items.iter().enumerate() {
+                    walk(child, format!("{}[{}]", path, i), found);
+                }
+            }
+            Value::String(s) => {
+                for token in find_tokens(s) {
+                    found.push((if path.is_empty() { ".".to_string() } else { path.clone() }, token));
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut found = Vec::new();
+    walk(value, String::new(), &mut found);
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn token(header: &Value, payload: &Value) -> String {
+        let part = |value: &Value| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(value.to_string());
+        format!("{}.{}.c2lnbmF0dXJl", part(header), part(payload))
+    }
+
+    #[test]
+    fn decodes_bearer_tokens_and_reads_claims() {
+        let now_ms = 1_700_000_000_000;
+        let jwt = token(&json!({"alg": "HS256", "typ": "JWT"}), &json!({"sub": "42", "aud": ["a", "b"], "exp": 1_699_996_400, "nbf": 1_700_000_090}));
+        let decoded = decode(&format!("Bearer {}", jwt)).unwrap();
+        assert_eq!(decoded.algorithm(), "HS256");
+        assert_eq!(decoded.signature, "c2lnbmF0dXJl");
+        let claims = decoded.claims(now_ms);
+        let keys: Vec<&str> = claims.iter().map(|c| c.key).collect();
+        assert_eq!(keys, ["sub", "aud", "exp", "nbf"]);
+        assert_eq!(claims[0].value, "42");
+        assert_eq!(claims[1].value, "[\"a\",\"b\"]");
+        assert!(claims[2].value.ends_with("(1699996400)"));
+        assert_eq!(claims[2].problem.as_deref(), Some("Expired 1 h ago"));
+        assert_eq!(claims[3].problem.as_deref(), Some("Not valid for another 1 min"));
+    }
+
+    #[test]
+    fn malformed_tokens_say_what_is_wrong() {
+        assert_eq!(decode("a.b").unwrap_err(), "A JWT has 3 parts separated by dots, this has 2");
+        assert!(decode("!!.e30.x").unwrap_err().starts_with("The header is not base64url"));
+        assert!(decode("bm90IGpzb24.e30.x").unwrap_err().starts_with("The header is not JSON"));
+        assert_eq!(decode("WzFd.e30.x").unwrap_err(), "The header is not a JSON object");
+        assert_eq!(decode("e30.e30.").unwrap().algorithm(), "none");
+    }
+
+    #[test]
+    fn finds_tokens_in_text_and_json() {
+        let jwt = token(&json!({"alg": "RS256"}), &json!({"sub": "1"}));
+        let text = format!("Set-Cookie: s={}; Path=/ and again {} but not x{}", jwt, jwt, jwt);
+        assert_eq!(find_tokens(&text), vec![jwt.clone()]);
+        assert!(find_tokens("eyJhbGciOi.not.valid").is_empty());
+        let body = json!({"data": {"access_token": jwt}, "items": [{"id": 1}, {"jwt": format!("Bearer {}", jwt)}]});
+        assert_eq!(find_in_json(&body), [
+            (".data.access_token".to_string(), jwt.clone()),
+            (".items[1].jwt".to_string(), jwt.clone()),
+        ]);
+        assert_eq!(find_in_json(&json!(jwt)), [(".".to_string(), jwt)]);
+    }
+}
@@ -1,238 +1,6598 @@
 use dioxus::prelude::*;
+use dioxus::html::geometry::PixelsVector2D;
 mod storage;
+mod assertions;
+mod auth;
+mod body_template;
+mod backend;
 mod client;
+mod cli;
+mod cookies;
+mod cors;
+mod curl;
+mod dates;
+mod decode;
+mod diff;
+mod dns;
+mod effective;
+mod environment;
+mod export;
+mod fixtures;
+mod fuzz;
+mod history;
+mod hooks;
+mod poll;
+mod redact;
+mod jmx;
+mod jwt;
+mod markdown;
+mod runner;
+mod settings;
+mod share;
+mod shell;
+mod soap;
+mod templates;
+mod transform;
+mod viewer;
+mod workspace;
 
-use storage::{FileNode, HttpRequest as RequestData};
-use client::{HttpResponse, execute_request};
-use std::path::PathBuf;
+use history::{HistoryEntry, HistoryQuery};
+use runner::{CancelFlag, ErrorPolicy, FolderReport, LatencyStats, PinnedPolicy, RunBadge, RunContext, RunRecord, RunResult, RunSummary};
+use poll::{PollConfig, PollEnd, PollStep};
+use backend::BackendKind;
+use settings::{HeaderPreset, Settings};
+use auth::Auth;
+use effective::HeaderOrigin;
+use workspace::LockState;
+use storage::{FileNode, HttpRequest as RequestData, RequestOptions};
+use client::{HttpResponse, execute_queued, execute_request};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
 fn main() {
-    dioxus::launch(app);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|a| a == "run") {
+        std::process::exit(cli::run_command(&args[1..]));
+    }
+    if args.first().is_some_and(|a| a == "import-jmx") {
+        std::process::exit(cli::import_jmx_command(&args[1..]));
+    }
+    if args.first().is_some_and(|a| a == "switch-storage") {
+        std::process::exit(cli::switch_storage_command(&args[1..]));
+    }
+    let launch = match cli::LaunchOptions::parse(args) {
+        Ok(launch) => launch,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("Usage: requester [-X METHOD] [-H 'Name: value']... [URL] [< body]");
+            eprintln!("       requester run FILE.req [--env NAME] [--allow-hooks] [--trust] [--ignore-pin]");
+            eprintln!("       requester import-jmx PLAN.jmx [--into FOLDER]");
+            eprintln!("       requester switch-storage files|sqlite");
+            std::process::exit(2);
+        }
+    };
+    dioxus::LaunchBuilder::new().with_context(launch).launch(app);
+}
+
+/// Folder listings loaded so far; the sidebar fetches a folder's children
+/// the first time it is expanded.
+#[derive(Clone, Default, PartialEq)]
+struct TreeCache {
+    root: PathBuf,
+    children: HashMap<PathBuf, Vec<FileNode>>,
+    expanded: HashSet<PathBuf>,
+    loading: HashSet<PathBuf>,
+    /// Last run outcomes of the folders listed so far.
+    summaries: HashMap<PathBuf, RunSummary>,
+    /// Folders listed so far that have a README.
+    readmes: HashSet<PathBuf>,
+}
+
+impl TreeCache {
+    fn new(root: FileNode) -> Self {
+        let mut cache = TreeCache::default();
+        if let FileNode::Folder { path, children, .. } = root {
+            cache.summaries = folder_summaries(&path, &children);
+            cache.readmes = folder_readmes(&path, &children);
+            cache.children.insert(path.clone(), children);
+            cache.expanded.insert(path.clone());
+            cache.root = path;
+        }
+        cache
+    }
+}
+
+/// A node shown in the sidebar tree, in display order.
+#[derive(Clone, PartialEq)]
+struct VisibleNode {
+    path: PathBuf,
+    folder: bool,
+    /// The text shown, which type-ahead matches against.
+    label: String,
+}
+
+impl TreeCache {
+    /// The nodes on screen from the root down, skipping the contents of
+    /// collapsed folders; keyboard navigation moves through these.
+    fn visible(&self) -> Vec<VisibleNode> {
+        fn walk(cache: &TreeCache, folder: &Path, out: &mut Vec<VisibleNode>) {
+            if !cache.expanded.contains(folder) {
+                return;
+            }
+            for child in cache.children.get(folder).into_iter().flatten() {
+                match child {
+                    FileNode::Folder { name, path, .. } => {
+                        out.push(VisibleNode { path: path.clone(), folder: true, label: name.clone() });
+                        walk(cache, path, out);
+                    }
+                    FileNode::File { name, path, title } => {
+                        out.push(VisibleNode { path: path.clone(), folder: false, label: title.clone().unwrap_or_else(|| name.clone()) });
+                    }
+                }
+            }
+        }
+        let label = self.root.file_name().map_or("requester".to_string(), |n| n.to_string_lossy().into_owned());
+        let mut out = vec![VisibleNode { path: self.root.clone(), folder: true, label }];
+        walk(self, &self.root, &mut out);
+        out
+    }
+
+    /// The root and every folder listed so far, sorted by path.
+    fn folders(&self) -> Vec<PathBuf> {
+        let mut folders: Vec<PathBuf> = self.children.values()
+            .flatten()
+            .filter_map(|node| match node {
+                FileNode::Folder { path, .. } => Some(path.clone()),
+                FileNode::File { .. } => None,
+            })
+            .collect();
+        folders.sort();
+        folders.dedup();
+        folders.insert(0, self.root.clone());
+        folders
+    }
+}
+
+/// What a key pressed in the sidebar tree does.
+#[derive(Debug, PartialEq)]
+enum TreeAction {
+    Focus(usize),
+    Toggle(PathBuf),
+    Open(PathBuf),
+}
+
+/// The action for `key` with the node at `focused` in `nodes` focused:
+/// arrows move and fold, Home and End jump, Enter opens or toggles.
+fn tree_key(nodes: &[VisibleNode], expanded: &HashSet<PathBuf>, focused: usize, key: &Key) -> Option<TreeAction> {
+    let node = nodes.get(focused)?;
+    match key {
+        Key::ArrowDown => Some(TreeAction::Focus((focused + 1).min(nodes.len() - 1))),
+        Key::ArrowUp => Some(TreeAction::Focus(focused.saturating_sub(1))),
+        Key::Home => Some(TreeAction::Focus(0)),
+        Key::End => Some(TreeAction::Focus(nodes.len() - 1)),
+        Key::ArrowRight if node.folder && !expanded.contains(&node.path) => Some(TreeAction::Toggle(node.path.clone())),
+        Key::ArrowRight if node.folder => {
+            nodes.get(focused + 1).filter(|next| next.path.parent() == Some(&node.path)).map(|_| TreeAction::Focus(focused + 1))
+        }
+        Key::ArrowLeft if node.folder && expanded.contains(&node.path) && focused > 0 => Some(TreeAction::Toggle(node.path.clone())),
+        Key::ArrowLeft => {
+            let parent = node.path.parent()?;
+            nodes.iter().position(|n| n.path == parent).map(TreeAction::Focus)
+        }
+        Key::Enter if node.folder => Some(TreeAction::Toggle(node.path.clone())),
+        Key::Enter => Some(TreeAction::Open(node.path.clone())),
+        _ => None,
+    }
+}
+
+/// The next node after `focused`, wrapping around, whose label starts with
+/// `typed`; while the same letter is repeated it cycles through the nodes
+/// starting with it.
+fn type_ahead(nodes: &[VisibleNode], focused: usize, typed: &str) -> Option<usize> {
+    let typed = typed.to_lowercase();
+    let repeated = typed.chars().all(|c| typed.starts_with(c));
+    let (prefix, start) = if repeated { (&typed[..typed.chars().next()?.len_utf8()], focused + 1) } else { (typed.as_str(), focused) };
+    (0..nodes.len())
+        .map(|i| (start + i) % nodes.len())
+        .find(|&i| nodes[i].label.to_lowercase().starts_with(prefix))
+}
+
+/// `folder` and the folders among its `children` that have a README.
+fn folder_readmes(folder: &Path, children: &[FileNode]) -> HashSet<PathBuf> {
+    let folders = children.iter().filter(|c| matches!(c, FileNode::Folder { .. })).map(FileNode::path);
+    std::iter::once(folder)
+        .chain(folders)
+        .filter(|path| storage::backend().exists(&path.join(markdown::README)))
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+/// Run summaries of `folder` and of the folders among its `children`.
+fn folder_summaries(folder: &std::path::Path, children: &[FileNode]) -> HashMap<PathBuf, RunSummary> {
+    let folders = children.iter().filter(|c| matches!(c, FileNode::Folder { .. })).map(FileNode::path);
+    std::iter::once(folder)
+        .chain(folders)
+        .filter_map(|path| Some((path.to_path_buf(), RunSummary::load(path)?)))
+        .collect()
+}
+
+/// Polls made after a send, for the progress list.
+#[derive(Clone, Default, PartialEq)]
+struct PollProgress {
+    steps: Vec<PollStep>,
+    /// Set once polling has stopped.
+    end: Option<PollEnd>,
+}
+
+/// A send held back until the user confirms it.
+#[derive(Clone, Copy, PartialEq)]
+enum PendingSend {
+    /// A single send, with the method override if any.
+    Send(Option<&'static str>),
+    Repeat,
+}
+
+/// How the response body is displayed, besides its view kind.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BodyView {
+    linkify: bool,
+    /// Show only the contents of a SOAP envelope's Body.
+    envelope_body_only: bool,
+    /// Mask what the redaction rules match, e.g. while sharing the screen.
+    privacy: bool,
+}
+
+impl Default for BodyView {
+    fn default() -> Self {
+        BodyView { linkify: true, envelope_body_only: false, privacy: false }
+    }
+}
+
+/// The response panel of a request that is not shown, kept so switching
+/// back to the request resumes where it was left.
+#[derive(Clone, Default)]
+struct ParkedResponse {
+    response: Option<Result<HttpResponse, String>>,
+    view: BodyView,
+    scroll_top: f64,
 }
 
 #[derive(Clone, Copy, PartialEq)]
 enum Tab {
     Headers,
     Body,
+    Params,
+    Auth,
+    Settings,
 }
 
 fn app() -> Element {
-    let mut tree = use_signal(|| storage::scan_directory());
-    let mut current_request = use_signal(RequestData::new);
+    let mut tree = use_signal(|| TreeCache::new(storage::scan_directory()));
+    // Bumped whenever cached listings are dropped, so the flat list rescans
+    let mut tree_revision = use_signal(|| 0u32);
+    // Request IDs, re-indexed whenever the tree is refreshed
+    let mut id_index = use_signal(storage::IdIndex::default);
+    let launch = use_context::<cli::LaunchOptions>();
+    let mut current_request = use_signal(|| launch.initial_request());
     let mut current_path = use_signal(|| None::<PathBuf>);
     let mut response = use_signal(|| None::<Result<HttpResponse, String>>);
+    let mut body_view = use_signal(BodyView::default);
+    // Kept up to date while scrolling; read without subscribing
+    let mut response_scroll = use_signal(|| 0.0);
+    let mut restore_scroll = use_signal(|| None::<f64>);
+    let mut result_area = use_signal(|| None::<Rc<MountedData>>);
+    // Keyed by path, `None` being the scratch request
+    let mut parked_responses = use_signal(HashMap::<Option<PathBuf>, ParkedResponse>::new);
     let mut active_tab = use_signal(|| Tab::Headers);
     let mut loading = use_signal(|| false);
+    // Set while the send waits for a free slot of its host
+    let mut queued = use_signal(|| false);
+    let mut send_cancel = use_signal(|| None::<CancelFlag>);
+    // While on, every response to a send is written out as a fixture
+    let mut record_fixtures = use_signal(|| false);
+    let mut fixture_note = use_signal(|| None::<Result<PathBuf, String>>);
+    let mut settings = use_signal(Settings::load);
+    let mut show_settings = use_signal(|| false);
+    let mut lock_state = use_signal(workspace::acquire);
+    let read_only = move || lock_state() != LockState::Acquired;
+    let mut trusted = use_signal(workspace::workspace_trusted);
+    let mut trust_error = use_signal(|| None::<String>);
+    let mut detached = use_signal(|| false);
+    // Pointer y and panel height captured when a resize drag starts
+    let mut drag_start = use_signal(|| None::<(f64, f64)>);
+    let mut history = use_signal(Vec::<HistoryEntry>::new);
+    let mut show_history = use_signal(|| false);
+    let mut list_view = use_signal(|| false);
+    let mut show_archived = use_signal(|| false);
+    let mut tree_error = use_signal(|| None::<String>);
+    let mut pending_delete = use_signal(|| None::<PathBuf>);
+    // The node keyboard navigation is on, kept by path across rescans, and
+    // its index for when that path disappears
+    let mut tree_focus = use_signal(|| None::<PathBuf>);
+    let mut tree_index = use_signal(|| 0usize);
+    let mut typed = use_signal(|| (String::new(), Instant::now()));
+    // The unsaved scratch request, parked while a file is open
+    let mut scratch = use_signal(|| None::<RequestData>);
+    let mut show_save_as = use_signal(|| false);
+    let mut show_new_request = use_signal(|| false);
+    let mut show_preflight = use_signal(|| false);
+    let mut show_copy_curl = use_signal(|| false);
+    let mut show_raw_mode = use_signal(|| false);
+    let mut show_compare = use_signal(|| false);
+    let mut show_fuzz = use_signal(|| false);
+    let mut show_markdown = use_signal(|| false);
+    // Folder whose README is shown in a dialog
+    let mut show_readme = use_signal(|| None::<PathBuf>);
+    // Folder whose response times across runs are shown
+    let mut show_timings = use_signal(|| None::<PathBuf>);
+    let mut show_saved_diff = use_signal(|| false);
+    let mut show_templates = use_signal(|| false);
+    let mut show_import_link = use_signal(|| false);
+    let mut show_import_curl = use_signal(|| false);
+    let mut show_cookies = use_signal(|| false);
+    let mut pending_send = use_signal(|| None::<PendingSend>);
+    let mut pending_protected_run = use_signal(|| None::<PathBuf>);
+    // Set when a link in a response body is clicked, from either window
+    let mut follow_url = use_signal(|| None::<String>);
+    let mut new_request = use_signal(|| None::<RequestData>);
+    // A request found on the clipboard, waiting for the user to import it
+    let mut clipboard_offer = use_signal(|| None::<RequestData>);
+    // Set when a response suggests sending the request to another URL
+    let mut resend_url = use_signal(|| None::<String>);
+    let mut repeat_count = use_signal(|| 20u32);
+    // (done, total) while a repeat batch runs
+    let mut repeat_progress = use_signal(|| None::<(u32, u32)>);
+    let mut repeat_stats = use_signal(|| None::<Result<LatencyStats, String>>);
+    let mut run_report = use_signal(|| None::<FolderReport>);
+    // Set while a folder run is in progress
+    let mut run_cancel = use_signal(|| None::<CancelFlag>);
+    let mut poll_progress = use_signal(|| None::<PollProgress>);
+    // Set while polling after a send
+    let mut poll_cancel = use_signal(|| None::<CancelFlag>);
+
+    use_hook(move || {
+        if let Some(stdin) = launch.take_stdin() {
+            spawn(async move {
+                if let Ok(body) = stdin.await {
+                    current_request.write().body = body;
+                }
+            });
+        }
+    });
+
+    // Looks at the clipboard each time the window gains focus, if enabled.
+    // The same text is offered only once.
+    use_future(move || async move {
+        let mut focus = document::eval("window.addEventListener('focus', () => dioxus.send(true)); await new Promise(() => {});");
+        let mut last_seen = String::new();
+        while focus.recv::<bool>().await.is_ok() {
+            if !settings.peek().watch_clipboard {
+                continue;
+            }
+            let Ok(text) = document::eval("return await navigator.clipboard.readText();").join::<String>().await else {
+                continue;
+            };
+            if text != last_seen {
+                clipboard_offer.set(clipboard_request(&text));
+                last_seen = text;
+            }
+        }
+    });
+
+    // Load the history index in the background; new entries are appended as they are sent
+    use_hook(move || {
+        spawn(async move {
+            if let Ok(entries) = tokio::task::spawn_blocking(history::load).await {
+                let mut loaded = entries;
+                loaded.append(&mut history.write());
+                history.set(loaded);
+            }
+        });
+    });
+
+    let mut load_folder = move |path: PathBuf| {
+        if !tree.write().loading.insert(path.clone()) {
+            return;
+        }
+        spawn(async move {
+            let children = storage::scan_children(&path).await;
+            let (folder, listed) = (path.clone(), children.clone());
+            let (summaries, readmes) = tokio::task::spawn_blocking(move || {
+                (folder_summaries(&folder, &listed), folder_readmes(&folder, &listed))
+            }).await.unwrap_or_default();
+            let mut cache = tree.write();
+            cache.loading.remove(&path);
+            // Drop the outcomes and READMEs of this folder and its subfolders that are gone
+            for child in std::iter::once(path.as_path()).chain(children.iter().map(FileNode::path)) {
+                cache.summaries.remove(child);
+                cache.readmes.remove(child);
+            }
+            cache.summaries.extend(summaries);
+            cache.readmes.extend(readmes);
+            cache.children.insert(path, children);
+        });
+    };
 
-    let on_refresh_tree = move |_| {
-        tree.set(storage::scan_directory());
+    let mut on_toggle_folder = move |path: PathBuf| {
+        let mut cache = tree.write();
+        if !cache.expanded.remove(&path) {
+            cache.expanded.insert(path.clone());
+            if !cache.children.contains_key(&path) {
+                drop(cache);
+                load_folder(path);
+            }
+        }
     };
 
-    let on_select_file = move |path: PathBuf| {
-        if let Ok(req) = storage::load_request(&path) {
-            current_request.set(req);
-            current_path.set(Some(path));
+    // Drops every cached listing and reloads the folders that are open
+    let mut refresh_tree = move || {
+        let expanded: Vec<PathBuf> = {
+            let mut cache = tree.write();
+            cache.children.clear();
+            cache.loading.clear();
+            cache.expanded.iter().cloned().collect()
+        };
+        for path in expanded {
+            load_folder(path);
         }
+        tree_revision += 1;
     };
 
-    let on_send = move |_| {
+    let on_refresh_tree = move |_| refresh_tree();
+
+    use_effect(move || {
+        tree_revision();
         spawn(async move {
-            loading.set(true);
-            let req = current_request.read().clone();
-            let res = execute_request(&req).await;
-            response.set(Some(res));
-            loading.set(false);
+            if let Ok(index) = tokio::task::spawn_blocking(storage::index_ids).await {
+                id_index.set(index);
+            }
+        });
+    });
+
+    use_effect(move || {
+        let limit = settings.read().per_host_limit;
+        client::set_host_limit((limit > 0).then_some(limit as usize));
+    });
+
+    use_effect(move || client::set_body_limits(settings.read().body_limits.clone()));
+
+    // Dry run of the body as it will be sent, redone once typing pauses
+    let mut body_preview = use_signal(|| None::<Result<client::BodyPreview, String>>);
+    let mut body_preview_revision = use_signal(|| 0u64);
+    use_effect(move || {
+        let request = current_request.read().clone();
+        let defaults = settings.read().request_defaults();
+        let revision = *body_preview_revision.peek() + 1;
+        body_preview_revision.set(revision);
+        spawn(async move {
+            tokio::time::sleep(BODY_PREVIEW_DELAY).await;
+            if *body_preview_revision.peek() != revision {
+                return;
+            }
+            let preview = tokio::task::spawn_blocking(move || client::preview_body(&request, &defaults)).await;
+            if let Ok(preview) = preview && *body_preview_revision.peek() == revision {
+                body_preview.set(Some(preview));
+            }
         });
+    });
+
+    // Re-read with the tree, so Refresh picks up new environment files
+    let environments = use_memo(move || {
+        tree_revision();
+        environment::list_environments()
+    });
+
+    // Named in the open request but defined by no folder above it; checked
+    // again with the tree, so fixing `.folder.json` and refreshing clears it
+    let missing_assert_sets = use_memo(move || {
+        tree_revision();
+        assertions::missing_sets(current_path().as_deref(), &current_request.read().options)
+    });
+
+    // Parks the response panel of the request being left and brings back
+    // the one `path` had
+    let mut swap_response = move |path: &Option<PathBuf>| {
+        let leaving = current_path.peek().clone();
+        if leaving == *path {
+            return;
+        }
+        let parked = ParkedResponse {
+            response: response.peek().clone(),
+            view: *body_view.peek(),
+            scroll_top: *response_scroll.peek(),
+        };
+        let restored = {
+            let mut parked_responses = parked_responses.write();
+            parked_responses.insert(leaving, parked);
+            parked_responses.remove(path).unwrap_or_default()
+        };
+        response.set(restored.response);
+        body_view.set(restored.view);
+        restore_scroll.set(Some(restored.scroll_top));
     };
 
-    let on_save = move |_| {
-        if let Some(path) = current_path.read().as_ref() {
-            let _ = storage::save_request(path, &current_request.read());
-        } else {
-            // New file logic could be added here
+    // Runs after the swapped-in response has rendered
+    use_effect(move || {
+        let Some(top) = restore_scroll() else { return };
+        restore_scroll.set(None);
+        response_scroll.set(top);
+        if let Some(area) = result_area.peek().clone() {
+            spawn(async move {
+                let _ = area.scroll(PixelsVector2D::new(0.0, top), ScrollBehavior::Instant).await;
+            });
+        }
+    });
+
+    let mut park_scratch = move || {
+        if current_path.read().is_none() {
+            scratch.set(Some(current_request.read().clone()));
         }
     };
 
-    rsx! {
-        style { {include_str!("style.css")} }
-        div { id: "main",
-            div { class: "sidebar",
-                h3 { "Requests" }
-                button { onclick: on_refresh_tree, "Refresh" }
-                Sidebar { node: tree.read().clone(), on_select: on_select_file, current_path: current_path.read().clone() }
+    let mut on_select_file = move |path: PathBuf| {
+        if let Ok(req) = storage::load_request(&path) {
+            park_scratch();
+            let path = Some(path);
+            swap_response(&path);
+            current_request.set(req);
+            current_path.set(path);
+        }
+    };
+
+    // Opens the folders down to `path` so it shows selected
+    let mut reveal_in_tree = move |path: &std::path::Path| {
+        {
+            let mut cache = tree.write();
+            let root = cache.root.clone();
+            for folder in path.ancestors().skip(1).take_while(|f| f.starts_with(&root)) {
+                cache.expanded.insert(folder.to_path_buf());
             }
-            div { class: "content",
-                div { class: "address-bar",
-                    select {
-                        value: "{current_request.read().method}",
-                        onchange: move |evt| {
-                            current_request.write().method = evt.value();
-                        },
-                        option { value: "GET", "GET" }
-                        option { value: "POST", "POST" }
-                        option { value: "PUT", "PUT" }
-                        option { value: "DELETE", "DELETE" }
-                        option { value: "PATCH", "PATCH" }
-                    }
-                    input {
-                        r#type: "text",
-                        placeholder: "https://api.example.com",
-                        value: "{current_request.read().url}",
-                        oninput: move |evt| {
-                            current_request.write().url = evt.value();
-                        }
-                    }
-                    button { 
-                        disabled: loading(),
-                        onclick: on_send, 
-                        if loading() { "Sending..." } else { "Send" }
-                    }
-                    button { onclick: on_save, "Save" }
+        }
+        tree_focus.set(Some(path.to_path_buf()));
+    };
+
+    let on_open_scratch = move |_| {
+        if current_path.read().is_some() {
+            swap_response(&None);
+            current_request.set(scratch.take().unwrap_or_else(RequestData::new));
+            current_path.set(None);
+        }
+    };
+
+    // Arrow keys, Home/End, Enter and type-ahead over the visible nodes
+    let on_tree_key = move |evt: KeyboardEvent| {
+        let (nodes, expanded) = {
+            let cache = tree.read();
+            (cache.visible(), cache.expanded.clone())
+        };
+        let current = tree_focus.read().as_ref()
+            .and_then(|path| nodes.iter().position(|n| &n.path == path))
+            .unwrap_or_else(|| tree_index().min(nodes.len() - 1));
+        let modifiers = evt.modifiers();
+        let action = match evt.key() {
+            Key::Character(c) if !modifiers.ctrl() && !modifiers.meta() && !modifiers.alt() => {
+                let mut typed = typed.write();
+                if typed.1.elapsed() > Duration::from_millis(800) {
+                    typed.0.clear();
                 }
+                typed.0.push_str(&c);
+                typed.1 = Instant::now();
+                type_ahead(&nodes, current, &typed.0).map(TreeAction::Focus)
+            }
+            key => tree_key(&nodes, &expanded, current, &key),
+        };
+        let Some(action) = action else { return };
+        evt.prevent_default();
+        match action {
+            TreeAction::Focus(i) => {
+                tree_focus.set(Some(nodes[i].path.clone()));
+                tree_index.set(i);
+            }
+            TreeAction::Toggle(path) => {
+                tree_focus.set(Some(path.clone()));
+                tree_index.set(current);
+                on_toggle_folder(path);
+            }
+            TreeAction::Open(path) => on_select_file(path),
+        }
+    };
 
-                div { class: "tabs",
-                    div { 
-                        class: if active_tab() == Tab::Headers { "tab active" } else { "tab" },
-                        onclick: move |_| active_tab.set(Tab::Headers),
-                        "Headers"
-                    }
-                    div { 
-                        class: if active_tab() == Tab::Body { "tab active" } else { "tab" },
-                        onclick: move |_| active_tab.set(Tab::Body),
-                        "Body"
-                    }
+    // Keeps the focused node in view as it moves
+    use_effect(move || {
+        if tree_focus().is_some() {
+            document::eval("document.querySelector('.tree .focused')?.scrollIntoView({ block: 'nearest' });");
+        }
+    });
+
+    // A followed link becomes an unsaved GET that keeps the current headers
+    // and options, so authentication carries over
+    use_effect(move || {
+        let Some(url) = follow_url() else { return };
+        follow_url.set(None);
+        let current = current_request.peek().clone();
+        current_request.set(RequestData { url, headers: current.headers, options: current.options, ..RequestData::new() });
+        current_path.set(None);
+    });
+
+    use_effect(move || {
+        let Some(request) = new_request() else { return };
+        new_request.set(None);
+        current_request.set(request);
+        current_path.set(None);
+    });
+
+    // `method` overrides the editor's method for this send only
+    let dispatch = move |method: Option<&'static str>| {
+        if let Some(cancel) = poll_cancel.peek().as_ref() {
+            cancel.cancel();
+        }
+        spawn(async move {
+            loading.set(true);
+            let cancel = CancelFlag::default();
+            send_cancel.set(Some(cancel.clone()));
+            let mut req = current_request.read().clone();
+            if let Some(method) = method {
+                req.method = method.to_string();
+            }
+            let resolver = settings.read().active_resolver();
+            let resolved = match resolver {
+                Ok(resolver) => hooks::prepare(&resolver, current_path().as_deref(), &req).await
+                    .and_then(|resolver| resolver.resolve_request(&auth::inherit(current_path().as_deref(), &req))),
+                Err(e) => Err(e),
+            };
+            let (url, res, resolved) = match resolved {
+                Ok(resolved) => {
+                    let res = execute_queued(&resolved, &cancel, |waiting| queued.set(waiting)).await;
+                    (resolved.url.clone(), res, Some(resolved))
                 }
+                Err(e) => (req.url.clone(), Err(e), None),
+            };
+            send_cancel.set(None);
+            // Cancelled while queued: nothing was sent
+            if cancel.is_cancelled() {
+                loading.set(false);
+                return;
+            }
+            // What is written down, redacted if the settings say so
+            let persisted = match &res {
+                Ok(sent) if settings.read().redact_persisted => Ok(settings.read().redactor().response(sent)),
+                other => other.clone(),
+            };
+            let entry = HistoryEntry::new(current_path(), &req, &url, &persisted);
+            history.write().push(entry.clone());
+            tokio::task::spawn_blocking(move || history::append(&entry));
+            response.set(Some(res.clone()));
+            loading.set(false);
 
-                div { class: "tab-content",
-                    match active_tab() {
-                        Tab::Headers => rsx! {
-                            HeadersEditor { 
-                                headers: current_request.read().headers.clone(),
-                                on_change: move |new_headers| {
-                                    current_request.write().headers = new_headers;
-                                }
-                            }
-                        },
-                        Tab::Body => rsx! {
-                            textarea {
-                                class: "body-editor",
-                                value: "{current_request.read().body}",
-                                oninput: move |evt| {
-                                    current_request.write().body = evt.value();
-                                }
-                            }
-                        }
+            if record_fixtures()
+                && let Ok(recorded) = &persisted
+            {
+                let dir = settings.read().fixtures_dir();
+                let title = req.name.clone()
+                    .or_else(|| current_path().and_then(|p| p.file_stem().map(|n| n.to_string_lossy().into_owned())))
+                    .unwrap_or_default();
+                let recorded = recorded.clone();
+                let written = tokio::task::spawn_blocking(move || fixtures::record(&dir, &title, &req, &recorded)).await;
+                fixture_note.set(Some(match written {
+                    Ok(Ok(folder)) => Ok(folder),
+                    Ok(Err(e)) => Err(format!("Cannot record the fixture: {}", e)),
+                    Err(e) => Err(format!("Cannot record the fixture: {}", e)),
+                }));
+            }
+
+            if let (Some(resolved), Ok(first)) = (resolved, res)
+                && let Some(config) = PollConfig::from_options(&resolved.options)
+            {
+                let cancel = CancelFlag::default();
+                poll_cancel.set(Some(cancel.clone()));
+                poll_progress.set(Some(PollProgress::default()));
+                let (last, end) = poll::poll(&config, &resolved, first, &cancel, |step| {
+                    if let Some(progress) = poll_progress.write().as_mut() {
+                        progress.steps.push(step);
                     }
+                }).await;
+                if let Some(progress) = poll_progress.write().as_mut() {
+                    progress.end = Some(end);
                 }
+                poll_cancel.set(None);
+                response.set(Some(Ok(last)));
+            }
+        });
+    };
 
-                div { class: "result-area",
-                    match response.read().as_ref() {
-                        Some(Ok(res)) => rsx! {
-                            div { class: "result-header", "Status: {res.status} {res.status_text}" }
-                            pre { class: "result-body", "{res.body}" }
-                        },
-                        Some(Err(e)) => rsx! {
-                            div { class: "result-header", style: "color: #f44747", "Error" }
-                            pre { class: "result-body", "{e}" }
-                        },
-                        None => rsx! {
-                            div { class: "result-header", "No response yet" }
-                        }
-                    }
+    let repeat = move || {
+        if repeat_progress.read().is_some() {
+            return;
+        }
+        let total = repeat_count();
+        spawn(async move {
+            let req = current_request.read().clone();
+            // The hook runs once for the whole batch
+            let resolver = settings.read().active_resolver();
+            let resolved = match resolver {
+                Ok(resolver) => hooks::prepare(&resolver, current_path().as_deref(), &req).await
+                    .and_then(|resolver| resolver.resolve_request(&auth::inherit(current_path().as_deref(), &req))),
+                Err(e) => Err(e),
+            };
+            let resolved = match resolved {
+                Ok(resolved) => resolved,
+                Err(e) => {
+                    repeat_stats.set(Some(Err(e)));
+                    return;
                 }
+            };
+            repeat_stats.set(None);
+            let mut responses = Vec::new();
+            let never_cancelled = CancelFlag::default();
+            for done in 0..total {
+                repeat_progress.set(Some((done, total)));
+                runner::pause(resolved.options.delay_before_ms, &never_cancelled).await;
+                responses.push(execute_request(&resolved).await);
+                runner::pause(resolved.options.delay_after_ms, &never_cancelled).await;
+            }
+            repeat_progress.set(None);
+            repeat_stats.set(Some(
+                LatencyStats::from_responses(&responses, &resolved.options)
+                    .ok_or_else(|| format!("None of the {} runs got a response", total)),
+            ));
+            if let Some(last) = responses.pop() {
+                response.set(Some(last));
             }
+        });
+    };
+
+    // Methods listed in the settings, and requests pinned to other
+    // environments than the active one, wait for confirmation in a dialog
+    let needs_confirmation = move |method: Option<&'static str>| {
+        let method = method.map(str::to_string).unwrap_or_else(|| current_request.read().method.clone());
+        let settings = settings.read();
+        let options = &current_request.read().options;
+        settings.confirm_methods.iter().any(|m| m.eq_ignore_ascii_case(&method))
+            || options.pinned_elsewhere(settings.active_environment.as_deref())
+            || (settings.protects(&method) && options.confirm_protected != Some(false))
+    };
+    let mut send = move |method: Option<&'static str>| {
+        if needs_confirmation(method) {
+            pending_send.set(Some(PendingSend::Send(method)));
+        } else {
+            dispatch(method);
         }
-    }
-}
+    };
+    let on_send = move |_| send(None);
 
-#[component]
-fn Sidebar(node: FileNode, on_select: EventHandler<PathBuf>, current_path: Option<PathBuf>) -> Element {
-    match node {
-        FileNode::Folder { name, children, .. } => {
-            rsx! {
-                div { class: "tree-node",
-                    div { class: "folder-node", "📁 {name}" }
-                    div { style: "margin-left: 10px",
-                        for child in children {
-                            Sidebar { 
-                                node: child.clone(), 
-                                on_select: move |p| on_select.call(p),
-                                current_path: current_path.clone()
-                            }
-                        }
-                    }
+    use_effect(move || {
+        let Some(url) = resend_url() else { return };
+        resend_url.set(None);
+        current_request.write().url = url;
+        send(None);
+    });
+    let on_repeat = move |_| {
+        if needs_confirmation(None) {
+            pending_send.set(Some(PendingSend::Repeat));
+        } else {
+            repeat();
+        }
+    };
+
+    // Archiving and unarchiving move a folder; an open request inside it
+    // moves along
+    let mut move_folder = move |folder: PathBuf, archive: bool| {
+        let moved = if archive { storage::archive_folder(&folder) } else { storage::unarchive_folder(&folder) };
+        match moved {
+            Ok(target) => {
+                tree_error.set(None);
+                let open = current_path.peek().clone();
+                if let Some(relative) = open.as_ref().and_then(|p| p.strip_prefix(&folder).ok()) {
+                    current_path.set(Some(target.join(relative)));
                 }
+                refresh_tree();
             }
+            Err(e) => tree_error.set(Some(format!("Could not {} {}: {}", if archive { "archive" } else { "unarchive" }, folder.display(), e))),
         }
-        FileNode::File { name, path } => {
-            let is_selected = current_path.map_or(false, |p| p == path);
-            rsx! {
-                div { 
-                    class: if is_selected { "file-node selected" } else { "file-node" },
-                    onclick: move |_| on_select.call(path.clone()),
-                    "📄 {name}" 
+    };
+
+    // Paths below `from` now live below `to`: the open request, the
+    // expanded folders and the focus follow
+    let mut remap_paths = move |from: &Path, to: Option<&Path>| {
+        let remap = |path: &Path| match path.strip_prefix(from) {
+            Ok(relative) => Some(to.map(|to| to.join(relative))),
+            Err(_) => None,
+        };
+        let open = current_path.peek().clone();
+        if let Some(moved) = open.as_deref().and_then(remap) {
+            current_path.set(moved);
+        }
+        let focus = tree_focus.peek().clone();
+        if let Some(moved) = focus.as_deref().and_then(remap) {
+            tree_focus.set(moved);
+        }
+        let mut cache = tree.write();
+        let expanded = std::mem::take(&mut cache.expanded);
+        cache.expanded = expanded.into_iter()
+            .filter_map(|path| remap(&path).unwrap_or(Some(path)))
+            .collect();
+    };
+
+    let on_rename_entry = move |(path, name): (PathBuf, String)| {
+        if read_only() {
+            return;
+        }
+        match storage::rename_entry(&path, &name) {
+            Ok(target) => {
+                tree_error.set(None);
+                remap_paths(&path, Some(&target));
+                refresh_tree();
+            }
+            Err(e) => tree_error.set(Some(format!("Could not rename {}: {}", path.display(), e))),
+        }
+    };
+
+    let mut delete_entry = move |path: PathBuf| {
+        match storage::delete_entry(&path) {
+            Ok(()) => {
+                tree_error.set(None);
+                remap_paths(&path, None);
+                refresh_tree();
+            }
+            Err(e) => tree_error.set(Some(format!("Could not delete {}: {}", path.display(), e))),
+        }
+    };
+
+    // `override_protected` lets the run send with a protected environment
+    let mut run_folder = move |folder: PathBuf, override_protected: bool| {
+        if run_cancel.read().is_some() {
+            return;
+        }
+        let cancel = CancelFlag::default();
+        run_cancel.set(Some(cancel.clone()));
+        run_report.set(None);
+        spawn(async move {
+            let settings = settings.read().clone();
+            let resolver = match settings.active_resolver() {
+                Ok(resolver) => resolver,
+                Err(e) => {
+                    response.set(Some(Err(e)));
+                    run_cancel.set(None);
+                    return;
                 }
+            };
+            let mut run = RunContext::new(resolver, settings.run_error_policy, settings.active_environment.clone(), settings.run_pinned_policy);
+            if !override_protected {
+                run.refuse(settings.protected_environments.clone());
             }
+            let report = runner::run_folder(&folder, &mut run, &cancel).await;
+            let saved = report.clone();
+            let _ = tokio::task::spawn_blocking(move || {
+                let _ = RunRecord::append_all(&saved);
+                RunSummary::save_all(&saved)
+            }).await;
+            run_report.set(Some(report));
+            run_cancel.set(None);
+            refresh_tree();
+        });
+    };
+    let on_run_folder = move |folder: PathBuf| {
+        if settings.read().is_protected(settings.read().active_environment.as_deref()) {
+            pending_protected_run.set(Some(folder));
+        } else {
+            run_folder(folder, false);
         }
-    }
-}
+    };
 
-#[component]
-fn HeadersEditor(headers: Vec<(String, String)>, on_change: EventHandler<Vec<(String, String)>>) -> Element {
-    // Use Rc to share the read-only props with closures
-    let headers_rc = std::rc::Rc::new(headers);
+    let on_save = move |_| {
+        if read_only() {
+            return;
+        }
+        if let Some(path) = current_path.read().as_ref() {
+            // Refresh so a changed title shows up in the sidebar
+            let saved = storage::save_request(path, &current_request.read());
+            if let Ok(id) = saved {
+                current_request.write().id = Some(id);
+                refresh_tree();
+            }
+        } else {
+            show_save_as.set(true);
+        }
+    };
 
-    let mut display_headers = headers_rc.as_ref().clone();
-    if display_headers.is_empty() || !display_headers.last().unwrap().0.is_empty() {
-        display_headers.push(("".to_string(), "".to_string()));
-    }
+    let scheme_hint = {
+        let req = current_request.read();
+        let defaults = settings.read().request_defaults();
+        let scheme = req.options.inherit(&defaults).default_scheme.unwrap_or_else(|| "https".to_string());
+        client::with_default_scheme(&req.url, &scheme)
+    };
+    // With no request open, the workspace README takes the response's place
+    let show_workspace_readme = current_path.read().is_none()
+        && response.read().is_none()
+        && current_request.read().url.is_empty()
+        && tree.read().readmes.contains(&tree.read().root);
+    let pins = current_request.read().options.environments.join(", ");
+    let timeout = current_request.read().options.get("timeout");
+    let default_timeout = settings.read().default_timeout_ms;
+    let pin_mismatch = current_request.read().options.pinned_elsewhere(settings.read().active_environment.as_deref());
+    let title_placeholder = current_path.read().as_ref()
+        .and_then(|p| p.file_stem())
+        .map_or("Untitled request".to_string(), |n| n.to_string_lossy().into_owned());
+    // An export is shared like a copy and kept like history, so either asks for redaction
+    let exported_response = show_markdown().then(|| match response() {
+        Some(Ok(res)) if body_view().privacy || settings.read().redact_persisted => Some(Ok(settings.read().redactor().response(&res))),
+        other => other,
+    }).flatten();
 
     rsx! {
-        div {
-            for (i, (k, v)) in display_headers.into_iter().enumerate() {
-                // Clone Rc for the closures in this iteration
-                let headers_for_key = headers_rc.clone();
-                let headers_for_val = headers_rc.clone();
-                let headers_for_del = headers_rc.clone();
-
-                div { class: "header-row", key: "{i}",
-                    input {
-                        r#type: "text",
-                        placeholder: "Key",
-                        value: "{k}",
-                        oninput: move |evt| {
-                            let mut new_headers = headers_for_key.as_ref().clone();
-                            if i < new_headers.len() {
-                                new_headers[i].0 = evt.value();
-                            } else {
-                                new_headers.push((evt.value(), "".to_string()));
-                            }
-                            on_change.call(new_headers);
-                        }
+        style { {include_str!("style.css")} }
+        if show_settings() {
+            AppSettings {
+                settings,
+                trusted,
+                on_close: move |_| show_settings.set(false),
+            }
+        }
+        if let Some(folder) = show_timings() {
+            TimingsDialog {
+                folder,
+                utc_times: settings.read().utc_times,
+                on_open: move |(path, result): (PathBuf, Result<HttpResponse, String>)| {
+                    on_select_file(path.clone());
+                    if current_path.read().as_ref() == Some(&path) {
+                        response.set(Some(result));
                     }
-                    input {
+                    show_timings.set(None);
+                },
+                on_close: move |_| show_timings.set(None),
+            }
+        }
+        if let Some(folder) = show_readme() {
+            ReadmeDialog {
+                folder,
+                on_open: on_select_file,
+                on_close: move |_| show_readme.set(None),
+            }
+        }
+        if show_markdown() {
+            MarkdownExportDialog {
+                markdown: export::to_markdown(
+                    &current_request.read(),
+                    exported_response.as_ref(),
+                    current_path.read().as_ref().and_then(|p| p.file_stem()).and_then(|n| n.to_str()),
+                ),
+                on_close: move |_| show_markdown.set(false),
+            }
+        }
+        if show_saved_diff() && let Some(path) = current_path() {
+            SavedDiffDialog {
+                request: current_request.read().clone(),
+                path,
+                on_change: move |req: RequestData| current_request.set(req),
+                on_close: move |_| show_saved_diff.set(false),
+            }
+        }
+        if let Some(pending) = pending_send() {
+            ConfirmSendDialog {
+                request: auth::inherit(current_path().as_deref(), &current_request.read()),
+                method: match pending {
+                    PendingSend::Send(Some(method)) => method.to_string(),
+                    _ => current_request.read().method.clone(),
+                },
+                repeat: matches!(pending, PendingSend::Repeat).then_some(repeat_count()),
+                settings: settings.read().clone(),
+                on_never_ask: move |_| current_request.write().options.confirm_protected = Some(false),
+                on_confirm: move |_| {
+                    pending_send.set(None);
+                    match pending {
+                        PendingSend::Send(method) => dispatch(method),
+                        PendingSend::Repeat => repeat(),
+                    }
+                },
+                on_close: move |_| pending_send.set(None),
+            }
+        }
+        if let Some(folder) = pending_protected_run() {
+            ProtectedRunDialog {
+                folder: folder.clone(),
+                environment: settings.read().active_environment.clone().unwrap_or_default(),
+                on_confirm: move |_| {
+                    pending_protected_run.set(None);
+                    run_folder(folder.clone(), true);
+                },
+                on_close: move |_| pending_protected_run.set(None),
+            }
+        }
+        if let Some(path) = pending_delete() {
+            DeleteDialog {
+                path: path.clone(),
+                on_confirm: move |_| {
+                    pending_delete.set(None);
+                    delete_entry(path.clone());
+                },
+                on_close: move |_| pending_delete.set(None),
+            }
+        }
+        if show_templates() {
+            TemplateDialog {
+                on_created: move |_| {
+                    refresh_tree();
+                    show_templates.set(false);
+                },
+                on_close: move |_| show_templates.set(false),
+            }
+        }
+        if show_fuzz() {
+            FuzzDialog {
+                request: auth::inherit(current_path().as_deref(), &current_request.read()),
+                settings: settings.read().clone(),
+                on_close: move |_| show_fuzz.set(false),
+            }
+        }
+        if show_compare() {
+            CompareDialog {
+                request: auth::inherit(current_path().as_deref(), &current_request.read()),
+                settings: settings.read().clone(),
+                on_close: move |_| show_compare.set(false),
+            }
+        }
+        if show_copy_curl() {
+            CopyCurlDialog {
+                request: current_request.read().clone(),
+                path: current_path(),
+                settings: settings.read().clone(),
+                on_close: move |_| show_copy_curl.set(false),
+            }
+        }
+        if show_preflight() {
+            PreflightDialog {
+                request: auth::inherit(current_path().as_deref(), &current_request.read()),
+                settings: settings.read().clone(),
+                on_close: move |_| show_preflight.set(false),
+            }
+        }
+        if show_raw_mode() {
+            RawModeDialog {
+                request: current_request.read().clone(),
+                settings: settings.read().clone(),
+                on_close: move |_| show_raw_mode.set(false),
+            }
+        }
+        if show_save_as() {
+            SaveAsDialog {
+                request: current_request.read().clone(),
+                root: tree.read().root.clone(),
+                folders: tree.read().folders(),
+                creating: false,
+                on_saved: move |path: PathBuf| {
+                    current_request.write().id = storage::read_id(&path);
+                    reveal_in_tree(&path);
+                    current_path.set(Some(path));
+                    scratch.set(None);
+                    refresh_tree();
+                    show_save_as.set(false);
+                },
+                on_close: move |_| show_save_as.set(false),
+            }
+        }
+        if show_new_request() {
+            SaveAsDialog {
+                request: RequestData::new(),
+                root: tree.read().root.clone(),
+                folders: tree.read().folders(),
+                creating: true,
+                on_saved: move |path: PathBuf| {
+                    on_select_file(path.clone());
+                    reveal_in_tree(&path);
+                    refresh_tree();
+                    show_new_request.set(false);
+                },
+                on_close: move |_| show_new_request.set(false),
+            }
+        }
+        if show_history() {
+            HistoryView {
+                entries: history,
+                ids: id_index,
+                utc_times: settings.read().utc_times,
+                on_open: move |entry: HistoryEntry| {
+                    if let Ok(req) = RequestData::from_http_string(&entry.request) {
+                        park_scratch();
+                        let path = entry.resolved_path(&id_index.read());
+                        swap_response(&path);
+                        current_request.set(req);
+                        current_path.set(path);
+                        response.set(Some(entry.response));
+                    }
+                    show_history.set(false);
+                },
+                on_close: move |_| show_history.set(false),
+            }
+        }
+        if show_import_link() {
+            ImportLinkDialog {
+                on_import: move |req: RequestData| {
+                    new_request.set(Some(req));
+                    show_import_link.set(false);
+                },
+                on_close: move |_| show_import_link.set(false),
+            }
+        }
+        if show_import_curl() {
+            ImportCurlDialog {
+                on_import: move |req: RequestData| {
+                    new_request.set(Some(req));
+                    show_import_curl.set(false);
+                },
+                on_close: move |_| show_import_curl.set(false),
+            }
+        }
+        if show_cookies() {
+            CookieJarDialog {
+                utc_times: settings.read().utc_times,
+                on_close: move |_| show_cookies.set(false),
+            }
+        }
+        if let Some(offer) = clipboard_offer() {
+            div { class: "toast",
+                span {
+                    if offer.body.is_empty() && offer.headers.is_empty() {
+                        "Open copied URL {offer.url}?"
+                    } else {
+                        "Import copied curl command ({offer.method} {offer.url})?"
+                    }
+                }
+                button {
+                    onclick: move |_| {
+                        new_request.set(Some(offer.clone()));
+                        clipboard_offer.set(None);
+                    },
+                    "Import"
+                }
+                button { class: "remove-view", title: "Dismiss", onclick: move |_| clipboard_offer.set(None), "×" }
+            }
+        }
+        if run_cancel.read().is_some() || run_report.read().is_some() {
+            RunReportView {
+                report: run_report(),
+                running: run_cancel.read().is_some(),
+                on_cancel: move |_| {
+                    if let Some(cancel) = run_cancel.read().as_ref() {
+                        cancel.cancel();
+                    }
+                },
+                on_close: move |_| run_report.set(None),
+                on_timings: move |folder| {
+                    run_report.set(None);
+                    show_timings.set(Some(folder));
+                },
+            }
+        }
+        div { id: "main",
+            style: "--body-font-size: {settings.read().body_font_size}px",
+            onmousemove: move |evt| {
+                if let Some((start_y, start_height)) = drag_start() {
+                    let height = start_height + start_y - evt.client_coordinates().y;
+                    settings.write().response_height = height.clamp(80.0, 2000.0);
+                }
+            },
+            onmouseup: move |_| {
+                if drag_start().is_some() {
+                    drag_start.set(None);
+                    let _ = settings.read().save();
+                }
+            },
+            div { class: "sidebar",
+                h3 { "Requests" }
+                button { onclick: on_refresh_tree, "Refresh" }
+                button { onclick: move |_| show_settings.set(true), "⚙ Settings" }
+                select {
+                    class: "env-picker",
+                    title: "Environment whose variables requests are sent with",
+                    value: settings.read().active_environment.clone().unwrap_or_default(),
+                    onchange: move |evt| {
+                        let name = evt.value();
+                        settings.write().active_environment = (!name.is_empty()).then_some(name);
+                        let _ = settings.read().save();
+                    },
+                    option { value: "", "No environment" }
+                    for name in environments() {
+                        option { value: "{name}", "{name}" }
+                    }
+                }
+                button { onclick: move |_| show_history.set(true), "History" }
+                button {
+                    disabled: read_only(),
+                    title: "Create a folder of requests from a template",
+                    onclick: move |_| show_templates.set(true),
+                    "New from template…"
+                }
+                button {
+                    title: "Create an empty request file in a folder of your choice",
+                    disabled: read_only(),
+                    onclick: move |_| show_new_request.set(true),
+                    "New request…"
+                }
+                button {
+                    title: "Open a request from a share link or blob",
+                    onclick: move |_| show_import_link.set(true),
+                    "Import link…"
+                }
+                button {
+                    title: "Open a pasted curl command as a new unsaved request",
+                    onclick: move |_| show_import_curl.set(true),
+                    "Import curl…"
+                }
+                button {
+                    title: "See the stored cookies or import a cookies.txt",
+                    onclick: move |_| show_cookies.set(true),
+                    "Cookies…"
+                }
+                button {
+                    title: "Switch between the folder tree and a flat list",
+                    onclick: move |_| list_view.toggle(),
+                    if list_view() { "Tree" } else { "List" }
+                }
+                label { class: "show-archived",
+                    title: "List the folders moved to .archive, and include their requests in the list search",
+                    input {
+                        r#type: "checkbox",
+                        checked: show_archived(),
+                        onchange: move |evt| show_archived.set(evt.checked()),
+                    }
+                    "Show archived"
+                }
+                if let Some(e) = tree_error() {
+                    div { class: "settings-error", "{e}" }
+                }
+                div {
+                    class: if current_path.read().is_none() { "file-node scratch selected" } else { "file-node scratch" },
+                    title: "Unsaved request; use Save to keep it as a file",
+                    onclick: on_open_scratch,
+                    "✎ Scratch"
+                }
+                if list_view() {
+                    RequestList {
+                        revision: tree_revision,
+                        show_archived,
+                        history,
+                        ids: id_index,
+                        on_select: on_select_file,
+                        current_path: current_path.read().clone()
+                    }
+                } else {
+                    div { class: "tree", role: "tree", tabindex: "0", onkeydown: on_tree_key,
+                        Sidebar {
+                            path: tree.read().root.clone(),
+                            cache: tree,
+                            on_toggle: move |path: PathBuf| {
+                                tree_focus.set(Some(path.clone()));
+                                on_toggle_folder(path);
+                            },
+                            on_select: move |path: PathBuf| {
+                                tree_focus.set(Some(path.clone()));
+                                on_select_file(path);
+                            },
+                            on_run: on_run_folder,
+                            on_clear_run: move |folder: PathBuf| {
+                                let _ = RunSummary::clear(&folder);
+                                tree.write().summaries.remove(&folder);
+                            },
+                            on_info: move |folder| show_readme.set(Some(folder)),
+                            on_timings: move |folder| show_timings.set(Some(folder)),
+                            on_archive: move |folder| {
+                                if !read_only() {
+                                    move_folder(folder, true);
+                                }
+                            },
+                            on_rename: on_rename_entry,
+                            on_delete: move |path| {
+                                if !read_only() {
+                                    pending_delete.set(Some(path));
+                                }
+                            },
+                            current_path: current_path.read().clone(),
+                            focused: tree_focus(),
+                            utc_times: settings.read().utc_times,
+                        }
+                    }
+                    if show_archived() {
+                        ArchivedSection {
+                            revision: tree_revision,
+                            current_path: current_path.read().clone(),
+                            on_select: on_select_file,
+                            on_unarchive: move |folder| {
+                                if !read_only() {
+                                    move_folder(folder, false);
+                                }
+                            },
+                        }
+                    }
+                }
+            }
+            div { class: "content",
+                if let LockState::ReadOnly { holder_pid } = lock_state() {
+                    div { class: "read-only-banner",
+                        "Another Requester instance"
+                        if let Some(pid) = holder_pid { " (PID {pid})" }
+                        " is using this workspace, so it is open read-only: saving, settings and history are disabled."
+                        button {
+                            title: "Take over the workspace if the other instance has exited",
+                            onclick: move |_| lock_state.set(workspace::acquire()),
+                            "Retry"
+                        }
+                    }
+                }
+                if !trusted() {
+                    div { class: "read-only-banner",
+                        "This workspace is not trusted, so request hooks and {{{{env:NAME}}}} placeholders are blocked."
+                        if let Some(e) = trust_error() { " {e}" }
+                        button {
+                            title: "Remembered in your configuration, outside the workspace; revoke it in Settings",
+                            onclick: move |_| match workspace::trust(&storage::get_base_dir()) {
+                                Ok(()) => {
+                                    trust_error.set(None);
+                                    trusted.set(true);
+                                }
+                                Err(e) => trust_error.set(Some(format!("Could not save trust: {}", e))),
+                            },
+                            "Trust this workspace"
+                        }
+                    }
+                }
+                if !id_index.read().notices.is_empty() {
+                    div { class: "read-only-banner",
+                        div {
+                            for notice in id_index.read().notices.iter() {
+                                div { "{notice}" }
+                            }
+                        }
+                        button { onclick: move |_| id_index.write().notices.clear(), "Dismiss" }
+                    }
+                }
+                if current_path.read().as_deref().is_some_and(storage::is_archived) {
+                    div { class: "archived-note",
+                        span { class: "archived-badge", "Archived" }
+                        "This request is in an archived folder; unarchive it from the Archived section to bring it back."
+                    }
+                }
+                if !missing_assert_sets.read().is_empty() {
+                    div { class: "result-warning",
+                        {format!("Unknown assertion sets: {}. No .folder.json above this request defines them, so it fails when sent.", missing_assert_sets.read().join(", "))}
+                    }
+                }
+                input {
+                    r#type: "text",
+                    class: "request-title",
+                    placeholder: "{title_placeholder}",
+                    value: "{current_request.read().name.clone().unwrap_or_default()}",
+                    oninput: move |evt| {
+                        let title = evt.value();
+                        current_request.write().name = (!title.trim().is_empty()).then_some(title);
+                    }
+                }
+                div { class: "address-bar",
+                    select {
+                        value: "{current_request.read().method}",
+                        onchange: move |evt| {
+                            current_request.write().method = evt.value();
+                        },
+                        option { value: "GET", "GET" }
+                        option { value: "POST", "POST" }
+                        option { value: "PUT", "PUT" }
+                        option { value: "DELETE", "DELETE" }
+                        option { value: "PATCH", "PATCH" }
+                    }
+                    input {
                         r#type: "text",
-                        placeholder: "Value",
-                        value: "{v}",
+                        placeholder: "https://api.example.com",
+                        value: "{current_request.read().url}",
+                        oninput: move |evt| {
+                            current_request.write().url = evt.value();
+                        }
+                    }
+                    button { 
+                        disabled: loading(),
+                        onclick: on_send, 
+                        if queued() { "Queued..." } else if loading() { "Sending..." } else { "Send" }
+                    }
+                    if queued() {
+                        button {
+                            title: "Another request to this host is in flight; cancel to drop this one before it is sent",
+                            onclick: move |_| {
+                                if let Some(cancel) = send_cancel.read().as_ref() {
+                                    cancel.cancel();
+                                }
+                            },
+                            "Cancel"
+                        }
+                    }
+                    if !pins.is_empty() {
+                        span {
+                            class: if pin_mismatch { "env-pin mismatch" } else { "env-pin" },
+                            title: if pin_mismatch { "Pinned to {pins}; sending with another environment asks first" } else { "Pinned to {pins}" },
+                            "📌 {pins}"
+                        }
+                    }
+                    input {
+                        r#type: "number",
+                        class: "timeout-input",
+                        title: "Timeout for this request in ms; empty uses the default from Settings",
+                        min: "1",
+                        placeholder: "{default_timeout} ms",
+                        value: "{timeout}",
+                        oninput: move |evt| {
+                            let _ = current_request.write().options.set("timeout", &evt.value());
+                        }
+                    }
+                    button {
+                        disabled: loading(),
+                        title: "Send this request with the HEAD method, keeping the saved method",
+                        onclick: move |_| send(Some("HEAD")),
+                        "HEAD"
+                    }
+                    input {
+                        r#type: "number",
+                        class: "repeat-count",
+                        title: "Number of times Repeat sends the request",
+                        min: "1",
+                        max: "10000",
+                        value: "{repeat_count}",
                         oninput: move |evt| {
-                            let mut new_headers = headers_for_val.as_ref().clone();
-                            if i < new_headers.len() {
-                                new_headers[i].1 = evt.value();
-                            } else {
-                                new_headers.push(("".to_string(), evt.value()));
+                            if let Ok(n) = evt.value().parse::<u32>() {
+                                repeat_count.set(n.clamp(1, 10_000));
                             }
-                            on_change.call(new_headers);
                         }
                     }
                     button {
+                        disabled: repeat_progress.read().is_some(),
+                        title: "Send the request repeatedly and summarize the latencies",
+                        onclick: on_repeat,
+                        if let Some((done, total)) = repeat_progress() { "{done}/{total}" } else { "Repeat" }
+                    }
+                    button {
+                        title: "Send this request to several environments and compare the responses",
+                        onclick: move |_| show_compare.set(true),
+                        "Compare…"
+                    }
+                    button {
+                        title: "Send variations of one field and tabulate the statuses",
+                        onclick: move |_| show_fuzz.set(true),
+                        "Fuzz…"
+                    }
+                    button {
+                        title: "Send the CORS preflight a browser would send for this request",
+                        onclick: move |_| show_preflight.set(true),
+                        "Preflight"
+                    }
+                    button {
+                        class: "raw-mode-toggle",
+                        title: "Expert: edit the literal HTTP/1.1 message and send it unchecked, e.g. to test how a server handles malformed requests",
+                        onclick: move |_| show_raw_mode.set(true),
+                        "Raw…"
+                    }
+                    button {
+                        class: if record_fixtures() { "record-toggle recording" } else { "record-toggle" },
+                        title: "Write each response to a send, with its request, as a fixture for contract tests",
+                        onclick: move |_| {
+                            record_fixtures.toggle();
+                            fixture_note.set(None);
+                        },
+                        if record_fixtures() { "● Recording" } else { "Record fixtures" }
+                    }
+                    if current_path.read().is_some() {
+                        button {
+                            title: "Show what changed since the file was last saved",
+                            onclick: move |_| show_saved_diff.set(true),
+                            "Diff"
+                        }
+                    }
+                    button {
+                        title: "Copy a link that opens this request elsewhere; literal credentials are replaced by placeholders",
+                        onclick: move |_| copy_to_clipboard(&share::to_link(&current_request.read())),
+                        "Copy share link"
+                    }
+                    button {
+                        title: "Copy a curl command that sends this request, placeholders left as they are, and show it",
                         onclick: move |_| {
-                            let mut new_headers = headers_for_del.as_ref().clone();
-                            if i < new_headers.len() {
-                                new_headers.remove(i);
-                                on_change.call(new_headers);
+                            copy_to_clipboard(&export::to_curl(&current_request.read()));
+                            show_copy_curl.set(true);
+                        },
+                        "Copy as cURL"
+                    }
+                    button { disabled: read_only(), onclick: on_save, "Save" }
+                }
+
+                if let Some(url) = scheme_hint {
+                    div { class: "result-note address-hint", "No scheme given; the request will go to {url}" }
+                }
+                match fixture_note() {
+                    Some(Ok(folder)) => rsx! { div { class: "result-note address-hint", "Recorded fixture {folder.display()}" } },
+                    Some(Err(e)) => rsx! { div { class: "settings-error address-hint", "{e}" } },
+                    None => rsx! {},
+                }
+                div { class: "tabs",
+                    div { 
+                        class: if active_tab() == Tab::Headers { "tab active" } else { "tab" },
+                        onclick: move |_| active_tab.set(Tab::Headers),
+                        "Headers"
+                    }
+                    div { 
+                        class: if active_tab() == Tab::Body { "tab active" } else { "tab" },
+                        onclick: move |_| active_tab.set(Tab::Body),
+                        "Body"
+                    }
+                    div {
+                        class: if active_tab() == Tab::Params { "tab active" } else { "tab" },
+                        onclick: move |_| active_tab.set(Tab::Params),
+                        "Params"
+                    }
+                    div {
+                        class: if active_tab() == Tab::Auth { "tab active" } else { "tab" },
+                        onclick: move |_| active_tab.set(Tab::Auth),
+                        "Auth"
+                    }
+                    div { 
+                        class: if active_tab() == Tab::Settings { "tab active" } else { "tab" },
+                        onclick: move |_| active_tab.set(Tab::Settings),
+                        "Settings"
+                    }
+                    div { class: "font-size-controls",
+                        button {
+                            title: "Smaller body font",
+                            onclick: move |_| {
+                                settings.write().change_body_font_size(-1);
+                                let _ = settings.read().save();
+                            },
+                            "A−"
+                        }
+                        span { "{settings.read().body_font_size}px" }
+                        button {
+                            title: "Larger body font",
+                            onclick: move |_| {
+                                settings.write().change_body_font_size(1);
+                                let _ = settings.read().save();
+                            },
+                            "A+"
+                        }
+                    }
+                }
+
+                div { class: "tab-content",
+                    match active_tab() {
+                        Tab::Headers => rsx! {
+                            HeaderPresets {
+                                headers: current_request.read().headers.clone(),
+                                presets: settings.read().header_presets.clone(),
+                                on_apply: move |new_headers| current_request.write().headers = new_headers,
+                                on_save: move |preset| {
+                                    settings.write().save_header_preset(preset);
+                                    let _ = settings.read().save();
+                                },
+                            }
+                            HeadersEditor { 
+                                headers: current_request.read().headers.clone(),
+                                on_change: move |new_headers| {
+                                    current_request.write().headers = new_headers;
+                                }
+                            }
+                            EffectiveHeaders {
+                                headers: effective::effective_headers(
+                                    &current_request.read(),
+                                    auth::effective(current_path().as_deref(), &current_request.read()).as_ref(),
+                                    cookies::cookie_for(&current_request.read().url),
+                                ),
+                                on_jump: move |origin| match origin {
+                                    HeaderOrigin::Request(row) => {
+                                        document::eval(&format!(
+                                            "document.querySelectorAll('.headers-editor .header-row')[{}]?.querySelector('input')?.focus()",
+                                            row,
+                                        ));
+                                    }
+                                    HeaderOrigin::Auth(_) => active_tab.set(Tab::Auth),
+                                    HeaderOrigin::Soap => active_tab.set(Tab::Body),
+                                    HeaderOrigin::Option(_) => active_tab.set(Tab::Settings),
+                                    HeaderOrigin::Automatic => {}
+                                },
                             }
                         },
-                        "✕"
+                        Tab::Body => rsx! {
+                            SoapEditor {
+                                soap: current_request.read().soap.clone(),
+                                on_change: move |soap| current_request.write().soap = soap,
+                            }
+                            BodyEditor {
+                                body: current_request.read().body.clone(),
+                                on_input: move |body| current_request.write().body = body,
+                            }
+                            match body_preview() {
+                                Some(Ok(preview)) => {
+                                    let mut parts = vec![format!("{} as sent", format_size(preview.bytes)), preview.kind.to_string()];
+                                    if let Some(sizes) = preview.compressed {
+                                        parts.push(format!("{}-compressed from {}", sizes.encoding.as_str(), format_size(sizes.original)));
+                                    }
+                                    if current_request.read().options.body_template {
+                                        parts.push("template expanded".to_string());
+                                    }
+                                    if !environment::placeholders(&current_request.read().body).is_empty() {
+                                        parts.push("before variables are filled in".to_string());
+                                    }
+                                    rsx! { div { class: "body-footer", {parts.join(" · ")} } }
+                                }
+                                Some(Err(e)) => rsx! { div { class: "settings-error", "{e}" } },
+                                None => rsx! {},
+                            }
+                        },
+                        Tab::Params => rsx! {
+                            ParamsEditor {
+                                url: current_request.read().url.clone(),
+                                on_change: move |url| current_request.write().url = url,
+                            }
+                        },
+                        Tab::Auth => rsx! {
+                            AuthEditor {
+                                auth: current_request.read().options.auth.clone(),
+                                inherited: folder_auth(current_path().as_deref()),
+                                header_names: current_request.read().headers.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>(),
+                                on_change: move |auth| current_request.write().options.auth = auth,
+                            }
+                        },
+                        Tab::Settings => rsx! {
+                            RequestSettings {
+                                key: "{current_path.read():?}",
+                                options: current_request.read().options.clone(),
+                                defaults: settings.read().request_defaults(),
+                                trusted: trusted(),
+                                on_change: move |options| {
+                                    current_request.write().options = options;
+                                }
+                            }
+                        }
+                    }
+                }
+
+                div {
+                    class: "resize-handle",
+                    onmousedown: move |evt| {
+                        drag_start.set(Some((evt.client_coordinates().y, settings.read().response_height)));
+                    }
+                }
+                div { class: "result-area", style: "height: {settings.read().response_height}px",
+                    onmounted: move |evt| result_area.set(Some(evt.data())),
+                    onscroll: move |evt| response_scroll.set(evt.scroll_top()),
+                    div { class: "result-toolbar",
+                        button {
+                            title: "Export the request and its response as a Markdown report",
+                            onclick: move |_| show_markdown.set(true),
+                            "Export Markdown"
+                        }
+                        if detached() {
+                            button { onclick: move |_| detached.set(false), "Show here" }
+                        } else {
+                            button {
+                                onclick: move |_| {
+                                    detached.set(true);
+                                    open_response_window(ResponseWindowProps {
+                                        response,
+                                        view: body_view,
+                                        request: current_request,
+                                        path: current_path,
+                                        settings,
+                                        detached,
+                                        follow_url,
+                                        new_request,
+                                        resend_url,
+                                    });
+                                },
+                                "⧉ Pop out"
+                            }
+                        }
+                    }
+                    if let Some(path) = current_path() {
+                        RecentRuns {
+                            history,
+                            ids: id_index,
+                            path,
+                            utc_times: settings.read().utc_times,
+                            on_open: move |entry: HistoryEntry| response.set(Some(entry.response)),
+                        }
+                    }
+                    if let Some(stats) = repeat_stats() {
+                        LatencyStatsView { stats, on_close: move |_| repeat_stats.set(None) }
+                    }
+                    if let Some(progress) = poll_progress() {
+                        PollProgressView {
+                            progress,
+                            running: poll_cancel.read().is_some(),
+                            on_cancel: move |_| {
+                                if let Some(cancel) = poll_cancel.read().as_ref() {
+                                    cancel.cancel();
+                                }
+                            },
+                            on_close: move |_| poll_progress.set(None),
+                        }
+                    }
+                    if detached() {
+                        div { class: "result-header", "Response is shown in a separate window" }
+                    } else if show_workspace_readme {
+                        ReadmeView { folder: tree.read().root.clone(), on_open: on_select_file }
+                    } else {
+                        ResponseView {
+                            response: response(),
+                            view: body_view,
+                            options: current_request.read().options.clone(),
+                            path: current_path(),
+                            body_view: current_request.read().body_view.clone(),
+                            on_body_view_change: move |kind| current_request.write().body_view = kind,
+                            view_overrides: settings.read().view_overrides.clone(),
+                            redact_rules: settings.read().redact_rules.clone(),
+                            views: current_request.read().views.clone(),
+                            on_views_change: move |views| current_request.write().views = views,
+                            decode_fields: current_request.read().decode_fields.clone(),
+                            on_decode_fields_change: move |fields| current_request.write().decode_fields = fields,
+                            on_use_as_body: move |body| set_json_body(&mut current_request.write(), body),
+                            on_follow: move |url| follow_url.set(Some(url)),
+                            on_use_as_request: move |res: HttpResponse| new_request.set(Some(res.to_request(&current_request.read().url))),
+                            on_resend_to: move |url| resend_url.set(Some(url)),
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// What the Connection panel shows: the address the response came from and
+/// how its host was resolved.
+fn connection_rows(res: &HttpResponse) -> Vec<(&'static str, String)> {
+    let mut rows = Vec::new();
+    if let Some(addr) = &res.remote_addr {
+        rows.push(("Address", addr.clone()));
+    }
+    if let Some(lookup) = &res.dns {
+        let resolver = if lookup.resolver == dns::SYSTEM { "System resolver".to_string() } else { format!("DNS server {}", lookup.resolver) };
+        rows.push(("Resolved by", format!("{} for {}", resolver, lookup.host)));
+        rows.push(("TTL", lookup.ttl_s.map_or_else(|| "not reported".to_string(), |ttl| format!("{} s", ttl))));
+    }
+    rows
+}
+
+#[component]
+fn ResponseView(
+    response: Option<Result<HttpResponse, String>>,
+    mut view: Signal<BodyView>,
+    options: RequestOptions,
+    /// File of the request, where its assertion sets are looked up.
+    path: Option<PathBuf>,
+    /// The request's own choice of view, if any.
+    body_view: Option<String>,
+    on_body_view_change: EventHandler<Option<String>>,
+    view_overrides: Vec<viewer::ViewOverride>,
+    redact_rules: Vec<String>,
+    views: Vec<(String, String)>,
+    on_views_change: EventHandler<Vec<(String, String)>>,
+    decode_fields: Vec<String>,
+    on_decode_fields_change: EventHandler<Vec<String>>,
+    on_use_as_body: EventHandler<String>,
+    on_follow: EventHandler<String>,
+    on_use_as_request: EventHandler<HttpResponse>,
+    on_resend_to: EventHandler<String>,
+) -> Element {
+    let mut strip_read_only = use_signal(|| true);
+    let mut show_csv_export = use_signal(|| false);
+    let mut show_tokens = use_signal(|| false);
+    let applied = use_signal(|| None::<String>);
+
+    // Checked before redaction, which would change what they see
+    let outcomes = match &response {
+        Some(Ok(res)) => assertions::evaluate(path.as_deref(), &options, res),
+        _ => Vec::new(),
+    };
+    let failed_assertions = outcomes.iter().filter(|o| !o.passed()).count();
+
+    // Privacy mode works on a redacted copy, so everything shown or copied
+    // below is masked
+    let (redactor, _) = redact::Redactor::new(&redact_rules);
+    let response = match response {
+        Some(Ok(res)) if view().privacy => Some(Ok(redactor.response(&res))),
+        Some(Err(e)) if view().privacy => Some(Err(redactor.text(&e))),
+        other => other,
+    };
+    let privacy_title = if redactor.is_empty() {
+        "No redaction rules yet; add them in Settings"
+    } else {
+        "Mask what the redaction rules match, in the view and in copies"
+    };
+
+    // The transform is re-run against whichever response is shown
+    let json_body = match &response {
+        Some(Ok(res)) => serde_json::from_str::<serde_json::Value>(&res.body).ok(),
+        _ => None,
+    };
+    let xml_body = match &response {
+        Some(Ok(res)) if json_body.is_none() && is_xml(res) => Some(res.body.clone()),
+        _ => None,
+    };
+    let has_envelope = xml_body.as_deref().and_then(soap::envelope_body).is_some();
+    // The request's own choice wins while it can show the response
+    let (auto_view, viewer, viewers) = match &response {
+        Some(Ok(res)) => {
+            let auto_view = viewer::default_view(res, &view_overrides);
+            let mime = viewer::mime_type(res).unwrap_or_default();
+            let chosen = body_view.as_deref().and_then(viewer::find).filter(|v| v.can_render(&mime, res));
+            (auto_view, chosen.unwrap_or(auto_view), viewer::available(res))
+        }
+        _ => (viewer::find("text").unwrap(), viewer::find("text").unwrap(), Vec::new()),
+    };
+    let is_json_view = viewer.id() == "json";
+    // JSON bodies get a shortcut between formatted and as-received text
+    let pretty_toggle = viewers.iter().any(|v| v.id() == "pretty").then_some(viewer.id() != "text");
+    let tokens = match &response {
+        Some(Ok(res)) => response_tokens(res, json_body.as_ref()),
+        _ => Vec::new(),
+    };
+    let token_count = tokens.len();
+    let connection = match &response {
+        Some(Ok(res)) => connection_rows(res),
+        _ => Vec::new(),
+    };
+    let rendered = match &response {
+        Some(Ok(res)) => Some(viewer.render(res, &viewer::ViewContext {
+            linkify: view().linkify,
+            envelope_body_only: view().envelope_body_only,
+            on_follow,
+        })),
+        _ => None,
+    };
+    let transformed = match (applied(), &json_body) {
+        (Some(expression), Some(value)) => Some(
+            transform::apply(&expression, value)
+                .map(|out| serde_json::to_string_pretty(&out).unwrap_or_default())
+                .map_err(|e| e.to_string()),
+        ),
+        _ => None,
+    };
+    // What Export CSV writes, and whether it is the expression's selection
+    // rather than the whole body
+    let csv_source = match (&transformed, &response) {
+        (Some(Ok(output)), _) if is_json_view && output.trim_start().starts_with('[') => Some((output.clone(), true)),
+        (_, Some(Ok(res))) if res.body.trim_start().starts_with('[') => Some((res.body.clone(), false)),
+        _ => None,
+    };
+
+    rsx! {
+        match response {
+            Some(Ok(res)) => rsx! {
+                div { class: "result-header",
+                    "Status: {res.status_text} · {res.elapsed_ms} ms · {format_size(res.size_bytes)}"
+                    if !outcomes.is_empty() {
+                        if failed_assertions == 0 {
+                            span { class: "status-badge accepted", "✓ {outcomes.len()} assertions passed" }
+                        } else {
+                            span { class: "status-badge rejected", "✗ {failed_assertions} of {outcomes.len()} assertions failed" }
+                        }
+                    } else if options.accepts(res.status) {
+                        span { class: "status-badge accepted", "✓ accepted" }
+                    } else {
+                        span { class: "status-badge rejected", "✗ not accepted" }
+                    }
+                }
+                if !outcomes.is_empty() {
+                    details { class: "assertions", open: failed_assertions > 0,
+                        summary { "Assertions" }
+                        table {
+                            for outcome in outcomes.iter() {
+                                tr { class: if outcome.passed() { "passed" } else { "failed" },
+                                    td { if outcome.passed() { "✓" } else { "✗" } }
+                                    td { class: "assertion-text", "{outcome.text}" }
+                                    td { class: "assertion-source",
+                                        {outcome.set.clone().unwrap_or_else(|| "this request".to_string())}
+                                    }
+                                    td { {outcome.failure.clone().unwrap_or_default()} }
+                                }
+                            }
+                        }
+                    }
+                }
+                if !connection.is_empty() {
+                    details { class: "connection",
+                        summary { "Connection" }
+                        table {
+                            for (name, value) in connection {
+                                tr {
+                                    th { "{name}" }
+                                    td { "{value}" }
+                                }
+                            }
+                        }
+                    }
+                }
+                details { class: "headers-section",
+                    summary { "Headers ({res.headers.len()})" }
+                    ResponseHeaders { headers: res.headers.clone() }
+                }
+                if let Some(outcome) = res.expect_continue {
+                    div { class: "result-note", "{outcome}" }
+                }
+                if let Some(truncation) = res.truncated.as_ref() {
+                    div { class: "result-warning",
+                        "⚠ Only the first {format_size(truncation.limit_bytes)}"
+                        if let Some(total) = truncation.total_bytes { " of {format_size(total as usize)}" }
+                        " of the body were read, by the body limit for {truncation.rule}. Change it in Settings."
+                    }
+                }
+                if let Some(compressed) = res.compressed_body {
+                    div { class: "result-note", "{compressed}" }
+                }
+                for warning in res.warnings.iter() {
+                    div { class: "result-warning", "⚠ {warning}" }
+                }
+                if let Some(location) = res.downgrading_redirect.clone() {
+                    div { class: "result-toolbar",
+                        button {
+                            title: "Send the request again, with its method and body, to the redirect target",
+                            onclick: move |_| on_resend_to.call(location.clone()),
+                            "Re-send to {location}"
+                        }
+                    }
+                }
+                div { class: "result-toolbar",
+                    label { class: "result-note",
+                        title: "Leave out id, created_at, updated_at and *_url fields",
+                        input {
+                            r#type: "checkbox",
+                            checked: strip_read_only(),
+                            onchange: move |evt| strip_read_only.set(evt.checked()),
+                        }
+                        "strip read-only"
+                    }
+                    button {
+                        disabled: json_body.is_none(),
+                        title: if json_body.is_some() { "Copy this JSON into the request body" } else { "The response is not JSON" },
+                        onclick: {
+                            let json = json_body.clone();
+                            move |_| {
+                                if let Some(json) = &json {
+                                    on_use_as_body.call(json_skeleton(json, strip_read_only()));
+                                }
+                            }
+                        },
+                        "Use as body"
+                    }
+                    button {
+                        title: "Open a new POST request with this body and its content headers",
+                        onclick: {
+                            let res = res.clone();
+                            move |_| on_use_as_request.call(res.clone())
+                        },
+                        "Use as request"
+                    }
+                    if let Some((_, selection)) = csv_source.as_ref() {
+                        button {
+                            title: if *selection { "Export the array the expression selected" } else { "Export the array in the response" },
+                            onclick: move |_| show_csv_export.set(true),
+                            "Export CSV"
+                        }
+                    }
+                    if let Some(pretty) = pretty_toggle {
+                        span { class: "pretty-toggle",
+                            button {
+                                class: if pretty { "active" } else { "" },
+                                title: "Show the JSON indented",
+                                onclick: move |_| on_body_view_change.call(Some("pretty".to_string())),
+                                "Pretty"
+                            }
+                            button {
+                                class: if pretty { "" } else { "active" },
+                                title: "Show the body exactly as received",
+                                onclick: move |_| on_body_view_change.call(Some("text".to_string())),
+                                "Raw"
+                            }
+                        }
+                    }
+                    select {
+                        title: "Chosen views are remembered for this request",
+                        value: body_view.clone().unwrap_or_else(|| "auto".to_string()),
+                        onchange: move |evt| on_body_view_change.call(Some(evt.value()).filter(|id| id != "auto")),
+                        option { value: "auto", "Auto ({auto_view.label()})" }
+                        for option in viewers.iter() {
+                            option { value: option.id(), "{option.label()}" }
+                        }
+                    }
+                    if has_envelope && viewer.id() == "xml" {
+                        label { class: "result-note",
+                            title: "Show only the contents of the SOAP Body",
+                            input {
+                                r#type: "checkbox",
+                                checked: view().envelope_body_only,
+                                onchange: move |evt| view.write().envelope_body_only = evt.checked(),
+                            }
+                            "body only"
+                        }
+                    }
+                    if token_count > 0 {
+                        button {
+                            title: "Decode the JSON Web Tokens in the body and cookies",
+                            onclick: move |_| show_tokens.toggle(),
+                            "JWT ({token_count})"
+                        }
+                    }
+                    button {
+                        title: "Make URLs in the body open as new requests",
+                        onclick: move |_| view.write().linkify ^= true,
+                        if view().linkify { "Plain" } else { "Links" }
+                    }
+                    button {
+                        class: if view().privacy { "privacy-toggle active" } else { "privacy-toggle" },
+                        title: privacy_title,
+                        onclick: move |_| view.write().privacy ^= true,
+                        if view().privacy { "Privacy on" } else { "Privacy" }
+                    }
+                }
+                if let Some((body, _)) = csv_source.filter(|_| show_csv_export()) {
+                    CsvExportDialog { body, on_close: move |_| show_csv_export.set(false) }
+                }
+                if show_tokens() {
+                    for (source, token) in tokens {
+                        div { class: "jwt-source", "{source}" }
+                        JwtInspector { token }
+                    }
+                }
+                if let Some(json) = json_body.as_ref().filter(|_| is_json_view) {
+                    TransformBar { views, on_views_change, applied }
+                    DecodedFields { body: json.clone(), paths: decode_fields, on_change: on_decode_fields_change }
+                }
+                if let Some(Ok(output)) = transformed.as_ref().filter(|_| is_json_view) {
+                    div { class: "result-toolbar",
+                        button {
+                            onclick: {
+                                let output = output.clone();
+                                move |_| copy_to_clipboard(&output)
+                            },
+                            "Copy"
+                        }
+                    }
+                    if view().linkify {
+                        LinkedBody { text: output.clone(), on_follow }
+                    } else {
+                        pre { class: "result-body", "{output}" }
+                    }
+                } else if let Some(Err(e)) = transformed.as_ref().filter(|_| is_json_view) {
+                    div { class: "settings-error", "{e}" }
+                    pre { class: "result-body", "{res.body}" }
+                } else {
+                    {rendered}
+                }
+            },
+            Some(Err(e)) => rsx! {
+                div { class: "result-header", style: "color: #f44747", "Error" }
+                if let Some(url) = client::plain_http_retry(&e) {
+                    div { class: "result-toolbar",
+                        button {
+                            onclick: move |_| on_resend_to.call(url.clone()),
+                            "Retry as http://"
+                        }
+                    }
+                }
+                pre { class: "result-body", "{e}" }
+            },
+            None => rsx! {
+                div { class: "result-header", "No response yet" }
+            }
+        }
+    }
+}
+
+/// Response headers in the order received, one row per occurrence so
+/// repeated ones such as `Set-Cookie` each get their own.
+#[component]
+fn ResponseHeaders(headers: Vec<(String, String)>) -> Element {
+    let all: String = headers.iter().map(|(name, value)| format!("{}: {}\n", name, value)).collect();
+    let none = headers.is_empty();
+    rsx! {
+        div { class: "result-toolbar",
+            button {
+                disabled: none,
+                onclick: move |_| copy_to_clipboard(&all),
+                "Copy all"
+            }
+        }
+        table { class: "response-headers",
+            for (i, (name, value)) in headers.into_iter().enumerate() {
+                tr { key: "{i}",
+                    th { "{name}" }
+                    td { "{value}" }
+                    td {
+                        button {
+                            class: "copy-value",
+                            title: "Copy the value",
+                            onclick: move |_| copy_to_clipboard(&value),
+                            "Copy"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Summary table shown after a Repeat batch.
+#[component]
+fn LatencyStatsView(stats: Result<LatencyStats, String>, on_close: EventHandler<()>) -> Element {
+    rsx! {
+        div { class: "latency-stats",
+            match stats {
+                Ok(stats) => rsx! {
+                    table {
+                        tr {
+                            th { "Runs" }
+                            th { "Errors" }
+                            th { "Min" }
+                            th { "p50" }
+                            th { "p90" }
+                            th { "p99" }
+                            th { "Max" }
+                        }
+                        tr {
+                            td { "{stats.runs}" }
+                            td { "{stats.failures} ({stats.error_rate():.1}%)" }
+                            td { "{stats.min_ms} ms" }
+                            td { "{stats.p50_ms} ms" }
+                            td { "{stats.p90_ms} ms" }
+                            td { "{stats.p99_ms} ms" }
+                            td { "{stats.max_ms} ms" }
+                        }
+                    }
+                },
+                Err(e) => rsx! { span { class: "settings-error", "{e}" } },
+            }
+            button { class: "remove-view", title: "Dismiss", onclick: move |_| on_close.call(()), "×" }
+        }
+    }
+}
+
+/// The polls made after the last send, newest last.
+#[component]
+fn PollProgressView(progress: PollProgress, running: bool, on_cancel: EventHandler<()>, on_close: EventHandler<()>) -> Element {
+    let problem = progress.end.as_ref().and_then(PollEnd::problem);
+    rsx! {
+        div { class: "latency-stats poll-progress",
+            div {
+                table {
+                    tr {
+                        th { "After" }
+                        th { "Status" }
+                    }
+                    for step in progress.steps.iter() {
+                        tr {
+                            td { "{step.at_ms} ms" }
+                            match &step.outcome {
+                                Ok(status) => rsx! { td { title: "{step.url}", "{status}" } },
+                                Err(e) => rsx! { td { class: "settings-error", title: "{step.url}", "{e}" } },
+                            }
+                        }
+                    }
+                }
+                if running {
+                    div { class: "result-note", "Polling..." }
+                } else if let Some(problem) = problem {
+                    div { class: "settings-error", "{problem}" }
+                } else {
+                    div { class: "result-note", "Done after {progress.steps.len()} poll(s)" }
+                }
+            }
+            if running {
+                button { onclick: move |_| on_cancel.call(()), "Cancel" }
+            } else {
+                button { class: "remove-view", title: "Dismiss", onclick: move |_| on_close.call(()), "×" }
+            }
+        }
+    }
+}
+
+/// Expression box for reshaping a JSON response, with the request's saved views.
+#[component]
+fn TransformBar(
+    views: Vec<(String, String)>,
+    on_views_change: EventHandler<Vec<(String, String)>>,
+    applied: Signal<Option<String>>,
+) -> Element {
+    let mut expression = use_signal(|| applied().unwrap_or_default());
+    let mut view_name = use_signal(String::new);
+    let mut apply = move |expr: String| {
+        applied.set((!expr.trim().is_empty()).then_some(expr));
+    };
+
+    let save_views = views.clone();
+    let on_save_view = move |_| {
+        let name = view_name.read().trim().to_string();
+        let expr = expression.read().trim().to_string();
+        if name.is_empty() || expr.is_empty() {
+            return;
+        }
+        let mut views = save_views.clone();
+        match views.iter_mut().find(|(n, _)| *n == name) {
+            Some(view) => view.1 = expr,
+            None => views.push((name, expr)),
+        }
+        on_views_change.call(views);
+        view_name.set(String::new());
+    };
+
+    rsx! {
+        div { class: "transform-bar",
+            input {
+                r#type: "text",
+                class: "transform-input",
+                placeholder: ".items | select(.active == true) | sort_by(.created_at) | limit(10)",
+                value: "{expression}",
+                oninput: move |evt| expression.set(evt.value()),
+                onkeydown: move |evt| {
+                    if evt.key() == Key::Enter {
+                        apply(expression());
+                    }
+                },
+            }
+            button { onclick: move |_| apply(expression()), "Apply" }
+            if applied().is_some() {
+                button {
+                    onclick: move |_| {
+                        expression.set(String::new());
+                        apply(String::new());
+                    },
+                    "Clear"
+                }
+            }
+            input {
+                r#type: "text",
+                class: "transform-view-name",
+                placeholder: "View name",
+                value: "{view_name}",
+                oninput: move |evt| view_name.set(evt.value()),
+            }
+            button { onclick: on_save_view, "Save view" }
+        }
+        if !views.is_empty() {
+            div { class: "transform-views",
+                for (index, (name, expr)) in views.iter().cloned().enumerate() {
+                    span { class: "transform-view", key: "{name}",
+                        button {
+                            title: "{expr}",
+                            onclick: move |_| {
+                                expression.set(expr.clone());
+                                apply(expr.clone());
+                            },
+                            "{name}"
+                        }
+                        button {
+                            class: "remove-view",
+                            title: "Remove view",
+                            onclick: {
+                                let views = views.clone();
+                                move |_| {
+                                    let mut views = views.clone();
+                                    views.remove(index);
+                                    on_views_change.call(views);
+                                }
+                            },
+                            "×"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Clipboard text longer than this is never looked at for imports.
+const MAX_CLIPBOARD_IMPORT: usize = 64 * 1024;
+
+/// Pause in typing after which the Body tab's footer is worked out again.
+const BODY_PREVIEW_DELAY: Duration = Duration::from_millis(300);
+
+/// The request a copied curl command or bare http(s) URL describes.
+fn clipboard_request(text: &str) -> Option<RequestData> {
+    let text = text.trim();
+    if text.len() > MAX_CLIPBOARD_IMPORT {
+        return None;
+    }
+    if text.starts_with("curl ") {
+        return curl::from_curl(text).ok().map(|(req, _)| req);
+    }
+    if text.starts_with(share::LINK_PREFIX) {
+        return share::decode(text).ok();
+    }
+    let is_url = (text.starts_with("http://") || text.starts_with("https://")) && !text.contains(char::is_whitespace);
+    is_url.then(|| RequestData { url: text.to_string(), ..RequestData::new() })
+}
+
+fn copy_to_clipboard(text: &str) {
+    let text = serde_json::to_string(text).unwrap_or_default();
+    document::eval(&format!("navigator.clipboard.writeText({})", text));
+}
+
+/// Asks before deleting a request, or a folder with how many requests go
+/// with it.
+#[component]
+fn DeleteDialog(path: PathBuf, on_confirm: EventHandler<()>, on_close: EventHandler<()>) -> Element {
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let message = if path.extension().is_some_and(|e| e == "req") {
+        format!("Delete the request {}?", name)
+    } else {
+        match storage::count_requests(&path) {
+            0 => format!("Delete the empty folder {}?", name),
+            1 => format!("Delete the folder {} and the request in it?", name),
+            n => format!("Delete the folder {} and the {} requests in it?", name, n),
+        }
+    };
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal",
+                h3 { "Delete" }
+                p { "{message}" }
+                div { class: "settings-hint", "This cannot be undone." }
+                div { class: "modal-actions",
+                    button { class: "delete-confirm", onclick: move |_| on_confirm.call(()), "Delete" }
+                    button { onclick: move |_| on_close.call(()), "Cancel" }
+                }
+            }
+        }
+    }
+}
+
+/// Shows the curl command just copied for a request. When what is sent
+/// differs from what is written, through placeholders, auth or default
+/// options, it can be switched to the form resolved against the active
+/// environment.
+#[component]
+fn CopyCurlDialog(request: RequestData, path: Option<PathBuf>, settings: Settings, on_close: EventHandler<()>) -> Element {
+    let mut resolved_form = use_signal(|| false);
+    let mut copied = use_signal(|| true);
+    let written = export::to_curl(&request);
+    // Placeholders that cannot be resolved stay as written and are listed
+    let (resolved, unresolved) = match settings.active_resolver()
+        .and_then(|resolver| resolver.substitute_request(&auth::inherit(path.as_deref(), &request)))
+    {
+        Ok((req, problems)) => (Ok(export::to_curl(&req)), problems),
+        Err(e) => (Err(e), Vec::new()),
+    };
+    // Placeholders, auth or default options make the sent request differ
+    let differs = resolved.as_ref() != Ok(&written);
+    let shown = if resolved_form() { resolved.clone() } else { Ok(written) };
+    let on_copy = {
+        let shown = shown.clone();
+        move |_| {
+            if let Ok(command) = &shown {
+                copy_to_clipboard(command);
+                copied.set(true);
+            }
+        }
+    };
+
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal",
+                h3 { "Copy as cURL" }
+                if differs {
+                    div { class: "settings-row",
+                        label {
+                            input {
+                                r#type: "radio",
+                                checked: !resolved_form(),
+                                onchange: move |_| {
+                                    resolved_form.set(false);
+                                    copied.set(false);
+                                },
+                            }
+                            " As written"
+                        }
+                        label {
+                            input {
+                                r#type: "radio",
+                                checked: resolved_form(),
+                                onchange: move |_| {
+                                    resolved_form.set(true);
+                                    copied.set(false);
+                                },
+                            }
+                            " Resolved"
+                        }
+                    }
+                    if resolved_form() {
+                        div { class: "result-warning", "The resolved command holds the environment's values, secrets included." }
+                        for problem in unresolved {
+                            div { class: "result-warning", "⚠ {problem}; left as written" }
+                        }
+                    }
+                }
+                match shown {
+                    Ok(command) => rsx! { pre { class: "result-body markdown-preview", "{command}" } },
+                    Err(e) => rsx! { div { class: "settings-error", "{e}" } },
+                }
+                if copied() {
+                    div { class: "settings-hint", "Copied to the clipboard" }
+                }
+                div { class: "modal-actions",
+                    button { onclick: on_copy, "Copy" }
+                    button { onclick: move |_| on_close.call(()), "Close" }
+                }
+            }
+        }
+    }
+}
+
+/// Pastes a curl command and opens the request it sends as a draft.
+#[component]
+fn ImportCurlDialog(on_import: EventHandler<RequestData>, on_close: EventHandler<()>) -> Element {
+    let mut text = use_signal(String::new);
+    let mut error = use_signal(|| None::<String>);
+    // Told before opening, so the request can be checked for what is missing
+    let ignored = curl::from_curl(&text.read()).map(|(_, ignored)| ignored).unwrap_or_default();
+
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal",
+                h3 { "Import curl" }
+                textarea {
+                    class: "share-input",
+                    placeholder: "curl https://api.example.com/items -H 'Accept: application/json'",
+                    value: "{text}",
+                    oninput: move |evt| {
+                        text.set(evt.value());
+                        error.set(None);
+                    }
+                }
+                if !ignored.is_empty() {
+                    div { class: "result-warning",
+                        {format!("Ignored, as they have no equivalent here: {}", ignored.join(", "))}
+                    }
+                }
+                if let Some(message) = error() {
+                    div { class: "settings-error", "{message}" }
+                }
+                div { class: "modal-actions",
+                    button {
+                        disabled: text.read().trim().is_empty(),
+                        onclick: move |_| match curl::from_curl(&text.read()) {
+                            Ok((req, _)) => on_import.call(req),
+                            Err(e) => error.set(Some(e)),
+                        },
+                        "Open"
+                    }
+                    button { onclick: move |_| on_close.call(()), "Cancel" }
+                }
+            }
+        }
+    }
+}
+
+/// Pastes a share link or blob and opens the request it carries as a draft.
+#[component]
+fn ImportLinkDialog(on_import: EventHandler<RequestData>, on_close: EventHandler<()>) -> Element {
+    let mut text = use_signal(String::new);
+    let mut error = use_signal(|| None::<String>);
+
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal",
+                h3 { "Import from link" }
+                textarea {
+                    class: "share-input",
+                    placeholder: "requester://import?v=1&data=... or the blob alone",
+                    value: "{text}",
+                    oninput: move |evt| {
+                        text.set(evt.value());
+                        error.set(None);
+                    }
+                }
+                if let Some(message) = error() {
+                    div { class: "settings-error", "{message}" }
+                }
+                div { class: "modal-actions",
+                    button {
+                        disabled: text.read().trim().is_empty(),
+                        onclick: move |_| match share::decode(&text.read()) {
+                            Ok(req) => on_import.call(req),
+                            Err(e) => error.set(Some(e)),
+                        },
+                        "Open"
+                    }
+                    button { onclick: move |_| on_close.call(()), "Cancel" }
+                }
+            }
+        }
+    }
+}
+
+/// Lists the cookie jar and imports a browser-exported `cookies.txt` into it.
+#[component]
+fn CookieJarDialog(utc_times: bool, on_close: EventHandler<()>) -> Element {
+    let mut jar = use_signal(|| cookies::with_jar(|jar| (jar.clone(), false)));
+    let mut path = use_signal(|| {
+        directories::UserDirs::new().map_or(PathBuf::from("cookies.txt"), |dirs| dirs.home_dir().join("cookies.txt")).display().to_string()
+    });
+    // Cookies read from the file, the problems with its lines and the domains chosen
+    let mut preview = use_signal(|| None::<(Vec<cookies::Cookie>, Vec<String>)>);
+    let mut chosen = use_signal(HashSet::<String>::new);
+    let mut status = use_signal(|| None::<String>);
+
+    let mut edit = move |f: &dyn Fn(&mut cookies::CookieJar)| {
+        jar.set(cookies::with_jar(|jar| {
+            f(jar);
+            (jar.clone(), true)
+        }));
+    };
+    let on_read = move |_| {
+        status.set(None);
+        match std::fs::read_to_string(path()) {
+            Ok(content) => {
+                let (read, problems) = cookies::parse_cookies_txt(&content);
+                chosen.set(read.iter().map(|c| c.domain.clone()).collect());
+                preview.set(Some((read, problems)));
+            }
+            Err(e) => status.set(Some(format!("Cannot read {}: {}", path(), e))),
+        }
+    };
+    let now_ms = chrono::Local::now().timestamp_millis();
+    let on_import = move |_| {
+        let Some((read, _)) = preview() else { return };
+        let (fresh, expired): (Vec<cookies::Cookie>, Vec<cookies::Cookie>) = read.into_iter()
+            .filter(|c| chosen.read().contains(&c.domain))
+            .partition(|c| c.expires_ms.is_none_or(|expires| expires > now_ms));
+        let (counts, merged) = cookies::with_jar(|jar| {
+            let counts = jar.merge(fresh, now_ms);
+            ((counts, jar.clone()), true)
+        });
+        jar.set(merged);
+        preview.set(None);
+        status.set(Some(format!(
+            "Imported {} new and replaced {}; skipped {} expired",
+            counts.added, counts.replaced, expired.len(),
+        )));
+    };
+
+    // Domains in the file with their live and expired cookie counts
+    let preview_domains: Vec<(String, usize, usize)> = match &*preview.read() {
+        Some((read, _)) => {
+            let mut domains: Vec<(String, usize, usize)> = Vec::new();
+            for cookie in read {
+                let expired = cookie.expires_ms.is_some_and(|expires| expires <= now_ms);
+                let i = match domains.iter().position(|(d, _, _)| *d == cookie.domain) {
+                    Some(i) => i,
+                    None => {
+                        domains.push((cookie.domain.clone(), 0, 0));
+                        domains.len() - 1
+                    }
+                };
+                if expired { domains[i].2 += 1 } else { domains[i].1 += 1 }
+            }
+            domains.sort();
+            domains
+        }
+        None => Vec::new(),
+    };
+    let problems = preview.read().as_ref().map(|(_, problems)| problems.clone()).unwrap_or_default();
+    let has_preview = preview.read().is_some();
+    let domains = jar.read().domains();
+
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal cookie-jar",
+                h3 { "Cookies" }
+                if domains.is_empty() {
+                    div { class: "settings-hint", "The jar is empty. Responses that set cookies fill it, or import a cookies.txt below." }
+                }
+                for (domain, count) in domains {
+                    details { key: "{domain}",
+                        summary {
+                            "{domain} ({count})"
+                            button {
+                                class: "remove-view",
+                                title: "Delete the cookies of {domain}",
+                                onclick: {
+                                    let domain = domain.clone();
+                                    move |evt: MouseEvent| {
+                                        evt.stop_propagation();
+                                        edit(&|jar| jar.cookies.retain(|c| c.domain != domain));
+                                    }
+                                },
+                                "×"
+                            }
+                        }
+                        table { class: "cookie-table",
+                            for cookie in jar.read().cookies.iter().filter(|c| c.domain == domain).cloned() {
+                                tr {
+                                    td { "{cookie.name}" }
+                                    td { class: "cookie-value", title: "{cookie.value}", "{cookie.value}" }
+                                    td { "{cookie.path}" }
+                                    td {
+                                        if cookie.secure { "Secure " }
+                                        if cookie.http_only { "HttpOnly " }
+                                        if cookie.host_only { "host only" }
+                                    }
+                                    td {
+                                        match cookie.expires_ms {
+                                            Some(ms) => dates::display_ms(ms, "%Y-%m-%d %H:%M", utc_times),
+                                            None => "session".to_string(),
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                h4 { "Import cookies.txt" }
+                div { class: "settings-row",
+                    input {
+                        r#type: "text",
+                        class: "path-input",
+                        value: "{path}",
+                        oninput: move |evt| path.set(evt.value()),
+                    }
+                    button { onclick: on_read, "Read" }
+                }
+                div { class: "settings-hint", "The Netscape format browser extensions export. Expired cookies are skipped." }
+                for problem in problems {
+                    div { class: "settings-error", "{problem}" }
+                }
+                if has_preview {
+                    if preview_domains.is_empty() {
+                        div { class: "settings-hint", "The file has no cookies." }
+                    }
+                    for (domain, live, expired) in preview_domains {
+                        label { class: "settings-row", key: "{domain}",
+                            input {
+                                r#type: "checkbox",
+                                checked: chosen.read().contains(&domain),
+                                onchange: {
+                                    let domain = domain.clone();
+                                    move |evt: FormEvent| {
+                                        if evt.checked() {
+                                            chosen.write().insert(domain.clone());
+                                        } else {
+                                            chosen.write().remove(&domain);
+                                        }
+                                    }
+                                }
+                            }
+                            span { "{domain}: {live} cookies" }
+                            if expired > 0 {
+                                span { class: "settings-hint", "{expired} expired, skipped" }
+                            }
+                        }
+                    }
+                }
+                if let Some(message) = status() {
+                    div { class: "settings-hint", "{message}" }
+                }
+                div { class: "modal-actions",
+                    if has_preview {
+                        button { disabled: chosen.read().is_empty(), onclick: on_import, "Import selected" }
+                    }
+                    button {
+                        disabled: jar.read().cookies.is_empty(),
+                        onclick: move |_| edit(&|jar| jar.cookies.clear()),
+                        "Clear all"
+                    }
+                    button { onclick: move |_| on_close.call(()), "Close" }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn CsvExportDialog(body: String, on_close: EventHandler<()>) -> Element {
+    let records = use_hook(|| {
+        serde_json::from_str::<serde_json::Value>(&body)
+            .map_err(|e| format!("Response is not valid JSON: {}", e))
+            .and_then(|value| export::flatten_records(&value))
+    });
+    let columns = match &records {
+        Ok(rows) => export::csv_columns(rows),
+        Err(_) => Vec::new(),
+    };
+    let mut selected = use_signal(|| columns.clone());
+    let mut path = use_signal(|| storage::get_base_dir().join("exports").join("response.csv").display().to_string());
+    let mut status = use_signal(|| None::<String>);
+
+    let on_save = {
+        let records = records.clone();
+        let columns = columns.clone();
+        move |_| {
+            let Ok(rows) = &records else { return };
+            // Keep the response's column order regardless of click order
+            let chosen: Vec<String> = columns.iter().filter(|c| selected.read().contains(c)).cloned().collect();
+            let target = PathBuf::from(path());
+            let result = target.parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                // The byte order mark makes spreadsheets read the file as UTF-8
+                .and_then(|_| std::fs::write(&target, format!("\u{feff}{}", export::to_csv(rows, &chosen))));
+            status.set(Some(match result {
+                Ok(()) => format!("Saved {} rows to {}", rows.len(), target.display()),
+                Err(e) => format!("Could not save: {}", e),
+            }));
+        }
+    };
+
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal",
+                h3 { "Export as CSV" }
+                match &records {
+                    Err(e) => rsx! { div { class: "settings-error", "{e}" } },
+                    Ok(rows) => rsx! {
+                        div { class: "settings-hint", "{rows.len()} rows. Nested fields use dotted names; missing values are left empty." }
+                        div { class: "column-list",
+                            for column in columns.iter().cloned() {
+                                label { key: "{column}",
+                                    input {
+                                        r#type: "checkbox",
+                                        checked: selected.read().contains(&column),
+                                        onchange: {
+                                            let column = column.clone();
+                                            move |evt: FormEvent| {
+                                                if evt.checked() {
+                                                    selected.write().push(column.clone());
+                                                } else {
+                                                    selected.write().retain(|c| c != &column);
+                                                }
+                                            }
+                                        }
+                                    }
+                                    "{column}"
+                                }
+                            }
+                        }
+                        label { class: "settings-row",
+                            span { "Save to" }
+                            input {
+                                r#type: "text",
+                                class: "path-input",
+                                value: "{path}",
+                                oninput: move |evt| path.set(evt.value()),
+                            }
+                        }
+                    }
+                }
+                if let Some(message) = status() {
+                    div { class: "settings-hint", "{message}" }
+                }
+                div { class: "modal-actions",
+                    button { disabled: records.is_err(), onclick: on_save, "Save" }
+                    button { onclick: move |_| on_close.call(()), "Close" }
+                }
+            }
+        }
+    }
+}
+
+/// A folder's README rendered; links to requests in the workspace open them.
+#[component]
+fn ReadmeView(folder: PathBuf, on_open: EventHandler<PathBuf>) -> Element {
+    let blocks = match storage::backend().load(&folder.join(markdown::README)) {
+        Ok(source) => markdown::parse(&source),
+        Err(e) => return rsx! { div { class: "settings-error", "Cannot read {markdown::README}: {e}" } },
+    };
+    let links = ReadmeLinks { folder, base: storage::get_base_dir(), on_open };
+    rsx! {
+        div { class: "readme", {markdown_blocks(&blocks, &links)} }
+    }
+}
+
+#[component]
+fn ReadmeDialog(folder: PathBuf, on_open: EventHandler<PathBuf>, on_close: EventHandler<()>) -> Element {
+    let name = folder.file_name().map_or("requester".to_string(), |n| n.to_string_lossy().into_owned());
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal readme-dialog",
+                h3 { "About {name}" }
+                ReadmeView {
+                    folder,
+                    on_open: move |path| {
+                        on_close.call(());
+                        on_open.call(path);
+                    },
+                }
+                div { class: "modal-actions",
+                    button { onclick: move |_| on_close.call(()), "Close" }
+                }
+            }
+        }
+    }
+}
+
+/// How links in a README resolve: relative to its folder, within the workspace.
+struct ReadmeLinks {
+    folder: PathBuf,
+    base: PathBuf,
+    on_open: EventHandler<PathBuf>,
+}
+
+fn markdown_blocks(blocks: &[markdown::Block], links: &ReadmeLinks) -> Element {
+    use markdown::Block;
+    rsx! {
+        for block in blocks {
+            match block {
+                Block::Heading(1, text) => rsx! { h1 { {markdown_inline(text, links)} } },
+                Block::Heading(2, text) => rsx! { h2 { {markdown_inline(text, links)} } },
+                Block::Heading(3, text) => rsx! { h3 { {markdown_inline(text, links)} } },
+                Block::Heading(4, text) => rsx! { h4 { {markdown_inline(text, links)} } },
+                Block::Heading(5, text) => rsx! { h5 { {markdown_inline(text, links)} } },
+                Block::Heading(_, text) => rsx! { h6 { {markdown_inline(text, links)} } },
+                Block::Paragraph(text) => rsx! { p { {markdown_inline(text, links)} } },
+                Block::List { ordered: true, items } => rsx! {
+                    ol {
+                        for item in items {
+                            li { {markdown_blocks(item, links)} }
+                        }
+                    }
+                },
+                Block::List { ordered: false, items } => rsx! {
+                    ul {
+                        for item in items {
+                            li { {markdown_blocks(item, links)} }
+                        }
+                    }
+                },
+                Block::Code { language, text } => rsx! { pre { class: "readme-code", title: "{language}", "{text}" } },
+                Block::Quote(inner) => rsx! { blockquote { {markdown_blocks(inner, links)} } },
+                Block::Table { header, rows } => rsx! {
+                    table {
+                        thead {
+                            tr {
+                                for cell in header {
+                                    th { {markdown_inline(cell, links)} }
+                                }
+                            }
+                        }
+                        tbody {
+                            for row in rows {
+                                tr {
+                                    for cell in row {
+                                        td { {markdown_inline(cell, links)} }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                Block::Rule => rsx! { hr {} },
+            }
+        }
+    }
+}
+
+fn markdown_inline(inlines: &[markdown::Inline], links: &ReadmeLinks) -> Element {
+    use markdown::{Inline, LinkTarget};
+    rsx! {
+        for inline in inlines {
+            match inline {
+                Inline::Text(text) => rsx! { "{text}" },
+                Inline::Code(code) => rsx! { code { "{code}" } },
+                Inline::Strong(inner) => rsx! { strong { {markdown_inline(inner, links)} } },
+                Inline::Emphasis(inner) => rsx! { em { {markdown_inline(inner, links)} } },
+                Inline::Link { text, target } => match markdown::link_target(target, &links.folder, &links.base) {
+                    LinkTarget::Request(path) => {
+                        let on_open = links.on_open;
+                        rsx! {
+                            a {
+                                class: "body-link",
+                                title: "Open {path.display()}",
+                                onclick: move |_| on_open.call(path.clone()),
+                                {markdown_inline(text, links)}
+                            }
+                        }
+                    }
+                    LinkTarget::External(url) => rsx! {
+                        a { href: "{url}", target: "_blank", title: "{url}", {markdown_inline(text, links)} }
+                    },
+                    LinkTarget::Inert => rsx! { span { title: "{target}", {markdown_inline(text, links)} } },
+                },
+            }
+        }
+    }
+}
+
+#[component]
+fn MarkdownExportDialog(markdown: String, on_close: EventHandler<()>) -> Element {
+    let mut path = use_signal(|| storage::get_base_dir().join("exports").join("report.md").display().to_string());
+    let mut status = use_signal(|| None::<String>);
+
+    let on_save = {
+        let markdown = markdown.clone();
+        move |_| {
+            let target = PathBuf::from(path());
+            let result = target.parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|_| std::fs::write(&target, &markdown));
+            status.set(Some(match result {
+                Ok(()) => format!("Saved to {}", target.display()),
+                Err(e) => format!("Could not save: {}", e),
+            }));
+        }
+    };
+    let on_copy = {
+        let markdown = markdown.clone();
+        move |_| {
+            copy_to_clipboard(&markdown);
+            status.set(Some("Copied to the clipboard".to_string()));
+        }
+    };
+
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal",
+                h3 { "Export as Markdown" }
+                pre { class: "result-body markdown-preview", "{markdown}" }
+                label { class: "settings-row",
+                    span { "Save to" }
+                    input {
+                        r#type: "text",
+                        class: "path-input",
+                        value: "{path}",
+                        oninput: move |evt| path.set(evt.value()),
+                    }
+                }
+                if let Some(message) = status() {
+                    div { class: "settings-hint", "{message}" }
+                }
+                div { class: "modal-actions",
+                    button { onclick: on_copy, "Copy" }
+                    button { onclick: on_save, "Save" }
+                    button { onclick: move |_| on_close.call(()), "Close" }
+                }
+            }
+        }
+    }
+}
+
+/// Side panel showing the request's marked base64/gzip fields decoded.
+#[component]
+fn DecodedFields(body: serde_json::Value, paths: Vec<String>, on_change: EventHandler<Vec<String>>) -> Element {
+    let mut new_path = use_signal(String::new);
+    let add_paths = paths.clone();
+    let on_add = move |_| {
+        let path = new_path.read().trim().to_string();
+        if path.is_empty() || add_paths.contains(&path) {
+            return;
+        }
+        let mut paths = add_paths.clone();
+        paths.push(path);
+        on_change.call(paths);
+        new_path.set(String::new());
+    };
+    let decoded: Vec<(usize, String, Result<decode::DecodedField, String>)> = paths.iter()
+        .enumerate()
+        .map(|(i, path)| (i, path.clone(), decode::decode_field(&body, path)))
+        .collect();
+
+    rsx! {
+        div { class: "decoded-fields",
+            div { class: "transform-bar",
+                input {
+                    r#type: "text",
+                    class: "transform-input",
+                    placeholder: "Decode a base64/gzip field, e.g. .data.payload",
+                    value: "{new_path}",
+                    oninput: move |evt| new_path.set(evt.value()),
+                }
+                button { onclick: on_add, "Decode" }
+            }
+            for (i, path, result) in decoded {
+                div { class: "decoded-field",
+                    div { class: "decoded-field-header",
+                        span { class: "reversal-label", "{path}" }
+                        if let Ok(field) = &result {
+                            span { class: "result-note",
+                                if field.gunzipped { " base64 + gzip, " } else { " base64, " }
+                                "{field.bytes.len()} bytes"
+                            }
+                        }
+                        button {
+                            class: "remove-view",
+                            title: "Stop decoding this field",
+                            onclick: {
+                                let paths = paths.clone();
+                                move |_| {
+                                    let mut paths = paths.clone();
+                                    paths.remove(i);
+                                    on_change.call(paths);
+                                }
+                            },
+                            "×"
+                        }
+                    }
+                    match result {
+                        Ok(field) => match field.text() {
+                            Some(text) => rsx! { pre { class: "result-body", "{text}" } },
+                            None => rsx! { pre { class: "result-body hex", "{hex_dump(&field.bytes)}" } },
+                        },
+                        Err(e) => rsx! { div { class: "settings-error", "{e}" } },
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn is_xml(res: &HttpResponse) -> bool {
+    let content_type = res.headers.iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+        .map(|(_, v)| v.to_ascii_lowercase())
+        .unwrap_or_default();
+    content_type.contains("xml") || (content_type.is_empty() && res.body.trim_start().starts_with("<?xml"))
+}
+
+/// Indented, highlighted XML.
+#[component]
+fn XmlBody(xml: String) -> Element {
+    let parts = soap::highlight(&soap::pretty_xml(&xml));
+    rsx! {
+        pre { class: "result-body xml",
+            for (part, text) in parts {
+                span { class: part.class(), "{text}" }
+            }
+        }
+    }
+}
+
+/// The body textarea. JSON bodies get their highlighting from a copy drawn
+/// behind the transparent text, kept in step with its scrolling; the text
+/// itself is never touched.
+#[component]
+fn BodyEditor(body: String, on_input: EventHandler<String>) -> Element {
+    let mut scroll = use_signal(|| (0.0, 0.0));
+    let highlighted = body.trim_start().starts_with(['{', '[']) && body.len() <= viewer::HIGHLIGHT_MAX_BYTES;
+    if !highlighted {
+        return rsx! {
+            textarea {
+                class: "body-editor",
+                value: "{body}",
+                oninput: move |evt| on_input.call(evt.value()),
+            }
+        };
+    }
+    let parts = viewer::highlight_json(&body);
+    let (left, top) = scroll();
+    rsx! {
+        div { class: "highlighted-editor",
+            pre { class: "body-editor body-highlight", aria_hidden: "true",
+                code { style: "transform: translate({-left}px, {-top}px)",
+                    for (part, run) in parts {
+                        span { class: part.class(), "{run}" }
+                    }
+                    // A final line break only takes up room with something after it
+                    " "
+                }
+            }
+            textarea {
+                class: "body-editor",
+                spellcheck: "false",
+                value: "{body}",
+                oninput: move |evt| on_input.call(evt.value()),
+                onscroll: move |evt| scroll.set((evt.scroll_left(), evt.scroll_top())),
+            }
+        }
+    }
+}
+
+/// Body tab controls for wrapping the body in a SOAP envelope.
+#[component]
+fn SoapEditor(soap: Option<soap::SoapEnvelope>, on_change: EventHandler<Option<soap::SoapEnvelope>>) -> Element {
+    let Some(envelope) = soap else {
+        return rsx! {
+            div { class: "soap-editor",
+                label { class: "result-note",
+                    input {
+                        r#type: "checkbox",
+                        checked: false,
+                        onchange: move |_| on_change.call(Some(soap::SoapEnvelope::new(soap::SoapVersion::V11))),
+                    }
+                    "Wrap the body in a SOAP envelope"
+                }
+            }
+        };
+    };
+    let template = envelope.template();
+    let (version_env, action_env, template_env, reset_env) = (envelope.clone(), envelope.clone(), envelope.clone(), envelope.clone());
+
+    rsx! {
+        div { class: "soap-editor",
+            div { class: "header-row",
+                label { class: "result-note",
+                    input {
+                        r#type: "checkbox",
+                        checked: true,
+                        onchange: move |_| on_change.call(None),
+                    }
+                    "SOAP"
+                }
+                select {
+                    value: "{envelope.version.as_str()}",
+                    onchange: move |evt| {
+                        if let Ok(version) = soap::SoapVersion::parse(&evt.value()) {
+                            on_change.call(Some(soap::SoapEnvelope { version, ..version_env.clone() }));
+                        }
+                    },
+                    option { value: "1.1", "SOAP 1.1" }
+                    option { value: "1.2", "SOAP 1.2" }
+                }
+                input {
+                    r#type: "text",
+                    class: "soap-action",
+                    placeholder: "SOAPAction",
+                    value: "{envelope.action}",
+                    oninput: move |evt| on_change.call(Some(soap::SoapEnvelope { action: evt.value(), ..action_env.clone() })),
+                }
+                if envelope.template.is_some() {
+                    button {
+                        title: "Use the standard envelope for this version",
+                        onclick: move |_| on_change.call(Some(soap::SoapEnvelope { template: None, ..reset_env.clone() })),
+                        "Reset envelope"
+                    }
+                }
+            }
+            textarea {
+                class: "body-editor soap-envelope",
+                title: "The body replaces {soap::BODY_MARKER}; {{{{...}}}} placeholders are expanded when sending",
+                value: "{template}",
+                oninput: move |evt| on_change.call(Some(soap::SoapEnvelope { template: Some(evt.value()), ..template_env.clone() })),
+            }
+        }
+    }
+}
+
+/// Pretty-printed `json` for editing and sending back, optionally without
+/// the top-level fields a server usually sets itself.
+fn json_skeleton(json: &serde_json::Value, strip_read_only: bool) -> String {
+    let mut json = json.clone();
+    if strip_read_only {
+        let strip = |value: &mut serde_json::Value| {
+            if let Some(object) = value.as_object_mut() {
+                object.retain(|key, _| {
+                    let key = key.to_ascii_lowercase();
+                    !["id", "created_at", "createdat", "updated_at", "updatedat"].contains(&key.as_str())
+                        && !key.ends_with("_url")
+                });
+            }
+        };
+        match &mut json {
+            serde_json::Value::Array(items) => items.iter_mut().for_each(strip),
+            other => strip(other),
+        }
+    }
+    serde_json::to_string_pretty(&json).unwrap_or_default()
+}
+
+/// Replaces the body with JSON and makes the `Content-Type` say so.
+fn set_json_body(req: &mut RequestData, body: String) {
+    req.body = body;
+    match req.headers.iter_mut().find(|(k, _)| k.eq_ignore_ascii_case("content-type")) {
+        Some(header) if !header.1.to_ascii_lowercase().contains("json") => header.1 = "application/json".to_string(),
+        Some(_) => {}
+        None => req.headers.push(("Content-Type".to_string(), "application/json".to_string())),
+    }
+}
+
+/// Response text with every absolute http(s) URL clickable.
+#[component]
+fn LinkedBody(text: String, on_follow: EventHandler<String>) -> Element {
+    rsx! {
+        pre { class: "result-body",
+            for (segment, is_link) in link_segments(&text) {
+                if is_link {
+                    a {
+                        class: "body-link",
+                        title: "Open as a new request",
+                        onclick: move |_| on_follow.call(segment.clone()),
+                        "{segment}"
+                    }
+                } else {
+                    "{segment}"
+                }
+            }
+        }
+    }
+}
+
+/// Splits `text` into plain runs and URLs, flagged `true`. A URL ends at
+/// whitespace, a quote or an angle bracket; trailing punctuation is left out.
+fn link_segments(text: &str) -> Vec<(String, bool)> {
+    let mut segments = Vec::new();
+    let mut rest = text;
+    while let Some(start) = ["http://", "https://"].iter().filter_map(|p| rest.find(p)).min() {
+        let tail = &rest[start..];
+        let mut end = tail.find(|c: char| c.is_whitespace() || "\"'<>`".contains(c)).unwrap_or(tail.len());
+        end = tail[..end].trim_end_matches(['.', ',', ';', ':', ')', ']', '}']).len();
+        let scheme_len = if tail.starts_with("https://") { 8 } else { 7 };
+        if end <= scheme_len {
+            // A bare scheme is not a link
+            segments.push((rest[..start + scheme_len].to_string(), false));
+            rest = &rest[start + scheme_len..];
+            continue;
+        }
+        if start > 0 {
+            segments.push((rest[..start].to_string(), false));
+        }
+        segments.push((tail[..end].to_string(), true));
+        rest = &tail[end..];
+    }
+    if !rest.is_empty() {
+        segments.push((rest.to_string(), false));
+    }
+    segments
+}
+
+/// A byte count in B, KB or MB, e.g. `2.1 KB`.
+fn format_size(bytes: usize) -> String {
+    match bytes {
+        b if b < 1024 => format!("{} B", b),
+        b if b < 1024 * 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+/// Classic 16-bytes-per-line hex view, truncated for very large bodies.
+fn hex_dump(bytes: &[u8]) -> String {
+    const MAX_BYTES: usize = 64 * 1024;
+    let mut out = String::new();
+    for (i, line) in bytes[..bytes.len().min(MAX_BYTES)].chunks(16).enumerate() {
+        let hex: Vec<String> = line.iter().map(|b| format!("{:02x}", b)).collect();
+        let ascii: String = line.iter()
+            .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+            .collect();
+        out.push_str(&format!("{:08x}  {:<47}  {}\n", i * 16, hex.join(" "), ascii));
+    }
+    if bytes.len() > MAX_BYTES {
+        out.push_str(&format!("... {} more bytes\n", bytes.len() - MAX_BYTES));
+    }
+    out
+}
+
+#[component]
+fn ResponseWindow(
+    response: Signal<Option<Result<HttpResponse, String>>>,
+    view: Signal<BodyView>,
+    mut request: Signal<RequestData>,
+    path: Signal<Option<PathBuf>>,
+    settings: Signal<Settings>,
+    detached: Signal<bool>,
+    mut follow_url: Signal<Option<String>>,
+    mut new_request: Signal<Option<RequestData>>,
+    mut resend_url: Signal<Option<String>>,
+) -> Element {
+    use_drop(move || detached.set(false));
+
+    rsx! {
+        style { {include_str!("style.css")} }
+        div { class: "result-area detached",
+            style: "--body-font-size: {settings.read().body_font_size}px",
+            ResponseView {
+                response: response(),
+                view,
+                options: request.read().options.clone(),
+                path: path(),
+                body_view: request.read().body_view.clone(),
+                on_body_view_change: move |kind| request.write().body_view = kind,
+                view_overrides: settings.read().view_overrides.clone(),
+                redact_rules: settings.read().redact_rules.clone(),
+                views: request.read().views.clone(),
+                on_views_change: move |views| request.write().views = views,
+                decode_fields: request.read().decode_fields.clone(),
+                on_decode_fields_change: move |fields| request.write().decode_fields = fields,
+                on_use_as_body: move |body| set_json_body(&mut request.write(), body),
+                on_follow: move |url| follow_url.set(Some(url)),
+                on_use_as_request: move |res: HttpResponse| new_request.set(Some(res.to_request(&request.read().url))),
+                on_resend_to: move |url| resend_url.set(Some(url)),
+            }
+        }
+    }
+}
+
+/// Opens a secondary desktop window that follows the same response signal.
+fn open_response_window(props: ResponseWindowProps) {
+    use dioxus::desktop::{Config, LogicalSize, WindowBuilder};
+
+    let dom = VirtualDom::new_with_props(ResponseWindow, props);
+    let window = WindowBuilder::new()
+        .with_title("Response")
+        .with_inner_size(LogicalSize::new(800.0, 600.0));
+    let _ = dioxus::desktop::window().new_window(dom, Config::new().with_window(window));
+}
+
+/// The folder at `path` and, when expanded, its cached children.
+#[component]
+fn Sidebar(
+    path: PathBuf,
+    cache: Signal<TreeCache>,
+    on_toggle: EventHandler<PathBuf>,
+    on_select: EventHandler<PathBuf>,
+    on_run: EventHandler<PathBuf>,
+    on_clear_run: EventHandler<PathBuf>,
+    /// Shows the folder's README.
+    on_info: EventHandler<PathBuf>,
+    /// Shows the folder's response times across its recorded runs.
+    on_timings: EventHandler<PathBuf>,
+    /// Moves the folder to the archive.
+    on_archive: EventHandler<PathBuf>,
+    /// Renames a file or folder to the name given.
+    on_rename: EventHandler<(PathBuf, String)>,
+    on_delete: EventHandler<PathBuf>,
+    current_path: Option<PathBuf>,
+    /// The node keyboard navigation is on.
+    focused: Option<PathBuf>,
+    utc_times: bool,
+) -> Element {
+    // The node of this folder whose right-click menu is open, and the one
+    // being renamed
+    let mut menu = use_signal(|| None::<PathBuf>);
+    let mut renaming = use_signal(|| None::<PathBuf>);
+    let name = path.file_name().map_or("requester".to_string(), |n| n.to_string_lossy().into_owned());
+    let (expanded, loading, children, summary, has_readme) = {
+        let cache = cache.read();
+        let expanded = cache.expanded.contains(&path);
+        let children = if expanded { cache.children.get(&path).cloned().unwrap_or_default() } else { Vec::new() };
+        (expanded, cache.loading.contains(&path), children, cache.summaries.get(&path).cloned(), cache.readmes.contains(&path))
+    };
+    let toggle_path = path.clone();
+    let info_path = path.clone();
+    let clear_path = path.clone();
+    let is_root = path == cache.read().root;
+    let archive_path = (!is_root).then(|| path.clone());
+    let menu_path = path.clone();
+    let rename_path = path.clone();
+    let rename_start = path.clone();
+    let delete_path = path.clone();
+    let timings_path = path.clone();
+    let run_path = path.clone();
+    // File name to badge, for the files run directly in this folder
+    let file_badges: HashMap<String, RunBadge> = match &summary {
+        Some(summary) => summary.files.keys().filter_map(|name| Some((name.clone(), summary.file_badge(name)?))).collect(),
+        None => HashMap::new(),
+    };
+
+    rsx! {
+        div { class: "tree-node",
+            div {
+                class: if focused.as_ref() == Some(&path) { "folder-node focused" } else { "folder-node" },
+                role: "treeitem",
+                aria_expanded: "{expanded}",
+                onclick: move |_| on_toggle.call(toggle_path.clone()),
+                oncontextmenu: move |evt| {
+                    evt.prevent_default();
+                    if !is_root {
+                        menu.set(Some(menu_path.clone()));
+                    }
+                },
+                span { class: "folder-toggle", if expanded { "▾" } else { "▸" } }
+                if renaming().as_ref() == Some(&path) {
+                    "📁 "
+                    RenameInput {
+                        name: name.clone(),
+                        on_commit: move |name| {
+                            renaming.set(None);
+                            on_rename.call((rename_path.clone(), name));
+                        },
+                        on_cancel: move |_| renaming.set(None),
+                    }
+                } else {
+                    "📁 {name}"
+                }
+                if loading {
+                    span { class: "folder-loading", title: "Loading…" }
+                }
+                if let Some(summary) = summary {
+                    span {
+                        class: "run-badge {badge_class(summary.badge())}",
+                        title: "{summary.describe(utc_times)}. Click to clear.",
+                        onclick: move |evt| {
+                            evt.stop_propagation();
+                            on_clear_run.call(clear_path.clone());
+                        },
+                        {badge_symbol(summary.badge())}
+                    }
+                }
+                if has_readme {
+                    button {
+                        class: "node-action",
+                        title: "Folder info: show its {markdown::README}",
+                        onclick: move |evt| {
+                            evt.stop_propagation();
+                            on_info.call(info_path.clone());
+                        },
+                        "ℹ"
+                    }
+                }
+                if let Some(archive_path) = archive_path {
+                    button {
+                        class: "node-action",
+                        title: "Archive: move this folder to .archive, out of the tree and the search",
+                        onclick: move |evt| {
+                            evt.stop_propagation();
+                            on_archive.call(archive_path.clone());
+                        },
+                        "🗄"
+                    }
+                }
+                button {
+                    class: "node-action",
+                    title: "Run all requests in this folder",
+                    onclick: move |evt| {
+                        evt.stop_propagation();
+                        on_run.call(run_path.clone());
+                    },
+                    "▶"
+                }
+            }
+            if menu().as_ref() == Some(&path) {
+                EntryMenu {
+                    on_rename: move |_| {
+                        menu.set(None);
+                        renaming.set(Some(rename_start.clone()));
+                    },
+                    on_delete: move |_| {
+                        menu.set(None);
+                        on_delete.call(delete_path.clone());
+                    },
+                    on_timings: move |_| {
+                        menu.set(None);
+                        on_timings.call(timings_path.clone());
+                    },
+                    on_close: move |_| menu.set(None),
+                }
+            }
+            div { style: "margin-left: 10px",
+                for child in children {
+                    match child {
+                        FileNode::Folder { path, .. } => rsx! {
+                            Sidebar {
+                                path,
+                                cache,
+                                on_toggle: move |p| on_toggle.call(p),
+                                on_select: move |p| on_select.call(p),
+                                on_run: move |p| on_run.call(p),
+                                on_clear_run: move |p| on_clear_run.call(p),
+                                on_info: move |p| on_info.call(p),
+                                on_timings: move |p| on_timings.call(p),
+                                on_archive: move |p| on_archive.call(p),
+                                on_rename: move |change| on_rename.call(change),
+                                on_delete: move |p| on_delete.call(p),
+                                current_path: current_path.clone(),
+                                focused: focused.clone(),
+                                utc_times,
+                            }
+                        },
+                        FileNode::File { name, path, title } => {
+                            let is_selected = current_path.as_ref() == Some(&path);
+                            let class = match (is_selected, focused.as_ref() == Some(&path)) {
+                                (true, true) => "file-node selected focused",
+                                (true, false) => "file-node selected",
+                                (false, true) => "file-node focused",
+                                (false, false) => "file-node",
+                            };
+                            let label = title.unwrap_or_else(|| name.clone());
+                            let badge = file_badges.get(&name).copied();
+                            let is_renaming = renaming().as_ref() == Some(&path);
+                            let has_menu = menu().as_ref() == Some(&path);
+                            let stem = name.trim_end_matches(".req").to_string();
+                            let (menu_path, rename_path, rename_start, delete_path) = (path.clone(), path.clone(), path.clone(), path.clone());
+                            rsx! {
+                                div {
+                                    class,
+                                    role: "treeitem",
+                                    aria_selected: "{is_selected}",
+                                    title: "{name}",
+                                    onclick: move |_| on_select.call(path.clone()),
+                                    oncontextmenu: move |evt| {
+                                        evt.prevent_default();
+                                        menu.set(Some(menu_path.clone()));
+                                    },
+                                    if is_renaming {
+                                        "📄 "
+                                        RenameInput {
+                                            name: stem,
+                                            on_commit: move |name| {
+                                                renaming.set(None);
+                                                on_rename.call((rename_path.clone(), name));
+                                            },
+                                            on_cancel: move |_| renaming.set(None),
+                                        }
+                                    } else {
+                                        "📄 {label}"
+                                    }
+                                    if let Some(badge) = badge {
+                                        span {
+                                            class: "run-badge {badge_class(badge)}",
+                                            title: if badge == RunBadge::Warning { "Edited since the last run" } else { "Result of the last run" },
+                                            {badge_symbol(badge)}
+                                        }
+                                    }
+                                }
+                                if has_menu {
+                                    EntryMenu {
+                                        on_rename: move |_| {
+                                            menu.set(None);
+                                            renaming.set(Some(rename_start.clone()));
+                                        },
+                                        on_delete: move |_| {
+                                            menu.set(None);
+                                            on_delete.call(delete_path.clone());
+                                        },
+                                        on_close: move |_| menu.set(None),
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The right-click menu of a sidebar node.
+#[component]
+fn EntryMenu(
+    on_rename: EventHandler<()>,
+    on_delete: EventHandler<()>,
+    /// Folders only: shows their response times across runs.
+    on_timings: Option<EventHandler<()>>,
+    on_close: EventHandler<()>,
+) -> Element {
+    rsx! {
+        div { class: "context-menu",
+            onmouseleave: move |_| on_close.call(()),
+            button { onclick: move |_| on_rename.call(()), "Rename" }
+            button { onclick: move |_| on_delete.call(()), "Delete…" }
+            if let Some(on_timings) = on_timings {
+                button { onclick: move |_| on_timings.call(()), "Response times…" }
+            }
+        }
+    }
+}
+
+/// Edits a node's name in place: Enter renames, Escape or leaving the field
+/// keeps the old name.
+#[component]
+fn RenameInput(name: String, on_commit: EventHandler<String>, on_cancel: EventHandler<()>) -> Element {
+    let mut text = use_signal(|| name.clone());
+    rsx! {
+        input {
+            r#type: "text",
+            class: "rename-input",
+            value: "{text}",
+            autofocus: true,
+            onclick: move |evt| evt.stop_propagation(),
+            oninput: move |evt| text.set(evt.value()),
+            onkeydown: move |evt| {
+                evt.stop_propagation();
+                match evt.key() {
+                    Key::Enter => on_commit.call(text()),
+                    Key::Escape => on_cancel.call(()),
+                    _ => {}
+                }
+            },
+            onblur: move |_| on_cancel.call(()),
+        }
+    }
+}
+
+fn badge_class(badge: RunBadge) -> &'static str {
+    match badge {
+        RunBadge::Passed => "passed",
+        RunBadge::Failed => "failed",
+        RunBadge::Warning => "warning",
+    }
+}
+
+fn badge_symbol(badge: RunBadge) -> &'static str {
+    match badge {
+        RunBadge::Passed => "✓",
+        RunBadge::Failed => "✗",
+        RunBadge::Warning => "⚠",
+    }
+}
+
+/// Archived folders that hold requests, to open those requests or move a
+/// folder back into the tree.
+#[component]
+fn ArchivedSection(
+    revision: Signal<u32>,
+    current_path: Option<PathBuf>,
+    on_select: EventHandler<PathBuf>,
+    on_unarchive: EventHandler<PathBuf>,
+) -> Element {
+    let folders = use_resource(move || async move {
+        revision();
+        tokio::task::spawn_blocking(storage::archived_folders).await.unwrap_or_default()
+    });
+    let archive = storage::archive_dir();
+    // Each folder with its label and its files' paths, names and labels
+    type ArchivedFolder = (PathBuf, String, Vec<(PathBuf, String, String)>);
+    let folders: Vec<ArchivedFolder> = folders.read().iter().flatten()
+        .map(|(path, files)| {
+            let label = path.strip_prefix(&archive).unwrap_or(path).display().to_string();
+            let files = files.iter()
+                .filter_map(|file| match file {
+                    FileNode::File { name, path, title } => Some((path.clone(), name.clone(), title.clone().unwrap_or_else(|| name.clone()))),
+                    FileNode::Folder { .. } => None,
+                })
+                .collect();
+            (path.clone(), label, files)
+        })
+        .collect();
+
+    rsx! {
+        div { class: "archived-section",
+            h4 { "Archived" }
+            if folders.is_empty() {
+                div { class: "settings-hint", "Nothing archived. The 🗄 button of a folder moves it here." }
+            }
+            for (path, label, files) in folders {
+                details { key: "{path.display()}",
+                    summary { class: "folder-node",
+                        "📁 {label}"
+                        button {
+                            class: "node-action",
+                            title: "Move this folder back into the tree",
+                            onclick: {
+                                let path = path.clone();
+                                move |evt: Event<MouseData>| {
+                                    evt.stop_propagation();
+                                    on_unarchive.call(path.clone());
+                                }
+                            },
+                            "Unarchive"
+                        }
+                    }
+                    for (path, name, label) in files {
+                        div {
+                            class: if current_path.as_ref() == Some(&path) { "file-node selected" } else { "file-node" },
+                            title: "{name}",
+                            onclick: move |_| on_select.call(path.clone()),
+                            "📄 {label}"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ListSort {
+    Name,
+    Method,
+    LastRun,
+}
+
+/// One request file in the flat list.
+#[derive(Clone, PartialEq)]
+struct ListEntry {
+    /// The request's title, or its file name without extension.
+    name: String,
+    file_name: String,
+    path: PathBuf,
+    folder: String,
+    method: String,
+}
+
+/// Collects every request under `node`, counting files read in `scanned`.
+fn list_entries(node: &FileNode, base: &std::path::Path, scanned: &AtomicUsize, out: &mut Vec<ListEntry>) {
+    match node {
+        FileNode::Folder { children, .. } => {
+            for child in children {
+                list_entries(child, base, scanned, out);
+            }
+        }
+        FileNode::File { name, path, .. } => {
+            let folder = path.parent()
+                .and_then(|p| p.strip_prefix(base).ok())
+                .map(|p| p.display().to_string())
+                .unwrap_or_default();
+            let request = storage::load_request(path).ok();
+            scanned.fetch_add(1, Ordering::Relaxed);
+            let file_name = name.trim_end_matches(".req").to_string();
+            out.push(ListEntry {
+                name: request.as_ref().and_then(|r| r.name.clone()).unwrap_or_else(|| file_name.clone()),
+                file_name,
+                path: path.clone(),
+                folder,
+                method: request.map(|r| r.method).unwrap_or_default(),
+            });
+        }
+    }
+}
+
+/// Flat, searchable alternative to the folder tree.
+#[component]
+fn RequestList(
+    revision: Signal<u32>,
+    /// Also list the requests in archived folders.
+    show_archived: Signal<bool>,
+    history: Signal<Vec<HistoryEntry>>,
+    ids: Signal<storage::IdIndex>,
+    on_select: EventHandler<PathBuf>,
+    current_path: Option<PathBuf>,
+) -> Element {
+    let mut search = use_signal(String::new);
+    let mut sort = use_signal(|| ListSort::Name);
+    // The sidebar only loads folders as they are opened, so searching walks
+    // the whole tree in the background; redone when the tree is refreshed
+    let mut entries = use_signal(|| None::<Vec<ListEntry>>);
+    let mut scanned = use_signal(|| 0usize);
+    use_effect(move || {
+        let rev = revision();
+        let archived = show_archived();
+        entries.set(None);
+        spawn(async move {
+            let counter = Arc::new(AtomicUsize::new(0));
+            let walk = {
+                let counter = counter.clone();
+                tokio::task::spawn_blocking(move || {
+                    let base = storage::get_base_dir();
+                    let mut out = Vec::new();
+                    list_entries(&storage::build_tree(&base), &base, &counter, &mut out);
+                    if archived {
+                        list_entries(&storage::build_tree(&storage::archive_dir()), &base, &counter, &mut out);
+                    }
+                    out
+                })
+            };
+            while !walk.is_finished() {
+                scanned.set(counter.load(Ordering::Relaxed));
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            }
+            // A refresh while walking started a newer scan
+            if let Ok(list) = walk.await && *revision.peek() == rev && *show_archived.peek() == archived {
+                entries.set(Some(list));
+            }
+        });
+    });
+    let last_runs = use_memo(move || {
+        let mut last = std::collections::HashMap::new();
+        for entry in history.read().iter() {
+            if let Some(path) = entry.resolved_path(&ids.read()) {
+                last.insert(path, entry.timestamp_ms);
+            }
+        }
+        last
+    });
+
+    let needle = search.read().to_lowercase();
+    let mut rows: Vec<ListEntry> = entries.read().iter().flatten()
+        .filter(|e| needle.is_empty()
+            || e.name.to_lowercase().contains(&needle)
+            || e.file_name.to_lowercase().contains(&needle)
+            || e.folder.to_lowercase().contains(&needle))
+        .cloned()
+        .collect();
+    match sort() {
+        ListSort::Name => rows.sort_by_key(|e| e.name.to_lowercase()),
+        ListSort::Method => rows.sort_by(|a, b| a.method.cmp(&b.method).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))),
+        ListSort::LastRun => {
+            let last_runs = last_runs.read();
+            rows.sort_by_key(|e| std::cmp::Reverse(last_runs.get(&e.path).copied()));
+        }
+    }
+
+    rsx! {
+        div { class: "request-list",
+            input {
+                r#type: "text",
+                class: "list-search",
+                placeholder: "Search requests",
+                value: "{search}",
+                oninput: move |evt| search.set(evt.value()),
+            }
+            select {
+                class: "list-sort",
+                onchange: move |evt| sort.set(match evt.value().as_str() {
+                    "method" => ListSort::Method,
+                    "last-run" => ListSort::LastRun,
+                    _ => ListSort::Name,
+                }),
+                option { value: "name", "Sort by name" }
+                option { value: "method", "Sort by method" }
+                option { value: "last-run", "Sort by last run" }
+            }
+            if entries.read().is_none() {
+                div { class: "result-note list-progress", "Scanning… {scanned} requests read" }
+            }
+            for entry in rows {
+                div {
+                    key: "{entry.path.display()}",
+                    class: if current_path.as_ref() == Some(&entry.path) { "list-row selected" } else { "list-row" },
+                    title: "{entry.path.display()}",
+                    onclick: {
+                        let path = entry.path.clone();
+                        move |_| on_select.call(path.clone())
+                    },
+                    span { class: "list-method method-{entry.method.to_lowercase()}", "{entry.method}" }
+                    span { class: "list-name", "{entry.name}" }
+                    if storage::is_archived(&entry.path) {
+                        span { class: "archived-badge", "Archived" }
+                    }
+                    span { class: "list-folder", "{entry.folder}" }
+                }
+            }
+        }
+    }
+}
+
+/// Result of sending the request to one environment in the compare view.
+#[derive(Clone, PartialEq)]
+struct CompareColumn {
+    env: String,
+    url: String,
+    /// `None` while the request is in flight.
+    response: Option<Result<HttpResponse, String>>,
+}
+
+/// Body made comparable: JSON is indented the same way whatever its
+/// original layout.
+fn normalized_body(res: &HttpResponse) -> String {
+    viewer::pretty_json(&res.body).unwrap_or_else(|| res.body.clone())
+}
+
+fn header_lines(res: &HttpResponse) -> String {
+    let mut lines: Vec<String> = res.headers.iter().map(|(k, v)| format!("{}: {}", k, v)).collect();
+    lines.sort();
+    lines.join("\n")
+}
+
+/// One difference from the saved file and the request with it undone.
+#[derive(Clone, PartialEq)]
+struct Reversal {
+    label: String,
+    old: String,
+    new: String,
+    reverted: RequestData,
+}
+
+/// What changed in the editor since `path` was saved: fields and headers
+/// listed one by one, the body as a line diff. Each change can be reverted.
+#[component]
+fn SavedDiffDialog(
+    request: RequestData,
+    path: PathBuf,
+    on_change: EventHandler<RequestData>,
+    on_close: EventHandler<()>,
+) -> Element {
+    let saved = use_hook(|| storage::load_request(&path));
+    let saved = match saved {
+        Ok(saved) => saved,
+        Err(e) => {
+            return rsx! {
+                div { class: "modal-backdrop",
+                    div { class: "modal",
+                        h3 { "Diff against saved" }
+                        div { class: "settings-error", "Cannot read {path.display()}: {e}" }
+                        div { class: "modal-actions",
+                            button { onclick: move |_| on_close.call(()), "Close" }
+                        }
+                    }
+                }
+            };
+        }
+    };
+
+    let mut fields = Vec::new();
+    let mut field = |label: &str, old: String, new: String, revert: &dyn Fn(&mut RequestData)| {
+        if old != new {
+            let mut reverted = request.clone();
+            revert(&mut reverted);
+            fields.push(Reversal { label: label.to_string(), old, new, reverted });
+        }
+    };
+    field("Title", saved.name.clone().unwrap_or_default(), request.name.clone().unwrap_or_default(), &|r| r.name = saved.name.clone());
+    field("Method", saved.method.clone(), request.method.clone(), &|r| r.method = saved.method.clone());
+    field("URL", saved.url.clone(), request.url.clone(), &|r| r.url = saved.url.clone());
+    for key in storage::OPTION_KEYS {
+        field(key, saved.options.get(key), request.options.get(key), &|r| {
+            let _ = r.options.set(key, &saved.options.get(key));
+        });
+    }
+    let view_list = |views: &[(String, String)]| views.iter().map(|(n, e)| format!("{} = {}", n, e)).collect::<Vec<_>>().join("; ");
+    field("Views", view_list(&saved.views), view_list(&request.views), &|r| r.views = saved.views.clone());
+    let soap_summary = |soap: &Option<soap::SoapEnvelope>| soap.as_ref().map_or(String::new(), |soap| {
+        format!("SOAP {} {}{}", soap.version.as_str(), soap.action, if soap.template.is_some() { " (custom envelope)" } else { "" })
+    });
+    field("SOAP", soap_summary(&saved.soap), soap_summary(&request.soap), &|r| r.soap = saved.soap.clone());
+    field("Decoded fields", saved.decode_fields.join("; "), request.decode_fields.join("; "), &|r| {
+        r.decode_fields = saved.decode_fields.clone();
+    });
+
+    let headers: Vec<Reversal> = storage::diff_headers(&saved.headers, &request.headers).into_iter()
+        .map(|change| {
+            let mut reverted = request.clone();
+            change.revert(&mut reverted.headers);
+            let (label, old, new) = match change {
+                storage::HeaderChange::Added { name, value, .. } => (name, String::new(), value),
+                storage::HeaderChange::Removed { name, value, .. } => (name, value, String::new()),
+                storage::HeaderChange::Changed { name, old, new, .. } => (name, old, new),
+            };
+            Reversal { label, old, new, reverted }
+        })
+        .collect();
+
+    let body_chunks: Vec<(diff::Chunk, Option<RequestData>)> = diff::chunks(diff::diff_lines(&saved.body, &request.body))
+        .into_iter()
+        .map(|chunk| {
+            let reverted = chunk.hunk.map(|hunk| RequestData {
+                body: diff::revert_hunk(&saved.body, &request.body, hunk),
+                ..request.clone()
+            });
+            (chunk, reverted)
+        })
+        .collect();
+    let body_changed = body_chunks.iter().any(|(chunk, _)| chunk.hunk.is_some());
+    let unchanged = fields.is_empty() && headers.is_empty() && !body_changed;
+    let revert_all = RequestData { id: request.id.clone(), ..saved };
+
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal compare-view",
+                h3 { "Diff against saved" }
+                div { class: "compare-url", "{path.display()}" }
+                if unchanged {
+                    div { class: "result-note", "No unsaved changes." }
+                }
+                if !fields.is_empty() {
+                    h4 { "Request" }
+                    ReversalTable { rows: fields, on_change }
+                }
+                if !headers.is_empty() {
+                    h4 { "Headers" }
+                    ReversalTable { rows: headers, on_change }
+                }
+                if body_changed {
+                    h4 { "Body" }
+                    pre { class: "result-body diff",
+                        for (chunk, reverted) in body_chunks {
+                            div { class: if chunk.hunk.is_some() { "diff-hunk" } else { "" },
+                                if let Some(reverted) = reverted {
+                                    button {
+                                        class: "hunk-revert",
+                                        onclick: move |_| on_change.call(reverted.clone()),
+                                        "Revert"
+                                    }
+                                }
+                                for line in chunk.lines {
+                                    match line {
+                                        diff::DiffLine::Same(text) => rsx! { div { class: "diff-same", "  {text}" } },
+                                        diff::DiffLine::Removed(text) => rsx! { div { class: "diff-removed", "- {text}" } },
+                                        diff::DiffLine::Added(text) => rsx! { div { class: "diff-added", "+ {text}" } },
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                div { class: "modal-actions",
+                    button {
+                        disabled: unchanged,
+                        onclick: move |_| on_change.call(revert_all.clone()),
+                        "Revert all"
+                    }
+                    button { onclick: move |_| on_close.call(()), "Close" }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn ReversalTable(rows: Vec<Reversal>, on_change: EventHandler<RequestData>) -> Element {
+    rsx! {
+        table { class: "reversal-table",
+            for Reversal { label, old, new, reverted } in rows {
+                tr {
+                    td { class: "reversal-label", "{label}" }
+                    td { class: "diff-removed", "{old}" }
+                    td { class: "diff-added", "{new}" }
+                    td {
+                        button { onclick: move |_| on_change.call(reverted.clone()), "Revert" }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A body compared structurally when both sides are JSON, else line by line.
+type BodyDiff = Result<diff::JsonDiff, Vec<diff::DiffLine>>;
+
+/// Sends the current request to several environments at once and shows the
+/// responses side by side, optionally as a diff against the first column.
+#[component]
+fn CompareDialog(request: RequestData, settings: Settings, on_close: EventHandler<()>) -> Element {
+    let environments = use_hook(environment::list_environments);
+    let mut selected = use_signal(Vec::<String>::new);
+    let mut columns = use_signal(Vec::<CompareColumn>::new);
+    let mut show_diff = use_signal(|| false);
+    // Incremented per send so late responses of an earlier batch are dropped
+    let mut batch = use_signal(|| 0u32);
+
+    let on_send = {
+        let environments = environments.clone();
+        move |_| {
+            let envs: Vec<String> = environments.iter().filter(|e| selected.read().contains(e)).cloned().collect();
+            batch += 1;
+            let current = batch();
+            let mut prepared = Vec::new();
+            for env in &envs {
+                let resolved = environment::load_environment(env)
+                    .and_then(|vars| environment::Resolver { environment: Some(env.clone()), ..settings.resolver(vars) }.resolve_request(&request));
+                let url = resolved.as_ref().map(|r| r.url.clone()).unwrap_or_else(|_| request.url.clone());
+                prepared.push((CompareColumn { env: env.clone(), url, response: None }, resolved));
+            }
+            columns.set(prepared.iter().map(|(column, _)| column.clone()).collect());
+            for (index, (_, resolved)) in prepared.into_iter().enumerate() {
+                spawn(async move {
+                    let response = match resolved {
+                        Ok(resolved) => execute_request(&resolved).await,
+                        Err(e) => Err(e),
+                    };
+                    if batch() == current {
+                        columns.write()[index].response = Some(response);
+                    }
+                });
+            }
+        }
+    };
+
+    let columns_now = columns();
+    let baseline = columns_now.first()
+        .and_then(|c| c.response.clone())
+        .and_then(Result::ok);
+    // JSON bodies are compared structurally, anything else line by line
+    let views: Vec<(CompareColumn, Vec<diff::DiffLine>, BodyDiff, bool)> = columns_now.iter().enumerate()
+        .map(|(i, column)| {
+            let (headers, body, status_differs) = match (&column.response, &baseline) {
+                (Some(Ok(res)), Some(base)) if show_diff() && i > 0 => {
+                    let json = serde_json::from_str::<serde_json::Value>(&base.body).ok()
+                        .zip(serde_json::from_str::<serde_json::Value>(&res.body).ok());
+                    let body = match json {
+                        Some((old, new)) => Ok(diff::diff_json(&old, &new)),
+                        None => Err(diff::diff_lines(&normalized_body(base), &normalized_body(res))),
+                    };
+                    (diff::diff_lines(&header_lines(base), &header_lines(res)), body, base.status != res.status)
+                }
+                (Some(Ok(res)), _) => (
+                    diff::unchanged(&header_lines(res)),
+                    Err(diff::unchanged(&normalized_body(res))),
+                    false,
+                ),
+                _ => (Vec::new(), Err(Vec::new()), false),
+            };
+            (column.clone(), headers, body, status_differs)
+        })
+        .collect();
+
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal compare-view",
+                h3 { "Compare environments" }
+                if environments.is_empty() {
+                    div { class: "settings-hint",
+                        "No environments found. Create NAME.env or NAME.json files in {environment::environments_dir().display()}."
+                    }
+                }
+                div { class: "column-list",
+                    for env in environments.iter().cloned() {
+                        label { key: "{env}",
+                            input {
+                                r#type: "checkbox",
+                                checked: selected.read().contains(&env),
+                                onchange: {
+                                    let env = env.clone();
+                                    move |evt: FormEvent| {
+                                        if evt.checked() {
+                                            selected.write().push(env.clone());
+                                        } else {
+                                            selected.write().retain(|e| e != &env);
+                                        }
+                                    }
+                                }
+                            }
+                            "{env}"
+                        }
+                    }
+                }
+                div { class: "modal-actions",
+                    label {
+                        input {
+                            r#type: "checkbox",
+                            checked: show_diff(),
+                            onchange: move |evt| show_diff.set(evt.checked()),
+                        }
+                        "Diff against {columns_now.first().map(|c| c.env.clone()).unwrap_or_default()}"
+                    }
+                    button { disabled: selected.read().is_empty(), onclick: on_send, "Send" }
+                    button { onclick: move |_| on_close.call(()), "Close" }
+                }
+                div { class: "compare-columns",
+                    for (column, headers, body, status_differs) in views {
+                        div { class: "compare-column", key: "{column.env}",
+                            h4 { "{column.env}" }
+                            div { class: "compare-url", title: "{column.url}", "{column.url}" }
+                            match &column.response {
+                                None => rsx! { div { class: "result-note", "Sending..." } },
+                                Some(Err(e)) => rsx! { div { class: "settings-error", "{e}" } },
+                                Some(Ok(res)) => rsx! {
+                                    div { class: if status_differs { "result-header diff-changed" } else { "result-header" },
+                                        "{res.status_text} · {res.elapsed_ms} ms"
+                                    }
+                                    DiffBlock { lines: headers }
+                                    match body {
+                                        Ok(diff) => rsx! { JsonDiffView { diff } },
+                                        Err(lines) => rsx! { DiffBlock { lines } },
+                                    }
+                                },
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A structural JSON diff as a tree: branches with differences are open,
+/// unchanged ones are shown on one line.
+#[component]
+fn JsonDiffView(diff: diff::JsonDiff) -> Element {
+    let changes = diff.changes();
+    let summary = match changes.len() {
+        0 => "No differences".to_string(),
+        1 => "1 difference".to_string(),
+        n => format!("{} differences", n),
+    };
+    rsx! {
+        div { class: "result-body diff json-diff",
+            div { class: "result-note", "{summary}" }
+            JsonDiffEntry { label: String::new(), diff }
+        }
+    }
+}
+
+#[component]
+fn JsonDiffEntry(label: String, diff: diff::JsonDiff) -> Element {
+    let mut open = use_signal(|| true);
+    let label = if label.is_empty() { String::new() } else { format!("{}: ", label) };
+    let compact = |value: &serde_json::Value| {
+        let text = value.to_string();
+        if text.chars().count() > 80 { format!("{}…", text.chars().take(80).collect::<String>()) } else { text }
+    };
+    match diff {
+        diff::JsonDiff::Same(value) => rsx! { div { class: "diff-same", "  {label}{compact(&value)}" } },
+        diff::JsonDiff::Added(value) => rsx! { div { class: "diff-added", "+ {label}{compact(&value)}" } },
+        diff::JsonDiff::Removed(value) => rsx! { div { class: "diff-removed", "- {label}{compact(&value)}" } },
+        diff::JsonDiff::Changed { old, new } => rsx! {
+            div { class: "diff-removed", "- {label}{compact(&old)}" }
+            div { class: "diff-added", "+ {label}{compact(&new)}" }
+        },
+        diff::JsonDiff::Moved { from, value } => rsx! {
+            div { class: "diff-changed", "↕ {label}{compact(&value)} (was [{from}])" }
+        },
+        diff::JsonDiff::Children(children) => rsx! {
+            div {
+                class: "json-diff-branch",
+                onclick: move |_| open.toggle(),
+                if open() { "▾ " } else { "▸ " }
+                "{label}"
+            }
+            if open() {
+                div { class: "json-diff-children",
+                    for (key, child) in children {
+                        JsonDiffEntry { label: key, diff: child }
+                    }
+                }
+            }
+        },
+    }
+}
+
+#[component]
+fn DiffBlock(lines: Vec<diff::DiffLine>) -> Element {
+    rsx! {
+        pre { class: "result-body diff",
+            for line in lines {
+                match line {
+                    diff::DiffLine::Same(text) => rsx! { div { class: "diff-same", "  {text}" } },
+                    diff::DiffLine::Removed(text) => rsx! { div { class: "diff-removed", "- {text}" } },
+                    diff::DiffLine::Added(text) => rsx! { div { class: "diff-added", "+ {text}" } },
+                }
+            }
+        }
+    }
+}
+
+/// Sends variants of `request` with one field set to each of a list of
+/// values and tabulates the statuses.
+#[component]
+fn FuzzDialog(request: RequestData, settings: Settings, on_close: EventHandler<()>) -> Element {
+    let mut kind = use_signal(|| fuzz::FieldKind::Header);
+    let mut name = use_signal(String::new);
+    let mut sets = use_signal(|| fuzz::ValueSet::ALL.to_vec());
+    let mut custom = use_signal(String::new);
+    let mut max = use_signal(|| fuzz::DEFAULT_MAX_VARIANTS);
+    let mut results = use_signal(Vec::<fuzz::FuzzResult>::new);
+    let mut total = use_signal(|| 0usize);
+    let mut cancel = use_signal(|| None::<CancelFlag>);
+    let mut selected = use_signal(|| None::<usize>);
+    let mut status = use_signal(|| None::<String>);
+    let mut path = use_signal(|| storage::get_base_dir().join("exports").join("fuzz.csv").display().to_string());
+
+    let on_run = move |_| {
+        let field = fuzz::FuzzField { kind: kind(), name: name() };
+        let values = fuzz::values(&custom.read(), &sets.read(), max());
+        let variants = settings.active_resolver()
+            .and_then(|resolver| resolver.resolve_request(&request))
+            .and_then(|resolved| fuzz::variants(&resolved, &field, &values));
+        let variants = match variants {
+            Ok(variants) if variants.is_empty() => {
+                status.set(Some("No values to try".to_string()));
+                return;
+            }
+            Ok(variants) => variants,
+            Err(e) => {
+                status.set(Some(e));
+                return;
+            }
+        };
+        let flag = CancelFlag::default();
+        cancel.set(Some(flag.clone()));
+        results.set(Vec::new());
+        total.set(variants.len());
+        selected.set(None);
+        status.set(None);
+        spawn(async move {
+            fuzz::run(variants, &flag, |result| results.write().push(result)).await;
+            cancel.set(None);
+        });
+    };
+    let on_save = move |_| {
+        let target = PathBuf::from(path());
+        let result = target.parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&target, fuzz::to_csv(&results.read())));
+        status.set(Some(match result {
+            Ok(()) => format!("Saved {} rows to {}", results.read().len(), target.display()),
+            Err(e) => format!("Could not save: {}", e),
+        }));
+    };
+
+    let running = cancel.read().is_some();
+    let rows: Vec<(usize, String, Result<String, String>, u64)> = results.read().iter().enumerate()
+        .map(|(i, result)| (i, fuzz::preview(&result.value), result.outcome.clone(), result.elapsed_ms))
+        .collect();
+    let detail = selected().and_then(|i| results.read().get(i).map(|result| result.request.to_http_string()));
+    let name_placeholder = match kind() {
+        fuzz::FieldKind::Header => "Header name, e.g. X-User-Id",
+        fuzz::FieldKind::Query => "Query parameter, e.g. page",
+        fuzz::FieldKind::Body => "JSON path, e.g. .user.name or .items[0].id",
+    };
+
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal compare-view",
+                h3 { "Fuzz a field" }
+                div { class: "settings-form",
+                    label { class: "settings-row",
+                        span { "Field" }
+                        select {
+                            onchange: move |evt| kind.set(match evt.value().as_str() {
+                                "query" => fuzz::FieldKind::Query,
+                                "body" => fuzz::FieldKind::Body,
+                                _ => fuzz::FieldKind::Header,
+                            }),
+                            option { value: "header", "Header" }
+                            option { value: "query", "Query parameter" }
+                            option { value: "body", "JSON body" }
+                        }
+                        input {
+                            r#type: "text",
+                            placeholder: "{name_placeholder}",
+                            value: "{name}",
+                            oninput: move |evt| name.set(evt.value()),
+                        }
+                    }
+                    div { class: "column-list",
+                        for set in fuzz::ValueSet::ALL {
+                            label { key: "{set.label()}",
+                                input {
+                                    r#type: "checkbox",
+                                    checked: sets.read().contains(&set),
+                                    onchange: move |evt: FormEvent| {
+                                        if evt.checked() {
+                                            sets.write().push(set);
+                                        } else {
+                                            sets.write().retain(|s| *s != set);
+                                        }
+                                    }
+                                }
+                                "{set.label()}"
+                            }
+                        }
+                    }
+                    textarea {
+                        class: "share-input",
+                        placeholder: "Own values, one per line; tried before the sets above",
+                        value: "{custom}",
+                        oninput: move |evt| custom.set(evt.value()),
+                    }
+                    label { class: "settings-row",
+                        span { "At most" }
+                        input {
+                            r#type: "number",
+                            min: "1",
+                            max: "{fuzz::MAX_VARIANTS}",
+                            value: "{max}",
+                            oninput: move |evt| {
+                                if let Ok(n) = evt.value().parse::<usize>() {
+                                    max.set(n.clamp(1, fuzz::MAX_VARIANTS));
+                                }
+                            }
+                        }
+                        span { "variants" }
+                    }
+                }
+                div { class: "modal-actions",
+                    if running {
+                        button {
+                            onclick: move |_| {
+                                if let Some(flag) = cancel.read().as_ref() {
+                                    flag.cancel();
+                                }
+                            },
+                            "Cancel ({rows.len()}/{total})"
+                        }
+                    } else {
+                        button { onclick: on_run, "Run" }
+                    }
+                    button { onclick: move |_| on_close.call(()), "Close" }
+                }
+                if let Some(message) = status() {
+                    div { class: "settings-hint", "{message}" }
+                }
+                if !rows.is_empty() {
+                    div { class: "latency-stats",
+                        table {
+                            tr {
+                                th { "#" }
+                                th { "Value" }
+                                th { "Status" }
+                                th { "Time" }
+                            }
+                            for (i, value, outcome, elapsed_ms) in rows {
+                                tr {
+                                    key: "{i}",
+                                    class: if selected() == Some(i) { "fuzz-row selected" } else { "fuzz-row" },
+                                    title: "Show the request sent",
+                                    onclick: move |_| selected.set(Some(i)),
+                                    td { "{i + 1}" }
+                                    td { class: "fuzz-value", "{value}" }
+                                    match outcome {
+                                        Ok(status) => rsx! { td { "{status}" } },
+                                        Err(e) => rsx! { td { class: "settings-error", "{e}" } },
+                                    }
+                                    td { "{elapsed_ms} ms" }
+                                }
+                            }
+                        }
+                    }
+                    label { class: "settings-row",
+                        span { "Save CSV to" }
+                        input {
+                            r#type: "text",
+                            class: "path-input",
+                            value: "{path}",
+                            oninput: move |evt| path.set(evt.value()),
+                        }
+                        button { disabled: running, onclick: on_save, "Save" }
+                    }
+                }
+                if let Some(text) = detail {
+                    pre { class: "result-body markdown-preview", "{text}" }
+                }
+            }
+        }
+    }
+}
+
+/// Sends the OPTIONS preflight for `request` and reports what a browser on
+/// the chosen origin would make of the response.
+#[component]
+fn PreflightDialog(request: RequestData, settings: Settings, on_close: EventHandler<()>) -> Element {
+    let mut origin = use_signal(|| "http://localhost:3000".to_string());
+    let mut credentials = use_signal(|| false);
+    let mut result = use_signal(|| None::<Result<(HttpResponse, Vec<cors::CorsCheck>), String>>);
+    let mut sending = use_signal(|| false);
+
+    let resolved = settings.active_resolver().and_then(|resolver| resolver.resolve_request(&request));
+    let preview = resolved.as_ref()
+        .map(|req| cors::preflight_request(req, &origin()).to_http_string())
+        .unwrap_or_else(|e| e.clone());
+    let needed = resolved.as_ref().is_ok_and(cors::needs_preflight);
+
+    let on_send = move |_| {
+        let Ok(req) = resolved.clone() else { return };
+        spawn(async move {
+            sending.set(true);
+            let origin = origin();
+            let preflight = cors::preflight_request(&req, &origin);
+            let outcome = execute_request(&preflight).await.map(|res| {
+                let checks = cors::evaluate(&req, &origin, credentials(), &res);
+                (res, checks)
+            });
+            result.set(Some(outcome));
+            sending.set(false);
+        });
+    };
+
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal",
+                h3 { "CORS preflight" }
+                label { class: "settings-row",
+                    span { "Origin" }
+                    input {
+                        r#type: "text",
+                        class: "path-input",
+                        value: "{origin}",
+                        oninput: move |evt| origin.set(evt.value()),
+                    }
+                }
+                label { class: "settings-row",
+                    input {
+                        r#type: "checkbox",
+                        checked: credentials(),
+                        onchange: move |evt| credentials.set(evt.checked()),
+                    }
+                    "Send with credentials (cookies or HTTP auth)"
+                }
+                if !needed {
+                    div { class: "settings-hint", "A browser would not send a preflight for this request: the method and headers are all CORS-safelisted." }
+                }
+                pre { class: "result-body", "{preview}" }
+                match result() {
+                    Some(Ok((res, checks))) => rsx! {
+                        div { class: "result-header", "Status: {res.status_text}" }
+                        div { class: "cors-checks",
+                            for check in checks {
+                                div { class: if check.passed { "cors-check passed" } else { "cors-check failed" },
+                                    if check.passed { "✓ " } else { "✗ " }
+                                    "{check.message}"
+                                }
+                            }
+                        }
+                    },
+                    Some(Err(e)) => rsx! { div { class: "settings-error", "Preflight failed: {e}" } },
+                    None => rsx! {},
+                }
+                div { class: "modal-actions",
+                    button { disabled: sending(), onclick: on_send, if sending() { "Sending..." } else { "Send preflight" } }
+                    button { onclick: move |_| on_close.call(()), "Close" }
+                }
+            }
+        }
+    }
+}
+
+/// Expert mode for probing how servers handle malformed requests: the
+/// literal HTTP/1.1 message is edited and sent as written over its own
+/// connection, bypassing validation, cookies, auth and history.
+#[component]
+fn RawModeDialog(request: RequestData, settings: Settings, on_close: EventHandler<()>) -> Element {
+    // Starts from the request with its placeholders filled in, those that
+    // cannot be left as written and listed
+    let (start, unresolved) = settings.active_resolver()
+        .and_then(|resolver| resolver.substitute_request(&request))
+        .unwrap_or_else(|e| (request, vec![e]));
+    let insecure_default = start.options.insecure_tls == Some(true);
+    let timeout_ms = start.options.timeout_ms;
+    let mut target = use_signal(|| {
+        reqwest::Url::parse(&start.url).map_or_else(|_| start.url.clone(), |url| url.origin().ascii_serialization())
+    });
+    let mut message = use_signal(|| export::to_raw_message(&start));
+    let generated_length = export::raw_content_length(&start);
+    let mut crlf = use_signal(|| true);
+    let mut insecure = use_signal(|| insecure_default);
+    let mut hex = use_signal(|| false);
+    let mut sending = use_signal(|| false);
+    let mut result = use_signal(|| None::<Result<client::RawExchange, String>>);
+    // One line per send, newest first; nothing goes to history
+    let mut log = use_signal(Vec::<String>::new);
+
+    let on_send = move |_| {
+        spawn(async move {
+            sending.set(true);
+            let target = target();
+            let text = message();
+            let bytes = client::raw_message_bytes(&text, crlf(), generated_length);
+            let outcome = client::send_raw(&target, &bytes, insecure(), timeout_ms).await;
+            let first_line = text.lines().next().unwrap_or_default().to_string();
+            let answer = match &outcome {
+                Ok(exchange) => format!(
+                    "{} ({} bytes, {} ms)",
+                    exchange.status_line().unwrap_or_else(|| "no response".to_string()),
+                    exchange.received.len(),
+                    exchange.elapsed_ms,
+                ),
+                Err(e) => e.lines().next().unwrap_or_default().to_string(),
+            };
+            let time = chrono::Local::now().format("%H:%M:%S");
+            log.write().insert(0, format!("{} {} {} → {}", time, target, first_line, answer));
+            result.set(Some(outcome));
+            sending.set(false);
+        });
+    };
+
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal raw-mode",
+                h3 { "Raw mode" }
+                div { class: "settings-error",
+                    "The message below is sent exactly as written over a plain TCP or TLS connection. Nothing is checked or added: no cookies, auth, redirects, retries or history."
+                }
+                for problem in unresolved {
+                    div { class: "result-warning", "⚠ {problem}; left as written" }
+                }
+                label { class: "settings-row",
+                    span { "Target" }
+                    input {
+                        r#type: "text",
+                        class: "path-input",
+                        placeholder: "https://api.example.com:8443",
+                        value: "{target}",
+                        oninput: move |evt| target.set(evt.value()),
+                    }
+                }
+                textarea {
+                    class: "body-editor raw-message",
+                    spellcheck: "false",
+                    value: "{message}",
+                    oninput: move |evt| message.set(evt.value()),
+                }
+                label { class: "settings-row",
+                    input {
+                        r#type: "checkbox",
+                        checked: crlf(),
+                        onchange: move |evt| crlf.set(evt.checked()),
+                    }
+                    "Send line breaks in the head as CRLF"
+                }
+                label { class: "settings-row",
+                    input {
+                        r#type: "checkbox",
+                        checked: insecure(),
+                        onchange: move |evt| insecure.set(evt.checked()),
+                    }
+                    "Accept invalid TLS certificates"
+                }
+                match result() {
+                    Some(Ok(exchange)) => {
+                        let shown = if hex() { hex_dump(&exchange.received) } else { String::from_utf8_lossy(&exchange.received).into_owned() };
+                        let status = exchange.status_line().unwrap_or_else(|| "No response".to_string());
+                        rsx! {
+                            div { class: "result-header",
+                                "{status} · {exchange.elapsed_ms} ms · {format_size(exchange.received.len())} from {exchange.remote_addr}"
+                                label { class: "raw-hex-toggle",
+                                    input {
+                                        r#type: "checkbox",
+                                        checked: hex(),
+                                        onchange: move |evt| hex.set(evt.checked()),
+                                    }
+                                    "Hex"
+                                }
+                            }
+                            if let Some(note) = &exchange.cut_short {
+                                div { class: "result-warning", "{note}" }
+                            }
+                            pre { class: "result-body raw-response", "{shown}" }
+                        }
+                    }
+                    Some(Err(e)) => rsx! { div { class: "settings-error", "Raw send failed: {e}" } },
+                    None => rsx! {},
+                }
+                if !log.read().is_empty() {
+                    details { class: "raw-log",
+                        summary { "Sent this session ({log.read().len()})" }
+                        for line in log.read().iter() {
+                            div { "{line}" }
+                        }
+                    }
+                }
+                div { class: "modal-actions",
+                    button { disabled: sending(), onclick: on_send, if sending() { "Sending..." } else { "Send raw" } }
+                    button { onclick: move |_| on_close.call(()), "Close" }
+                }
+            }
+        }
+    }
+}
+
+/// Asks before sending a request whose method is on the confirmation list,
+/// or that is pinned to environments other than the active one.
+#[component]
+fn ConfirmSendDialog(
+    request: RequestData,
+    method: String,
+    repeat: Option<u32>,
+    settings: Settings,
+    /// Turns off the protected-environment question for the request.
+    on_never_ask: EventHandler<()>,
+    on_confirm: EventHandler<()>,
+    on_close: EventHandler<()>,
+) -> Element {
+    let mut never_ask = use_signal(|| false);
+    let protected = settings.protects(&method) && request.options.confirm_protected != Some(false);
+    let resolved = settings.active_resolver().and_then(|resolver| resolver.resolve_request(&request));
+    let compressed = resolved.as_ref().ok()
+        .and_then(|resolved| client::assemble_body(resolved).map(|(_, sizes)| sizes).transpose());
+    let url = resolved.map_or_else(|e| e, |resolved| resolved.url);
+    let active = settings.active_environment.clone().unwrap_or_else(|| "no environment".to_string());
+    let pin = request.options.pinned_elsewhere(settings.active_environment.as_deref())
+        .then(|| request.options.environments.join(", "));
+    let method_listed = settings.confirm_methods.iter().any(|m| m.eq_ignore_ascii_case(&method));
+
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal",
+                h3 {
+                    "Send {method}"
+                    if let Some(count) = repeat { " {count} times" }
+                    if pin.is_some() || protected { " with {active}" }
+                    "?"
+                }
+                if protected {
+                    div { class: "settings-error",
+                        "{active} is a protected environment. Check the URL before sending."
+                    }
+                }
+                if let Some(pins) = &pin {
+                    div { class: "settings-error",
+                        "This request is pinned to {pins}, but the active environment is {active}."
+                    }
+                }
+                div { class: "confirm-summary",
+                    span { class: "list-method method-{method.to_lowercase()}", "{method}" }
+                    span { class: "compare-url", "{url}" }
+                }
+                match compressed {
+                    Some(Ok(sizes)) => rsx! { div { class: "result-note", "{sizes}" } },
+                    Some(Err(e)) => rsx! { div { class: "settings-error", "{e}" } },
+                    None => rsx! {},
+                }
+                if method_listed {
+                    div { class: "settings-hint", "Confirmation for {method} is turned on in Settings." }
+                }
+                if protected {
+                    label { class: "settings-row",
+                        input {
+                            r#type: "checkbox",
+                            checked: never_ask(),
+                            onchange: move |evt| never_ask.set(evt.checked()),
+                        }
+                        "Never ask for this request in protected environments"
+                    }
+                }
+                div { class: "modal-actions",
+                    button {
+                        onclick: move |_| {
+                            if never_ask() {
+                                on_never_ask.call(());
+                            }
+                            on_confirm.call(());
+                        },
+                        if pin.is_some() { "Send with {active} anyway" } else { "Send" }
+                    }
+                    button { onclick: move |_| on_close.call(()), "Cancel" }
+                }
+            }
+        }
+    }
+}
+
+/// Asks before a folder run sends with a protected environment.
+#[component]
+fn ProtectedRunDialog(folder: PathBuf, environment: String, on_confirm: EventHandler<()>, on_close: EventHandler<()>) -> Element {
+    let name = folder.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal",
+                h3 { "Run {name} with {environment}?" }
+                div { class: "settings-error",
+                    "{environment} is a protected environment. Every request in the folder will be sent to it, whatever its method."
+                }
+                div { class: "modal-actions",
+                    button { class: "delete-confirm", onclick: move |_| on_confirm.call(()), "Run anyway" }
+                    button { onclick: move |_| on_close.call(()), "Cancel" }
+                }
+            }
+        }
+    }
+}
+
+/// Scaffolds a new folder of requests from one of the templates.
+#[component]
+fn TemplateDialog(on_created: EventHandler<PathBuf>, on_close: EventHandler<()>) -> Element {
+    let names = use_hook(templates::list_templates);
+    let mut template = use_signal(|| names.first().cloned().unwrap_or_default());
+    let mut folder = use_signal(String::new);
+    let mut base_url = use_signal(|| "https://".to_string());
+    let mut error = use_signal(|| None::<String>);
+
+    let on_create = move |_| {
+        let relative = PathBuf::from(folder.read().trim());
+        if relative.as_os_str().is_empty() {
+            error.set(Some("Enter a folder name".to_string()));
+            return;
+        }
+        if relative.is_absolute() || relative.components().any(|c| matches!(c, std::path::Component::ParentDir)) {
+            error.set(Some("The folder must be inside the workspace".to_string()));
+            return;
+        }
+        let path = storage::get_base_dir().join(relative);
+        match templates::scaffold(&template.read(), &path, &base_url.read()) {
+            Ok(_) => on_created.call(path),
+            Err(e) => error.set(Some(e)),
+        }
+    };
+
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal",
+                h3 { "New folder from template" }
+                if names.is_empty() {
+                    div { class: "settings-hint",
+                        "No templates found. Add folders of .req files to {templates::templates_dir().display()}."
+                    }
+                } else {
+                    label { class: "settings-row",
+                        span { "Template" }
+                        select {
+                            value: "{template}",
+                            onchange: move |evt| template.set(evt.value()),
+                            for name in names.iter() {
+                                option { value: "{name}", "{name}" }
+                            }
+                        }
+                    }
+                    label { class: "settings-row",
+                        span { "Folder" }
+                        input {
+                            r#type: "text",
+                            class: "path-input",
+                            value: "{folder}",
+                            oninput: move |evt| {
+                                folder.set(evt.value());
+                                error.set(None);
+                            },
+                        }
+                    }
+                    label { class: "settings-row",
+                        span { "Base URL" }
+                        input {
+                            r#type: "text",
+                            class: "path-input",
+                            value: "{base_url}",
+                            oninput: move |evt| base_url.set(evt.value()),
+                        }
+                    }
+                    div { class: "settings-hint",
+                        "${{base_url}} and ${{name}} (the folder name) are filled in; {{{{...}}}} placeholders are kept for environments."
+                    }
+                }
+                if let Some(e) = error() {
+                    div { class: "settings-error", "{e}" }
+                }
+                div { class: "modal-actions",
+                    button { disabled: names.is_empty(), onclick: on_create, "Create" }
+                    button { onclick: move |_| on_close.call(()), "Cancel" }
+                }
+            }
+        }
+    }
+}
+
+/// Asks where to save a request that has no file yet: a file name and one
+/// of the folders the sidebar has listed. The name gets a `.req` extension
+/// when it is missing.
+#[component]
+fn SaveAsDialog(
+    request: RequestData,
+    root: PathBuf,
+    /// Folders to choose from, the workspace root first.
+    folders: Vec<PathBuf>,
+    /// Creates an empty request rather than saving the open one.
+    creating: bool,
+    on_saved: EventHandler<PathBuf>,
+    on_close: EventHandler<()>,
+) -> Element {
+    let mut name = use_signal(|| if creating { "new-request.req" } else { "scratch.req" }.to_string());
+    let mut folder = use_signal(|| root.clone());
+    let mut error = use_signal(|| None::<String>);
+    // The file that exists and will be replaced on the next Save
+    let mut replacing = use_signal(|| None::<PathBuf>);
+
+    let on_save = move |_| {
+        let mut file_name = name.read().trim().to_string();
+        if file_name.is_empty() {
+            error.set(Some("Enter a file name".to_string()));
+            return;
+        }
+        if file_name.contains(['/', '\\']) || file_name == "." || file_name == ".." {
+            error.set(Some("The name cannot contain / or \\; choose the folder below".to_string()));
+            return;
+        }
+        if !file_name.ends_with(".req") {
+            file_name.push_str(".req");
+        }
+        let path = folder.read().join(file_name);
+        if creating && storage::request_exists(&path) {
+            error.set(Some(format!("{} already exists", path.display())));
+            return;
+        }
+        if storage::request_exists(&path) && replacing.read().as_ref() != Some(&path) {
+            replacing.set(Some(path));
+            return;
+        }
+        // A new file is a new request, even if the content came from another
+        // one; a replaced file keeps its ID
+        let request = RequestData { id: None, ..request.clone() };
+        match storage::save_request(&path, &request) {
+            Ok(_) => on_saved.call(path),
+            Err(e) => error.set(Some(format!("Could not save: {}", e))),
+        }
+    };
+
+    let options: Vec<(String, String)> = folders.iter()
+        .map(|path| {
+            let relative = path.strip_prefix(&root).unwrap_or(path).display().to_string();
+            (path.display().to_string(), if relative.is_empty() { "/".to_string() } else { format!("/{}", relative) })
+        })
+        .collect();
+    let replace_note = replacing().map(|path| format!("{} already exists. Save again to replace it.", path.display()));
+
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal",
+                h3 { if creating { "New request" } else { "Save request" } }
+                label { class: "settings-row",
+                    span { "Name" }
+                    input {
+                        r#type: "text",
+                        value: "{name}",
+                        oninput: move |evt| {
+                            name.set(evt.value());
+                            error.set(None);
+                            replacing.set(None);
+                        },
+                    }
+                }
+                label { class: "settings-row",
+                    span { "Folder" }
+                    select {
+                        value: "{folder.read().display()}",
+                        onchange: move |evt| {
+                            folder.set(PathBuf::from(evt.value()));
+                            replacing.set(None);
+                        },
+                        for (value, label) in options {
+                            option { value: "{value}", "{label}" }
+                        }
+                    }
+                }
+                div { class: "settings-hint", "Folders are those listed in the sidebar so far; expand one there to offer its subfolders." }
+                if let Some(e) = error() {
+                    div { class: "settings-error", "{e}" }
+                }
+                if let Some(note) = replace_note {
+                    div { class: "settings-error", "{note}" }
+                }
+                div { class: "modal-actions",
+                    button { onclick: on_save, if replacing().is_some() { "Replace" } else if creating { "Create" } else { "Save" } }
+                    button { onclick: move |_| on_close.call(()), "Cancel" }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn HistoryView(
+    entries: Signal<Vec<HistoryEntry>>,
+    ids: Signal<storage::IdIndex>,
+    utc_times: bool,
+    on_open: EventHandler<HistoryEntry>,
+    on_close: EventHandler<()>,
+) -> Element {
+    const PAGE_SIZE: usize = 50;
+    let mut query = use_signal(HistoryQuery::default);
+    let mut page = use_signal(|| 0usize);
+    // Every filter change starts again from the first page
+    let mut update = move |change: &dyn Fn(&mut HistoryQuery)| {
+        change(&mut query.write());
+        page.set(0);
+    };
+
+    let all = entries.read();
+    let (rows, total) = query.read().run(&all, page(), PAGE_SIZE);
+    let rows: Vec<HistoryEntry> = rows.into_iter().cloned().collect();
+    let pages = total.div_ceil(PAGE_SIZE).max(1);
+
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal history-view",
+                h3 { "History" }
+                div { class: "history-filters",
+                    select {
+                        value: "{query.read().method}",
+                        onchange: move |evt| update(&|q| q.method = evt.value()),
+                        option { value: "", "Any method" }
+                        for method in ["GET", "POST", "PUT", "DELETE", "PATCH"] {
+                            option { value: "{method}", "{method}" }
+                        }
+                    }
+                    select {
+                        onchange: move |evt| update(&|q| q.status_class = evt.value().parse().ok()),
+                        option { value: "", "Any status" }
+                        option { value: "2", "2xx" }
+                        option { value: "3", "3xx" }
+                        option { value: "4", "4xx" }
+                        option { value: "5", "5xx" }
+                        option { value: "0", "Failed" }
+                    }
+                    input {
+                        r#type: "text",
+                        placeholder: "Host",
+                        value: "{query.read().host}",
+                        oninput: move |evt| update(&|q| q.host = evt.value()),
+                    }
+                    input {
+                        r#type: "date",
+                        title: "From",
+                        value: "{query.read().from}",
+                        oninput: move |evt| update(&|q| q.from = evt.value()),
+                    }
+                    input {
+                        r#type: "date",
+                        title: "To",
+                        value: "{query.read().to}",
+                        oninput: move |evt| update(&|q| q.to = evt.value()),
+                    }
+                    input {
+                        r#type: "text",
+                        class: "history-search",
+                        placeholder: "Search URLs",
+                        value: "{query.read().text}",
+                        oninput: move |evt| update(&|q| q.text = evt.value()),
+                    }
+                }
+                div { class: "history-list",
+                    if rows.is_empty() {
+                        div { class: "settings-hint", "No matching requests" }
+                    }
+                    for entry in rows {
+                        HistoryRow {
+                            archived: entry.resolved_path(&ids.read()).as_deref().is_some_and(storage::is_archived),
+                            entry,
+                            utc_times,
+                            on_open,
+                        }
+                    }
+                }
+                div { class: "modal-actions",
+                    span { class: "settings-hint", "{total} requests" }
+                    button { disabled: page() == 0, onclick: move |_| page -= 1, "‹ Newer" }
+                    span { "{page() + 1} / {pages}" }
+                    button { disabled: page() + 1 >= pages, onclick: move |_| page += 1, "Older ›" }
+                    button { onclick: move |_| on_close.call(()), "Close" }
+                }
+            }
+        }
+    }
+}
+
+/// The last runs of the open request, newest first. Opening one shows its
+/// stored response in place of the current one.
+#[component]
+fn RecentRuns(
+    history: Signal<Vec<HistoryEntry>>,
+    ids: Signal<storage::IdIndex>,
+    path: PathBuf,
+    utc_times: bool,
+    on_open: EventHandler<HistoryEntry>,
+) -> Element {
+    const SHOWN: usize = 20;
+    let runs: Vec<HistoryEntry> = history.read().iter().rev()
+        .filter(|entry| entry.resolved_path(&ids.read()).as_deref() == Some(path.as_path()))
+        .take(SHOWN)
+        .cloned()
+        .collect();
+    if runs.is_empty() {
+        return rsx! {};
+    }
+    let rows: Vec<(String, String, String, String, HistoryEntry)> = runs.into_iter()
+        .map(|entry| {
+            let time = dates::display_ms(entry.timestamp_ms, "%Y-%m-%d %H:%M:%S", utc_times);
+            let status_class = format!("history-status s{}", entry.status().map_or(0, |s| s / 100));
+            let (status, detail) = match &entry.response {
+                Ok(res) => (res.status.to_string(), format!("{} ms · {}", res.elapsed_ms, format_size(res.size_bytes))),
+                Err(e) => ("ERR".to_string(), e.clone()),
+            };
+            (time, status_class, status, detail, entry)
+        })
+        .collect();
+    rsx! {
+        details { class: "recent-runs",
+            summary { "Recent runs" }
+            for (time, status_class, status, detail, entry) in rows {
+                div { class: "history-row",
+                    key: "{entry.timestamp_ms}",
+                    title: "Show this response",
+                    onclick: move |_| on_open.call(entry.clone()),
+                    span { class: "history-time", "{time}" }
+                    span { class: "{status_class}", "{status}" }
+                    span { class: "history-url", "{detail}" }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn HistoryRow(
+    entry: HistoryEntry,
+    /// The request file it was sent from is in an archived folder now.
+    archived: bool,
+    utc_times: bool,
+    on_open: EventHandler<HistoryEntry>,
+) -> Element {
+    let time = dates::display_ms(entry.timestamp_ms, "%Y-%m-%d %H:%M:%S", utc_times);
+    let status = match &entry.response {
+        Ok(res) => res.status.to_string(),
+        Err(_) => "ERR".to_string(),
+    };
+    let status_class = format!("history-status s{}", entry.status().map_or(0, |s| s / 100));
+    let method = entry.method.clone();
+    let url = entry.url.clone();
+    rsx! {
+        div { class: "history-row",
+            onclick: move |_| on_open.call(entry.clone()),
+            span { class: "history-time", "{time}" }
+            span { class: "history-method", "{method}" }
+            span { class: "{status_class}", "{status}" }
+            span { class: "history-url", "{url}" }
+            if archived {
+                span { class: "archived-badge", title: "Its request file is in an archived folder", "Archived" }
+            }
+        }
+    }
+}
+
+#[component]
+fn RunReportView(
+    report: Option<FolderReport>,
+    running: bool,
+    on_cancel: EventHandler<()>,
+    on_close: EventHandler<()>,
+    /// Shows the response times of the folder across its recorded runs.
+    on_timings: EventHandler<PathBuf>,
+) -> Element {
+    let timings_folder = report.as_ref().map(|report| report.path.clone());
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal run-report",
+                match report {
+                    None => rsx! {
+                        h3 { "Running…" }
+                    },
+                    Some(report) => {
+                        let (passed, failed, skipped) = report.counts();
+                        rsx! {
+                            h3 {
+                                if report.passed() { "✓ " } else { "✗ " }
+                                "Run finished: {passed} passed, {failed} failed"
+                                if skipped > 0 { ", {skipped} skipped" }
+                                if report.cancelled { " (cancelled)" }
+                            }
+                            if let Some(name) = &report.stopped_by {
+                                div { class: "settings-error", "Stopped after {name} got no response" }
+                            }
+                            FolderReportView { report }
+                        }
+                    }
+                }
+                div { class: "modal-actions",
+                    if running {
+                        button { onclick: move |_| on_cancel.call(()), "Cancel" }
+                    } else {
+                        if let Some(folder) = timings_folder {
+                            button { onclick: move |_| on_timings.call(folder.clone()), "Response times…" }
+                        }
+                        button { onclick: move |_| on_close.call(()), "Close" }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn FolderReportView(report: FolderReport) -> Element {
+    let name = report.path.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string();
+    rsx! {
+        div { class: "report-folder",
+            div { class: "report-folder-name", "📁 {name}" }
+            if let Some(setup) = report.setup {
+                div { class: "report-hook", "setup" }
+                RunResultRow { result: setup }
+            }
+            for result in report.results {
+                RunResultRow { result }
+            }
+            for folder in report.folders {
+                FolderReportView { report: folder }
+            }
+            if let Some(teardown) = report.teardown {
+                div { class: "report-hook", "teardown" }
+                RunResultRow { result: teardown }
+            }
+        }
+    }
+}
+
+#[component]
+fn RunResultRow(result: RunResult) -> Element {
+    let detail = match &result.response {
+        Ok(res) => format!("{} {}", res.status, res.status_text),
+        Err(e) => e.clone(),
+    };
+    rsx! {
+        if result.delay_before_ms > 0 {
+            div { class: "report-delay", "⏱ waited {result.delay_before_ms} ms" }
+        }
+        div { class: if result.passed { "report-row passed" } else if result.skipped { "report-row skipped" } else { "report-row failed" },
+            span { if result.passed { "✓" } else if result.skipped { "⤼" } else { "✗" } }
+            span { class: "report-name", "{result.name}" }
+            span { class: "report-detail", "{detail}" }
+        }
+        if result.delay_after_ms > 0 {
+            div { class: "report-delay", "⏱ waited {result.delay_after_ms} ms" }
+        }
+    }
+}
+
+/// Requests × the last runs of a folder, each cell coloured by how long the
+/// response took. Clicking a cell opens that result.
+#[component]
+fn TimingsDialog(
+    folder: PathBuf,
+    utc_times: bool,
+    on_open: EventHandler<(PathBuf, Result<HttpResponse, String>)>,
+    on_close: EventHandler<()>,
+) -> Element {
+    let name = folder.file_name().map_or("requester".to_string(), |n| n.to_string_lossy().into_owned());
+    let heatmap_folder = folder.clone();
+    // Rows with whether their file still exists, so gone ones cannot be opened
+    let heatmap = use_hook(move || {
+        let heatmap = runner::heatmap(&RunRecord::load_all(&heatmap_folder));
+        let exists: Vec<bool> = heatmap.rows.iter().map(|row| storage::backend().exists(&heatmap_folder.join(&row.name))).collect();
+        (heatmap, exists)
+    });
+    let (heatmap, exists) = heatmap;
+    let runs: Vec<String> = heatmap.runs.iter().map(|&ms| dates::display_ms(ms, "%m-%d %H:%M", utc_times)).collect();
+    let mut legend: Vec<String> = runner::DURATION_BUCKETS_MS.iter().map(|limit| format!("≤ {} ms", limit)).collect();
+    legend.push(format!("> {} ms", runner::DURATION_BUCKETS_MS[runner::DURATION_BUCKETS_MS.len() - 1]));
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal timings-dialog",
+                h3 { "Response times in {name}" }
+                if heatmap.runs.is_empty() {
+                    div { class: "result-warning", "No runs of this folder recorded yet. Run it to start its history." }
+                } else {
+                    div { class: "timings-legend",
+                        for (bucket, label) in legend.into_iter().enumerate() {
+                            span { class: "heat-{bucket}", "{label}" }
+                        }
+                        span { class: "heat-failed", "failed" }
+                    }
+                    div { class: "timings-table",
+                        table {
+                            tr {
+                                th { "Request" }
+                                for run in runs.iter() {
+                                    th { "{run}" }
+                                }
+                            }
+                            for (row, exists) in heatmap.rows.iter().zip(exists.iter().copied()) {
+                                tr { class: if row.dropped { "dropped" } else { "" },
+                                    td {
+                                        class: "timings-name",
+                                        title: if !exists { "No longer in the folder" } else if row.dropped { "Not part of the latest run" } else { "" },
+                                        "{row.name}"
+                                    }
+                                    for (cell, run) in row.cells.iter().zip(runs.iter()) {
+                                        {
+                                            let path = folder.join(&row.name);
+                                            match cell.clone() {
+                                                None => rsx! { td { class: "heat-none", title: "Not run on {run}", "–" } },
+                                                Some(result) => {
+                                                    let (class, text, title) = match &result.response {
+                                                        Ok(res) => {
+                                                            let bucket = runner::duration_bucket(res.elapsed_ms);
+                                                            (
+                                                                if result.passed { format!("heat-{}", bucket) } else { format!("heat-{} failed", bucket) },
+                                                                res.elapsed_ms.to_string(),
+                                                                format!("{}: {} {} in {} ms", run, res.status, res.status_text, res.elapsed_ms),
+                                                            )
+                                                        }
+                                                        Err(e) if result.skipped => ("heat-skipped".to_string(), "⤼".to_string(), format!("{}: {}", run, e)),
+                                                        Err(e) => ("heat-failed".to_string(), "✗".to_string(), format!("{}: {}", run, e)),
+                                                    };
+                                                    rsx! {
+                                                        td {
+                                                            class: if exists { "{class} openable" } else { "{class}" },
+                                                            title: "{title}",
+                                                            onclick: move |_| {
+                                                                if exists {
+                                                                    on_open.call((path.clone(), result.response.clone()));
+                                                                }
+                                                            },
+                                                            "{text}"
+                                                        }
+                                                    }
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                div { class: "modal-actions",
+                    button { onclick: move |_| on_close.call(()), "Close" }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn HeadersEditor(headers: Vec<(String, String)>, on_change: EventHandler<Vec<(String, String)>>) -> Element {
+    let mut display_headers = headers.clone();
+    if display_headers.is_empty() || !display_headers.last().unwrap().0.is_empty() {
+        display_headers.push(("".to_string(), "".to_string()));
+    }
+
+    // Row currently being dragged by its handle
+    let mut dragging = use_signal(|| None::<usize>);
+    // Names repeated with other headers in between; the client sends all
+    // values of a name together, so these do not go out in the order shown
+    let mut scattered: Vec<&str> = Vec::new();
+    for (i, (name, _)) in headers.iter().enumerate().skip(1) {
+        let same = |(other, _): &(String, String)| other.eq_ignore_ascii_case(name);
+        if !name.is_empty()
+            && !same(&headers[i - 1])
+            && headers[..i - 1].iter().any(same)
+            && !scattered.iter().any(|s| s.eq_ignore_ascii_case(name))
+        {
+            scattered.push(name);
+        }
+    }
+    let scattered = scattered.join(", ");
+
+    rsx! {
+        div {
+            class: if dragging().is_some() { "headers-editor dragging" } else { "headers-editor" },
+            onmouseup: move |_| dragging.set(None),
+            onmouseleave: move |_| dragging.set(None),
+            for (i, (k, v)) in display_headers.into_iter().enumerate() {
+                HeaderRow { key: "{i}", index: i, name: k, value: v, headers: headers.clone(), on_change, dragging }
+            }
+        }
+        if !scattered.is_empty() {
+            div { class: "settings-hint",
+                "Repeated names are sent with all their values at their first row: {scattered}"
+            }
+        }
+    }
+}
+
+/// Query parameters of the URL as editable rows. Edits rewrite the query
+/// string, and the rows are read back from the URL, so the two stay in step.
+#[component]
+fn ParamsEditor(url: String, on_change: EventHandler<String>) -> Element {
+    let params = storage::parse_query(&url);
+    let mut rows = params.clone();
+    rows.push((String::new(), String::new()));
+
+    rsx! {
+        div { class: "headers-editor params-editor",
+            for (i, (name, value)) in rows.into_iter().enumerate() {
+                div { key: "{i}", class: "header-row",
+                    input {
+                        r#type: "text",
+                        placeholder: "Name",
+                        value: "{name}",
+                        oninput: {
+                            let (url, params) = (url.clone(), params.clone());
+                            move |evt: FormEvent| on_change.call(storage::set_query(&url, &set_header(&params, i, Some(evt.value()), None)))
+                        }
+                    }
+                    input {
+                        r#type: "text",
+                        placeholder: "Value",
+                        value: "{value}",
+                        oninput: {
+                            let (url, params) = (url.clone(), params.clone());
+                            move |evt: FormEvent| on_change.call(storage::set_query(&url, &set_header(&params, i, None, Some(evt.value()))))
+                        }
+                    }
+                    button {
+                        disabled: i >= params.len(),
+                        onclick: {
+                            let (url, mut params) = (url.clone(), params.clone());
+                            move |_| {
+                                if i < params.len() {
+                                    params.remove(i);
+                                    on_change.call(storage::set_query(&url, &params));
+                                }
+                            }
+                        },
+                        "✕"
+                    }
+                }
+            }
+        }
+        div { class: "settings-hint", "Values are shown decoded and percent-encoded in the URL; {{{{placeholders}}}} are kept as they are." }
+    }
+}
+
+/// "Apply preset" menu and "Save selection as preset" action of the Headers tab.
+#[component]
+fn HeaderPresets(
+    headers: Vec<(String, String)>,
+    presets: Vec<HeaderPreset>,
+    on_apply: EventHandler<Vec<(String, String)>>,
+    on_save: EventHandler<HeaderPreset>,
+) -> Element {
+    let mut menu_open = use_signal(|| false);
+    let mut saving = use_signal(|| false);
+    let rows: Vec<(String, String)> = headers.iter().filter(|(k, _)| !k.is_empty()).cloned().collect();
+    let names: Vec<String> = presets.iter().map(|p| p.name.clone()).collect();
+    let no_presets = presets.is_empty();
+    let no_rows = rows.is_empty();
+
+    rsx! {
+        div { class: "result-toolbar",
+            button {
+                disabled: no_presets,
+                title: if no_presets { "No presets yet; save some headers as one first" } else { "Add a preset's headers; rows already present are skipped" },
+                onclick: move |_| menu_open.toggle(),
+                "Apply preset ▾"
+            }
+            button {
+                disabled: no_rows,
+                onclick: move |_| saving.set(true),
+                "Save selection as preset…"
+            }
+        }
+        if menu_open() {
+            div { class: "preset-menu",
+                for preset in presets {
+                    button {
+                        title: "{preset.to_text()}",
+                        onclick: {
+                            let headers = headers.clone();
+                            move |_| {
+                                on_apply.call(preset.apply(&headers));
+                                menu_open.set(false);
+                            }
+                        },
+                        "{preset.name} ({preset.headers.len()})"
+                    }
+                }
+            }
+        }
+        if saving() {
+            SavePresetDialog {
+                headers: rows,
+                names,
+                on_save: move |preset| {
+                    on_save.call(preset);
+                    saving.set(false);
+                },
+                on_close: move |_| saving.set(false),
+            }
+        }
+    }
+}
+
+/// Picks header rows and a name to save them as a preset.
+#[component]
+fn SavePresetDialog(
+    headers: Vec<(String, String)>,
+    names: Vec<String>,
+    on_save: EventHandler<HeaderPreset>,
+    on_close: EventHandler<()>,
+) -> Element {
+    let mut name = use_signal(String::new);
+    let mut selected = use_signal(|| (0..headers.len()).collect::<Vec<usize>>());
+    let replaces = names.contains(&name.read().trim().to_string());
+
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal",
+                h3 { "Save headers as preset" }
+                div { class: "column-list",
+                    for (i, (k, v)) in headers.iter().enumerate() {
+                        label { key: "{i}",
+                            input {
+                                r#type: "checkbox",
+                                checked: selected.read().contains(&i),
+                                onchange: move |evt| {
+                                    selected.write().retain(|&row| row != i);
+                                    if evt.checked() {
+                                        selected.write().push(i);
+                                    }
+                                }
+                            }
+                            "{k}: {v}"
+                        }
+                    }
+                }
+                label { class: "settings-row",
+                    span { "Name" }
+                    input {
+                        r#type: "text",
+                        placeholder: "JSON API",
+                        value: "{name}",
+                        oninput: move |evt| name.set(evt.value()),
+                    }
+                }
+                if replaces {
+                    div { class: "settings-hint", "Replaces the preset with this name." }
+                }
+                div { class: "modal-actions",
+                    button {
+                        disabled: name.read().trim().is_empty() || selected.read().is_empty(),
+                        onclick: {
+                            let headers = headers.clone();
+                            move |_| on_save.call(HeaderPreset {
+                                name: name.read().trim().to_string(),
+                                headers: headers.iter().enumerate()
+                                    .filter(|(i, _)| selected.read().contains(i))
+                                    .map(|(_, row)| row.clone())
+                                    .collect(),
+                            })
+                        },
+                        "Save"
+                    }
+                    button { onclick: move |_| on_close.call(()), "Cancel" }
+                }
+            }
+        }
+    }
+}
+
+/// Collapsible, read-only list of the headers the request will be sent
+/// with, coloured by where each comes from.
+#[component]
+fn EffectiveHeaders(headers: Vec<effective::EffectiveHeader>, on_jump: EventHandler<HeaderOrigin>) -> Element {
+    let mut expanded = use_signal(|| false);
+    let sent = headers.iter().filter(|h| h.sent).count();
+
+    rsx! {
+        div { class: "effective-headers",
+            div { class: "effective-toggle",
+                onclick: move |_| expanded.toggle(),
+                if expanded() { "▾ " } else { "▸ " }
+                "Effective headers ({sent})"
+            }
+            if expanded() {
+                table {
+                    for (i, header) in headers.into_iter().enumerate() {
+                        tr {
+                            key: "{i}",
+                            class: if header.sent { "effective-row origin-{header.origin.class()}" } else { "effective-row origin-{header.origin.class()} overridden" },
+                            title: if header.origin == HeaderOrigin::Automatic { "Added by the HTTP client" } else { "Click to edit where this comes from" },
+                            onclick: {
+                                let origin = header.origin.clone();
+                                move |_| on_jump.call(origin.clone())
+                            },
+                            td { class: "effective-name", "{header.name}" }
+                            td { class: "effective-value", "{header.value}" }
+                            td { class: "effective-origin", "{header.origin.label()}" }
+                            td { class: "result-note", {header.note.clone().unwrap_or_default()} }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn HeaderRow(
+    index: usize,
+    name: String,
+    value: String,
+    headers: Vec<(String, String)>,
+    on_change: EventHandler<Vec<(String, String)>>,
+    dragging: Signal<Option<usize>>,
+) -> Element {
+    let problem = client::header_name_problem(&name);
+    let is_real_row = index < headers.len();
+    let invalid = problem.is_some();
+    let tooltip = problem.as_ref().map(|p| p.message.clone()).unwrap_or_default();
+    let suggestion = problem.and_then(|p| p.suggestion);
+    let headers_for_key = headers.clone();
+    let headers_for_val = headers.clone();
+    let headers_for_fix = headers.clone();
+    let headers_for_move = headers.clone();
+    let headers_for_del = headers;
+    let token = jwt::find_tokens(&value).into_iter().next();
+    let mut inspecting = use_signal(|| false);
+
+    rsx! {
+        div {
+            class: if dragging() == Some(index) { "header-row drag-source" } else { "header-row" },
+            onmouseenter: move |_| {
+                if let Some(from) = dragging()
+                    && from != index
+                    && index < headers_for_move.len()
+                {
+                    let mut new_headers = headers_for_move.clone();
+                    let row = new_headers.remove(from);
+                    new_headers.insert(index, row);
+                    dragging.set(Some(index));
+                    on_change.call(new_headers);
+                }
+            },
+            span {
+                class: if is_real_row { "drag-handle" } else { "drag-handle hidden" },
+                title: "Drag to reorder",
+                onmousedown: move |evt| {
+                    if is_real_row {
+                        evt.prevent_default();
+                        dragging.set(Some(index));
+                    }
+                },
+                "⠿"
+            }
+            input {
+                r#type: "text",
+                class: if invalid { "invalid" },
+                title: "{tooltip}",
+                placeholder: "Key",
+                value: "{name}",
+                oninput: move |evt| {
+                    on_change.call(set_header(&headers_for_key, index, Some(evt.value()), None));
+                }
+            }
+            input {
+                r#type: "text",
+                placeholder: "Value",
+                value: "{value}",
+                oninput: move |evt| {
+                    on_change.call(set_header(&headers_for_val, index, None, Some(evt.value())));
+                }
+            }
+            if token.is_some() {
+                button {
+                    class: "fix-button",
+                    title: "Decode the JSON Web Token in this value",
+                    onclick: move |_| inspecting.set(true),
+                    "JWT"
+                }
+            }
+            if let Some(fix) = suggestion {
+                button {
+                    class: "fix-button",
+                    title: "Rename to \"{fix}\"",
+                    onclick: move |_| {
+                        on_change.call(set_header(&headers_for_fix, index, Some(fix.clone()), None));
+                    },
+                    "Fix"
+                }
+            }
+            button {
+                onclick: move |_| {
+                    let mut new_headers = headers_for_del.clone();
+                    if index < new_headers.len() {
+                        new_headers.remove(index);
+                        on_change.call(new_headers);
+                    }
+                },
+                "✕"
+            }
+        }
+        if let Some(token) = token.filter(|_| inspecting()) {
+            JwtDialog { token, on_close: move |_| inspecting.set(false) }
+        }
+    }
+}
+
+/// Decoded header, payload and standard claims of a JWT. The signature is
+/// not verified.
+#[component]
+fn JwtInspector(token: String) -> Element {
+    let jwt = match jwt::decode(&token) {
+        Ok(jwt) => jwt,
+        Err(e) => return rsx! { div { class: "settings-error", "{e}" } },
+    };
+    let claims = jwt.claims(chrono::Local::now().timestamp_millis());
+    let header = serde_json::to_string_pretty(&jwt.header).unwrap_or_default();
+    let payload = serde_json::to_string_pretty(&jwt.payload).unwrap_or_default();
+
+    rsx! {
+        div { class: "jwt-inspector",
+            div { class: "result-note", "Algorithm {jwt.algorithm()}; the signature is not verified." }
+            if !claims.is_empty() {
+                table {
+                    for claim in claims {
+                        tr {
+                            td { class: "jwt-claim", title: "{claim.key}", "{claim.name}" }
+                            td { "{claim.value}" }
+                            td {
+                                if let Some(problem) = claim.problem {
+                                    span { class: "settings-error", "⚠ {problem}" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            div { class: "jwt-part", "Header" }
+            pre { class: "result-body", "{header}" }
+            div { class: "jwt-part", "Payload" }
+            pre { class: "result-body", "{payload}" }
+        }
+    }
+}
+
+#[component]
+fn JwtDialog(token: String, on_close: EventHandler<()>) -> Element {
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal",
+                h3 { "JSON Web Token" }
+                JwtInspector { token }
+                div { class: "modal-actions",
+                    button { onclick: move |_| on_close.call(()), "Close" }
+                }
+            }
+        }
+    }
+}
+
+/// JWTs in a response with where each was found: JSON paths of the body,
+/// else the body text, and cookies being set.
+fn response_tokens(res: &HttpResponse, json: Option<&serde_json::Value>) -> Vec<(String, String)> {
+    let mut found = match json {
+        Some(json) => jwt::find_in_json(json),
+        None => jwt::find_tokens(&res.body).into_iter().map(|token| ("body".to_string(), token)).collect(),
+    };
+    for (name, value) in &res.headers {
+        if !name.eq_ignore_ascii_case("set-cookie") {
+            continue;
+        }
+        let cookie = value.split('=').next().unwrap_or_default().trim();
+        for token in jwt::find_tokens(value) {
+            found.push((format!("cookie {}", cookie), token));
+        }
+    }
+    found
+}
+
+/// Returns a copy of `headers` with row `i` updated, appending the row if it is the trailing blank one.
+fn set_header(headers: &[(String, String)], i: usize, key: Option<String>, value: Option<String>) -> Vec<(String, String)> {
+    let mut new_headers = headers.to_vec();
+    if i >= new_headers.len() {
+        new_headers.push(("".to_string(), "".to_string()));
+    }
+    if let Some(key) = key {
+        new_headers[i].0 = key;
+    }
+    if let Some(value) = value {
+        new_headers[i].1 = value;
+    }
+    new_headers
+}
+
+#[component]
+fn RequestSettings(options: RequestOptions, defaults: RequestOptions, trusted: bool, on_change: EventHandler<RequestOptions>) -> Element {
+    let inherited_dns = defaults.dns_server.clone().unwrap_or_else(|| dns::SYSTEM.to_string());
+    let inherited_jar = defaults.cookie_jar.unwrap_or(true).to_string();
+    rsx! {
+        div { class: "settings-form",
+            OptionRow {
+                label: "Accepted statuses",
+                name: "accept-status",
+                hint: "Decides pass/fail in runs when the request has no assertions, e.g. 2xx,404 or 200-204.",
+                options: options.clone(),
+                inherited: defaults.get("accept-status"),
+                on_change,
+            }
+            OptionRow {
+                label: "Timeout (ms)",
+                name: "timeout",
+                options: options.clone(),
+                inherited: defaults.get("timeout"),
+                on_change,
+            }
+            OptionRow {
+                label: "Connect timeout (ms)",
+                name: "connect-timeout",
+                hint: "Fails fast when the server cannot be reached.",
+                options: options.clone(),
+                inherited: defaults.get("connect-timeout"),
+                on_change,
+            }
+            OptionRow {
+                label: "Read timeout (ms)",
+                name: "read-timeout",
+                hint: "Longest pause allowed between chunks of the response.",
+                options: options.clone(),
+                inherited: defaults.get("read-timeout"),
+                on_change,
+            }
+            OptionRow {
+                label: "Delay before (ms)",
+                name: "delay-before",
+                hint: "Pause before sending in folder runs and repeats.",
+                options: options.clone(),
+                inherited: defaults.get("delay-before"),
+                on_change,
+            }
+            OptionRow {
+                label: "Delay after (ms)",
+                name: "delay-after",
+                hint: "Pause after the response arrives in folder runs and repeats.",
+                options: options.clone(),
+                inherited: defaults.get("delay-after"),
+                on_change,
+            }
+            OptionRow {
+                label: "Hook command",
+                name: "hook",
+                hint: "Runs before sending, e.g. ./fetch-token.sh; KEY=VALUE lines it prints become variables.",
+                options: options.clone(),
+                inherited: "folder hook".to_string(),
+                blocked: (!trusted).then(|| "Hooks do not run until the workspace is trusted".to_string()),
+                on_change,
+            }
+            OptionRow {
+                label: "Hook timeout (ms)",
+                name: "hook-timeout",
+                options: options.clone(),
+                inherited: hooks::DEFAULT_TIMEOUT_MS.to_string(),
+                on_change,
+            }
+            OptionRow {
+                label: "Capture",
+                name: "capture",
+                hint: "In folder runs, sets variables from the JSON response for later requests, e.g. token = .access_token; id = .user.id",
+                options: options.clone(),
+                inherited: "nothing".to_string(),
+                on_change,
+            }
+            OptionRow {
+                label: "Assertions",
+                name: "assert",
+                hint: "Checks that decide pass/fail instead of the accepted statuses, e.g. status 2xx; header Content-Type contains json; .error == null",
+                options: options.clone(),
+                inherited: "none".to_string(),
+                on_change,
+            }
+            OptionRow {
+                label: "Assertion sets",
+                name: "assert-set",
+                hint: "Adds the assertions of sets defined under assert_sets in a .folder.json above the request, e.g. standard-json.",
+                options: options.clone(),
+                inherited: "none".to_string(),
+                on_change,
+            }
+            OptionRow {
+                label: "On error in runs",
+                name: "on-error",
+                hint: "When this request gets no response in a folder run: continue, skip-dependents or stop.",
+                options: options.clone(),
+                inherited: "run setting".to_string(),
+                on_change,
+            }
+            OptionRow {
+                label: "Environments",
+                name: "env",
+                hint: "Pins the request to these environments, e.g. staging or staging, qa; sending with another one asks first.",
+                options: options.clone(),
+                inherited: "any".to_string(),
+                on_change,
+            }
+            OptionRow {
+                label: "Confirm in protected environments",
+                name: "confirm-protected",
+                hint: "false sends without asking, even when a protected environment is active.",
+                options: options.clone(),
+                inherited: "ask".to_string(),
+                on_change,
+            }
+            OptionRow {
+                label: "Poll status URL",
+                name: "poll",
+                hint: "After the response, poll 'location' (its Location header) or a JSON path such as .links.status.",
+                options: options.clone(),
+                inherited: "no polling".to_string(),
+                on_change,
+            }
+            OptionRow {
+                label: "Poll until",
+                name: "poll-until",
+                hint: "'status 200' or an expression such as .state == \"done\".",
+                options: options.clone(),
+                inherited: "status is not 202".to_string(),
+                on_change,
+            }
+            OptionRow {
+                label: "Poll interval (ms)",
+                name: "poll-interval",
+                options: options.clone(),
+                inherited: poll::DEFAULT_INTERVAL_MS.to_string(),
+                on_change,
+            }
+            OptionRow {
+                label: "Poll for at most (ms)",
+                name: "poll-max",
+                options: options.clone(),
+                inherited: poll::DEFAULT_MAX_MS.to_string(),
+                on_change,
+            }
+            OptionRow {
+                label: "Retries",
+                name: "retries",
+                hint: "Extra attempts after connection errors; error statuses are not retried.",
+                options: options.clone(),
+                inherited: defaults.get("retries"),
+                on_change,
+            }
+            OptionRow {
+                label: "Max redirects",
+                name: "max-redirects",
+                hint: "0 returns the redirect response itself.",
+                options: options.clone(),
+                inherited: defaults.get("max-redirects"),
+                on_change,
+            }
+            OptionRow {
+                label: "Credentials on redirect",
+                name: "redirect-credentials",
+                hint: "true keeps sending Authorization, cookies and the auth header when a redirect leads to another origin.",
+                options: options.clone(),
+                inherited: "false".to_string(),
+                on_change,
+            }
+            OptionRow {
+                label: "Proxy",
+                name: "proxy",
+                hint: "'none' bypasses proxies; a URL such as http://localhost:8888 forces one.",
+                options: options.clone(),
+                inherited: "system".to_string(),
+                on_change,
+            }
+            OptionRow {
+                label: "DNS server",
+                name: "dns",
+                hint: "ip:port of a DNS server to resolve the host through (UDP, port 53 by default), or system.",
+                options: options.clone(),
+                inherited: inherited_dns,
+                on_change,
+            }
+            OptionRow {
+                label: "Body charset",
+                name: "charset",
+                hint: "Encoding used to send the body, e.g. Shift_JIS or ISO-8859-1.",
+                options: options.clone(),
+                inherited: "Content-Type or UTF-8".to_string(),
+                on_change,
+            }
+            OptionRow {
+                label: "Compress body",
+                name: "compress",
+                hint: "gzip or deflate; sets Content-Encoding. The file keeps the body uncompressed.",
+                options: options.clone(),
+                inherited: defaults.get("compress"),
+                on_change,
+            }
+            OptionRow {
+                label: "Default scheme",
+                name: "default-scheme",
+                hint: "http or https, used when the URL has no scheme.",
+                options: options.clone(),
+                inherited: defaults.get("default-scheme"),
+                on_change,
+            }
+            OptionRow {
+                label: "Cookie jar",
+                name: "cookies",
+                hint: "On unless set to false: cookies responses set are saved to cookies.json in the data folder and sent with later requests. false sends no stored cookies and keeps none from the response.",
+                options: options.clone(),
+                inherited: inherited_jar,
+                on_change,
+            }
+            FlagRow {
+                label: "Accept invalid TLS certificates",
+                name: "insecure",
+                options: options.clone(),
+                on_change,
+            }
+            FlagRow {
+                label: "Expect: 100-continue",
+                name: "expect-continue",
+                options: options.clone(),
+                on_change,
+            }
+            div { class: "settings-hint",
+                "Holds the body back until the server answers 100 Continue, for up to {client::CONTINUE_WAIT.as_millis()} ms. Sent over a direct connection, without proxy, DNS server or redirects."
+            }
+            FlagRow {
+                label: "Body template",
+                name: "template",
+                options: options.clone(),
+                on_change,
+            }
+            OptionRow {
+                label: "Template max (MB)",
+                name: "template-max",
+                hint: "Largest body the template may expand to.",
+                options,
+                inherited: defaults.get("template-max"),
+                on_change,
+            }
+        }
+    }
+}
+
+/// The auth of the nearest folder above the request at `path`, with that
+/// folder's name for display.
+fn folder_auth(path: Option<&Path>) -> Option<(Auth, String)> {
+    let (folder, auth) = storage::FolderConfig::nearest(path, |config| config.auth)?;
+    let name = folder.strip_prefix(storage::get_base_dir()).unwrap_or(&folder).display().to_string();
+    Some((auth, if name.is_empty() { "the workspace root".to_string() } else { name }))
+}
+
+/// Auth tab: the request's own credentials, or the ones it inherits from a
+/// folder, shown read-only until overridden.
+#[component]
+fn AuthEditor(
+    auth: Option<Auth>,
+    inherited: Option<(Auth, String)>,
+    /// Names of the headers the request sets itself.
+    header_names: Vec<String>,
+    on_change: EventHandler<Option<Auth>>,
+) -> Element {
+    let kind = auth.as_ref().map_or("inherit", Auth::kind);
+    // The request's own header wins, so the auth's is never sent
+    let overridden = auth.as_ref().or(inherited.as_ref().map(|(auth, _)| auth))
+        .and_then(Auth::header_preview)
+        .map(|(name, _)| name)
+        .filter(|name| header_names.iter().any(|h| h.trim().eq_ignore_ascii_case(name)));
+    let effective = match (&auth, &inherited) {
+        (Some(auth), _) => Some((auth.summary(), "this request".to_string())),
+        (None, Some((auth, folder))) => Some((auth.summary(), format!("folder {}", folder))),
+        (None, None) => None,
+    };
+    let fields = match auth {
+        None => match inherited {
+            Some((inherited, folder)) => rsx! {
+                div { class: "auth-inherited",
+                    div { class: "result-note", "Inherited from {folder}" }
+                    div { class: "auth-summary", "{inherited.summary()}" }
+                    button {
+                        title: "Copy the folder's auth into this request to change it here",
+                        onclick: move |_| on_change.call(Some(inherited.clone())),
+                        "Override here"
+                    }
+                }
+            },
+            None => rsx! { div { class: "settings-hint", "No folder above this request sets auth." } },
+        },
+        Some(Auth::None) => rsx! {
+            div { class: "settings-hint", "Sent without credentials, even when a folder sets some." }
+        },
+        Some(Auth::Bearer { token }) => rsx! {
+            AuthField { label: "Token", value: token.clone(), on_input: move |token| on_change.call(Some(Auth::Bearer { token })) }
+            if jwt::decode(&token).is_ok() {
+                JwtInspector { token }
+            }
+        },
+        Some(Auth::Basic { username, password }) => {
+            let (user, pass) = (username.clone(), password.clone());
+            rsx! {
+                AuthField {
+                    label: "Username",
+                    value: username,
+                    on_input: move |username| on_change.call(Some(Auth::Basic { username, password: pass.clone() })),
+                }
+                AuthField {
+                    label: "Password",
+                    value: password,
+                    on_input: move |password| on_change.call(Some(Auth::Basic { username: user.clone(), password })),
+                }
+            }
+        }
+        Some(Auth::ApiKey { location, name, value }) => {
+            let (location_key, name_key, value_key) = ((name.clone(), value.clone()), value.clone(), name.clone());
+            rsx! {
+                label { class: "settings-row",
+                    span { "Send in" }
+                    select {
+                        value: if location == auth::KeyLocation::Query { "query" } else { "header" },
+                        onchange: move |evt| {
+                            let location = if evt.value() == "query" { auth::KeyLocation::Query } else { auth::KeyLocation::Header };
+                            let (name, value) = location_key.clone();
+                            on_change.call(Some(Auth::ApiKey { location, name, value }));
+                        },
+                        option { value: "header", "Header" }
+                        option { value: "query", "Query parameter" }
+                    }
+                }
+                AuthField {
+                    label: "Key name",
+                    value: name,
+                    on_input: move |name| on_change.call(Some(Auth::ApiKey { location, name, value: name_key.clone() })),
+                }
+                AuthField {
+                    label: "Key value",
+                    value: value,
+                    on_input: move |value| on_change.call(Some(Auth::ApiKey { location, name: value_key.clone(), value })),
+                }
+            }
+        }
+    };
+
+    rsx! {
+        div { class: "settings-form",
+            label { class: "settings-row",
+                span { "Type" }
+                select {
+                    value: "{kind}",
+                    onchange: move |evt| on_change.call(match evt.value().as_str() {
+                        "inherit" => None,
+                        kind => Some(Auth::empty(kind)),
+                    }),
+                    option { value: "inherit", "Inherit from folder" }
+                    option { value: "none", "No auth" }
+                    option { value: "bearer", "Bearer token" }
+                    option { value: "basic", "Basic" }
+                    option { value: "api-key", "API key" }
+                }
+            }
+            {fields}
+            div { class: "auth-effective",
+                if let Some((summary, source)) = effective {
+                    "Sent with: {summary}, from {source}"
+                } else {
+                    "Sent without credentials"
+                }
+            }
+            if let Some(name) = overridden {
+                div { class: "result-warning",
+                    "The request sets a {name} header itself, which is sent instead of this auth's. Remove it from Headers to use the auth."
+                }
+            }
+            div { class: "settings-hint", "Values may use {{{{variables}}}}. A header the request sets itself wins over its auth." }
+        }
+    }
+}
+
+#[component]
+fn AuthField(label: String, value: String, on_input: EventHandler<String>) -> Element {
+    rsx! {
+        label { class: "settings-row",
+            span { "{label}" }
+            input {
+                r#type: "text",
+                value: "{value}",
+                oninput: move |evt| on_input.call(evt.value()),
+            }
+        }
+    }
+}
+
+/// Text input bound to one request option. Input that does not parse yet is
+/// kept locally with its error; valid input is applied immediately.
+#[component]
+fn OptionRow(
+    label: String,
+    name: String,
+    #[props(default)] hint: String,
+    options: RequestOptions,
+    inherited: String,
+    /// Why the option has no effect right now, shown as a badge.
+    #[props(default)] blocked: Option<String>,
+    on_change: EventHandler<RequestOptions>,
+) -> Element {
+    let mut draft = use_signal(|| None::<String>);
+    let mut error = use_signal(|| None::<String>);
+    let text = draft().unwrap_or_else(|| options.get(&name));
+    let placeholder = format!("{} (default)", inherited);
+    // Until edited, a value the file has that could not be applied
+    let shown_error = if draft().is_some() { error() } else { options.load_error(&name) };
+
+    rsx! {
+        label { class: "settings-row",
+            span { "{label}" }
+            input {
+                r#type: "text",
+                class: if shown_error.is_some() { "invalid" },
+                placeholder: "{placeholder}",
+                value: "{text}",
+                oninput: move |evt| {
+                    let text = evt.value();
+                    let mut options = options.clone();
+                    match options.set(&name, &text) {
+                        Ok(()) => {
+                            error.set(None);
+                            on_change.call(options);
+                        }
+                        Err(e) => error.set(Some(e)),
+                    }
+                    draft.set(Some(text));
+                }
+            }
+            if let Some(e) = &shown_error {
+                span { class: "settings-error", "{e}" }
+            }
+            if let Some(reason) = blocked {
+                span { class: "blocked-badge", title: "{reason}", "Blocked" }
+            }
+        }
+        if !hint.is_empty() {
+            div { class: "settings-hint", "{hint}" }
+        }
+    }
+}
+
+#[component]
+fn FlagRow(label: String, name: String, options: RequestOptions, on_change: EventHandler<RequestOptions>) -> Element {
+    let checked = options.get(&name) == "true";
+    rsx! {
+        label { class: "settings-row",
+            span { "{label}" }
+            input {
+                r#type: "checkbox",
+                checked,
+                onchange: move |evt| {
+                    let mut options = options.clone();
+                    if options.set(&name, if evt.checked() { "true" } else { "" }).is_ok() {
+                        on_change.call(options);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn AppSettings(settings: Signal<Settings>, trusted: Signal<bool>, on_close: EventHandler<()>) -> Element {
+    let mut storage_message = use_signal(|| None::<String>);
+    let mut dns_message = use_signal(|| None::<String>);
+    let mut trusted_paths = use_signal(workspace::trusted_paths);
+    let (_, redact_problems) = redact::Redactor::new(&settings.read().redact_rules);
+    let redact_text = settings.read().redact_rules.join("\n");
+    let environments = use_hook(environment::list_environments);
+    // Applies a change and persists it right away
+    let mut update = move |change: &dyn Fn(&mut Settings)| {
+        change(&mut settings.write());
+        let _ = settings.read().save();
+    };
+
+    rsx! {
+        div { class: "modal-backdrop",
+            div { class: "modal",
+                h3 { "Settings" }
+                label { class: "settings-row",
+                    span { "Allow {{{{env:NAME}}}}" }
+                    input {
+                        r#type: "checkbox",
+                        checked: settings.read().allow_os_env,
+                        onchange: move |evt| update(&|s| s.allow_os_env = evt.checked()),
+                    }
+                    if !trusted() {
+                        span { class: "blocked-badge", title: "Blocked until the workspace is trusted", "Blocked" }
+                    }
+                }
+                div { class: "settings-hint",
+                    "Lets requests read OS environment variables at send time. Values are never saved."
+                }
+                label { class: "settings-row",
+                    span { "Offer to import copied curl commands and URLs" }
+                    input {
+                        r#type: "checkbox",
+                        checked: settings.read().watch_clipboard,
+                        onchange: move |evt| update(&|s| s.watch_clipboard = evt.checked()),
+                    }
+                }
+                div { class: "settings-hint",
+                    "Checks the clipboard when the window gets focus; nothing is imported until you confirm."
+                }
+                label { class: "settings-row",
+                    span { "Show history and run times in UTC" }
+                    input {
+                        r#type: "checkbox",
+                        checked: settings.read().utc_times,
+                        onchange: move |evt| update(&|s| s.utc_times = evt.checked()),
+                    }
+                }
+                h4 { "Request defaults" }
+                NumberSetting {
+                    label: "Timeout (ms)",
+                    value: settings.read().default_timeout_ms,
+                    min: 1,
+                    on_change: move |value| update(&|s| s.default_timeout_ms = value),
+                }
+                NumberSetting {
+                    label: "Retries",
+                    value: settings.read().default_retries as u64,
+                    max: storage::MAX_RETRIES as u64,
+                    on_change: move |value| update(&|s| s.default_retries = value as u32),
+                }
+                NumberSetting {
+                    label: "Max redirects",
+                    value: settings.read().default_max_redirects as u64,
+                    max: 100,
+                    on_change: move |value| update(&|s| s.default_max_redirects = value as u32),
+                }
+                label { class: "settings-row",
+                    span { "Scheme for URLs without one" }
+                    select {
+                        value: "{settings.read().default_scheme}",
+                        onchange: move |evt| update(&|s| s.default_scheme = evt.value()),
+                        option { value: "https", "https" }
+                        option { value: "http", "http" }
+                    }
+                }
+                NumberSetting {
+                    label: "Largest body template expansion (MB)",
+                    value: settings.read().template_max_mb,
+                    min: 1,
+                    on_change: move |value| update(&|s| s.template_max_mb = value),
+                }
+                NumberSetting {
+                    label: "Requests at a time per host",
+                    value: settings.read().per_host_limit as u64,
+                    max: 100,
+                    on_change: move |value| update(&|s| s.per_host_limit = value as u32),
+                }
+                div { class: "settings-hint",
+                    "0 sends any number at once. With 1, sends to the same host and port wait for each other; other hosts still go ahead."
+                }
+                label { class: "settings-row",
+                    span { "DNS server" }
+                    input {
+                        r#type: "text",
+                        placeholder: "system",
+                        value: "{settings.read().dns_server}",
+                        oninput: move |evt| {
+                            let value = evt.value();
+                            let problem = (!value.trim().is_empty()).then(|| dns::parse_server(&value).err()).flatten();
+                            dns_message.set(problem);
+                            update(&|s| s.dns_server = value.clone());
+                        }
+                    }
+                    button {
+                        title: "Forget the addresses cached from custom DNS servers",
+                        onclick: move |_| {
+                            let servers = dns::flush_cache();
+                            dns_message.set(Some(format!("Flushed the cache of {} DNS server{}", servers, if servers == 1 { "" } else { "s" })));
+                        },
+                        "Flush DNS cache"
+                    }
+                }
+                div { class: "settings-hint",
+                    "ip:port of a DNS server reached over UDP, for zones the system resolver does not know. Requests can choose their own with the dns option, or system."
+                }
+                if let Some(message) = dns_message() {
+                    div { class: "settings-hint", "{message}" }
+                }
+                label { class: "settings-row",
+                    span { "Fixtures folder" }
+                    input {
+                        r#type: "text",
+                        class: "path-input",
+                        placeholder: "{storage::get_base_dir().join(\"fixtures\").display()}",
+                        value: "{settings.read().fixtures_dir}",
+                        oninput: move |evt| update(&|s| s.fixtures_dir = evt.value()),
+                    }
+                }
+                div { class: "settings-hint",
+                    "Record fixtures writes NAME-001/request.json and response.json here for each send. The request keeps its {{{{placeholders}}}}; secret headers and passwords become placeholders."
+                }
+                h4 { "Privacy mode" }
+                textarea {
+                    class: "preset-headers",
+                    placeholder: "$..email\n$.data[*].token\nBearer (\\S+)",
+                    value: "{redact_text}",
+                    onchange: move |evt| update(&|s| s.redact_rules = evt.value().lines().map(str::to_string).collect()),
+                }
+                for problem in redact_problems {
+                    div { class: "settings-error", "{problem}" }
+                }
+                div { class: "settings-hint",
+                    "One rule per line. Lines starting with $ are JSONPath selectors ($.a.b, [0], [*], ..name) whose values are masked; other lines are regexes, masked where they match, or only in their groups if they have any. The Privacy button of a response turns masking on for that view."
+                }
+                label { class: "settings-row",
+                    span { "Redact persisted data" }
+                    input {
+                        r#type: "checkbox",
+                        checked: settings.read().redact_persisted,
+                        onchange: move |evt| update(&|s| s.redact_persisted = evt.checked()),
+                    }
+                }
+                div { class: "settings-hint",
+                    "Also mask history, recorded fixtures and Markdown exports. Otherwise they keep the real values."
+                }
+                h4 { "Response views" }
+                for (i, (pattern, kind)) in settings.read().view_overrides.iter().cloned().enumerate() {
+                    div { class: "settings-row",
+                        input {
+                            r#type: "text",
+                            placeholder: "application/vnd.api+json or text/*",
+                            value: "{pattern}",
+                            oninput: move |evt| update(&|s| s.view_overrides[i].0 = evt.value()),
+                        }
+                        select {
+                            value: "{kind}",
+                            onchange: move |evt| update(&|s| s.view_overrides[i].1 = evt.value()),
+                            for option in viewer::VIEWERS {
+                                option { value: option.id(), "{option.label()}" }
+                            }
+                        }
+                        button { class: "remove-view", onclick: move |_| update(&|s| { s.view_overrides.remove(i); }), "×" }
+                    }
+                }
+                div { class: "settings-row",
+                    button {
+                        onclick: move |_| update(&|s| s.view_overrides.push((String::new(), "text".to_string()))),
+                        "Add content type"
+                    }
+                }
+                div { class: "settings-hint",
+                    "Responses open in the view of the first matching content type, else in the one made for their type; plain text is sniffed. Views chosen on a response are remembered per request."
+                }
+                h4 { "Body limits" }
+                for (i, limit) in settings.read().body_limits.iter().cloned().enumerate() {
+                    div { class: "settings-row",
+                        input {
+                            r#type: "text",
+                            placeholder: "application/octet-stream, image/* or *",
+                            value: "{limit.pattern}",
+                            oninput: move |evt| update(&|s| s.body_limits[i].pattern = evt.value()),
+                        }
+                        input {
+                            r#type: "number",
+                            min: "0",
+                            value: "{limit.max_bytes / 1024}",
+                            onchange: move |evt| {
+                                if let Ok(kb) = evt.value().trim().parse::<usize>() {
+                                    update(&|s| s.body_limits[i].max_bytes = kb.saturating_mul(1024));
+                                }
+                            },
+                        }
+                        span { "KB" }
+                        button { class: "remove-view", onclick: move |_| update(&|s| { s.body_limits.remove(i); }), "×" }
+                    }
+                }
+                div { class: "settings-row",
+                    button {
+                        onclick: move |_| update(&|s| s.body_limits.push(client::BodyLimit { pattern: String::new(), max_bytes: 1024 * 1024 })),
+                        "Add content type"
+                    }
+                    button {
+                        onclick: move |_| update(&|s| s.body_limits = client::default_body_limits()),
+                        "Reset"
+                    }
+                }
+                div { class: "settings-hint",
+                    "Response bodies stop being read at the limit of their content type: an exact type wins over type/*, which wins over *. 0 reads the whole body."
+                }
+                h4 { "Header presets" }
+                for (i, preset) in settings.read().header_presets.iter().cloned().enumerate() {
+                    div { class: "settings-row",
+                        input {
+                            r#type: "text",
+                            value: "{preset.name}",
+                            oninput: move |evt| update(&|s| s.header_presets[i].name = evt.value()),
+                        }
+                        button { class: "remove-view", title: "Delete preset", onclick: move |_| update(&|s| { s.header_presets.remove(i); }), "×" }
+                    }
+                    textarea {
+                        class: "preset-headers",
+                        value: "{preset.to_text()}",
+                        onchange: move |evt| update(&|s| s.header_presets[i].headers = HeaderPreset::parse_text(&evt.value())),
+                    }
+                }
+                div { class: "settings-hint",
+                    if settings.read().header_presets.is_empty() {
+                        "No presets yet. Save some from the Headers tab. "
+                    }
+                    "One Name: value per line; values may use {{{{variables}}}}."
+                }
+                h4 { "Folder runs" }
+                label { class: "settings-row",
+                    span { "When a request gets no response" }
+                    select {
+                        value: settings.read().run_error_policy.as_str(),
+                        onchange: move |evt| {
+                            if let Ok(policy) = ErrorPolicy::parse(&evt.value()) {
+                                update(&|s| s.run_error_policy = policy);
+                            }
+                        },
+                        for policy in ErrorPolicy::ALL {
+                            option { value: policy.as_str(), "{policy.label()}" }
+                        }
+                    }
+                }
+                div { class: "settings-hint",
+                    "A request depends on another when it uses a variable the other captures. Requests override this with their on-error option."
+                }
+                label { class: "settings-row",
+                    span { "When a request is pinned to another environment" }
+                    select {
+                        value: settings.read().run_pinned_policy.as_str(),
+                        onchange: move |evt| {
+                            if let Ok(policy) = PinnedPolicy::parse(&evt.value()) {
+                                update(&|s| s.run_pinned_policy = policy);
+                            }
+                        },
+                        for policy in PinnedPolicy::ALL {
+                            option { value: policy.as_str(), "{policy.label()}" }
+                        }
+                    }
+                }
+                h4 { "Protected environments" }
+                div { class: "settings-row",
+                    for env in environments.iter().cloned() {
+                        label {
+                            input {
+                                r#type: "checkbox",
+                                checked: settings.read().protected_environments.contains(&env),
+                                onchange: move |evt| update(&|s| {
+                                    s.protected_environments.retain(|e| *e != env);
+                                    if evt.checked() {
+                                        s.protected_environments.push(env.clone());
+                                    }
+                                }),
+                            }
+                            "{env}"
+                        }
+                    }
+                }
+                div { class: "settings-row",
+                    span { "Ask before" }
+                    for method in CONFIRMABLE_METHODS {
+                        label {
+                            input {
+                                r#type: "checkbox",
+                                checked: settings.read().protected_methods.iter().any(|m| m == method),
+                                onchange: move |evt| update(&|s| {
+                                    s.protected_methods.retain(|m| m != method);
+                                    if evt.checked() {
+                                        s.protected_methods.push(method.to_string());
+                                    }
+                                }),
+                            }
+                            "{method}"
+                        }
+                    }
+                }
+                div { class: "settings-hint",
+                    if environments.is_empty() { "No environments yet. " }
+                    "With a protected environment active, these methods always ask first, naming the environment, and folder runs only start after a second confirmation. A request's confirm-protected: false skips the question."
+                }
+                h4 { "Ask before sending" }
+                div { class: "settings-row",
+                    for method in CONFIRMABLE_METHODS {
+                        label {
+                            input {
+                                r#type: "checkbox",
+                                checked: settings.read().confirm_methods.iter().any(|m| m == method),
+                                onchange: move |evt| update(&|s| {
+                                    s.confirm_methods.retain(|m| m != method);
+                                    if evt.checked() {
+                                        s.confirm_methods.push(method.to_string());
+                                    }
+                                }),
+                            }
+                            "{method}"
+                        }
+                    }
+                }
+                h4 { "Workspace" }
+                label { class: "settings-row",
+                    span { "Store requests in" }
+                    select {
+                        value: if settings.read().storage_backend == BackendKind::Sqlite { "sqlite" } else { "files" },
+                        onchange: move |evt| {
+                            let kind = if evt.value() == "sqlite" { BackendKind::Sqlite } else { BackendKind::Files };
+                            storage_message.set(Some(match backend::switch_to(kind) {
+                                Ok(copied) => {
+                                    settings.write().storage_backend = kind;
+                                    format!("Copied {} request(s). Restart Requester to use the new storage.", copied)
+                                }
+                                Err(e) => format!("Could not switch storage: {}", e),
+                            }));
+                        },
+                        option { value: "files", "Folder of .req files" }
+                        option { value: "sqlite", "Single SQLite file" }
+                    }
+                }
+                div { class: "settings-hint",
+                    if let Some(message) = storage_message() {
+                        "{message}"
+                    } else {
+                        "Switching copies every request into the other storage; the old copy is kept."
+                    }
+                }
+                h4 { "Trusted workspaces" }
+                for path in trusted_paths() {
+                    div { class: "settings-row",
+                        span { "{path.display()}" }
+                        button {
+                            onclick: move |_| {
+                                if workspace::revoke(&path).is_ok() {
+                                    trusted_paths.set(workspace::trusted_paths());
+                                    trusted.set(workspace::workspace_trusted());
+                                }
+                            },
+                            "Revoke"
+                        }
+                    }
+                }
+                div { class: "settings-hint",
+                    if trusted_paths.read().is_empty() {
+                        "No workspace is trusted. "
+                    }
+                    "Hooks and {{{{env:NAME}}}} placeholders only work in trusted workspaces."
+                }
+                div { class: "modal-actions",
+                    button { onclick: move |_| on_close.call(()), "Close" }
+                }
+            }
+        }
+    }
+}
+
+/// Methods that can be set to require confirmation before sending.
+const CONFIRMABLE_METHODS: [&str; 4] = ["POST", "PUT", "PATCH", "DELETE"];
+
+/// Numeric settings input that only reports values within `min..=max`.
+#[component]
+fn NumberSetting(
+    label: String,
+    value: u64,
+    #[props(default = 0)] min: u64,
+    #[props(default = u64::MAX)] max: u64,
+    on_change: EventHandler<u64>,
+) -> Element {
+    rsx! {
+        label { class: "settings-row",
+            span { "{label}" }
+            input {
+                r#type: "number",
+                min: "{min}",
+                value: "{value}",
+                oninput: move |evt| {
+                    if let Ok(value) = evt.value().trim().parse::<u64>()
+                        && (min..=max).contains(&value)
+                    {
+                        on_change.call(value);
                     }
                 }
             }
@@ -0,0 +1,338 @@
+use std::path::{Component, Path, PathBuf};
+
+/// A block of a Markdown document. Raw HTML is not recognised and stays
+/// text, so rendering it cannot inject markup.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Heading(u8, Vec<Inline>),
+    Paragraph(Vec<Inline>),
+    /// Items hold blocks so lists can nest.
+    List { ordered: bool, items: Vec<Vec<Block>> },
+    Code { language: String, text: String },
+    Quote(Vec<Block>),
+    Table { header: Vec<Vec<Inline>>, rows: Vec<Vec<Vec<Inline>>> },
+    Rule,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inline {
+    Text(String),
+    Code(String),
+    Strong(Vec<Inline>),
+    Emphasis(Vec<Inline>),
+    Link { text: Vec<Inline>, target: String },
+}
+
+/// The Markdown file documenting a folder.
+pub const README: &str = "README.md";
+
+/// Parses the CommonMark subset READMEs use: ATX and setext headings,
+/// paragraphs, nested lists, fenced code, quotes, pipe tables and rules.
+pub fn parse(source: &str) -> Vec<Block> {
+    let lines: Vec<&str> = source.lines().collect();
+    parse_lines(&lines)
+}
+
+fn parse_lines(lines: &[&str]) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() {
+            i += 1;
+        } else if let Some(fence) = ["```", "~~~"].into_iter().find(|f| trimmed.starts_with(f)) {
+            let language = trimmed[3..].trim().to_string();
+            let end = lines[i + 1..].iter().position(|l| l.trim_start().starts_with(fence)).map_or(lines.len(), |p| i + 1 + p);
+            blocks.push(Block::Code { language, text: lines[i + 1..end].join("\n") });
+            i = end + 1;
+        } else if let Some((level, text)) = atx_heading(trimmed) {
+            blocks.push(Block::Heading(level, parse_inline(text)));
+            i += 1;
+        } else if is_rule(trimmed) {
+            blocks.push(Block::Rule);
+            i += 1;
+        } else if trimmed.starts_with('>') {
+            let end = lines[i..].iter().position(|l| !l.trim_start().starts_with('>')).map_or(lines.len(), |p| i + p);
+            let inner: Vec<&str> = lines[i..end].iter()
+                .map(|l| {
+                    let l = l.trim_start().strip_prefix('>').unwrap_or(l);
+                    l.strip_prefix(' ').unwrap_or(l)
+                })
+                .collect();
+            blocks.push(Block::Quote(parse_lines(&inner)));
+            i = end;
+        } else if let Some((ordered, _)) = list_marker(line) {
+            let (list, end) = parse_list(lines, i, ordered);
+            blocks.push(list);
+            i = end;
+        } else if line.contains('|') && lines.get(i + 1).is_some_and(|next| is_table_delimiter(next)) {
+            let header = table_cells(line);
+            let end = lines[i + 2..].iter().position(|l| l.trim().is_empty() || !l.contains('|')).map_or(lines.len(), |p| i + 2 + p);
+            let rows = lines[i + 2..end].iter().map(|l| table_cells(l)).collect();
+            blocks.push(Block::Table { header, rows });
+            i = end;
+        } else {
+            let start = i;
+            i += 1;
+            while i < lines.len() && !lines[i].trim().is_empty() && !starts_block(lines[i]) && setext_level(lines[i]).is_none() {
+                i += 1;
+            }
+            let text = parse_inline(&join_paragraph(&lines[start..i]));
+            match lines.get(i).and_then(|line| setext_level(line)) {
+                Some(level) => {
+                    blocks.push(Block::Heading(level, text));
+                    i += 1;
+                }
+                None => blocks.push(Block::Paragraph(text)),
+            }
+        }
+    }
+    blocks
+}
+
+fn join_paragraph(lines: &[&str]) -> String {
+    lines.iter().map(|l| l.trim()).collect::<Vec<_>>().join(" ")
+}
+
+/// Whether `line` starts a block other than a paragraph, ending the
+/// paragraph before it.
+fn starts_block(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed.starts_with("```")
+        || trimmed.starts_with("~~~")
+        || trimmed.starts_with('>')
+        || atx_heading(trimmed).is_some()
+        || (is_rule(trimmed) && setext_level(line).is_none())
+        || list_marker(line).is_some()
+}
+
+fn atx_heading(line: &str) -> Option<(u8, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    Some((level as u8, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+fn setext_level(line: &str) -> Option<u8> {
+    let line = line.trim();
+    if !line.is_empty() && line.chars().all(|c| c == '=') {
+        Some(1)
+    } else if !line.is_empty() && line.chars().all(|c| c == '-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+fn is_rule(line: &str) -> bool {
+    let chars: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    chars.len() >= 3 && ['-', '*', '_'].iter().any(|&m| chars.iter().all(|&c| c == m))
+}
+
+/// Whether `line` is a list item, and the column its text starts at.
+fn list_marker(line: &str) -> Option<(bool, usize)> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+    if indent > 3 || is_rule(rest) {
+        return None;
+    }
+    if let Some(after) = rest.strip_prefix(['-', '*', '+']) {
+        return (after.starts_with(' ') || after.is_empty()).then_some((false, indent + 2));
+    }
+    let digits = rest.chars().take_while(char::is_ascii_digit).count();
+    let after = rest[digits..].strip_prefix(['.', ')'])?;
+    (digits > 0 && digits < 10 && (after.starts_with(' ') || after.is_empty())).then_some((true, indent + digits + 2))
+}
+
+/// The list starting at `lines[start]` and the index of the first line
+/// after it. An item takes the lines indented past its marker, where nested
+/// lists live, and lazily continued paragraph lines.
+fn parse_list(lines: &[&str], start: usize, ordered: bool) -> (Block, usize) {
+    let mut items = Vec::new();
+    let mut i = start;
+    while i < lines.len() {
+        let Some((item_ordered, content)) = list_marker(lines[i]) else { break };
+        if item_ordered != ordered {
+            break;
+        }
+        let mut item_lines = vec![lines[i].get(content..).unwrap_or("")];
+        i += 1;
+        while i < lines.len() {
+            let line = lines[i];
+            let indent = line.len() - line.trim_start().len();
+            if line.trim().is_empty() {
+                // A blank line continues the item only if indented text follows
+                let next_indented = lines.get(i + 1).is_some_and(|next| {
+                    !next.trim().is_empty() && next.len() - next.trim_start().len() >= content
+                });
+                if !next_indented {
+                    break;
+                }
+                item_lines.push("");
+            } else if indent >= content {
+                item_lines.push(&line[content..]);
+            } else if list_marker(line).is_none() && !starts_block(line) && item_lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                item_lines.push(line.trim_start());
+            } else {
+                break;
+            }
+            i += 1;
+        }
+        items.push(parse_lines(&item_lines));
+        // Blank lines between items keep the list going
+        while i < lines.len() && lines[i].trim().is_empty() && lines.get(i + 1).and_then(|next| list_marker(next)).is_some_and(|(o, _)| o == ordered) {
+            i += 1;
+        }
+    }
+    (Block::List { ordered, items }, i)
+}
+
+fn is_table_delimiter(line: &str) -> bool {
+    let cells: Vec<&str> = split_row(line);
+    !cells.is_empty() && cells.iter().all(|cell| {
+        let cell = cell.trim().trim_start_matches(':').trim_end_matches(':');
+        !cell.is_empty() && cell.chars().all(|c| c == '-')
+    })
+}
+
+fn split_row(line: &str) -> Vec<&str> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+    line.split('|').collect()
+}
+
+fn table_cells(line: &str) -> Vec<Vec<Inline>> {
+    split_row(line).into_iter().map(|cell| parse_inline(cell.trim())).collect()
+}
+
+/// Parses code spans, `**strong**`, `*emphasis*`, `[links](target)` and
+/// `<https://autolinks>`; images keep only their alt text.
+pub fn parse_inline(text: &str) -> Vec<Inline> {
+    let mut out = Vec::new();
+    let mut plain = String::new();
+    let flush = |plain: &mut String, out: &mut Vec<Inline>| {
+        if !plain.is_empty() {
+            out.push(Inline::Text(std::mem::take(plain)));
+        }
+    };
+    let mut pos = 0;
+    while let Some(c) = text[pos..].chars().next() {
+        let rest = &text[pos..];
+        let next = rest[c.len_utf8()..].chars().next();
+        let after_word = text[..pos].chars().next_back().is_some_and(char::is_alphanumeric);
+        if c == '\\' && let Some(escaped) = next.filter(char::is_ascii_punctuation) {
+            plain.push(escaped);
+            pos += 2;
+        } else if c == '`' {
+            let ticks = rest.len() - rest.trim_start_matches('`').len();
+            match rest[ticks..].find(&rest[..ticks]) {
+                Some(end) => {
+                    flush(&mut plain, &mut out);
+                    out.push(Inline::Code(rest[ticks..ticks + end].trim().to_string()));
+                    pos += 2 * ticks + end;
+                }
+                None => {
+                    plain.push_str(&rest[..ticks]);
+                    pos += ticks;
+                }
+            }
+        } else if (c == '*' || c == '_') && next == Some(c)
+            && let Some(end) = rest[2..].find(&rest[..2]).filter(|&end| end > 0)
+        {
+            flush(&mut plain, &mut out);
+            out.push(Inline::Strong(parse_inline(&rest[2..2 + end])));
+            pos += end + 4;
+        } else if (c == '*' || (c == '_' && !after_word))
+            && next.is_some_and(|n| !n.is_whitespace())
+            && let Some(end) = rest[1..].find(c).filter(|&end| end > 0)
+        {
+            flush(&mut plain, &mut out);
+            out.push(Inline::Emphasis(parse_inline(&rest[1..1 + end])));
+            pos += end + 2;
+        } else if c == '!' && next == Some('[') && let Some((label, _, used)) = link_at(&rest[1..]) {
+            // Images are not loaded; their alt text stands in
+            plain.push_str(&label);
+            pos += 1 + used;
+        } else if c == '[' && let Some((label, target, used)) = link_at(rest) {
+            flush(&mut plain, &mut out);
+            out.push(Inline::Link { text: parse_inline(&label), target });
+            pos += used;
+        } else if c == '<'
+            && let Some(end) = rest.find('>')
+            && ["http://", "https://", "mailto:"].iter().any(|scheme| rest[1..end].starts_with(scheme))
+            && !rest[1..end].contains(char::is_whitespace)
+        {
+            flush(&mut plain, &mut out);
+            let target = rest[1..end].to_string();
+            out.push(Inline::Link { text: vec![Inline::Text(target.clone())], target });
+            pos += end + 1;
+        } else {
+            plain.push(c);
+            pos += c.len_utf8();
+        }
+    }
+    flush(&mut plain, &mut out);
+    out
+}
+
+/// `[label](target "title")` at the start of `text`: the label, the target
+/// and the number of bytes used.
+fn link_at(text: &str) -> Option<(String, String, usize)> {
+    let mut depth = 0;
+    let close = text.char_indices().find(|&(_, c)| {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            _ => {}
+        }
+        depth == 0
+    })?.0;
+    let after = text[close + 1..].strip_prefix('(')?;
+    let end = after.find(')')?;
+    let target = after[..end].trim();
+    let target = target.split_once(' ').map_or(target, |(url, _)| url);
+    let target = target.trim_start_matches('<').trim_end_matches('>');
+    Some((text[1..close].to_string(), target.to_string(), close + end + 3))
+}
+
+/// Where a link in a README points.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkTarget {
+    /// A request file inside the workspace.
+    Request(PathBuf),
+    /// An http(s) or mailto URL, opened outside the app.
+    External(String),
+    /// Anything else, shown as text.
+    Inert,
+}
+
+/// Classifies `target` of a link in the README of `folder`. Relative `.req`
+/// paths resolve against `folder` and must stay inside `base`.
+pub fn link_target(target: &str, folder: &Path, base: &Path) -> LinkTarget {
+    let lower = target.to_ascii_lowercase();
+    if ["http://", "https://", "mailto:"].iter().any(|scheme| lower.starts_with(scheme)) {
+        return LinkTarget::External(target.to_string());
+    }
+    let path = target.split(['#', '?']).next().unwrap_or_default();
+    if lower.contains(':') || !path.ends_with(".req") {
+        return LinkTarget::Inert;
+    }
+    let decoded = path.replace("%20", " ");
+    let joined = if decoded.starts_with('/') { base.join(decoded.trim_start_matches('/')) } else { folder.join(decoded) };
+    // Resolved by hand so links cannot climb out through `..`
+    let mut resolved = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::CurDir => {}
+            other => resolved.push(other),
+        }
+    }
+    if resolved.starts_with(base) { LinkTarget::Request(resolved) } else { LinkTarget::Inert }
+}
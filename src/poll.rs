@@ -0,0 +1,225 @@
+use std::time::{Duration, Instant};
+use crate::client::{HttpResponse, execute_request};
+use crate::runner::{CancelFlag, pause};
+use crate::storage::{HttpRequest, RequestOptions, StatusSet};
+use crate::transform;
+
+pub const DEFAULT_INTERVAL_MS: u64 = 1_000;
+pub const DEFAULT_MAX_MS: u64 = 60_000;
+
+/// Where the URL to poll comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatusUrl {
+    /// The `Location` header of the first response.
+    Location,
+    /// A transform expression such as `.links.status` over its JSON body.
+    Body(String),
+}
+
+impl StatusUrl {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.trim() {
+            "" => Err("Expected 'location' or an expression such as .links.status".to_string()),
+            s if s.eq_ignore_ascii_case("location") => Ok(StatusUrl::Location),
+            s if s.starts_with('.') => Ok(StatusUrl::Body(s.to_string())),
+            s => Err(format!("Expected 'location' or an expression starting with '.', got '{}'", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for StatusUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatusUrl::Location => write!(f, "location"),
+            StatusUrl::Body(expression) => write!(f, "{}", expression),
+        }
+    }
+}
+
+/// When polling is done.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PollUntil {
+    /// `status 200,303`: the poll response has one of these statuses.
+    Status(StatusSet),
+    /// `.state == "done"`: the expression is true for the JSON body.
+    Body(String),
+}
+
+impl PollUntil {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        if let Some(statuses) = s.strip_prefix("status") {
+            return Ok(PollUntil::Status(StatusSet::parse(statuses)?));
+        }
+        if s.starts_with('.') {
+            return Ok(PollUntil::Body(s.to_string()));
+        }
+        Err("Expected 'status CODES' or an expression such as .state == \"done\"".to_string())
+    }
+
+    pub fn is_met(&self, res: &HttpResponse) -> bool {
+        match self {
+            PollUntil::Status(statuses) => statuses.contains(res.status),
+            PollUntil::Body(expression) => serde_json::from_str(&res.body)
+                .ok()
+                .and_then(|body| transform::apply(expression, &body).ok())
+                .is_some_and(|value| value == serde_json::Value::Bool(true)),
+        }
+    }
+}
+
+impl std::fmt::Display for PollUntil {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PollUntil::Status(statuses) => write!(f, "status {}", statuses),
+            PollUntil::Body(expression) => write!(f, "{}", expression),
+        }
+    }
+}
+
+/// Polling set up by a request's `poll*` options.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PollConfig {
+    pub url: StatusUrl,
+    /// `None` waits for any status other than 202 Accepted.
+    pub until: Option<PollUntil>,
+    pub interval_ms: u64,
+    pub max_ms: u64,
+}
+
+impl PollConfig {
+    /// The polling `options` ask for, or `None` when `poll` is unset.
+    pub fn from_options(options: &RequestOptions) -> Option<PollConfig> {
+        Some(PollConfig {
+            url: options.poll.clone()?,
+            until: options.poll_until.clone(),
+            interval_ms: options.poll_interval_ms.unwrap_or(DEFAULT_INTERVAL_MS),
+            max_ms: options.poll_max_ms.unwrap_or(DEFAULT_MAX_MS),
+        })
+    }
+
+    fn is_done(&self, res: &HttpResponse) -> bool {
+        match &self.until {
+            Some(until) => until.is_met(res),
+            None => res.status != 202,
+        }
+    }
+}
+
+/// One poll, for the progress list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PollStep {
+    /// Time since the first response arrived.
+    pub at_ms: u64,
+    pub url: String,
+    /// Status, or why the poll failed.
+    pub outcome: Result<String, String>,
+}
+
+/// How polling ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PollEnd {
+    Met,
+    TimedOut,
+    Cancelled,
+    /// The status URL could not be found in the response.
+    NoUrl(String),
+}
+
+impl PollEnd {
+    /// Why polling stopped early, or `None` when the condition was met.
+    pub fn problem(&self) -> Option<String> {
+        match self {
+            PollEnd::Met => None,
+            PollEnd::TimedOut => Some("Polling timed out before the condition was met".to_string()),
+            PollEnd::Cancelled => Some("Polling was cancelled".to_string()),
+            PollEnd::NoUrl(e) => Some(format!("Cannot poll: {}", e)),
+        }
+    }
+}
+
+/// Polls after `first`, the response to `req` (already resolved), until the
+/// condition holds, `max_ms` passes or `cancel` is set. Each poll is a GET
+/// with the request's headers, reported through `on_step`. Returns the last
+/// response received and how polling ended.
+pub async fn poll(
+    config: &PollConfig,
+    req: &HttpRequest,
+    first: HttpResponse,
+    cancel: &CancelFlag,
+    mut on_step: impl FnMut(PollStep),
+) -> (HttpResponse, PollEnd) {
+    if config.is_done(&first) {
+        return (first, PollEnd::Met);
+    }
+    let url = match status_url(&config.url, &req.url, &first) {
+        Ok(url) => url,
+        Err(e) => return (first, PollEnd::NoUrl(e)),
+    };
+    let poll_request = HttpRequest {
+        method: "GET".to_string(),
+        url: url.clone(),
+        headers: req.headers.iter()
+            .filter(|(k, _)| !k.eq_ignore_ascii_case("content-type") && !k.eq_ignore_ascii_case("content-length"))
+            .cloned()
+            .collect(),
+        body: String::new(),
+        ..req.clone()
+    };
+    let started = Instant::now();
+    let deadline = started + Duration::from_millis(config.max_ms);
+    let mut last = first;
+    loop {
+        let wait = config.interval_ms.min(deadline.saturating_duration_since(Instant::now()).as_millis() as u64);
+        pause(Some(wait), cancel).await;
+        if cancel.is_cancelled() {
+            return (last, PollEnd::Cancelled);
+        }
+        if Instant::now() >= deadline {
+            return (last, PollEnd::TimedOut);
+        }
+        let result = tokio::select! {
+            result = execute_request(&poll_request) => result,
+            _ = cancelled(cancel) => return (last, PollEnd::Cancelled),
+        };
+        let at_ms = started.elapsed().as_millis() as u64;
+        on_step(PollStep {
+            at_ms,
+            url: url.clone(),
+            outcome: result.as_ref().map(|res| res.status_text.clone()).map_err(Clone::clone),
+        });
+        if let Ok(res) = result {
+            let met = config.is_done(&res);
+            last = res;
+            if met {
+                return (last, PollEnd::Met);
+            }
+        }
+    }
+}
+
+/// Resolves once `cancel` is set.
+async fn cancelled(cancel: &CancelFlag) {
+    while !cancel.is_cancelled() {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+/// The absolute URL to poll, relative ones taken against `request_url`.
+fn status_url(source: &StatusUrl, request_url: &str, res: &HttpResponse) -> Result<String, String> {
+    let found = match source {
+        StatusUrl::Location => res.headers.iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case("location"))
+            .map(|(_, v)| v.clone())
+            .ok_or("The response has no Location header")?,
+        StatusUrl::Body(expression) => {
+            let body: serde_json::Value = serde_json::from_str(&res.body).map_err(|_| "The response is not JSON")?;
+            match transform::apply(expression, &body).map_err(|e| e.to_string())? {
+                serde_json::Value::String(url) => url,
+                other => return Err(format!("{} is {}, not a URL", expression, other)),
+            }
+        }
+    };
+    let base = reqwest::Url::parse(request_url).map_err(|e| format!("Invalid request URL: {}", e))?;
+    base.join(&found).map(String::from).map_err(|e| format!("Invalid status URL '{}': {}", found, e))
+}
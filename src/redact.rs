@@ -0,0 +1,262 @@
+use regex::Regex;
+use serde_json::Value;
+use crate::client::HttpResponse;
+
+/// What masked values are replaced with.
+pub const MASK: &str = "•••";
+
+/// A step of a JSONPath selector.
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    /// `.name` or `['name']`
+    Key(String),
+    /// `[0]`
+    Index(usize),
+    /// `.*` or `[*]`: every member or item.
+    Any,
+    /// `..name`: `name` at any depth below.
+    Descendant(String),
+}
+
+#[derive(Debug, Clone)]
+enum Rule {
+    Path(Vec<Segment>),
+    Pattern(Regex),
+}
+
+/// Redaction rules, one per line of the setting: lines starting with `$` are
+/// JSONPath selectors whose values are masked in JSON bodies, any other line
+/// is a regex masked wherever it matches in bodies and header values. When
+/// the regex has groups, only what they capture is masked, so
+/// `token=(\w+)` keeps `token=`.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    rules: Vec<Rule>,
+}
+
+impl Redactor {
+    /// Compiles `lines`, skipping blank ones. Lines that do not compile are
+    /// left out and described in the second value.
+    pub fn new(lines: &[String]) -> (Redactor, Vec<String>) {
+        let mut rules = Vec::new();
+        let mut problems = Vec::new();
+        for line in lines.iter().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+            let rule = if line.starts_with('$') {
+                parse_path(line).map(Rule::Path)
+            } else {
+                Regex::new(line).map(Rule::Pattern)
+                    // The last line of the error says what is wrong
+                    .map_err(|e| e.to_string().lines().last().unwrap_or_default().trim_start_matches("error: ").to_string())
+            };
+            match rule {
+                Ok(rule) => rules.push(rule),
+                Err(e) => problems.push(format!("{}: {}", line, e)),
+            }
+        }
+        (Redactor { rules }, problems)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// `body` with the matches of every rule masked. JSON stays valid JSON:
+    /// selectors replace whole values and regexes only apply within strings.
+    /// Other text only has the regexes applied.
+    pub fn body(&self, body: &str) -> String {
+        let Ok(mut json) = serde_json::from_str::<Value>(body) else {
+            return self.text(body);
+        };
+        for rule in &self.rules {
+            match rule {
+                Rule::Path(segments) => mask_path(&mut json, segments),
+                Rule::Pattern(pattern) => mask_strings(&mut json, pattern),
+            }
+        }
+        let pretty = body.trim().contains('\n');
+        if pretty { serde_json::to_string_pretty(&json) } else { serde_json::to_string(&json) }
+            .unwrap_or_else(|_| body.to_string())
+    }
+
+    /// `text` with the matches of every regex masked.
+    pub fn text(&self, text: &str) -> String {
+        self.rules.iter().fold(text.to_string(), |text, rule| match rule {
+            Rule::Pattern(pattern) => mask_matches(&text, pattern),
+            Rule::Path(_) => text,
+        })
+    }
+
+    /// A copy of `res` with its body and header values redacted.
+    pub fn response(&self, res: &HttpResponse) -> HttpResponse {
+        let body = self.body(&res.body);
+        let raw_body = if body == res.body { res.raw_body.clone() } else { body.clone().into_bytes() };
+        HttpResponse {
+            headers: res.headers.iter().map(|(name, value)| (name.clone(), self.text(value))).collect(),
+            body,
+            raw_body,
+            ..res.clone()
+        }
+    }
+}
+
+fn parse_path(path: &str) -> Result<Vec<Segment>, String> {
+    let mut rest = path.strip_prefix('$').ok_or("A JSONPath starts with $")?;
+    let mut segments = Vec::new();
+    let name_end = |s: &str| s.find(['.', '[']).unwrap_or(s.len());
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("..") {
+            let end = name_end(after);
+            if end == 0 {
+                return Err("Expected a name after ..".to_string());
+            }
+            segments.push(Segment::Descendant(after[..end].to_string()));
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('.') {
+            let end = name_end(after);
+            segments.push(match &after[..end] {
+                "" => return Err("Expected a name after .".to_string()),
+                "*" => Segment::Any,
+                name => Segment::Key(name.to_string()),
+            });
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']').ok_or("Missing ]")?;
+            let inner = after[..end].trim();
+            segments.push(if inner == "*" {
+                Segment::Any
+            } else if let Some(name) = inner.strip_prefix('\'').and_then(|s| s.strip_suffix('\''))
+                .or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')))
+            {
+                Segment::Key(name.to_string())
+            } else {
+                Segment::Index(inner.parse().map_err(|_| format!("'{}' is not an index, a quoted name or *", inner))?)
+            });
+            rest = &after[end + 1..];
+        } else {
+            return Err(format!("Unexpected '{}'", rest));
+        }
+    }
+    Ok(segments)
+}
+
+/// Masks the values `segments` select below `value`.
+fn mask_path(value: &mut Value, segments: &[Segment]) {
+    let Some((segment, rest)) = segments.split_first() else {
+        *value = Value::String(MASK.to_string());
+        return;
+    };
+    match (segment, value) {
+        (Segment::Key(name), Value::Object(map)) => {
+            if let Some(child) = map.get_mut(name) {
+                mask_path(child, rest);
+            }
+        }
+        (Segment::Index(i), Value::Array(items)) => {
+            if let Some(child) = items.get_mut(*i) {
+                mask_path(child, rest);
+            }
+        }
+        (Segment::Any, Value::Object(map)) => map.values_mut().for_each(|child| mask_path(child, rest)),
+        (Segment::Any, Value::Array(items)) => items.iter_mut().for_each(|child| mask_path(child, rest)),
+        (Segment::Descendant(name), value) => {
+            if let Value::Object(map) = value
+                && let Some(child) = map.get_mut(name)
+            {
+                mask_path(child, rest);
+            }
+            match value {
+                Value::Object(map) => map.values_mut().for_each(|child| mask_path(child, segments)),
+                Value::Array(items) => items.iter_mut().for_each(|child| mask_path(child, segments)),
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Applies `pattern` to every string below `value`.
+fn mask_strings(value: &mut Value, pattern: &Regex) {
+    match value {
+        Value::String(s) => *s = mask_matches(s, pattern),
+        Value::Object(map) => map.values_mut().for_each(|child| mask_strings(child, pattern)),
+        Value::Array(items) => items.iter_mut().for_each(|child| mask_strings(child, pattern)),
+        _ => {}
+    }
+}
+
+fn mask_matches(text: &str, pattern: &Regex) -> String {
+    let mut ranges = Vec::new();
+    for captures in pattern.captures_iter(text) {
+        if pattern.captures_len() > 1 {
+            ranges.extend(captures.iter().skip(1).flatten().filter(|m| !m.is_empty()).map(|m| m.range()));
+        } else if let Some(whole) = captures.get(0).filter(|m| !m.is_empty()) {
+            ranges.push(whole.range());
+        }
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut end = 0;
+    for range in ranges {
+        // Nested groups: the outer one is already masked
+        if range.start < end {
+            continue;
+        }
+        out.push_str(&text[end..range.start]);
+        out.push_str(MASK);
+        end = range.end;
+    }
+    out.push_str(&text[end..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn redactor(lines: &[&str]) -> Redactor {
+        let (redactor, problems) = Redactor::new(&lines.iter().map(|l| l.to_string()).collect::<Vec<_>>());
+        assert!(problems.is_empty(), "{:?}", problems);
+        redactor
+    }
+
+    fn masked(lines: &[&str], body: Value) -> Value {
+        serde_json::from_str(&redactor(lines).body(&body.to_string())).unwrap()
+    }
+
+    #[test]
+    fn selectors_mask_nested_json_values() {
+        let body = json!({
+            "user": {"email": "a@x.io", "name": "A"},
+            "tokens": [{"value": "t1"}, {"value": "t2"}],
+            "deep": {"list": [{"password": "p1"}, {"inner": {"password": "p2"}}]},
+        });
+        assert_eq!(masked(&["$.user.email", "$.tokens[*].value", "$..password"], body.clone()), json!({
+            "user": {"email": MASK, "name": "A"},
+            "tokens": [{"value": MASK}, {"value": MASK}],
+            "deep": {"list": [{"password": MASK}, {"inner": {"password": MASK}}]},
+        }));
+        assert_eq!(masked(&["$.tokens[1]", "$['user'].*"], body.clone())["tokens"], json!([{"value": "t1"}, MASK]));
+        assert_eq!(masked(&["$.missing.key", "$.user[0]"], body.clone()), body);
+    }
+
+    #[test]
+    fn regexes_mask_within_strings_and_plain_text() {
+        assert_eq!(masked(&[r"\w+@\w+\.io"], json!({"to": ["a@x.io", "b@y.io"], "n": 5})), json!({"to": [MASK, MASK], "n": 5}));
+        let redactor = redactor(&[r"token=(\w+)", "secret"]);
+        assert_eq!(redactor.body("a token=abc&b=secret!"), format!("a token={}&b={}!", MASK, MASK));
+        // Pretty bodies stay pretty
+        assert_eq!(redactor.body("{\n  \"a\": \"secret\"\n}"), format!("{{\n  \"a\": \"{}\"\n}}", MASK));
+        assert_eq!(redactor.text("no match"), "no match");
+    }
+
+    #[test]
+    fn bad_rules_are_reported_and_skipped() {
+        let lines: Vec<String> = ["$.a[", "(unclosed", "", "$.ok"].map(String::from).to_vec();
+        let (redactor, problems) = Redactor::new(&lines);
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0], "$.a[: Missing ]");
+        assert!(problems[1].starts_with("(unclosed: "));
+        assert!(!redactor.is_empty());
+        assert!(Redactor::new(&[]).0.is_empty());
+    }
+}
@@ -0,0 +1,937 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use crate::assertions::{self, Outcome};
+use crate::auth;
+use crate::client::{HttpResponse, execute_queued};
+use crate::dates;
+use crate::environment::{self, Resolver};
+use crate::hooks;
+use crate::poll::{self, PollConfig, PollEnd};
+use crate::storage::{self, FileNode, FolderConfig, RequestOptions};
+use crate::transform;
+
+/// Shared flag used to stop a run between requests.
+#[derive(Clone, Default)]
+pub struct CancelFlag(Arc<CancelState>);
+
+#[derive(Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    /// Wakes the tasks waiting in [`CancelFlag::cancelled`].
+    notify: tokio::sync::Notify,
+}
+
+impl CancelFlag {
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Relaxed);
+        self.0.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Completes once the flag is set.
+    pub async fn cancelled(&self) {
+        let mut notified = std::pin::pin!(self.0.notify.notified());
+        notified.as_mut().enable();
+        if !self.is_cancelled() {
+            notified.await;
+        }
+    }
+}
+
+/// What a folder run does after a request gets no response, e.g. because the
+/// connection failed. Statuses the request does not accept are not errors here.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorPolicy {
+    /// Go on with the next request.
+    #[default]
+    Continue,
+    /// Skip the requests that use a variable the failed request captures.
+    SkipDependents,
+    /// Stop the run; teardowns still run.
+    Stop,
+}
+
+impl ErrorPolicy {
+    pub const ALL: [ErrorPolicy; 3] = [ErrorPolicy::Continue, ErrorPolicy::SkipDependents, ErrorPolicy::Stop];
+
+    pub fn parse(s: &str) -> Result<ErrorPolicy, String> {
+        let s = s.trim();
+        ErrorPolicy::ALL.into_iter()
+            .find(|policy| policy.as_str() == s)
+            .ok_or_else(|| format!("Expected continue, skip-dependents or stop, got '{}'", s))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorPolicy::Continue => "continue",
+            ErrorPolicy::SkipDependents => "skip-dependents",
+            ErrorPolicy::Stop => "stop",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ErrorPolicy::Continue => "Continue with the next request",
+            ErrorPolicy::SkipDependents => "Skip requests that use its captures",
+            ErrorPolicy::Stop => "Stop the run",
+        }
+    }
+}
+
+/// What a folder run does with a request pinned to environments other than
+/// the one the run uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PinnedPolicy {
+    /// Send it with the first environment it is pinned to.
+    #[default]
+    Switch,
+    /// Leave it out of the run.
+    Skip,
+}
+
+impl PinnedPolicy {
+    pub const ALL: [PinnedPolicy; 2] = [PinnedPolicy::Switch, PinnedPolicy::Skip];
+
+    pub fn parse(s: &str) -> Result<PinnedPolicy, String> {
+        let s = s.trim();
+        PinnedPolicy::ALL.into_iter()
+            .find(|policy| policy.as_str() == s)
+            .ok_or_else(|| format!("Expected switch or skip, got '{}'", s))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PinnedPolicy::Switch => "switch",
+            PinnedPolicy::Skip => "skip",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PinnedPolicy::Switch => "Send it with its pinned environment",
+            PinnedPolicy::Skip => "Skip it",
+        }
+    }
+}
+
+/// A variable set from the JSON response of a request in a folder run,
+/// written `NAME = EXPRESSION` with a [`transform`] expression such as
+/// `.access_token`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capture {
+    pub name: String,
+    pub expression: String,
+}
+
+impl Capture {
+    /// Parses captures separated by `;`.
+    pub fn parse_list(s: &str) -> Result<Vec<Capture>, String> {
+        s.split(';')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .map(|part| {
+                let (name, expression) = part.split_once('=').ok_or_else(|| format!("Expected NAME = EXPRESSION, got '{}'", part))?;
+                let (name, expression) = (name.trim(), expression.trim());
+                if name.is_empty() || name.contains(['{', '}', ' ']) {
+                    return Err(format!("Invalid capture name '{}'", name));
+                }
+                if expression.is_empty() {
+                    return Err(format!("Capture '{}' needs an expression", name));
+                }
+                Ok(Capture { name: name.to_string(), expression: expression.to_string() })
+            })
+            .collect()
+    }
+
+    /// The captured value: strings as they are, other JSON as text.
+    fn evaluate(&self, res: &HttpResponse) -> Result<String, String> {
+        let body: serde_json::Value = serde_json::from_str(&res.body)
+            .map_err(|_| format!("Cannot capture {}: the response is not JSON", self.name))?;
+        match transform::apply(&self.expression, &body) {
+            Ok(serde_json::Value::String(value)) => Ok(value),
+            Ok(serde_json::Value::Null) => Err(format!("Cannot capture {}: {} is null", self.name, self.expression)),
+            Ok(value) => Ok(value.to_string()),
+            Err(e) => Err(format!("Cannot capture {}: {}", self.name, e)),
+        }
+    }
+}
+
+impl std::fmt::Display for Capture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} = {}", self.name, self.expression)
+    }
+}
+
+/// State carried from one request of a folder run to the next.
+pub struct RunContext {
+    /// Resolver of the run, with the variables captured so far.
+    pub resolver: Resolver,
+    pub policy: ErrorPolicy,
+    /// Name of the environment whose variables `resolver` holds.
+    environment: Option<String>,
+    pinned: PinnedPolicy,
+    /// Variables captured so far, kept apart to carry them over when a
+    /// pinned request switches environment.
+    captured: HashMap<String, String>,
+    /// Captured names whose producing request failed or was skipped, with
+    /// that request's file name.
+    unavailable: HashMap<String, String>,
+    /// The request that stopped the run.
+    stopped_by: Option<String>,
+    /// Environments requests are not sent with, even when pinned to them.
+    refused: Vec<String>,
+}
+
+impl RunContext {
+    pub fn new(resolver: Resolver, policy: ErrorPolicy, environment: Option<String>, pinned: PinnedPolicy) -> RunContext {
+        RunContext {
+            resolver,
+            policy,
+            environment,
+            pinned,
+            captured: HashMap::new(),
+            unavailable: HashMap::new(),
+            stopped_by: None,
+            refused: Vec::new(),
+        }
+    }
+
+    /// Keeps the run from sending anything with `environments`.
+    pub fn refuse(&mut self, environments: Vec<String>) {
+        self.refused = environments;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunResult {
+    pub path: PathBuf,
+    pub name: String,
+    pub response: Result<HttpResponse, String>,
+    pub passed: bool,
+    /// Not sent because a request it depends on failed; `response` says which.
+    pub skipped: bool,
+    /// Time actually waited for the request's `delay-before`/`delay-after`.
+    pub delay_before_ms: u64,
+    pub delay_after_ms: u64,
+}
+
+/// Outcome of running one folder. Setup and teardown are kept apart from the
+/// main sequence; subfolders nest their own hooks inside the parent's.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FolderReport {
+    pub path: PathBuf,
+    pub setup: Option<RunResult>,
+    pub results: Vec<RunResult>,
+    pub folders: Vec<FolderReport>,
+    pub teardown: Option<RunResult>,
+    pub cancelled: bool,
+    /// The request whose error stopped the run, in the folder it ran in and
+    /// the folders above.
+    pub stopped_by: Option<String>,
+}
+
+impl FolderReport {
+    pub fn passed(&self) -> bool {
+        self.setup.iter().chain(&self.results).chain(&self.teardown).all(|r| r.passed)
+            && self.folders.iter().all(|f| f.passed())
+    }
+
+    /// (passed, failed, skipped) counts over the main sequences, hooks excluded.
+    pub fn counts(&self) -> (usize, usize, usize) {
+        let passed = self.results.iter().filter(|r| r.passed).count();
+        let skipped = self.results.iter().filter(|r| r.skipped).count();
+        let mut counts = (passed, self.results.len() - passed - skipped, skipped);
+        for folder in &self.folders {
+            let (p, f, s) = folder.counts();
+            counts.0 += p;
+            counts.1 += f;
+            counts.2 += s;
+        }
+        counts
+    }
+}
+
+/// Outcome of the last run of a folder, kept as `.last-run.json` in the
+/// folder until the next run or until it is cleared.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct RunSummary {
+    /// When the run finished, in milliseconds since the Unix epoch.
+    pub finished_ms: i64,
+    /// Counts over the folder and its subfolders, hooks excluded.
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub cancelled: bool,
+    /// Whether each request run directly in the folder passed, by file name.
+    /// Skipped requests are left out.
+    pub files: HashMap<String, bool>,
+    /// Files edited since the run; worked out when loading.
+    #[serde(skip)]
+    pub stale: HashSet<String>,
+}
+
+/// How a sidebar badge shows a run outcome.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunBadge {
+    Passed,
+    Failed,
+    /// Passed, but cancelled or with requests edited since.
+    Warning,
+}
+
+impl RunSummary {
+    fn path(folder: &Path) -> PathBuf {
+        folder.join(".last-run.json")
+    }
+
+    pub fn load(folder: &Path) -> Option<RunSummary> {
+        let content = storage::backend().load(&Self::path(folder)).ok()?;
+        let mut summary: RunSummary = serde_json::from_str(&content).ok()?;
+        let finished = UNIX_EPOCH + Duration::from_millis(summary.finished_ms.max(0) as u64);
+        summary.stale = summary.files.keys()
+            .filter(|name| storage::backend().modified(&folder.join(name)).is_some_and(|t| t > finished))
+            .cloned()
+            .collect();
+        Some(summary)
+    }
+
+    pub fn clear(folder: &Path) -> std::io::Result<()> {
+        crate::workspace::ensure_writable()?;
+        storage::backend().delete(&Self::path(folder))
+    }
+
+    /// Writes the summary of `report` and of each subfolder run in it.
+    pub fn save_all(report: &FolderReport) -> std::io::Result<()> {
+        crate::workspace::ensure_writable()?;
+        let (passed, failed, skipped) = report.counts();
+        let summary = RunSummary {
+            finished_ms: chrono::Local::now().timestamp_millis(),
+            passed,
+            failed,
+            skipped,
+            cancelled: report.cancelled || report.stopped_by.is_some(),
+            files: report.setup.iter().chain(&report.results).chain(&report.teardown)
+                .filter(|r| !r.skipped)
+                .map(|r| (r.name.clone(), r.passed))
+                .collect(),
+            stale: HashSet::new(),
+        };
+        let content = serde_json::to_string_pretty(&summary).map_err(std::io::Error::other)?;
+        storage::backend().save(&Self::path(&report.path), &content)?;
+        for folder in &report.folders {
+            Self::save_all(folder)?;
+        }
+        Ok(())
+    }
+
+    pub fn badge(&self) -> RunBadge {
+        if self.failed > 0 {
+            RunBadge::Failed
+        } else if self.cancelled || !self.stale.is_empty() {
+            RunBadge::Warning
+        } else {
+            RunBadge::Passed
+        }
+    }
+
+    /// The badge of the file `name`, if it was run.
+    pub fn file_badge(&self, name: &str) -> Option<RunBadge> {
+        let passed = *self.files.get(name)?;
+        Some(if self.stale.contains(name) {
+            RunBadge::Warning
+        } else if passed {
+            RunBadge::Passed
+        } else {
+            RunBadge::Failed
+        })
+    }
+
+    pub fn describe(&self, utc: bool) -> String {
+        let finished = dates::display_ms(self.finished_ms, "%Y-%m-%d %H:%M", utc);
+        let mut text = format!("Last run {}: {} passed, {} failed", finished, self.passed, self.failed);
+        if self.skipped > 0 {
+            text.push_str(&format!(", {} skipped", self.skipped));
+        }
+        if self.cancelled {
+            text.push_str(", cancelled");
+        }
+        if !self.stale.is_empty() {
+            text.push_str(&format!("; {} edited since", self.stale.len()));
+        }
+        text
+    }
+}
+
+/// How many runs of a folder `.run-history.json` keeps.
+pub const RUN_HISTORY_LEN: usize = 10;
+
+/// Bytes of each response body kept in the run history; the rest is dropped.
+pub const RECORDED_BODY_BYTES: usize = 16 * 1024;
+
+/// Upper bounds, in milliseconds, of the duration buckets of the timings
+/// heatmap; slower responses fall in one more bucket after the last.
+pub const DURATION_BUCKETS_MS: [u128; 4] = [200, 500, 1000, 3000];
+
+/// The bucket of `elapsed_ms`, 0 being the fastest.
+pub fn duration_bucket(elapsed_ms: u128) -> usize {
+    DURATION_BUCKETS_MS.iter().position(|&limit| elapsed_ms <= limit).unwrap_or(DURATION_BUCKETS_MS.len())
+}
+
+/// How one request went in a recorded run.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RecordedResult {
+    /// File name, within the folder.
+    pub name: String,
+    pub passed: bool,
+    pub skipped: bool,
+    pub response: Result<HttpResponse, String>,
+}
+
+/// One run of a folder. `.run-history.json` in the folder keeps the last
+/// [`RUN_HISTORY_LEN`], oldest first, with the requests run directly in it.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RunRecord {
+    pub finished_ms: i64,
+    pub results: Vec<RecordedResult>,
+}
+
+impl RunRecord {
+    fn path(folder: &Path) -> PathBuf {
+        folder.join(".run-history.json")
+    }
+
+    /// The recorded runs of `folder`, oldest first.
+    pub fn load_all(folder: &Path) -> Vec<RunRecord> {
+        storage::backend().load(&Self::path(folder)).ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Adds the run in `report` to the history of its folder and of each
+    /// subfolder run in it, dropping the oldest past [`RUN_HISTORY_LEN`].
+    pub fn append_all(report: &FolderReport) -> std::io::Result<()> {
+        crate::workspace::ensure_writable()?;
+        let mut records = Self::load_all(&report.path);
+        records.push(RunRecord {
+            finished_ms: chrono::Local::now().timestamp_millis(),
+            results: report.setup.iter().chain(&report.results).chain(&report.teardown)
+                .map(|r| RecordedResult { name: r.name.clone(), passed: r.passed, skipped: r.skipped, response: r.response.clone().map(recorded) })
+                .collect(),
+        });
+        let excess = records.len().saturating_sub(RUN_HISTORY_LEN);
+        records.drain(..excess);
+        let content = serde_json::to_string(&records).map_err(std::io::Error::other)?;
+        storage::backend().save(&Self::path(&report.path), &content)?;
+        for folder in &report.folders {
+            Self::append_all(folder)?;
+        }
+        Ok(())
+    }
+}
+
+/// `res` as kept in the run history, its body cut to [`RECORDED_BODY_BYTES`].
+fn recorded(mut res: HttpResponse) -> HttpResponse {
+    if res.body.len() > RECORDED_BODY_BYTES {
+        res.body.truncate(res.body.floor_char_boundary(RECORDED_BODY_BYTES));
+        res.warnings.push(format!("Only the first {} KB of the body was kept in the run history", RECORDED_BODY_BYTES / 1024));
+    }
+    res
+}
+
+/// A request's row of the timings heatmap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeatmapRow {
+    pub name: String,
+    /// Its result in each run, in the order of [`Heatmap::runs`]; `None`
+    /// where it was not part of the run, having been added since or removed
+    /// before, or where the run was cancelled before reaching it.
+    pub cells: Vec<Option<RecordedResult>>,
+    /// Not part of the latest run.
+    pub dropped: bool,
+}
+
+/// Requests × runs of a folder, from its run history.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Heatmap {
+    /// When each run finished, oldest first.
+    pub runs: Vec<i64>,
+    /// Requests of the latest run in the order they ran, then those only
+    /// older runs had, most recently run first.
+    pub rows: Vec<HeatmapRow>,
+}
+
+/// Lays out `records` (oldest first) as a heatmap.
+pub fn heatmap(records: &[RunRecord]) -> Heatmap {
+    let mut names: Vec<&str> = Vec::new();
+    for record in records.iter().rev() {
+        for result in &record.results {
+            if !names.contains(&result.name.as_str()) {
+                names.push(&result.name);
+            }
+        }
+    }
+    let latest = records.last();
+    let rows = names.into_iter()
+        .map(|name| HeatmapRow {
+            name: name.to_string(),
+            cells: records.iter().map(|record| record.results.iter().find(|r| r.name == name).cloned()).collect(),
+            dropped: !latest.is_some_and(|record| record.results.iter().any(|r| r.name == name)),
+        })
+        .collect();
+    Heatmap { runs: records.iter().map(|r| r.finished_ms).collect(), rows }
+}
+
+/// Runs every request in `folder` and its subfolders in sidebar order.
+///
+/// A failing setup skips the folder's requests; teardown always runs. What
+/// happens after a request gets no response follows its `on-error` option,
+/// else the policy of `run`, which also says whether a request pinned to
+/// other environments switches to its own or is skipped.
+pub fn run_folder<'a>(
+    folder: &'a Path,
+    run: &'a mut RunContext,
+    cancel: &'a CancelFlag,
+) -> Pin<Box<dyn Future<Output = FolderReport> + 'a>> {
+    Box::pin(async move {
+        let config = FolderConfig::load(folder);
+        let setup_path = config.setup.as_ref().map(|f| folder.join(f));
+        let teardown_path = config.teardown.as_ref().map(|f| folder.join(f));
+        let mut report = FolderReport {
+            path: folder.to_path_buf(),
+            setup: None,
+            results: Vec::new(),
+            folders: Vec::new(),
+            teardown: None,
+            cancelled: false,
+            stopped_by: None,
+        };
+
+        if let Some(path) = &setup_path {
+            report.setup = Some(run_file(path, run, cancel).await);
+        }
+
+        if report.setup.as_ref().is_none_or(|r| r.passed)
+            && let FileNode::Folder { children, .. } = storage::build_tree(folder)
+        {
+            for child in children {
+                if cancel.is_cancelled() {
+                    report.cancelled = true;
+                    break;
+                }
+                if run.stopped_by.is_some() {
+                    break;
+                }
+                match child {
+                    FileNode::Folder { path, .. } => {
+                        let sub = run_folder(&path, run, cancel).await;
+                        report.cancelled |= sub.cancelled;
+                        report.folders.push(sub);
+                    }
+                    FileNode::File { path, .. } => {
+                        if Some(&path) == setup_path.as_ref() || Some(&path) == teardown_path.as_ref() {
+                            continue;
+                        }
+                        report.results.push(run_file(&path, run, cancel).await);
+                    }
+                }
+            }
+        }
+        report.stopped_by = run.stopped_by.clone();
+
+        if let Some(path) = &teardown_path {
+            report.teardown = Some(run_file(path, run, cancel).await);
+        }
+        report
+    })
+}
+
+async fn run_file(path: &Path, run: &mut RunContext, cancel: &CancelFlag) -> RunResult {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string();
+    let not_sent = |response: Result<HttpResponse, String>, skipped: bool| RunResult {
+        path: path.to_path_buf(),
+        name: name.clone(),
+        response,
+        passed: false,
+        skipped,
+        delay_before_ms: 0,
+        delay_after_ms: 0,
+    };
+    let request = match storage::load_request(path) {
+        Ok(request) => request,
+        Err(e) => return not_sent(Err(e), false),
+    };
+    let text = request.to_http_string();
+    let missing = environment::placeholders(&text).into_iter()
+        .find_map(|used| run.unavailable.get(used).map(|producer| (used.to_string(), producer.clone())));
+    if let Some((variable, producer)) = missing {
+        // Whatever this request captures is missing too, for its own dependents
+        for capture in &request.options.captures {
+            run.unavailable.insert(capture.name.clone(), name.clone());
+        }
+        return not_sent(Err(format!("Skipped: uses {{{{{}}}}}, which {} did not capture", variable, producer)), true);
+    }
+
+    let mut notes = Vec::new();
+    let mut switched = None;
+    if request.options.pinned_elsewhere(run.environment.as_deref()) {
+        let pins = request.options.environments.join(", ");
+        let active = run.environment.as_deref().unwrap_or("no environment");
+        match run.pinned {
+            PinnedPolicy::Skip => {
+                for capture in &request.options.captures {
+                    run.unavailable.insert(capture.name.clone(), name.clone());
+                }
+                return not_sent(Err(format!("Skipped: pinned to {}, but the run uses {}", pins, active)), true);
+            }
+            PinnedPolicy::Switch => {
+                let pinned = &request.options.environments[0];
+                let mut vars = match environment::load_environment(pinned) {
+                    Ok(vars) => vars,
+                    Err(e) => return not_sent(Err(e), false),
+                };
+                vars.extend(run.captured.clone());
+                switched = Some(Resolver { vars, environment: Some(pinned.clone()), ..run.resolver.clone() });
+                notes.push(format!("Sent with environment {} instead of {}: the request is pinned to it", pinned, active));
+            }
+        }
+    }
+    let resolver = switched.as_ref().unwrap_or(&run.resolver);
+    if let Some(env) = resolver.environment.as_deref().filter(|env| run.refused.iter().any(|r| r == env)) {
+        return not_sent(Err(format!("Refused: {} is a protected environment; folder runs only send to it when started with Run anyway", env)), false);
+    }
+
+    let delay_before_ms = pause(request.options.delay_before_ms, cancel).await;
+    let resolved = hooks::prepare(resolver, Some(path), &request).await
+        .and_then(|resolver| resolver.resolve_request(&auth::inherit(Some(path), &request)));
+    let mut polled = true;
+    let response = match resolved {
+        Ok(resolved) => match (execute_queued(&resolved, cancel, |_| {}).await, PollConfig::from_options(&resolved.options)) {
+            (Ok(first), Some(config)) => {
+                let (last, end) = poll::poll(&config, &resolved, first, cancel, |_| {}).await;
+                polled = end == PollEnd::Met;
+                Ok(last)
+            }
+            (response, _) => response,
+        },
+        Err(e) => Err(e),
+    };
+    let delay_after_ms = pause(request.options.delay_after_ms, cancel).await;
+    let mut passed = polled && response.as_ref().is_ok_and(|r| request.options.accepts(r.status));
+    let mut problems = Vec::new();
+    match &response {
+        Ok(res) => {
+            let outcomes = assertions::evaluate(Some(path), &request.options, res);
+            if !outcomes.is_empty() {
+                passed = polled && outcomes.iter().all(Outcome::passed);
+                problems.extend(outcomes.iter().filter(|o| !o.passed()).map(assertions::describe_failure));
+            }
+            for capture in &request.options.captures {
+                match capture.evaluate(res) {
+                    Ok(value) => {
+                        run.unavailable.remove(&capture.name);
+                        run.captured.insert(capture.name.clone(), value.clone());
+                        run.resolver.vars.insert(capture.name.clone(), value);
+                    }
+                    Err(e) => {
+                        // Its dependents are skipped, as after a failed request
+                        run.unavailable.insert(capture.name.clone(), name.clone());
+                        problems.push(e);
+                    }
+                }
+            }
+        }
+        Err(_) => match request.options.on_error.unwrap_or(run.policy) {
+            ErrorPolicy::Continue => {}
+            ErrorPolicy::SkipDependents => {
+                for capture in &request.options.captures {
+                    run.unavailable.insert(capture.name.clone(), name.clone());
+                }
+            }
+            ErrorPolicy::Stop => run.stopped_by = Some(name.clone()),
+        },
+    }
+    let mut response = response;
+    if let Ok(res) = &mut response {
+        passed &= problems.is_empty();
+        res.warnings.extend(notes.into_iter().chain(problems));
+    }
+    RunResult { path: path.to_path_buf(), name, response, passed, skipped: false, delay_before_ms, delay_after_ms }
+}
+
+/// Sleeps for `ms`, waking early when the run is cancelled. Returns the
+/// milliseconds actually waited.
+pub async fn pause(ms: Option<u64>, cancel: &CancelFlag) -> u64 {
+    let Some(ms) = ms.filter(|&ms| ms > 0) else { return 0 };
+    let started = Instant::now();
+    let deadline = started + Duration::from_millis(ms);
+    while !cancel.is_cancelled() && Instant::now() < deadline {
+        let step = deadline.saturating_duration_since(Instant::now()).min(Duration::from_millis(100));
+        tokio::time::sleep(step).await;
+    }
+    started.elapsed().as_millis() as u64
+}
+
+/// Latency and failure summary of sending one request repeatedly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyStats {
+    pub runs: usize,
+    /// Runs that failed to get a response or got one the request does not accept.
+    pub failures: usize,
+    pub min_ms: u128,
+    pub max_ms: u128,
+    pub p50_ms: u128,
+    pub p90_ms: u128,
+    pub p99_ms: u128,
+}
+
+impl LatencyStats {
+    /// Summarizes `responses`; latencies come from the runs that got a response.
+    /// Returns `None` when no run got one.
+    pub fn from_responses(responses: &[Result<HttpResponse, String>], options: &RequestOptions) -> Option<LatencyStats> {
+        let mut latencies: Vec<u128> = responses.iter()
+            .filter_map(|r| r.as_ref().ok().map(|res| res.elapsed_ms))
+            .collect();
+        latencies.sort_unstable();
+        let percentile = |p: usize| {
+            // Nearest-rank method
+            let rank = (p * latencies.len()).div_ceil(100).max(1);
+            latencies[rank - 1]
+        };
+        Some(LatencyStats {
+            runs: responses.len(),
+            failures: responses.iter().filter(|r| !r.as_ref().is_ok_and(|res| options.accepts(res.status))).count(),
+            min_ms: *latencies.first()?,
+            max_ms: *latencies.last()?,
+            p50_ms: percentile(50),
+            p90_ms: percentile(90),
+            p99_ms: percentile(99),
+        })
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        if self.runs == 0 { 0.0 } else { self.failures as f64 * 100.0 / self.runs as f64 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Answers every request on a local port with `json`. Returns the port.
+    async fn json_server(json: &'static str) -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut received = Vec::new();
+                    let mut buf = [0; 4096];
+                    while !received.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match stream.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => received.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let response = format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", json.len(), json);
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        port
+    }
+
+    /// A folder where 1-login captures a token that 2-profile uses to
+    /// capture a user id, which 3-orders uses; 4-health stands alone.
+    /// Logging in goes to `login_port`, the rest to `port`.
+    fn chain(login_port: u16, port: u16) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!("requester-runner-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&folder).unwrap();
+        let files = [
+            ("1-login.req", format!("# capture: token = .token\nPOST http://127.0.0.1:{}/login\n", login_port)),
+            ("2-profile.req", format!("# capture: user = .id\nGET http://127.0.0.1:{}/me\nAuthorization: Bearer {{{{token}}}}\n", port)),
+            ("3-orders.req", format!("GET http://127.0.0.1:{}/users/{{{{user}}}}/orders\n", port)),
+            ("4-health.req", format!("GET http://127.0.0.1:{}/health\n", port)),
+        ];
+        for (name, content) in files {
+            std::fs::write(folder.join(name), content).unwrap();
+        }
+        folder
+    }
+
+    async fn closed_port() -> u16 {
+        tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port()
+    }
+
+    async fn run_chain(login_port: u16, port: u16, policy: ErrorPolicy) -> FolderReport {
+        let folder = chain(login_port, port);
+        let mut run = RunContext::new(Resolver::default(), policy, None, PinnedPolicy::Switch);
+        let report = run_folder(&folder, &mut run, &CancelFlag::default()).await;
+        std::fs::remove_dir_all(&folder).unwrap();
+        report
+    }
+
+    /// Each result as (name, passed, skipped, error).
+    fn outcomes(report: &FolderReport) -> Vec<(&str, bool, bool, Option<&str>)> {
+        report.results.iter()
+            .map(|r| (r.name.as_str(), r.passed, r.skipped, r.response.as_ref().err().map(String::as_str)))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn failed_producer_with_continue_runs_everything() {
+        let port = json_server(r#"{"token": "t1", "id": 7}"#).await;
+        let report = run_chain(closed_port().await, port, ErrorPolicy::Continue).await;
+        let outcomes = outcomes(&report);
+        assert_eq!(outcomes.iter().map(|o| (o.0, o.1, o.2)).collect::<Vec<_>>(), [
+            ("1-login.req", false, false),
+            ("2-profile.req", false, false),
+            ("3-orders.req", false, false),
+            ("4-health.req", true, false),
+        ]);
+        assert!(outcomes[1].3.is_some_and(|e| e.starts_with("Unresolved variables: token")));
+        assert!(outcomes[2].3.is_some_and(|e| e.starts_with("Unresolved variables: user")));
+        assert_eq!(report.counts(), (1, 3, 0));
+        assert_eq!(report.stopped_by, None);
+    }
+
+    #[tokio::test]
+    async fn failed_producer_with_skip_dependents_skips_the_whole_chain() {
+        let port = json_server(r#"{"token": "t1", "id": 7}"#).await;
+        let report = run_chain(closed_port().await, port, ErrorPolicy::SkipDependents).await;
+        assert_eq!(outcomes(&report)[1..], [
+            ("2-profile.req", false, true, Some("Skipped: uses {{token}}, which 1-login.req did not capture")),
+            ("3-orders.req", false, true, Some("Skipped: uses {{user}}, which 2-profile.req did not capture")),
+            ("4-health.req", true, false, None),
+        ]);
+        assert!(!report.results[0].passed && !report.results[0].skipped);
+        assert_eq!(report.counts(), (1, 1, 2));
+    }
+
+    #[tokio::test]
+    async fn failed_producer_with_stop_ends_the_run() {
+        let port = json_server(r#"{"token": "t1", "id": 7}"#).await;
+        let report = run_chain(closed_port().await, port, ErrorPolicy::Stop).await;
+        assert_eq!(report.results.len(), 1);
+        assert!(report.results[0].response.is_err());
+        assert_eq!(report.stopped_by.as_deref(), Some("1-login.req"));
+
+        // The request's own policy wins over the run's
+        let folder = chain(closed_port().await, port);
+        std::fs::write(folder.join("1-login.req"), format!("# on-error: stop\n# capture: token = .token\nPOST http://127.0.0.1:{}/login\n", closed_port().await)).unwrap();
+        let mut run = RunContext::new(Resolver::default(), ErrorPolicy::Continue, None, PinnedPolicy::Switch);
+        let report = run_folder(&folder, &mut run, &CancelFlag::default()).await;
+        std::fs::remove_dir_all(&folder).unwrap();
+        assert_eq!(report.stopped_by.as_deref(), Some("1-login.req"));
+    }
+
+    #[tokio::test]
+    async fn failed_capture_skips_dependents() {
+        // The login answers, but without a token
+        let port = json_server(r#"{"id": 7}"#).await;
+        let report = run_chain(port, port, ErrorPolicy::Continue).await;
+        let outcomes = outcomes(&report);
+        assert_eq!((outcomes[0].1, outcomes[0].2), (false, false));
+        assert!(report.results[0].response.as_ref().unwrap().warnings.contains(&"Cannot capture token: .token is null".to_string()));
+        assert_eq!(outcomes[1..], [
+            ("2-profile.req", false, true, Some("Skipped: uses {{token}}, which 1-login.req did not capture")),
+            ("3-orders.req", false, true, Some("Skipped: uses {{user}}, which 2-profile.req did not capture")),
+            ("4-health.req", true, false, None),
+        ]);
+    }
+
+    fn result(name: &str, elapsed_ms: Option<u128>) -> RecordedResult {
+        let response = elapsed_ms.map(|elapsed_ms| HttpResponse {
+            status: 200,
+            status_text: "200 OK".to_string(),
+            headers: Vec::new(),
+            body: String::new(),
+            raw_body: Vec::new(),
+            warnings: Vec::new(),
+            elapsed_ms,
+            size_bytes: 0,
+            downgrading_redirect: None,
+            compressed_body: None,
+            remote_addr: None,
+            dns: None,
+            truncated: None,
+            expect_continue: None,
+        });
+        RecordedResult { name: name.to_string(), passed: response.is_some(), skipped: false, response: response.ok_or_else(|| "Timed out".to_string()) }
+    }
+
+    fn run(finished_ms: i64, results: Vec<RecordedResult>) -> RunRecord {
+        RunRecord { finished_ms, results }
+    }
+
+    fn elapsed(cell: &Option<RecordedResult>) -> Option<u128> {
+        cell.as_ref()?.response.as_ref().ok().map(|res| res.elapsed_ms)
+    }
+
+    #[test]
+    fn run_history_keeps_the_start_of_long_bodies() {
+        let mut res = result("a.req", Some(5)).response.unwrap();
+        // The cut falls inside a two-byte character, which is left out
+        res.body = format!("x{}", "é".repeat(RECORDED_BODY_BYTES));
+        let kept = recorded(res.clone());
+        assert_eq!(kept.body.len(), RECORDED_BODY_BYTES - 1);
+        assert!(res.body.starts_with(&kept.body));
+        assert_eq!(kept.warnings, ["Only the first 16 KB of the body was kept in the run history"]);
+        assert_eq!((kept.status, kept.elapsed_ms), (200, 5));
+
+        res.body = "x".repeat(RECORDED_BODY_BYTES);
+        assert_eq!(recorded(res.clone()), res);
+    }
+
+    #[test]
+    fn durations_fall_in_buckets_by_upper_bound() {
+        assert_eq!(duration_bucket(0), 0);
+        assert_eq!(duration_bucket(200), 0);
+        assert_eq!(duration_bucket(201), 1);
+        assert_eq!(duration_bucket(1000), 2);
+        assert_eq!(duration_bucket(3000), 3);
+        assert_eq!(duration_bucket(3001), 4);
+        assert_eq!(duration_bucket(u128::MAX), DURATION_BUCKETS_MS.len());
+    }
+
+    #[test]
+    fn heatmap_keeps_requests_added_and_removed_between_runs() {
+        let records = [
+            run(1, vec![result("login", Some(120)), result("old", Some(900))]),
+            run(2, vec![result("login", Some(150)), result("old", None), result("gone", Some(40))]),
+            run(3, vec![result("new", Some(2500)), result("login", Some(180))]),
+        ];
+        let map = heatmap(&records);
+        assert_eq!(map.runs, [1, 2, 3]);
+        let names: Vec<&str> = map.rows.iter().map(|row| row.name.as_str()).collect();
+        // Latest run in its order, then the dropped, most recently run first
+        assert_eq!(names, ["new", "login", "old", "gone"]);
+
+        let row = |name: &str| map.rows.iter().find(|row| row.name == name).unwrap();
+        assert_eq!(row("new").cells.iter().map(elapsed).collect::<Vec<_>>(), [None, None, Some(2500)]);
+        assert_eq!(row("login").cells.iter().map(elapsed).collect::<Vec<_>>(), [Some(120), Some(150), Some(180)]);
+        assert!(!row("new").dropped && !row("login").dropped);
+
+        let old = row("old");
+        assert!(old.dropped);
+        assert_eq!(old.cells[0].as_ref().map(|cell| cell.passed), Some(true));
+        assert_eq!(old.cells[1].as_ref().map(|cell| cell.response.clone()), Some(Err("Timed out".to_string())));
+        assert!(old.cells[2].is_none());
+        assert_eq!(row("gone").cells.iter().map(|cell| cell.is_some()).collect::<Vec<_>>(), [false, true, false]);
+    }
+
+    #[test]
+    fn heatmap_of_no_runs_is_empty() {
+        assert_eq!(heatmap(&[]), Heatmap::default());
+    }
+}
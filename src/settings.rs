@@ -0,0 +1,239 @@
+use std::fs;
+use std::path::PathBuf;
+use std::collections::HashMap;
+use crate::backend::BackendKind;
+use crate::client::{self, BodyLimit};
+use crate::environment::{self, Resolver};
+use crate::redact::Redactor;
+use crate::storage::{RequestOptions, StatusSet, get_base_dir};
+use crate::runner::{ErrorPolicy, PinnedPolicy};
+use crate::viewer::ViewOverride;
+
+/// Application-wide preferences persisted as `settings.json` in the base dir.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Whether `{{env:NAME}}` placeholders may read the OS environment.
+    pub allow_os_env: bool,
+    /// Height of the response panel in pixels.
+    pub response_height: f64,
+    /// Font size in pixels of the request body editor and response body.
+    pub body_font_size: u32,
+    /// Request timeout in milliseconds used when a request sets none.
+    pub default_timeout_ms: u64,
+    /// Retry count used when a request sets none.
+    pub default_retries: u32,
+    /// Redirect limit used when a request sets none.
+    pub default_max_redirects: u32,
+    /// Scheme (`https` or `http`) prepended to URLs typed without one.
+    pub default_scheme: String,
+    /// Where this workspace keeps its requests; read once at startup.
+    pub storage_backend: BackendKind,
+    /// Methods that need confirming in a dialog before they are sent.
+    pub confirm_methods: Vec<String>,
+    /// Environments where sending `protected_methods` always asks first and
+    /// folder runs refuse to send.
+    pub protected_environments: Vec<String>,
+    pub protected_methods: Vec<String>,
+    /// Offer to import a copied curl command or URL when the window gets focus.
+    pub watch_clipboard: bool,
+    /// Show history and run times in UTC instead of local time.
+    pub utc_times: bool,
+    /// Content type patterns mapped to the view their responses open in,
+    /// checked before the built-in mapping.
+    pub view_overrides: Vec<ViewOverride>,
+    /// How much of a response body is read, per content type pattern.
+    pub body_limits: Vec<BodyLimit>,
+    /// Named sets of headers that can be added to a request in one go.
+    pub header_presets: Vec<HeaderPreset>,
+    /// What folder runs do after a request gets no response, unless the
+    /// request sets its own `on-error`.
+    pub run_error_policy: ErrorPolicy,
+    /// What folder runs do with requests pinned to other environments.
+    pub run_pinned_policy: PinnedPolicy,
+    /// Environment whose variables requests are sent with; `None` sends
+    /// them with none.
+    pub active_environment: Option<String>,
+    /// Requests sent to the same host at once; 0 for no limit. Others wait
+    /// in line, while requests to other hosts go ahead.
+    pub per_host_limit: u32,
+    /// `ip:port` of the DNS server requests resolve host names through
+    /// unless they set their own; empty uses the system resolver.
+    pub dns_server: String,
+    /// Folder recorded fixtures are written to; empty uses `fixtures` in
+    /// the base dir.
+    pub fixtures_dir: String,
+    /// Redaction rules for privacy mode, one JSONPath selector or regex each.
+    pub redact_rules: Vec<String>,
+    /// Also apply the redaction rules to what is written to history, fixtures
+    /// and exports, not just to what privacy mode shows.
+    pub redact_persisted: bool,
+    /// Largest body a body template may expand to, in megabytes, unless the
+    /// request sets its own `template-max`.
+    pub template_max_mb: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            allow_os_env: true,
+            response_height: 300.0,
+            body_font_size: 13,
+            default_timeout_ms: 30_000,
+            default_retries: 0,
+            default_max_redirects: 10,
+            default_scheme: "https".to_string(),
+            storage_backend: BackendKind::Files,
+            confirm_methods: Vec::new(),
+            protected_environments: Vec::new(),
+            protected_methods: ["PUT", "PATCH", "DELETE"].map(str::to_string).to_vec(),
+            watch_clipboard: false,
+            utc_times: false,
+            view_overrides: Vec::new(),
+            body_limits: client::default_body_limits(),
+            header_presets: Vec::new(),
+            run_error_policy: ErrorPolicy::Continue,
+            run_pinned_policy: PinnedPolicy::Switch,
+            active_environment: None,
+            per_host_limit: 0,
+            dns_server: String::new(),
+            fixtures_dir: String::new(),
+            redact_rules: Vec::new(),
+            redact_persisted: false,
+            template_max_mb: 16,
+        }
+    }
+}
+
+impl Settings {
+    fn path() -> PathBuf {
+        get_base_dir().join("settings.json")
+    }
+
+    /// Loads the settings, falling back to defaults if the file is missing or unreadable.
+    pub fn load() -> Self {
+        fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn fixtures_dir(&self) -> PathBuf {
+        match self.fixtures_dir.trim() {
+            "" => get_base_dir().join("fixtures"),
+            dir => PathBuf::from(dir),
+        }
+    }
+
+    pub fn is_protected(&self, environment: Option<&str>) -> bool {
+        environment.is_some_and(|env| self.protected_environments.iter().any(|p| p == env))
+    }
+
+    /// Whether sending `method` with the active environment asks first
+    /// because the environment is protected.
+    pub fn protects(&self, method: &str) -> bool {
+        self.is_protected(self.active_environment.as_deref())
+            && self.protected_methods.iter().any(|m| m.eq_ignore_ascii_case(method))
+    }
+
+    pub fn redactor(&self) -> Redactor {
+        Redactor::new(&self.redact_rules).0
+    }
+
+    /// Global values that request options fall back to.
+    pub fn request_defaults(&self) -> RequestOptions {
+        RequestOptions {
+            accepted_status: Some(StatusSet::default_success()),
+            timeout_ms: Some(self.default_timeout_ms),
+            retries: Some(self.default_retries),
+            max_redirects: Some(self.default_max_redirects),
+            insecure_tls: Some(false),
+            default_scheme: Some(self.default_scheme.clone()),
+            dns_server: (!self.dns_server.trim().is_empty()).then(|| self.dns_server.trim().to_string()),
+            template_max_mb: Some(self.template_max_mb),
+            ..Default::default()
+        }
+    }
+
+    /// The resolver used to prepare requests for sending with these settings.
+    /// Hooks and OS environment access stay off until the workspace is trusted.
+    pub fn resolver(&self, vars: HashMap<String, String>) -> Resolver {
+        self.resolver_with_trust(vars, crate::workspace::workspace_trusted())
+    }
+
+    /// The resolver with the variables of the active environment.
+    pub fn active_resolver(&self) -> Result<Resolver, String> {
+        let vars = self.active_environment.as_deref().map(environment::load_environment).transpose()?;
+        Ok(Resolver { environment: self.active_environment.clone(), ..self.resolver(vars.unwrap_or_default()) })
+    }
+
+    pub fn resolver_with_trust(&self, vars: HashMap<String, String>, trusted: bool) -> Resolver {
+        Resolver {
+            vars,
+            environment: None,
+            allow_os_env: self.allow_os_env && trusted,
+            // The CLI opts in with --allow-hooks
+            allow_hooks: trusted,
+            untrusted: !trusted,
+            defaults: self.request_defaults(),
+        }
+    }
+
+    /// Adds `preset`, replacing the one with the same name.
+    pub fn save_header_preset(&mut self, preset: HeaderPreset) {
+        match self.header_presets.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => self.header_presets.push(preset),
+        }
+    }
+
+    pub fn change_body_font_size(&mut self, delta: i32) {
+        self.body_font_size = self.body_font_size.saturating_add_signed(delta).clamp(8, 32);
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        crate::workspace::ensure_writable()?;
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        fs::write(path, content)
+    }
+}
+
+/// A named set of header rows. Values may contain placeholders, which are
+/// expanded when the request is sent like any other header.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HeaderPreset {
+    pub name: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl HeaderPreset {
+    /// `headers` with the preset's rows appended, leaving out rows that
+    /// are already there with the same name and value.
+    pub fn apply(&self, headers: &[(String, String)]) -> Vec<(String, String)> {
+        let mut applied: Vec<(String, String)> = headers.iter().filter(|(k, _)| !k.is_empty()).cloned().collect();
+        for (name, value) in &self.headers {
+            if !applied.iter().any(|(k, v)| k.eq_ignore_ascii_case(name) && v == value) {
+                applied.push((name.clone(), value.clone()));
+            }
+        }
+        applied
+    }
+
+    /// The rows as `Name: value` lines, for editing as text.
+    pub fn to_text(&self) -> String {
+        self.headers.iter().map(|(k, v)| format!("{}: {}\n", k, v)).collect()
+    }
+
+    /// Parses `Name: value` lines; blank lines and lines without a colon are skipped.
+    pub fn parse_text(text: &str) -> Vec<(String, String)> {
+        text.lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+            .filter(|(k, _)| !k.is_empty())
+            .collect()
+    }
+}
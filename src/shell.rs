@@ -0,0 +1,86 @@
+/// Splits `line` into words the way a POSIX shell would: whitespace separates
+/// words, single quotes keep everything literally, double quotes allow `\"`,
+/// `\\`, `\$` and `` \` `` escapes, and an unquoted backslash escapes the next
+/// character. A backslash before a line break continues the line. Bash's
+/// `$'...'` quoting, which browsers use for bodies with line breaks, is
+/// understood too.
+pub fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    // Bytes of the `$'...'` string being read; `\xNN` escapes spell out
+    // UTF-8 sequences, so it is decoded once it closes
+    let mut ansi = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('$'), '\'') => {
+                quote = None;
+                let text = String::from_utf8_lossy(&ansi).into_owned();
+                word.get_or_insert_with(String::new).push_str(&text);
+                ansi.clear();
+            }
+            (Some('$'), '\\') => {
+                let escaped = chars.next().ok_or("Command ends with a backslash")?;
+                match escaped {
+                    'n' => ansi.push(b'\n'),
+                    'r' => ansi.push(b'\r'),
+                    't' => ansi.push(b'\t'),
+                    '0' => ansi.push(0),
+                    'x' => {
+                        let mut hex = String::new();
+                        while hex.len() < 2 && let Some(digit) = chars.next_if(char::is_ascii_hexdigit) {
+                            hex.push(digit);
+                        }
+                        ansi.push(u8::from_str_radix(&hex, 16).unwrap_or(b'x'));
+                    }
+                    c => ansi.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+                }
+            }
+            (Some('$'), c) => ansi.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            (None, '$') if chars.peek() == Some(&'\'') => {
+                chars.next();
+                quote = Some('$');
+                word.get_or_insert_with(String::new);
+            }
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), c) => word.get_or_insert_with(String::new).push(c),
+            (_, '\\') => {
+                let escaped = chars.next().ok_or("Command ends with a backslash")?;
+                if escaped == '\r' {
+                    chars.next_if_eq(&'\n');
+                } else if escaped != '\n' {
+                    let word = word.get_or_insert_with(String::new);
+                    if quote == Some('"') && !matches!(escaped, '"' | '\\' | '$' | '`') {
+                        word.push('\\');
+                    }
+                    word.push(escaped);
+                }
+            }
+            (Some(_), '"') => quote = None,
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err("Command has an unclosed quote".to_string());
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ansi_c_hex_escapes_are_utf8_bytes() {
+        assert_eq!(split_words("a $'\\xc3\\xa9' x$'\\x41'y").unwrap(), vec!["a", "é", "xAy"]);
+        assert_eq!(split_words("$'\\xff\\tok'").unwrap(), vec!["\u{fffd}\tok"]);
+    }
+}
@@ -0,0 +1,279 @@
+use crate::storage::HttpRequest;
+
+/// Marks where the request body goes in an envelope template.
+pub const BODY_MARKER: &str = "${body}";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SoapVersion {
+    V11,
+    V12,
+}
+
+impl SoapVersion {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.trim() {
+            "1.1" => Ok(SoapVersion::V11),
+            "1.2" => Ok(SoapVersion::V12),
+            other => Err(format!("Unknown SOAP version '{}'; expected 1.1 or 1.2", other)),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SoapVersion::V11 => "1.1",
+            SoapVersion::V12 => "1.2",
+        }
+    }
+
+    fn namespace(self) -> &'static str {
+        match self {
+            SoapVersion::V11 => "http://schemas.xmlsoap.org/soap/envelope/",
+            SoapVersion::V12 => "http://www.w3.org/2003/05/soap-envelope",
+        }
+    }
+}
+
+/// Wraps the body in a SOAP envelope at send time. Stored as the `# soap:`,
+/// `# soap-action:` and (when customized) `# soap-envelope:` lines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoapEnvelope {
+    pub version: SoapVersion,
+    pub action: String,
+    /// Custom envelope with a `${body}` marker; `None` uses the standard one.
+    /// `{{...}}` placeholders in it are expanded like the rest of the request.
+    pub template: Option<String>,
+}
+
+impl SoapEnvelope {
+    pub fn new(version: SoapVersion) -> Self {
+        SoapEnvelope { version, action: String::new(), template: None }
+    }
+
+    pub fn default_template(version: SoapVersion) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<soap:Envelope xmlns:soap=\"{}\">\n  <soap:Header/>\n  <soap:Body>\n{}\n  </soap:Body>\n</soap:Envelope>",
+            version.namespace(),
+            BODY_MARKER,
+        )
+    }
+
+    pub fn template(&self) -> String {
+        self.template.clone().unwrap_or_else(|| Self::default_template(self.version))
+    }
+}
+
+/// `req` with its body wrapped in the envelope and the SOAP headers added,
+/// unless the request already sets them. Requests without SOAP are returned
+/// unchanged.
+pub fn wrap(req: &HttpRequest) -> HttpRequest {
+    let mut wrapped = req.clone();
+    let Some(soap) = wrapped.soap.take() else { return wrapped };
+    wrapped.body = soap.template().replace(BODY_MARKER, req.body.trim_end());
+
+    let has = |headers: &[(String, String)], name: &str| headers.iter().any(|(k, _)| k.eq_ignore_ascii_case(name));
+    if !has(&wrapped.headers, "Content-Type") {
+        let content_type = match soap.version {
+            SoapVersion::V11 => "text/xml; charset=utf-8".to_string(),
+            SoapVersion::V12 if soap.action.is_empty() => "application/soap+xml; charset=utf-8".to_string(),
+            SoapVersion::V12 => format!("application/soap+xml; charset=utf-8; action=\"{}\"", soap.action),
+        };
+        wrapped.headers.push(("Content-Type".to_string(), content_type));
+    }
+    if soap.version == SoapVersion::V11 && !has(&wrapped.headers, "SOAPAction") {
+        wrapped.headers.push(("SOAPAction".to_string(), format!("\"{}\"", soap.action)));
+    }
+    wrapped
+}
+
+/// The contents of the `Body` element of a SOAP envelope, if `xml` is one.
+pub fn envelope_body(xml: &str) -> Option<&str> {
+    let open = find_tag(xml, "Body", false)?;
+    let content_start = open + xml[open..].find('>')? + 1;
+    if xml[..content_start].ends_with("/>") {
+        return Some("");
+    }
+    let close = content_start + find_tag(&xml[content_start..], "Body", true)?;
+    Some(&xml[content_start..close])
+}
+
+/// Byte offset of the first `<Body` / `<prefix:Body` (or closing) tag.
+fn find_tag(xml: &str, local_name: &str, closing: bool) -> Option<usize> {
+    let mut from = 0;
+    while let Some(pos) = xml[from..].find('<') {
+        let start = from + pos;
+        let rest = &xml[start + 1..];
+        let rest = if closing { rest.strip_prefix('/') } else { Some(rest) };
+        if let Some(rest) = rest {
+            let name_end = rest.find(|c: char| c.is_whitespace() || c == '>' || c == '/').unwrap_or(rest.len());
+            let name = &rest[..name_end];
+            if name.rsplit(':').next() == Some(local_name) && !name.starts_with('/') {
+                return Some(start);
+            }
+        }
+        from = start + 1;
+    }
+    None
+}
+
+/// Indents `xml` one element per line. Elements holding only text stay on
+/// one line. Anything that does not look like XML is returned as is.
+pub fn pretty_xml(xml: &str) -> String {
+    let tokens = tokenize(xml.trim());
+    if tokens.is_empty() || !tokens.iter().any(|t| matches!(t, Token::Open(_) | Token::SelfClosing(_))) {
+        return xml.to_string();
+    }
+    let mut out = String::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < tokens.len() {
+        let indent = "  ".repeat(depth);
+        match &tokens[i] {
+            Token::Open(tag) => {
+                // <a>text</a> on one line
+                if let (Some(Token::Text(text)), Some(Token::Close(close))) = (tokens.get(i + 1), tokens.get(i + 2)) {
+                    out.push_str(&format!("{}{}{}{}\n", indent, tag, text, close));
+                    i += 3;
+                    continue;
+                }
+                if let Some(Token::Close(close)) = tokens.get(i + 1) {
+                    out.push_str(&format!("{}{}{}\n", indent, tag, close));
+                    i += 2;
+                    continue;
+                }
+                out.push_str(&format!("{}{}\n", indent, tag));
+                depth += 1;
+            }
+            Token::Close(tag) => {
+                depth = depth.saturating_sub(1);
+                out.push_str(&format!("{}{}\n", "  ".repeat(depth), tag));
+            }
+            Token::SelfClosing(tag) | Token::Other(tag) | Token::Text(tag) => {
+                out.push_str(&format!("{}{}\n", indent, tag));
+            }
+        }
+        i += 1;
+    }
+    out.trim_end().to_string()
+}
+
+enum Token<'a> {
+    Open(&'a str),
+    Close(&'a str),
+    SelfClosing(&'a str),
+    /// Declarations, comments, CDATA and processing instructions.
+    Other(&'a str),
+    Text(&'a str),
+}
+
+fn tokenize(xml: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = xml;
+    while !rest.is_empty() {
+        if !rest.starts_with('<') {
+            let end = rest.find('<').unwrap_or(rest.len());
+            let text = rest[..end].trim();
+            if !text.is_empty() {
+                tokens.push(Token::Text(text));
+            }
+            rest = &rest[end..];
+            continue;
+        }
+        let terminator = if rest.starts_with("<!--") {
+            "-->"
+        } else if rest.starts_with("<![CDATA[") {
+            "]]>"
+        } else {
+            ">"
+        };
+        let end = rest.find(terminator).map_or(rest.len(), |e| e + terminator.len());
+        let tag = &rest[..end];
+        tokens.push(if tag.starts_with("</") {
+            Token::Close(tag)
+        } else if tag.starts_with("<!") || tag.starts_with("<?") {
+            Token::Other(tag)
+        } else if tag.ends_with("/>") {
+            Token::SelfClosing(tag)
+        } else {
+            Token::Open(tag)
+        });
+        rest = &rest[end..];
+    }
+    tokens
+}
+
+/// Kinds of XML text, for syntax highlighting.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum XmlPart {
+    Tag,
+    Attr,
+    Value,
+    Comment,
+    Text,
+}
+
+impl XmlPart {
+    pub fn class(self) -> &'static str {
+        match self {
+            XmlPart::Tag => "xml-tag",
+            XmlPart::Attr => "xml-attr",
+            XmlPart::Value => "xml-value",
+            XmlPart::Comment => "xml-comment",
+            XmlPart::Text => "xml-text",
+        }
+    }
+}
+
+/// Splits `xml` into highlighted runs.
+pub fn highlight(xml: &str) -> Vec<(XmlPart, String)> {
+    let mut parts: Vec<(XmlPart, String)> = Vec::new();
+    let mut push = |part: XmlPart, text: &str| {
+        if text.is_empty() {
+            return;
+        }
+        match parts.last_mut() {
+            Some((last, run)) if *last == part => run.push_str(text),
+            _ => parts.push((part, text.to_string())),
+        }
+    };
+    let mut rest = xml;
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            push(XmlPart::Text, rest);
+            break;
+        };
+        push(XmlPart::Text, &rest[..start]);
+        rest = &rest[start..];
+        if rest.starts_with("<!--") || rest.starts_with("<![CDATA[") || rest.starts_with("<?") {
+            let terminator = if rest.starts_with("<!--") { "-->" } else if rest.starts_with("<?") { "?>" } else { "]]>" };
+            let end = rest.find(terminator).map_or(rest.len(), |e| e + terminator.len());
+            push(XmlPart::Comment, &rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+        let end = rest.find('>').map_or(rest.len(), |e| e + 1);
+        let tag = &rest[..end];
+        rest = &rest[end..];
+        // Tag name, then name="value" pairs
+        let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        push(XmlPart::Tag, &tag[..name_end]);
+        let mut attrs = &tag[name_end..];
+        while !attrs.is_empty() {
+            if let Some(quote) = attrs.strip_prefix(['"', '\'']).and(attrs.chars().next()) {
+                let close = attrs[1..].find(quote).map_or(attrs.len(), |e| e + 2);
+                push(XmlPart::Value, &attrs[..close]);
+                attrs = &attrs[close..];
+            } else if attrs.starts_with(['>', '/', '?', '=']) || attrs.starts_with(char::is_whitespace) {
+                let next = attrs.chars().next().map_or(1, char::len_utf8);
+                let part = if attrs.starts_with(['>', '/', '?']) { XmlPart::Tag } else { XmlPart::Attr };
+                push(part, &attrs[..next]);
+                attrs = &attrs[next..];
+            } else {
+                let end = attrs.find(|c: char| c == '=' || c == '>' || c == '/' || c.is_whitespace()).unwrap_or(attrs.len());
+                let end = end.max(1);
+                push(XmlPart::Attr, &attrs[..end]);
+                attrs = &attrs[end..];
+            }
+        }
+    }
+    parts
+}
@@ -1,29 +1,513 @@
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 use directories::UserDirs;
+use crate::assertions::Assertion;
+use crate::auth::Auth;
+use crate::backend::{self, FsBackend, StorageBackend};
+use crate::client::BodyCompression;
+use crate::dns;
+use crate::hooks::Hook;
+use crate::poll::{PollUntil, StatusUrl};
+use crate::runner::{Capture, ErrorPolicy};
+use crate::soap::{SoapEnvelope, SoapVersion};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct HttpRequest {
+    /// Stable identity from the `# id:` line, assigned on first save. It
+    /// survives renames and moves, unlike the path.
+    pub id: Option<String>,
+    /// Display title from the `# name:` line; the file name is used when unset.
+    pub name: Option<String>,
     pub method: String,
     pub url: String,
     pub headers: Vec<(String, String)>,
     pub body: String,
+    pub options: RequestOptions,
+    /// Named response transforms, stored as `# view: NAME = EXPRESSION` lines.
+    pub views: Vec<(String, String)>,
+    /// Paths of base64 (optionally gzipped) fields in a JSON response that
+    /// are shown decoded, stored as `# decode: PATH` lines.
+    pub decode_fields: Vec<String>,
+    /// Id of the viewer the response is shown in, chosen by hand and stored
+    /// as a `# body-view:` line; unset picks one from the content type.
+    pub body_view: Option<String>,
+    /// SOAP envelope the body is wrapped in when sent.
+    pub soap: Option<SoapEnvelope>,
+}
+
+/// Per-request settings stored as `# key: value` lines at the top of the file.
+/// Unset values are inherited from the global defaults at send time.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequestOptions {
+    /// Statuses counted as success when no assertions decide the outcome.
+    /// `None` means the default of 2xx and 3xx.
+    pub accepted_status: Option<StatusSet>,
+    /// An `accept-status` line of the file that does not parse, as written
+    /// and with what is wrong with it. The default applies until it is
+    /// fixed; saving keeps the text.
+    pub invalid_accept_status: Option<(String, String)>,
+    /// Send `Expect: 100-continue` with a request that has a body, and hold
+    /// the body back until the server asks for it.
+    pub expect_continue: bool,
+    /// Time allowed for the whole request, in milliseconds.
+    pub timeout_ms: Option<u64>,
+    /// Time allowed for establishing the connection, in milliseconds.
+    pub connect_timeout_ms: Option<u64>,
+    /// Longest wait for the next chunk of the response, in milliseconds.
+    pub read_timeout_ms: Option<u64>,
+    /// Extra attempts after a transport error; error statuses are not retried.
+    pub retries: Option<u32>,
+    /// Redirects followed before giving up; 0 disables following them.
+    pub max_redirects: Option<u32>,
+    /// Keep sending credentials when a redirect leads to another origin;
+    /// unset means they are left out.
+    pub redirect_credentials: Option<bool>,
+    /// `none` to bypass proxies or a proxy URL; unset uses the system proxy.
+    pub proxy: Option<String>,
+    /// `system`, or the `ip:port` of a DNS server to resolve host names
+    /// through; unset uses the system resolver.
+    pub dns_server: Option<String>,
+    /// Accept invalid TLS certificates and host names.
+    pub insecure_tls: Option<bool>,
+    /// `http` or `https`, prepended to URLs typed without a scheme.
+    pub default_scheme: Option<String>,
+    /// Encoding of the body on the wire; unset uses the `Content-Type` charset, then UTF-8.
+    pub body_charset: Option<String>,
+    /// Expand the body's `{{#repeat}}` and `{{#range}}` blocks when sending;
+    /// the file keeps the template. See [`body_template`](crate::body_template).
+    pub body_template: bool,
+    /// Largest body a template may expand to, in megabytes.
+    pub template_max_mb: Option<u64>,
+    /// Pause before sending in folder runs and repeats, in milliseconds.
+    pub delay_before_ms: Option<u64>,
+    /// Pause after the response arrives in folder runs and repeats, in milliseconds.
+    pub delay_after_ms: Option<u64>,
+    /// Command line run before the request is prepared; see [`Hook`].
+    pub hook: Option<String>,
+    /// Time allowed for the hook, in milliseconds.
+    pub hook_timeout_ms: Option<u64>,
+    /// Keep polling a status URL after the response arrives; see [`poll`](crate::poll).
+    pub poll: Option<StatusUrl>,
+    pub poll_until: Option<PollUntil>,
+    pub poll_interval_ms: Option<u64>,
+    /// Longest time to keep polling, in milliseconds.
+    pub poll_max_ms: Option<u64>,
+    /// Credentials to send; unset inherits the folder's, see [`Auth`].
+    pub auth: Option<Auth>,
+    /// Compress the body with this `Content-Encoding` when sending; the
+    /// file keeps it uncompressed.
+    pub compress: Option<BodyCompression>,
+    /// What a folder run does when this request gets no response; unset
+    /// uses the run's policy.
+    pub on_error: Option<ErrorPolicy>,
+    /// Variables taken from the JSON response in folder runs, for the
+    /// requests after this one; not inherited.
+    pub captures: Vec<Capture>,
+    /// Checks the response must pass; when there are any they decide the
+    /// outcome instead of `accepted_status`. Not inherited.
+    pub assertions: Vec<Assertion>,
+    /// Names of assertion sets defined in `.folder.json` files whose checks
+    /// are added to `assertions`. Not inherited.
+    pub assert_sets: Vec<String>,
+    /// Environments the request may be sent to; empty allows any. Not
+    /// inherited.
+    pub environments: Vec<String>,
+    /// `false` sends without asking even when the active environment is
+    /// protected. Not inherited.
+    pub confirm_protected: Option<bool>,
+    /// Send the cookie jar's cookies and keep the ones the response sets;
+    /// unset means yes. See [`CookieJar`](crate::cookies::CookieJar).
+    pub cookie_jar: Option<bool>,
+}
+
+pub const MAX_RETRIES: u32 = 10;
+
+/// Metadata keys of all request options, in the order they are written.
+pub const OPTION_KEYS: &[&str] = &[
+    "accept-status",
+    "timeout",
+    "connect-timeout",
+    "read-timeout",
+    "retries",
+    "max-redirects",
+    "redirect-credentials",
+    "proxy",
+    "dns",
+    "insecure",
+    "cookies",
+    "expect-continue",
+    "charset",
+    "template",
+    "template-max",
+    "compress",
+    "default-scheme",
+    "delay-before",
+    "delay-after",
+    "hook",
+    "hook-timeout",
+    "poll",
+    "poll-until",
+    "poll-interval",
+    "poll-max",
+    "auth",
+    "capture",
+    "assert",
+    "assert-set",
+    "on-error",
+    "env",
+    "confirm-protected",
+];
+
+impl RequestOptions {
+    pub fn accepts(&self, status: u16) -> bool {
+        match &self.accepted_status {
+            Some(set) => set.contains(status),
+            None => StatusSet::default_success().contains(status),
+        }
+    }
+
+    /// Sets the option `key` from its textual form; an empty value unsets it.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        match key {
+            "accept-status" => {
+                self.accepted_status = if value.is_empty() { None } else { Some(StatusSet::parse(value)?) };
+                self.invalid_accept_status = None;
+            }
+            "timeout" => self.timeout_ms = parse_timeout(value, "Timeout")?,
+            "connect-timeout" => self.connect_timeout_ms = parse_timeout(value, "Connect timeout")?,
+            "read-timeout" => self.read_timeout_ms = parse_timeout(value, "Read timeout")?,
+            "delay-before" => self.delay_before_ms = parse_count(value, "Delay before")?,
+            "delay-after" => self.delay_after_ms = parse_count(value, "Delay after")?,
+            "hook" => {
+                Hook::parse(value, None)?;
+                self.hook = (!value.is_empty()).then(|| value.to_string());
+            }
+            "hook-timeout" => self.hook_timeout_ms = parse_timeout(value, "Hook timeout")?,
+            "poll" => self.poll = if value.is_empty() { None } else { Some(StatusUrl::parse(value)?) },
+            "poll-until" => self.poll_until = if value.is_empty() { None } else { Some(PollUntil::parse(value)?) },
+            "poll-interval" => self.poll_interval_ms = parse_count(value, "Poll interval")?,
+            "poll-max" => self.poll_max_ms = parse_timeout(value, "Poll max")?,
+            "auth" => self.auth = if value.is_empty() { None } else { Some(Auth::parse(value)?) },
+            "compress" => self.compress = if value.is_empty() { None } else { Some(BodyCompression::parse(value)?) },
+            "on-error" => self.on_error = if value.is_empty() { None } else { Some(ErrorPolicy::parse(value)?) },
+            "capture" => self.captures = Capture::parse_list(value)?,
+            "assert" => self.assertions = Assertion::parse_list(value)?,
+            "assert-set" => {
+                self.assert_sets = value.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect();
+            }
+            "env" => {
+                self.environments = value.split(',').map(str::trim).filter(|e| !e.is_empty()).map(str::to_string).collect();
+            }
+            "confirm-protected" => self.confirm_protected = parse_flag(value)?,
+            "retries" => {
+                let retries = parse_count::<u32>(value, "Retries")?;
+                if retries.is_some_and(|r| r > MAX_RETRIES) {
+                    return Err(format!("Retries cannot exceed {}", MAX_RETRIES));
+                }
+                self.retries = retries;
+            }
+            "max-redirects" => self.max_redirects = parse_count(value, "Max redirects")?,
+            "redirect-credentials" => self.redirect_credentials = parse_flag(value)?,
+            "proxy" => {
+                let valid = value.is_empty()
+                    || value == "none"
+                    || ["http://", "https://", "socks5://"].iter().any(|p| value.starts_with(p));
+                if !valid {
+                    return Err("Proxy must be 'none' or an http://, https:// or socks5:// URL".to_string());
+                }
+                self.proxy = (!value.is_empty()).then(|| value.to_string());
+            }
+            "dns" => {
+                if !value.is_empty() && value != dns::SYSTEM {
+                    dns::parse_server(value)?;
+                }
+                self.dns_server = (!value.is_empty()).then(|| value.to_string());
+            }
+            "insecure" => self.insecure_tls = parse_flag(value)?,
+            "cookies" => self.cookie_jar = parse_flag(value)?,
+            "expect-continue" => self.expect_continue = parse_flag(value)?.unwrap_or(false),
+            "template" => self.body_template = parse_flag(value)?.unwrap_or(false),
+            "template-max" => self.template_max_mb = parse_timeout(value, "Template max")?,
+            "default-scheme" => {
+                if !["", "http", "https"].contains(&value) {
+                    return Err("Default scheme must be http or https".to_string());
+                }
+                self.default_scheme = (!value.is_empty()).then(|| value.to_string());
+            }
+            "charset" => {
+                if !value.is_empty() && encoding_rs::Encoding::for_label(value.as_bytes()).is_none() {
+                    return Err(format!("Unknown charset '{}'", value));
+                }
+                self.body_charset = (!value.is_empty()).then(|| value.to_string());
+            }
+            _ => return Err(format!("Unknown option: {}", key)),
+        }
+        Ok(())
+    }
+
+    /// Textual form of the option `key`, empty when unset.
+    pub fn get(&self, key: &str) -> String {
+        match key {
+            "accept-status" => match (&self.accepted_status, &self.invalid_accept_status) {
+                (Some(set), _) => set.to_string(),
+                (None, Some((text, _))) => text.clone(),
+                (None, None) => String::new(),
+            },
+            "timeout" => self.timeout_ms.map(|t| t.to_string()).unwrap_or_default(),
+            "connect-timeout" => self.connect_timeout_ms.map(|t| t.to_string()).unwrap_or_default(),
+            "read-timeout" => self.read_timeout_ms.map(|t| t.to_string()).unwrap_or_default(),
+            "delay-before" => self.delay_before_ms.map(|t| t.to_string()).unwrap_or_default(),
+            "delay-after" => self.delay_after_ms.map(|t| t.to_string()).unwrap_or_default(),
+            "hook" => self.hook.clone().unwrap_or_default(),
+            "hook-timeout" => self.hook_timeout_ms.map(|t| t.to_string()).unwrap_or_default(),
+            "poll" => self.poll.as_ref().map(|p| p.to_string()).unwrap_or_default(),
+            "poll-until" => self.poll_until.as_ref().map(|p| p.to_string()).unwrap_or_default(),
+            "poll-interval" => self.poll_interval_ms.map(|t| t.to_string()).unwrap_or_default(),
+            "poll-max" => self.poll_max_ms.map(|t| t.to_string()).unwrap_or_default(),
+            "auth" => self.auth.as_ref().map(|a| a.to_string()).unwrap_or_default(),
+            "compress" => self.compress.map(|c| c.as_str().to_string()).unwrap_or_default(),
+            "on-error" => self.on_error.map(|p| p.as_str().to_string()).unwrap_or_default(),
+            "capture" => self.captures.iter().map(|c| c.to_string()).collect::<Vec<_>>().join("; "),
+            "assert" => self.assertions.iter().map(|a| a.to_string()).collect::<Vec<_>>().join("; "),
+            "assert-set" => self.assert_sets.join(", "),
+            "env" => self.environments.join(", "),
+            "confirm-protected" => self.confirm_protected.map(|f| f.to_string()).unwrap_or_default(),
+            "retries" => self.retries.map(|r| r.to_string()).unwrap_or_default(),
+            "max-redirects" => self.max_redirects.map(|r| r.to_string()).unwrap_or_default(),
+            "redirect-credentials" => self.redirect_credentials.map(|f| f.to_string()).unwrap_or_default(),
+            "proxy" => self.proxy.clone().unwrap_or_default(),
+            "dns" => self.dns_server.clone().unwrap_or_default(),
+            "insecure" => self.insecure_tls.map(|f| f.to_string()).unwrap_or_default(),
+            "cookies" => self.cookie_jar.map(|f| f.to_string()).unwrap_or_default(),
+            "expect-continue" => if self.expect_continue { "true".to_string() } else { String::new() },
+            "template" => if self.body_template { "true".to_string() } else { String::new() },
+            "template-max" => self.template_max_mb.map(|m| m.to_string()).unwrap_or_default(),
+            "charset" => self.body_charset.clone().unwrap_or_default(),
+            "default-scheme" => self.default_scheme.clone().unwrap_or_default(),
+            _ => String::new(),
+        }
+    }
+
+    /// What is wrong with the option `key` as the file has it, when it was
+    /// loaded without it.
+    pub fn load_error(&self, key: &str) -> Option<String> {
+        match key {
+            "accept-status" => self.invalid_accept_status.as_ref()
+                .map(|(_, e)| format!("{}; 2xx and 3xx count until this is fixed", e)),
+            _ => None,
+        }
+    }
+
+    /// Returns these options with unset values taken from `defaults`.
+    pub fn inherit(&self, defaults: &RequestOptions) -> RequestOptions {
+        RequestOptions {
+            accepted_status: self.accepted_status.clone().or_else(|| defaults.accepted_status.clone()),
+            invalid_accept_status: self.invalid_accept_status.clone(),
+            expect_continue: self.expect_continue || defaults.expect_continue,
+            timeout_ms: self.timeout_ms.or(defaults.timeout_ms),
+            connect_timeout_ms: self.connect_timeout_ms.or(defaults.connect_timeout_ms),
+            read_timeout_ms: self.read_timeout_ms.or(defaults.read_timeout_ms),
+            retries: self.retries.or(defaults.retries),
+            max_redirects: self.max_redirects.or(defaults.max_redirects),
+            redirect_credentials: self.redirect_credentials.or(defaults.redirect_credentials),
+            proxy: self.proxy.clone().or_else(|| defaults.proxy.clone()),
+            dns_server: self.dns_server.clone().or_else(|| defaults.dns_server.clone()),
+            insecure_tls: self.insecure_tls.or(defaults.insecure_tls),
+            cookie_jar: self.cookie_jar.or(defaults.cookie_jar),
+            body_charset: self.body_charset.clone().or_else(|| defaults.body_charset.clone()),
+            body_template: self.body_template || defaults.body_template,
+            template_max_mb: self.template_max_mb.or(defaults.template_max_mb),
+            default_scheme: self.default_scheme.clone().or_else(|| defaults.default_scheme.clone()),
+            delay_before_ms: self.delay_before_ms.or(defaults.delay_before_ms),
+            delay_after_ms: self.delay_after_ms.or(defaults.delay_after_ms),
+            hook: self.hook.clone().or_else(|| defaults.hook.clone()),
+            hook_timeout_ms: self.hook_timeout_ms.or(defaults.hook_timeout_ms),
+            poll: self.poll.clone().or_else(|| defaults.poll.clone()),
+            poll_until: self.poll_until.clone().or_else(|| defaults.poll_until.clone()),
+            poll_interval_ms: self.poll_interval_ms.or(defaults.poll_interval_ms),
+            poll_max_ms: self.poll_max_ms.or(defaults.poll_max_ms),
+            auth: self.auth.clone().or_else(|| defaults.auth.clone()),
+            compress: self.compress.or(defaults.compress),
+            on_error: self.on_error.or(defaults.on_error),
+            captures: self.captures.clone(),
+            assertions: self.assertions.clone(),
+            assert_sets: self.assert_sets.clone(),
+            environments: self.environments.clone(),
+            confirm_protected: self.confirm_protected,
+        }
+    }
+
+    /// Whether the request is pinned to environments other than `active`;
+    /// sending with no environment counts as another one.
+    pub fn pinned_elsewhere(&self, active: Option<&str>) -> bool {
+        !self.environments.is_empty() && active.is_none_or(|active| !self.environments.iter().any(|e| e == active))
+    }
+}
+
+fn parse_count<T: std::str::FromStr>(value: &str, what: &str) -> Result<Option<T>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    if value.starts_with('-') {
+        return Err(format!("{} cannot be negative", what));
+    }
+    value.parse().map(Some).map_err(|_| format!("{} must be a whole number", what))
+}
+
+fn parse_timeout(value: &str, what: &str) -> Result<Option<u64>, String> {
+    let timeout = parse_count::<u64>(value, what)?;
+    if timeout == Some(0) {
+        return Err(format!("{} must be greater than 0", what));
+    }
+    Ok(timeout)
+}
+
+fn parse_flag(value: &str) -> Result<Option<bool>, String> {
+    match value {
+        "" => Ok(None),
+        "true" | "yes" => Ok(Some(true)),
+        "false" | "no" => Ok(Some(false)),
+        _ => Err(format!("Expected true or false, got '{}'", value)),
+    }
+}
+
+/// A list of status codes and ranges such as `2xx, 3xx, 404, 500-503`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusSet(Vec<(u16, u16)>);
+
+impl StatusSet {
+    pub fn default_success() -> Self {
+        StatusSet(vec![(200, 299), (300, 399)])
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut ranges = Vec::new();
+        for token in s.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            let lower = token.to_ascii_lowercase();
+            let range = if let Some((from, to)) = lower.split_once('-') {
+                let (from, _) = parse_status_bound(from.trim(), token)?;
+                let (_, to) = parse_status_bound(to.trim(), token)?;
+                if from > to {
+                    return Err(format!("Empty status range: {}", token));
+                }
+                (from, to)
+            } else {
+                parse_status_bound(&lower, token)?
+            };
+            if !(100..=599).contains(&range.0) || !(100..=599).contains(&range.1) {
+                return Err(format!("Status out of range: {}", token));
+            }
+            ranges.push(range);
+        }
+        if ranges.is_empty() {
+            return Err("No statuses given".to_string());
+        }
+        Ok(StatusSet(ranges))
+    }
+
+    pub fn contains(&self, status: u16) -> bool {
+        self.0.iter().any(|(from, to)| (*from..=*to).contains(&status))
+    }
+}
+
+/// The codes one end of a status range covers: `404` or a class such as `2xx`.
+fn parse_status_bound(s: &str, token: &str) -> Result<(u16, u16), String> {
+    if let Some(class) = s.strip_suffix("xx") {
+        let digit: u16 = class.parse().ok().filter(|d| (1..=5).contains(d))
+            .ok_or_else(|| format!("Invalid status class: {}", token))?;
+        return Ok((digit * 100, digit * 100 + 99));
+    }
+    let code = s.parse().map_err(|_| format!("Invalid status: {}", token))?;
+    Ok((code, code))
+}
+
+impl std::fmt::Display for StatusSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parts: Vec<String> = self.0.iter().map(|(from, to)| {
+            if from == to {
+                from.to_string()
+            } else if from % 100 == 0 && *to == from + 99 {
+                format!("{}xx", from / 100)
+            } else {
+                format!("{}-{}", from, to)
+            }
+        }).collect();
+        write!(f, "{}", parts.join(","))
+    }
 }
 
 impl HttpRequest {
     pub fn new() -> Self {
         Self {
+            id: None,
+            name: None,
             method: "GET".to_string(),
             url: "https://httpbin.org/get".to_string(),
             headers: Vec::new(),
             body: String::new(),
+            options: RequestOptions::default(),
+            views: Vec::new(),
+            decode_fields: Vec::new(),
+            body_view: None,
+            soap: None,
+        }
+    }
+
+    /// Checks for values that cannot be written unambiguously in the file format.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.method.is_empty() || self.method.contains(char::is_whitespace) {
+            return Err(format!("Invalid method '{}'", self.method));
         }
+        if let Some((name, _)) = self.headers.iter().find(|(k, _)| k.contains(':')) {
+            return Err(format!("Header name '{}' cannot contain ':'", name));
+        }
+        if let Some((name, _)) = self.views.iter().find(|(n, _)| n.contains('=')) {
+            return Err(format!("View name '{}' cannot contain '='", name));
+        }
+        Ok(())
     }
 
+    /// Serializes the request. Line breaks inside single-line fields are
+    /// written as `\n`/`\r` (and backslashes doubled) so they cannot split
+    /// the line; the body is written as is.
     pub fn to_http_string(&self) -> String {
-        let mut s = format!("{} {}\n", self.method, self.url);
+        let mut s = String::new();
+        if let Some(id) = &self.id {
+            s.push_str(&format!("# id: {}\n", id));
+        }
+        if let Some(name) = &self.name {
+            s.push_str(&format!("# name: {}\n", escape_line(name)));
+        }
+        for key in OPTION_KEYS {
+            let value = self.options.get(key);
+            if !value.is_empty() {
+                s.push_str(&format!("# {}: {}\n", key, value));
+            }
+        }
+        for (name, expression) in &self.views {
+            s.push_str(&format!("# view: {} = {}\n", escape_line(name), escape_line(expression)));
+        }
+        for path in &self.decode_fields {
+            s.push_str(&format!("# decode: {}\n", escape_line(path)));
+        }
+        if let Some(id) = &self.body_view {
+            s.push_str(&format!("# body-view: {}\n", id));
+        }
+        if let Some(soap) = &self.soap {
+            s.push_str(&format!("# soap: {}\n", soap.version.as_str()));
+            if !soap.action.is_empty() {
+                s.push_str(&format!("# soap-action: {}\n", escape_line(&soap.action)));
+            }
+            if let Some(template) = &soap.template {
+                s.push_str(&format!("# soap-envelope: {}\n", escape_line(template)));
+            }
+        }
+        s.push_str(&format!("{} {}\n", self.method, escape_line(&self.url)));
         for (k, v) in &self.headers {
-            s.push_str(&format!("{}: {}\n", k, v));
+            s.push_str(&format!("{}: {}\n", escape_line(k), escape_line(v)));
         }
         s.push_str("\n");
         s.push_str(&self.body);
@@ -31,14 +515,59 @@ impl HttpRequest {
     }
 
     pub fn from_http_string(s: &str) -> Result<Self, String> {
-        let mut lines = s.lines();
-        let first_line = lines.next().ok_or("Empty file")?;
-        let parts: Vec<&str> = first_line.split_whitespace().collect();
-        if parts.len() < 2 {
-            return Err("Invalid first line: search for 'METHOD URL'".to_string());
+        let mut lines = s.lines().peekable();
+        let mut options = RequestOptions::default();
+        let mut views = Vec::new();
+        let mut decode_fields = Vec::new();
+        let mut body_view = None;
+        let mut soap: Option<SoapEnvelope> = None;
+        let mut soap_action = String::new();
+        let mut soap_template = None;
+        let mut id = None;
+        let mut name = None;
+        while let Some(meta) = lines.next_if(|l| l.starts_with('#')) {
+            let Some((key, value)) = meta.trim_start_matches('#').split_once(':') else { continue };
+            let key = key.trim();
+            if key == "id" {
+                id = Some(value.trim().to_string()).filter(|id| !id.is_empty());
+            } else if key == "name" {
+                name = Some(unescape_line(value.trim())).filter(|n| !n.is_empty());
+            } else if key == "view" {
+                let (name, expression) = value.split_once('=').ok_or("view: expected 'NAME = EXPRESSION'")?;
+                views.push((unescape_line(name.trim()), unescape_line(expression.trim())));
+            } else if key == "decode" {
+                decode_fields.push(unescape_line(value.trim()));
+            } else if key == "body-view" {
+                body_view = Some(value.trim().to_string()).filter(|id| !id.is_empty());
+            } else if key == "soap" {
+                soap = Some(SoapEnvelope::new(SoapVersion::parse(value).map_err(|e| format!("soap: {}", e))?));
+            } else if key == "soap-action" {
+                soap_action = unescape_line(value.trim());
+            } else if key == "soap-envelope" {
+                soap_template = Some(unescape_line(value.trim()));
+            } else if key == "accept-status" {
+                // Only costs the request its own statuses, not the whole file
+                if let Err(e) = options.set(key, value) {
+                    options.invalid_accept_status = Some((value.trim().to_string(), e));
+                }
+            } else if OPTION_KEYS.contains(&key) {
+                options.set(key, value).map_err(|e| format!("{}: {}", key, e))?;
+            }
+        }
+        if let Some(soap) = &mut soap {
+            soap.action = soap_action;
+            soap.template = soap_template;
         }
-        let method = parts[0].to_uppercase();
-        let url = parts[1..].join(" ");
+        let first_line = lines.next().ok_or("Empty file")?;
+        // Only the method is split off; the rest is the URL as written, so
+        // tabs and runs of spaces in it survive a reload
+        let (method, url) = first_line.trim_start()
+            .split_once(char::is_whitespace)
+            .map(|(method, url)| (method, url.trim()))
+            .filter(|(_, url)| !url.is_empty())
+            .ok_or("Invalid first line: search for 'METHOD URL'")?;
+        let method = method.to_uppercase();
+        let url = unescape_line(url);
 
         let mut headers = Vec::new();
         let mut body = String::new();
@@ -51,17 +580,232 @@ impl HttpRequest {
             } else if line.trim().is_empty() {
                 reading_body = true;
             } else if let Some((k, v)) = line.split_once(':') {
-                headers.push((k.trim().to_string(), v.trim().to_string()));
+                headers.push((unescape_line(k.trim()), unescape_line(v.trim())));
             }
         }
 
         Ok(Self {
+            id,
+            name,
             method,
             url,
             headers,
             body: body.trim_end().to_string(),
+            options,
+            views,
+            decode_fields,
+            body_view,
+            soap,
+        })
+    }
+}
+
+/// One difference between two header lists, with indexes into the list it
+/// came from so it can be reverted.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeaderChange {
+    /// Present only in the new list, at `index`.
+    Added { index: usize, name: String, value: String },
+    /// Present only in the old list, at `index`.
+    Removed { index: usize, name: String, value: String },
+    /// Present in both with a different value (or name casing); `index` is in the new list.
+    Changed { index: usize, name: String, old: String, new: String },
+}
+
+impl HeaderChange {
+    /// Undoes this change in `headers`, the new list it was computed from.
+    pub fn revert(&self, headers: &mut Vec<(String, String)>) {
+        match self {
+            HeaderChange::Added { index, .. } => {
+                if *index < headers.len() {
+                    headers.remove(*index);
+                }
+            }
+            HeaderChange::Removed { index, name, value } => {
+                headers.insert((*index).min(headers.len()), (name.clone(), value.clone()));
+            }
+            HeaderChange::Changed { index, name, old, .. } => {
+                if let Some(header) = headers.get_mut(*index) {
+                    *header = (name.clone(), old.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Compares header lists structurally. Repeated names are paired by
+/// occurrence: the second `Accept` in `old` with the second in `new`.
+pub fn diff_headers(old: &[(String, String)], new: &[(String, String)]) -> Vec<HeaderChange> {
+    let occurrence = |list: &[(String, String)], i: usize| {
+        list[..i].iter().filter(|(k, _)| k.eq_ignore_ascii_case(&list[i].0)).count()
+    };
+    let find = |list: &[(String, String)], name: &str, nth: usize| {
+        list.iter().enumerate().filter(|(_, (k, _))| k.eq_ignore_ascii_case(name)).nth(nth).map(|(i, _)| i)
+    };
+
+    let mut changes = Vec::new();
+    for (i, (name, value)) in old.iter().enumerate() {
+        match find(new, name, occurrence(old, i)) {
+            None => changes.push(HeaderChange::Removed { index: i, name: name.clone(), value: value.clone() }),
+            Some(j) if new[j].0 != *name || new[j].1 != *value => changes.push(HeaderChange::Changed {
+                index: j,
+                name: name.clone(),
+                old: value.clone(),
+                new: new[j].1.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (j, (name, value)) in new.iter().enumerate() {
+        if find(old, name, occurrence(new, j)).is_none() {
+            changes.push(HeaderChange::Added { index: j, name: name.clone(), value: value.clone() });
+        }
+    }
+    changes
+}
+
+/// `url` split at its fragment, which is returned with its `#`.
+fn split_fragment(url: &str) -> (&str, &str) {
+    match url.find('#') {
+        Some(i) => (&url[..i], &url[i..]),
+        None => (url, ""),
+    }
+}
+
+/// Raw `name=value` pairs of the query string of `url`.
+fn query_pairs(url: &str) -> Vec<&str> {
+    let (url, _) = split_fragment(url);
+    url.split_once('?').map_or_else(Vec::new, |(_, query)| query.split('&').filter(|pair| !pair.is_empty()).collect())
+}
+
+fn decode_pair(pair: &str) -> (String, String) {
+    let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+    (percent_decode(name), percent_decode(value))
+}
+
+/// Query parameters of `url`, percent-decoded, in order. A `+` is kept as
+/// is rather than read as a space.
+pub fn parse_query(url: &str) -> Vec<(String, String)> {
+    query_pairs(url).into_iter().map(decode_pair).collect()
+}
+
+/// `url` with its query string made of `params`; rows with neither a name
+/// nor a value are left out, and so is the `?` when none remain. Parameters
+/// already in `url` keep their text as written, so signed URLs stay valid
+/// and `b=` stays apart from `c`; new and edited ones are percent-encoded,
+/// those without a value written as just the name.
+pub fn set_query(url: &str, params: &[(String, String)]) -> String {
+    let mut existing: Vec<Option<&str>> = query_pairs(url).into_iter().map(Some).collect();
+    let (url, fragment) = split_fragment(url);
+    let base = url.split_once('?').map_or(url, |(base, _)| base);
+    let query = params.iter()
+        .filter(|(name, value)| !name.is_empty() || !value.is_empty())
+        .map(|param| {
+            let unchanged = existing.iter_mut().find(|pair| pair.is_some_and(|pair| decode_pair(pair) == *param));
+            match unchanged.and_then(Option::take) {
+                Some(pair) => pair.to_string(),
+                // A flag such as `?debug`: no `=` for an empty value
+                None if param.1.is_empty() => encode_query(&param.0, true),
+                None => format!("{}={}", encode_query(&param.0, true), encode_query(&param.1, false)),
+            }
         })
+        .collect::<Vec<_>>()
+        .join("&");
+    if query.is_empty() {
+        format!("{}{}", base, fragment)
+    } else {
+        format!("{}?{}{}", base, query, fragment)
+    }
+}
+
+/// Percent-encodes what would change the meaning of a query string, and
+/// non-ASCII characters; `{{...}}` placeholders and `%XX` escapes are left
+/// intact.
+fn encode_query(s: &str, is_name: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with("{{")
+            && let Some(end) = rest.find("}}")
+        {
+            out.push_str(&rest[..end + 2]);
+            rest = &rest[end + 2..];
+            continue;
+        }
+        // An escape already there stays, e.g. one percent_decode could not decode
+        let escape = c == '%' && rest.get(1..3).is_some_and(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()));
+        let reserved = c.is_ascii_control() || " \"#&+<>".contains(c) || (c == '%' && !escape) || (is_name && c == '=');
+        if reserved || !c.is_ascii() {
+            let mut bytes = [0; 4];
+            for byte in c.encode_utf8(&mut bytes).bytes() {
+                out.push_str(&format!("%{:02X}", byte));
+            }
+        } else {
+            out.push(c);
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// Decodes `%XX` escapes; text that does not decode to UTF-8 is returned as is.
+fn percent_decode(s: &str) -> String {
+    if !s.contains('%') {
+        return s.to_string();
     }
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| s.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(out).unwrap_or_else(|_| s.to_string())
+}
+
+fn escape_line(s: &str) -> String {
+    if !s.contains(['\\', '\n', '\r']) {
+        return s.to_string();
+    }
+    s.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn unescape_line(s: &str) -> String {
+    if !s.contains('\\') {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('\\') => out.push('\\'),
+            // Anything else was not written by escape_line; keep it verbatim
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
 }
 
 pub fn get_base_dir() -> PathBuf {
@@ -80,43 +824,145 @@ pub fn ensure_base_dir() -> std::io::Result<()> {
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum FileNode {
-    File { name: String, path: PathBuf },
+    File { name: String, path: PathBuf, title: Option<String> },
     Folder { name: String, path: PathBuf, children: Vec<FileNode> },
 }
 
+/// The root folder with only its top level loaded; deeper folders are
+/// fetched on demand with `scan_children`.
 pub fn scan_directory() -> FileNode {
     let base_path = get_base_dir();
     if !base_path.exists() {
         let _ = fs::create_dir_all(&base_path);
     }
-    
-    build_tree(&base_path)
+
+    let name = base_path.file_name().and_then(|n| n.to_str()).unwrap_or("requester").to_string();
+    let children = list_children(&base_path);
+    FileNode::Folder { name, path: base_path, children }
 }
 
-fn build_tree(path: &Path) -> FileNode {
+/// Loads one folder level off the UI thread. Subfolders come back with no
+/// children.
+pub async fn scan_children(path: &Path) -> Vec<FileNode> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || list_children(&path)).await.unwrap_or_default()
+}
+
+/// The full tree under the folder `path`, walked eagerly.
+pub fn build_tree(path: &Path) -> FileNode {
     let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("requester").to_string();
-    if path.is_dir() {
-        let mut children = Vec::new();
-        if let Ok(entries) = fs::read_dir(path) {
-            for entry in entries.flatten() {
-                let p = entry.path();
-                if p.is_dir() || p.extension().map_or(false, |ext| ext == "req") {
-                    children.push(build_tree(&p));
-                }
-            }
+    let children = list_children(path).into_iter()
+        .map(|child| match child {
+            FileNode::Folder { path, .. } => build_tree(&path),
+            file => file,
+        })
+        .collect();
+    FileNode::Folder { name, path: path.to_path_buf(), children }
+}
+
+/// The folders and request files directly inside `path`.
+fn list_children(path: &Path) -> Vec<FileNode> {
+    let mut children = backend().scan(path);
+    // Folder templates are scaffolding, not requests
+    if path == get_base_dir() {
+        children.retain(|c| c.path() != crate::templates::templates_dir());
+    }
+    for child in &mut children {
+        if let FileNode::File { path, title, .. } = child {
+            *title = read_title(path);
         }
-        // Sort folders first, then files
-        children.sort_by(|a, b| {
-            match (a, b) {
-                (FileNode::Folder { .. }, FileNode::File { .. }) => std::cmp::Ordering::Less,
-                (FileNode::File { .. }, FileNode::Folder { .. }) => std::cmp::Ordering::Greater,
-                _ => a.name().cmp(b.name()),
-            }
+    }
+    // Sort folders first, then files
+    children.sort_by(|a, b| {
+        match (a, b) {
+            (FileNode::Folder { .. }, FileNode::File { .. }) => std::cmp::Ordering::Less,
+            (FileNode::File { .. }, FileNode::Folder { .. }) => std::cmp::Ordering::Greater,
+            _ => a.name().cmp(b.name()),
+        }
+    });
+    children
+}
+
+/// The `# name:` title of a request file, reading only its metadata lines.
+fn read_title(path: &Path) -> Option<String> {
+    read_meta(path, "name")
+}
+
+/// The `# id:` of a request file, reading only its metadata lines.
+pub fn read_id(path: &Path) -> Option<String> {
+    read_meta(path, "id")
+}
+
+fn read_meta(path: &Path, wanted: &str) -> Option<String> {
+    let content = backend().load(path).ok()?;
+    content.lines()
+        .take_while(|l| l.starts_with('#'))
+        .filter_map(|l| l.trim_start_matches('#').split_once(':'))
+        .find(|(key, _)| key.trim() == wanted)
+        .map(|(_, value)| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+pub fn new_request_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// Request IDs found under the base folder, mapped to the file holding each.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IdIndex {
+    pub paths: HashMap<String, PathBuf>,
+    /// Files that shared an ID with another file and were given a new one.
+    pub notices: Vec<String>,
+}
+
+impl IdIndex {
+    pub fn path_of(&self, id: &str) -> Option<&PathBuf> {
+        self.paths.get(id)
+    }
+}
+
+/// Walks every request file, recording its ID. When two files share an ID
+/// (usually a copy made outside the app) the newer one gets a fresh ID so
+/// references keep pointing at the original.
+pub fn index_ids() -> IdIndex {
+    let mut paths = Vec::new();
+    collect_files(&build_tree(&get_base_dir()), &mut paths);
+    // History still finds requests whose folder was archived
+    collect_files(&build_tree(&archive_dir()), &mut paths);
+    let mut files: Vec<(std::time::SystemTime, PathBuf, String)> = paths.into_iter()
+        .filter_map(|path| {
+            let id = read_id(&path)?;
+            let modified = backend().modified(&path)?;
+            Some((modified, path, id))
+        })
+        .collect();
+    files.sort();
+
+    let mut index = IdIndex::default();
+    for (_, path, id) in files {
+        let Some(original) = index.paths.get(&id) else {
+            index.paths.insert(id, path);
+            continue;
+        };
+        let notice = format!(
+            "{} had the same ID as {}",
+            path.display(),
+            original.display(),
+        );
+        let renumbered = load_request(&path).map_err(std::io::Error::other).and_then(|mut req| {
+            let id = new_request_id();
+            req.id = Some(id.clone());
+            save_request(&path, &req).map(|_| id)
         });
-        FileNode::Folder { name, path: path.to_path_buf(), children }
-    } else {
-        FileNode::File { name, path: path.to_path_buf() }
+        match renumbered {
+            Ok(new_id) => {
+                index.notices.push(format!("{}; it was given a new ID", notice));
+                index.paths.insert(new_id, path);
+            }
+            Err(e) => index.notices.push(format!("{}; it could not be given a new ID: {}", notice, e)),
+        }
     }
+    index
 }
 
 impl FileNode {
@@ -135,14 +981,378 @@ impl FileNode {
     }
 }
 
+/// Per-folder settings stored as `.folder.json` inside the folder.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct FolderConfig {
+    /// Request file (relative to the folder) run before everything else in a folder run.
+    pub setup: Option<String>,
+    /// Request file run at the end of a folder run, even if it failed or was cancelled.
+    pub teardown: Option<String>,
+    /// Command run before each request in the folder and its subfolders that
+    /// has no hook of its own.
+    pub hook: Option<Hook>,
+    /// Credentials for the requests in the folder and its subfolders that
+    /// set none of their own.
+    pub auth: Option<Auth>,
+    /// Named lists of assertions requests in the folder and its subfolders
+    /// use with `# assert-set: NAME`, one assertion per entry.
+    pub assert_sets: HashMap<String, Vec<String>>,
+}
+
+impl FolderConfig {
+    pub fn load(folder: &Path) -> Self {
+        backend().load(&folder.join(".folder.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// The first setting `pick` finds walking up from the request at `path`
+    /// to the base directory, with the folder it was found in.
+    pub fn nearest<T>(path: Option<&Path>, pick: impl Fn(FolderConfig) -> Option<T>) -> Option<(PathBuf, T)> {
+        let base = get_base_dir();
+        let path = path.and_then(|p| std::path::absolute(p).ok());
+        let mut folder = path.as_deref().and_then(Path::parent);
+        while let Some(dir) = folder.filter(|dir| dir.starts_with(&base)) {
+            if let Some(found) = pick(FolderConfig::load(dir)) {
+                return Some((dir.to_path_buf(), found));
+            }
+            folder = dir.parent();
+        }
+        None
+    }
+}
+
 pub fn load_request(path: &Path) -> Result<HttpRequest, String> {
-    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let content = backend().load(path).map_err(|e| e.to_string())?;
     HttpRequest::from_http_string(&content)
 }
 
-pub fn save_request(path: &Path, req: &HttpRequest) -> std::io::Result<()> {
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)?;
+/// Where archived folders are kept, each at the path it had below the base
+/// dir. Being a dot-folder keeps it out of the tree and searches.
+pub fn archive_dir() -> PathBuf {
+    get_base_dir().join(".archive")
+}
+
+pub fn is_archived(path: &Path) -> bool {
+    path.starts_with(archive_dir())
+}
+
+/// Moves `folder` into the archive. Returns where it went.
+pub fn archive_folder(folder: &Path) -> std::io::Result<PathBuf> {
+    crate::workspace::ensure_writable()?;
+    let relative = folder.strip_prefix(get_base_dir())
+        .ok()
+        .filter(|r| !r.as_os_str().is_empty() && !is_archived(folder))
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "Only folders inside the workspace can be archived"))?;
+    // Folders below it that were archived before are merged with it
+    let target = archive_dir().join(relative);
+    backend().rename(folder, &target)?;
+    Ok(target)
+}
+
+/// Moves an archived folder back to where it was. Returns where it went.
+pub fn unarchive_folder(folder: &Path) -> std::io::Result<PathBuf> {
+    crate::workspace::ensure_writable()?;
+    let archive = archive_dir();
+    let relative = folder.strip_prefix(&archive)
+        .ok()
+        .filter(|r| !r.as_os_str().is_empty())
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "The folder is not archived"))?;
+    let target = get_base_dir().join(relative);
+    backend().rename(folder, &target)?;
+    // Drop the folders left empty in the archive
+    for parent in folder.ancestors().skip(1).take_while(|p| *p != archive) {
+        if fs::remove_dir(parent).is_err() {
+            break;
+        }
+    }
+    Ok(target)
+}
+
+/// The archived folders that hold requests themselves, each with those
+/// requests, sorted by path.
+pub fn archived_folders() -> Vec<(PathBuf, Vec<FileNode>)> {
+    fn walk(node: FileNode, out: &mut Vec<(PathBuf, Vec<FileNode>)>) {
+        let FileNode::Folder { path, children, .. } = node else { return };
+        let (folders, files): (Vec<FileNode>, Vec<FileNode>) = children.into_iter()
+            .partition(|c| matches!(c, FileNode::Folder { .. }));
+        if !files.is_empty() {
+            out.push((path, files));
+        }
+        for folder in folders {
+            walk(folder, out);
+        }
+    }
+    let mut out = Vec::new();
+    walk(build_tree(&archive_dir()), &mut out);
+    out
+}
+
+/// Renames the request file or folder at `path` to `name`, keeping a
+/// file's `.req` extension. Returns the new path.
+pub fn rename_entry(path: &Path, name: &str) -> std::io::Result<PathBuf> {
+    crate::workspace::ensure_writable()?;
+    let invalid = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidInput, message.to_string());
+    let name = name.trim();
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return Err(invalid("A name cannot be empty, start with a dot or contain / or \\"));
+    }
+    if path == get_base_dir() {
+        return Err(invalid("The workspace folder cannot be renamed"));
+    }
+    let is_file = path.extension().is_some_and(|e| e == "req");
+    if !is_file && name.ends_with(".req") {
+        return Err(invalid("A folder name cannot end in .req"));
+    }
+    let name = if is_file && !name.ends_with(".req") { format!("{}.req", name) } else { name.to_string() };
+    let target = path.with_file_name(name);
+    if target == path {
+        return Ok(target);
+    }
+    if backend().exists(&target) {
+        return Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, format!("{} already exists", target.display())));
+    }
+    backend().rename(path, &target)?;
+    Ok(target)
+}
+
+/// Deletes the request file, or the folder with every request in it.
+pub fn delete_entry(path: &Path) -> std::io::Result<()> {
+    crate::workspace::ensure_writable()?;
+    if path == get_base_dir() {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "The workspace folder cannot be deleted"));
+    }
+    backend().delete(path)
+}
+
+/// How many requests are in `folder`, at any depth.
+pub fn count_requests(folder: &Path) -> usize {
+    let mut files = Vec::new();
+    collect_files(&build_tree(folder), &mut files);
+    files.len()
+}
+
+/// Whether a request file or folder exists at `path`.
+pub fn request_exists(path: &Path) -> bool {
+    backend().exists(path)
+}
+
+/// Writes `req` to `path`. A request without an ID keeps the one already in
+/// the file, or gets a new one on its first save; the ID written is returned.
+pub fn save_request(path: &Path, req: &HttpRequest) -> std::io::Result<String> {
+    crate::workspace::ensure_writable()?;
+    req.validate().map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let id = req.id.clone().or_else(|| read_id(path)).unwrap_or_else(new_request_id);
+    let req = HttpRequest { id: Some(id.clone()), ..req.clone() };
+    backend().save(path, &req.to_http_string())?;
+    Ok(id)
+}
+
+/// Every request file in `node`, in tree order.
+pub fn collect_files(node: &FileNode, out: &mut Vec<PathBuf>) {
+    match node {
+        FileNode::Folder { children, .. } => {
+            for child in children {
+                collect_files(child, out);
+            }
+        }
+        FileNode::File { path, .. } => out.push(path.clone()),
+    }
+}
+
+static BACKEND: OnceLock<Box<dyn StorageBackend>> = OnceLock::new();
+
+/// The backend chosen in the workspace settings, opened on first use. A
+/// SQLite workspace that cannot be opened falls back to the files so the
+/// app still starts.
+pub fn backend() -> &'static dyn StorageBackend {
+    BACKEND.get_or_init(|| {
+        backend::open(crate::settings::Settings::load().storage_backend).unwrap_or_else(|e| {
+            eprintln!("Cannot open {}: {}", backend::sqlite_file().display(), e);
+            Box::new(FsBackend)
+        })
+    }).as_ref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_classes_cover_their_hundred() {
+        let set = StatusSet::parse("2xx").unwrap();
+        assert_eq!(set, StatusSet(vec![(200, 299)]));
+        assert_eq!(set.to_string(), "2xx");
+        let set = StatusSet::parse("1xx-3xx").unwrap();
+        assert_eq!(set, StatusSet(vec![(100, 399)]));
+        assert!(set.contains(101) && set.contains(399) && !set.contains(400));
+        assert_eq!(StatusSet::parse("404, 500-503").unwrap(), StatusSet(vec![(404, 404), (500, 503)]));
+    }
+
+    #[test]
+    fn status_classes_outside_1_to_5_are_rejected() {
+        for token in ["0xx", "6xx", "9xx", "700xx", "99999xx", "axx", "xx"] {
+            assert_eq!(StatusSet::parse(token), Err(format!("Invalid status class: {}", token)));
+        }
+        assert_eq!(StatusSet::parse("3xx-1xx"), Err("Empty status range: 3xx-1xx".to_string()));
+        assert_eq!(StatusSet::parse("600"), Err("Status out of range: 600".to_string()));
+    }
+
+    #[test]
+    fn options_reject_negative_zero_and_excessive_values() {
+        let mut options = RequestOptions::default();
+        assert_eq!(options.set("timeout", "-5"), Err("Timeout cannot be negative".to_string()));
+        assert_eq!(options.set("connect-timeout", "0"), Err("Connect timeout must be greater than 0".to_string()));
+        assert_eq!(options.set("read-timeout", "1.5"), Err("Read timeout must be a whole number".to_string()));
+        assert_eq!(options.set("retries", &(MAX_RETRIES + 1).to_string()), Err(format!("Retries cannot exceed {}", MAX_RETRIES)));
+        assert_eq!(options.set("bogus", "1"), Err("Unknown option: bogus".to_string()));
+        assert_eq!(options, RequestOptions::default());
+    }
+
+    #[test]
+    fn options_round_trip_and_inherit_unset_values() {
+        let mut options = RequestOptions::default();
+        options.set("timeout", " 2500 ").unwrap();
+        options.set("retries", &MAX_RETRIES.to_string()).unwrap();
+        options.set("accept-status", "2xx, 404").unwrap();
+        assert_eq!(options.get("timeout"), "2500");
+        assert_eq!(options.get("accept-status"), "2xx,404");
+        options.set("timeout", "").unwrap();
+        assert_eq!(options.timeout_ms, None);
+
+        let mut defaults = RequestOptions::default();
+        defaults.set("timeout", "30000").unwrap();
+        defaults.set("retries", "1").unwrap();
+        let effective = options.inherit(&defaults);
+        assert_eq!(effective.timeout_ms, Some(30000));
+        assert_eq!(effective.retries, Some(MAX_RETRIES));
+    }
+
+    #[test]
+    fn bad_accept_status_loads_with_the_default_until_fixed() {
+        let text = "# accept-status: 2xx, 9xx\n# timeout: 500\nDELETE https://example.com/items/1\n\n";
+        let mut req = HttpRequest::from_http_string(text).unwrap();
+        assert_eq!(req.options.timeout_ms, Some(500));
+        assert_eq!(req.options.accepted_status, None);
+        assert!(req.options.accepts(204) && !req.options.accepts(404));
+        assert_eq!(req.options.load_error("accept-status").as_deref(), Some("Invalid status class: 9xx; 2xx and 3xx count until this is fixed"));
+        assert_eq!(req.options.load_error("timeout"), None);
+        // Saved as written, so the mistake is not lost
+        assert_eq!(req.options.get("accept-status"), "2xx, 9xx");
+        assert_eq!(HttpRequest::from_http_string(&req.to_http_string()).unwrap(), req);
+
+        req.options.set("accept-status", "2xx, 404").unwrap();
+        assert_eq!(req.options.load_error("accept-status"), None);
+        assert!(req.options.accepts(404));
+        // Other bad options still fail the load
+        assert_eq!(HttpRequest::from_http_string("# timeout: -1\nGET https://example.com\n").unwrap_err(), "timeout: Timeout cannot be negative");
+    }
+
+    /// Strings that would split a line or mimic file structure if written raw.
+    const ADVERSARIAL: &[&str] = &[
+        "line\nbreak",
+        "cr\rlf\r\nmix",
+        "back\\slash",
+        "literal \\n not a break",
+        "trailing\\",
+        "# id: forged",
+        "X-Injected: yes\n\nbody",
+        "ünïcode\ttab",
+    ];
+
+    #[test]
+    fn adversarial_fields_survive_save_and_load() {
+        for text in ADVERSARIAL {
+            let mut req = HttpRequest::new();
+            req.name = Some(format!("n{}n", text));
+            req.url = format!("https://example.com/{}/end", text);
+            req.headers = vec![
+                (format!("X{}", text.replace(':', "")), format!("v{}v", text)),
+                ("Accept".to_string(), text.to_string()),
+            ];
+            req.views = vec![(format!("v{}", text), format!("$.a{}", text))];
+            req.body = format!("{{\n  \"text\": {:?}\n}}", text);
+            assert_eq!(req.validate(), Ok(()));
+            let loaded = HttpRequest::from_http_string(&req.to_http_string()).unwrap();
+            assert_eq!(loaded, req, "round trip of {:?}", text);
+        }
+    }
+
+    #[test]
+    fn ambiguous_names_are_refused_before_saving() {
+        let mut req = HttpRequest::new();
+        req.headers.push(("X-A: b".to_string(), "c".to_string()));
+        assert_eq!(req.validate(), Err("Header name 'X-A: b' cannot contain ':'".to_string()));
+        let mut req = HttpRequest::new();
+        req.method = "GET /x".to_string();
+        assert!(req.validate().is_err());
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn header_diff_lists_added_removed_and_changed_rows() {
+        let old = pairs(&[("Accept", "a"), ("X-Old", "1"), ("Accept", "b"), ("Host", "h")]);
+        let new = pairs(&[("host", "h"), ("Accept", "a"), ("Accept", "c"), ("X-New", "2")]);
+        assert_eq!(diff_headers(&old, &new), vec![
+            HeaderChange::Removed { index: 1, name: "X-Old".to_string(), value: "1".to_string() },
+            HeaderChange::Changed { index: 2, name: "Accept".to_string(), old: "b".to_string(), new: "c".to_string() },
+            HeaderChange::Changed { index: 0, name: "Host".to_string(), old: "h".to_string(), new: "h".to_string() },
+            HeaderChange::Added { index: 3, name: "X-New".to_string(), value: "2".to_string() },
+        ]);
+        assert!(diff_headers(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn reverting_every_header_change_restores_the_saved_rows() {
+        let old = pairs(&[("Accept", "a"), ("X-Old", "1"), ("Accept", "b")]);
+        let mut new = pairs(&[("Accept", "a"), ("Accept", "c"), ("X-New", "2")]);
+        // Reverted from the end so earlier indexes stay valid
+        for change in diff_headers(&old, &new).iter().rev() {
+            change.revert(&mut new);
+        }
+        assert_eq!(new, old);
+    }
+
+    #[test]
+    fn urls_are_kept_verbatim_through_save_and_load() {
+        for url in [
+            "https://example.com/a%20b/c?name=J%C3%B6rg&x=1&y=a=b",
+            "https://example.com/search?q=one  two\tthree",
+            "{{base}}/items?filter=status eq 'open'&$top=5#frag",
+        ] {
+            let mut req = HttpRequest::new();
+            req.method = "GET".to_string();
+            req.url = url.to_string();
+            req.headers.push(("Accept".to_string(), "*/*".to_string()));
+            assert_eq!(HttpRequest::from_http_string(&req.to_http_string()).unwrap(), req);
+        }
+        let loaded = HttpRequest::from_http_string("get \t https://example.com/x y  \n").unwrap();
+        assert_eq!((loaded.method.as_str(), loaded.url.as_str()), ("GET", "https://example.com/x y"));
+        assert!(HttpRequest::from_http_string("GET\n").is_err());
+    }
+
+    #[test]
+    fn query_strings_round_trip_empty_values_and_flags() {
+        let url = "https://example.com/p?a=1&b=&c#top";
+        assert_eq!(parse_query(url), pairs(&[("a", "1"), ("b", ""), ("c", "")]));
+        assert_eq!(set_query(url, &parse_query(url)), url);
+        assert_eq!(set_query("https://example.com/p", &pairs(&[("a", "1"), ("b", ""), ("c", "")])), "https://example.com/p?a=1&b&c");
+        assert_eq!(set_query(url, &[]), "https://example.com/p#top");
+        assert_eq!(set_query(url, &pairs(&[("", ""), ("a", "1")])), "https://example.com/p?a=1#top");
+    }
+
+    #[test]
+    fn query_values_are_decoded_and_encoded() {
+        let url = "https://example.com/?q=a%20b%26c&name=J%C3%B6rg&plus=a+b";
+        assert_eq!(parse_query(url), pairs(&[("q", "a b&c"), ("name", "Jörg"), ("plus", "a+b")]));
+        assert_eq!(set_query(url, &parse_query(url)), url);
+        let edited = set_query("https://example.com/", &pairs(&[("a=b", "x y#"), ("id", "{{id}}"), ("p", "100%")]));
+        assert_eq!(edited, "https://example.com/?a%3Db=x%20y%23&id={{id}}&p=100%25");
+        assert_eq!(parse_query(&edited), pairs(&[("a=b", "x y#"), ("id", "{{id}}"), ("p", "100%")]));
     }
-    fs::write(path, req.to_http_string())
 }
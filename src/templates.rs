@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use crate::storage::{self, HttpRequest, get_base_dir};
+
+// Each folder under `templates/` is a template. Its `.req` files are copied
+// into the new folder with `${base_url}` and `${name}` substituted;
+// `{{...}}` placeholders are left for environments to fill at send time.
+
+/// Written to `templates/rest-api` when there are no templates yet.
+const DEFAULT_TEMPLATE: &[(&str, &str)] = &[
+    ("health.req", "# name: Health check\nGET ${base_url}/health\n\n"),
+    (
+        "auth/login.req",
+        "# name: Log in\nPOST ${base_url}/auth/login\nContent-Type: application/json\n\n{\n  \"username\": \"{{username}}\",\n  \"password\": \"{{password}}\"\n}\n",
+    ),
+    ("crud/list.req", "# name: List ${name}\nGET ${base_url}/${name}\nAuthorization: Bearer {{token}}\n\n"),
+    ("crud/get.req", "# name: Get one\nGET ${base_url}/${name}/{{id}}\nAuthorization: Bearer {{token}}\n\n"),
+    (
+        "crud/create.req",
+        "# name: Create\nPOST ${base_url}/${name}\nAuthorization: Bearer {{token}}\nContent-Type: application/json\n\n{\n}\n",
+    ),
+    (
+        "crud/update.req",
+        "# name: Update\nPUT ${base_url}/${name}/{{id}}\nAuthorization: Bearer {{token}}\nContent-Type: application/json\n\n{\n}\n",
+    ),
+    ("crud/delete.req", "# name: Delete\nDELETE ${base_url}/${name}/{{id}}\nAuthorization: Bearer {{token}}\n\n"),
+];
+
+pub fn templates_dir() -> PathBuf {
+    get_base_dir().join("templates")
+}
+
+/// Names of the available templates, sorted. Creates the default template
+/// the first time, when the templates folder does not exist.
+pub fn list_templates() -> Vec<String> {
+    let dir = templates_dir();
+    if !dir.exists() && crate::workspace::ensure_writable().is_ok() {
+        for (file, content) in DEFAULT_TEMPLATE {
+            let path = dir.join("rest-api").join(file);
+            if let Some(parent) = path.parent() {
+                let _ = fs::create_dir_all(parent);
+            }
+            let _ = fs::write(path, content);
+        }
+    }
+    let mut names: Vec<String> = fs::read_dir(&dir)
+        .map(|entries| {
+            entries.flatten()
+                .filter(|e| e.path().is_dir())
+                .map(|e| e.file_name().to_string_lossy().into_owned())
+                .filter(|name| !name.starts_with('.'))
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Creates `folder` from the template `template`. Returns the request files
+/// written.
+pub fn scaffold(template: &str, folder: &Path, base_url: &str) -> Result<Vec<PathBuf>, String> {
+    let source = templates_dir().join(template);
+    if !source.is_dir() {
+        return Err(format!("Template '{}' not found", template));
+    }
+    if storage::request_exists(folder) {
+        return Err(format!("{} already exists", folder.display()));
+    }
+    let name = folder.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let base_url = base_url.trim().trim_end_matches('/');
+
+    let mut written = Vec::new();
+    for entry in WalkDir::new(&source).sort_by_file_name().into_iter().flatten() {
+        let path = entry.path();
+        if !entry.file_type().is_file() || path.extension().is_none_or(|ext| ext != "req") {
+            continue;
+        }
+        let Ok(relative) = path.strip_prefix(&source) else { continue };
+        let content = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        let content = content.replace("${base_url}", base_url).replace("${name}", &name);
+        let mut request = HttpRequest::from_http_string(&content)
+            .map_err(|e| format!("{}: {}", relative.display(), e))?;
+        // Every scaffolded file is a new request
+        request.id = None;
+        let target = folder.join(relative);
+        storage::save_request(&target, &request).map_err(|e| format!("Cannot write {}: {}", target.display(), e))?;
+        written.push(target);
+    }
+    if written.is_empty() {
+        return Err(format!("Template '{}' has no .req files", template));
+    }
+    Ok(written)
+}
@@ -0,0 +1,620 @@
+use std::cmp::Ordering;
+use serde_json::{Map, Value};
+
+/// A failed parse or evaluation, pointing at the part of the expression responsible.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransformError {
+    /// 1-based character column in the expression.
+    pub column: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for TransformError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Column {}: {}", self.column, self.message)
+    }
+}
+
+/// Applies a jq-like expression to a JSON value.
+///
+/// Stages are separated by `|`:
+/// - `.`, `.name`, `.items[0]`, `.items[2:5]`, `.["odd key"]` select parts of the value
+/// - `pick(.id, .user.name)` keeps only the given paths (of each element of an array)
+/// - `map(EXPR)`, `select(EXPR)`, `sort_by(EXPR)` work on the elements of an array
+/// - `limit(N)`, `first`, `last`, `reverse`, `length`, `keys`
+/// - `==`, `!=`, `<`, `<=`, `>`, `>=` compare against literals or paths
+///
+/// For example `.orders | select(.status == "open") | sort_by(.created_at) | reverse | limit(10)`.
+pub fn apply(expression: &str, input: &Value) -> Result<Value, TransformError> {
+    let tokens = tokenize(expression)?;
+    let mut parser = Parser { source: expression, tokens, pos: 0 };
+    let expr = parser.pipeline()?;
+    if let Some(token) = parser.peek() {
+        return Err(parser.error_at(token.start, "unexpected input"));
+    }
+    expr.eval(input).map_err(|(start, message)| TransformError { column: column(expression, start), message })
+}
+
+fn column(source: &str, byte: usize) -> usize {
+    source[..byte.min(source.len())].chars().count() + 1
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Dot,
+    LBracket,
+    RBracket,
+    LParen,
+    RParen,
+    Comma,
+    Pipe,
+    Colon,
+    Op(CompareOp),
+    Number(f64),
+    Str(String),
+    Ident(String),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    tok: Tok,
+    start: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, TransformError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let tok = match c {
+            c if c.is_whitespace() => continue,
+            '.' => Tok::Dot,
+            '[' => Tok::LBracket,
+            ']' => Tok::RBracket,
+            '(' => Tok::LParen,
+            ')' => Tok::RParen,
+            ',' => Tok::Comma,
+            '|' => Tok::Pipe,
+            ':' => Tok::Colon,
+            '=' | '!' | '<' | '>' => {
+                let eq = chars.next_if(|(_, c)| *c == '=').is_some();
+                Tok::Op(match (c, eq) {
+                    ('=', true) => CompareOp::Eq,
+                    ('!', true) => CompareOp::Ne,
+                    ('<', false) => CompareOp::Lt,
+                    ('<', true) => CompareOp::Le,
+                    ('>', false) => CompareOp::Gt,
+                    ('>', true) => CompareOp::Ge,
+                    _ => return Err(TransformError {
+                        column: column(source, start),
+                        message: format!("unknown operator '{}' (comparisons use ==)", c),
+                    }),
+                })
+            }
+            '"' => {
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, 'n')) => s.push('\n'),
+                            Some((_, 't')) => s.push('\t'),
+                            Some((_, c)) => s.push(c),
+                            None => break,
+                        },
+                        Some((_, c)) => s.push(c),
+                        None => return Err(TransformError {
+                            column: column(source, start),
+                            message: "unterminated string".to_string(),
+                        }),
+                    }
+                }
+                Tok::Str(s)
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, c)) = chars.next_if(|(_, c)| c.is_ascii_digit() || *c == '.' || *c == 'e' || *c == 'E') {
+                    end = i + c.len_utf8();
+                }
+                let text = &source[start..end];
+                Tok::Number(text.parse().map_err(|_| TransformError {
+                    column: column(source, start),
+                    message: format!("invalid number '{}'", text),
+                })?)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, c)) = chars.next_if(|(_, c)| c.is_alphanumeric() || *c == '_') {
+                    end = i + c.len_utf8();
+                }
+                Tok::Ident(source[start..end].to_string())
+            }
+            c => return Err(TransformError {
+                column: column(source, start),
+                message: format!("unexpected character '{}'", c),
+            }),
+        };
+        tokens.push(Token { tok, start });
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug)]
+enum Step {
+    Field(String),
+    Index(i64),
+    Slice(Option<i64>, Option<i64>),
+}
+
+#[derive(Debug)]
+enum Expr {
+    Path(usize, Vec<Step>),
+    Literal(Value),
+    Pipe(Vec<Expr>),
+    Compare(Box<Expr>, CompareOp, Box<Expr>),
+    Call(usize, String, Vec<Expr>),
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next_if(&mut self, tok: &Tok) -> bool {
+        if self.peek().is_some_and(|t| &t.tok == tok) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn here(&self) -> usize {
+        self.peek().map_or(self.source.len(), |t| t.start)
+    }
+
+    fn error_at(&self, start: usize, message: &str) -> TransformError {
+        TransformError { column: column(self.source, start), message: message.to_string() }
+    }
+
+    fn expect(&mut self, tok: Tok, what: &str) -> Result<(), TransformError> {
+        if self.next_if(&tok) { Ok(()) } else { Err(self.error_at(self.here(), &format!("expected {}", what))) }
+    }
+
+    fn pipeline(&mut self) -> Result<Expr, TransformError> {
+        let mut stages = vec![self.comparison()?];
+        while self.next_if(&Tok::Pipe) {
+            stages.push(self.comparison()?);
+        }
+        Ok(if stages.len() == 1 { stages.remove(0) } else { Expr::Pipe(stages) })
+    }
+
+    fn comparison(&mut self) -> Result<Expr, TransformError> {
+        let left = self.term()?;
+        if let Some(Token { tok: Tok::Op(op), .. }) = self.peek().cloned() {
+            self.pos += 1;
+            let right = self.term()?;
+            return Ok(Expr::Compare(Box::new(left), op, Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    fn term(&mut self) -> Result<Expr, TransformError> {
+        let Some(token) = self.peek().cloned() else {
+            return Err(self.error_at(self.source.len(), "expression ends unexpectedly"));
+        };
+        self.pos += 1;
+        match token.tok {
+            Tok::Dot => self.path(token.start),
+            Tok::Number(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => Ok(Expr::Literal(Value::from(n as i64))),
+            Tok::Number(n) => Ok(Expr::Literal(serde_json::Number::from_f64(n).map_or(Value::Null, Value::Number))),
+            Tok::Str(s) => Ok(Expr::Literal(Value::String(s))),
+            Tok::Ident(name) => match name.as_str() {
+                "true" => Ok(Expr::Literal(Value::Bool(true))),
+                "false" => Ok(Expr::Literal(Value::Bool(false))),
+                "null" => Ok(Expr::Literal(Value::Null)),
+                _ => {
+                    let mut args = Vec::new();
+                    if self.next_if(&Tok::LParen) {
+                        loop {
+                            args.push(self.pipeline()?);
+                            if !self.next_if(&Tok::Comma) {
+                                break;
+                            }
+                        }
+                        self.expect(Tok::RParen, "')'")?;
+                    }
+                    Ok(Expr::Call(token.start, name, args))
+                }
+            },
+            Tok::LParen => {
+                let inner = self.pipeline()?;
+                self.expect(Tok::RParen, "')'")?;
+                Ok(inner)
+            }
+            _ => Err(self.error_at(token.start, "expected a path, function or value")),
+        }
+    }
+
+    /// The steps after the leading `.` of a path.
+    fn path(&mut self, start: usize) -> Result<Expr, TransformError> {
+        let mut steps = Vec::new();
+        let mut expect_name = true;
+        while let Some(token) = self.peek().cloned() {
+            match token.tok {
+                Tok::Ident(name) if expect_name => {
+                    self.pos += 1;
+                    steps.push(Step::Field(name));
+                }
+                Tok::Str(name) if expect_name => {
+                    self.pos += 1;
+                    steps.push(Step::Field(name));
+                }
+                Tok::Dot if !expect_name => {
+                    self.pos += 1;
+                    expect_name = true;
+                    continue;
+                }
+                Tok::LBracket => {
+                    self.pos += 1;
+                    steps.push(self.bracket()?);
+                }
+                _ => break,
+            }
+            expect_name = false;
+        }
+        Ok(Expr::Path(start, steps))
+    }
+
+    fn bracket(&mut self) -> Result<Step, TransformError> {
+        let step = match self.peek().map(|t| t.tok.clone()) {
+            Some(Tok::Str(name)) => {
+                self.pos += 1;
+                Step::Field(name)
+            }
+            _ => {
+                let from = self.integer()?;
+                if self.next_if(&Tok::Colon) {
+                    Step::Slice(from, self.integer()?)
+                } else {
+                    Step::Index(from.ok_or_else(|| self.error_at(self.here(), "expected an index"))?)
+                }
+            }
+        };
+        self.expect(Tok::RBracket, "']'")?;
+        Ok(step)
+    }
+
+    fn integer(&mut self) -> Result<Option<i64>, TransformError> {
+        match self.peek().cloned() {
+            Some(Token { tok: Tok::Number(n), start }) => {
+                self.pos += 1;
+                if n.fract() != 0.0 {
+                    return Err(self.error_at(start, "indexes must be whole numbers"));
+                }
+                Ok(Some(n as i64))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+type EvalResult = Result<Value, (usize, String)>;
+
+impl Expr {
+    fn eval(&self, input: &Value) -> EvalResult {
+        match self {
+            Expr::Literal(value) => Ok(value.clone()),
+            Expr::Path(start, steps) => {
+                steps.iter().try_fold(input.clone(), |value, step| apply_step(&value, step).map_err(|e| (*start, e)))
+            }
+            Expr::Pipe(stages) => stages.iter().try_fold(input.clone(), |value, stage| stage.eval(&value)),
+            Expr::Compare(left, op, right) => {
+                let ordering = compare(&left.eval(input)?, &right.eval(input)?);
+                Ok(Value::Bool(match op {
+                    CompareOp::Eq => ordering == Ordering::Equal,
+                    CompareOp::Ne => ordering != Ordering::Equal,
+                    CompareOp::Lt => ordering == Ordering::Less,
+                    CompareOp::Le => ordering != Ordering::Greater,
+                    CompareOp::Gt => ordering == Ordering::Greater,
+                    CompareOp::Ge => ordering != Ordering::Less,
+                }))
+            }
+            Expr::Call(start, name, args) => self.call(*start, name, args, input),
+        }
+    }
+
+    fn call(&self, start: usize, name: &str, args: &[Expr], input: &Value) -> EvalResult {
+        let fail = |message: String| Err((start, message));
+        let arity = match name {
+            "length" | "keys" | "reverse" | "first" | "last" => 0,
+            "map" | "select" | "sort_by" | "limit" => 1,
+            "pick" => args.len().max(1),
+            _ => return fail(format!("unknown function '{}'", name)),
+        };
+        if args.len() != arity {
+            return fail(format!("{} takes {} argument{}", name, arity, if arity == 1 { "" } else { "s" }));
+        }
+        let array = |what: &str| match input {
+            Value::Array(items) => Ok(items),
+            other => Err((start, format!("{} needs an array, got {}", what, type_name(other)))),
+        };
+        match name {
+            "length" => Ok(Value::from(match input {
+                Value::Array(items) => items.len(),
+                Value::Object(map) => map.len(),
+                Value::String(s) => s.chars().count(),
+                Value::Null => 0,
+                other => return fail(format!("{} has no length", type_name(other))),
+            })),
+            "keys" => match input {
+                Value::Object(map) => Ok(Value::Array(map.keys().cloned().map(Value::String).collect())),
+                other => fail(format!("keys needs an object, got {}", type_name(other))),
+            },
+            "reverse" => Ok(Value::Array(array(name)?.iter().rev().cloned().collect())),
+            "first" => Ok(array(name)?.first().cloned().unwrap_or(Value::Null)),
+            "last" => Ok(array(name)?.last().cloned().unwrap_or(Value::Null)),
+            "limit" => {
+                let n = match args[0].eval(input)? {
+                    Value::Number(n) if n.as_u64().is_some() => n.as_u64().unwrap_or(0) as usize,
+                    _ => return fail("limit needs a non-negative whole number".to_string()),
+                };
+                Ok(Value::Array(array(name)?.iter().take(n).cloned().collect()))
+            }
+            "map" => array(name)?.iter().map(|item| args[0].eval(item)).collect::<Result<_, _>>().map(Value::Array),
+            "select" => match input {
+                Value::Array(items) => {
+                    let mut kept = Vec::new();
+                    for item in items {
+                        if truthy(&args[0].eval(item)?) {
+                            kept.push(item.clone());
+                        }
+                    }
+                    Ok(Value::Array(kept))
+                }
+                other => Ok(if truthy(&args[0].eval(other)?) { other.clone() } else { Value::Null }),
+            },
+            "sort_by" => {
+                let mut keyed = array(name)?.iter()
+                    .map(|item| Ok((args[0].eval(item)?, item.clone())))
+                    .collect::<Result<Vec<_>, _>>()?;
+                keyed.sort_by(|a, b| compare(&a.0, &b.0));
+                Ok(Value::Array(keyed.into_iter().map(|(_, item)| item).collect()))
+            }
+            "pick" => match input {
+                Value::Array(items) => items.iter().map(|item| pick(start, args, item)).collect::<Result<_, _>>().map(Value::Array),
+                other => pick(start, args, other),
+            },
+            _ => unreachable!("arity check covers every function"),
+        }
+    }
+}
+
+fn apply_step(value: &Value, step: &Step) -> Result<Value, String> {
+    match (value, step) {
+        (Value::Null, _) => Ok(Value::Null),
+        (Value::Object(map), Step::Field(name)) => Ok(map.get(name).cloned().unwrap_or(Value::Null)),
+        (Value::Array(items), Step::Index(i)) => {
+            let i = if *i < 0 { items.len() as i64 + i } else { *i };
+            Ok(usize::try_from(i).ok().and_then(|i| items.get(i)).cloned().unwrap_or(Value::Null))
+        }
+        (Value::Array(items), Step::Slice(from, to)) => {
+            let clamp = |i: i64| if i < 0 { (items.len() as i64 + i).max(0) as usize } else { (i as usize).min(items.len()) };
+            let from = from.map_or(0, clamp);
+            let to = to.map_or(items.len(), clamp);
+            Ok(Value::Array(items.get(from..to.max(from)).unwrap_or_default().to_vec()))
+        }
+        (other, Step::Field(name)) => Err(format!("cannot get field '{}' of {}", name, type_name(other))),
+        (other, _) => Err(format!("cannot index {}", type_name(other))),
+    }
+}
+
+/// Copies the paths named in `args` from `value` into a new object, keeping their nesting.
+fn pick(start: usize, args: &[Expr], value: &Value) -> EvalResult {
+    let mut out = Map::new();
+    for arg in args {
+        let Expr::Path(path_start, steps) = arg else {
+            return Err((start, "pick takes paths such as .id or .user.name".to_string()));
+        };
+        let mut fields = Vec::new();
+        for step in steps {
+            match step {
+                Step::Field(name) => fields.push(name.clone()),
+                _ => return Err((*path_start, "pick only supports field names".to_string())),
+            }
+        }
+        let Some((last, parents)) = fields.split_last() else {
+            return Err((*path_start, "pick needs a field name".to_string()));
+        };
+        let picked = arg.eval(value)?;
+        let mut map = &mut out;
+        for field in parents {
+            let entry = map.entry(field.clone()).or_insert_with(|| Value::Object(Map::new()));
+            if !entry.is_object() {
+                *entry = Value::Object(Map::new());
+            }
+            let Value::Object(next) = entry else { unreachable!("replaced by an object above") };
+            map = next;
+        }
+        map.insert(last.clone(), picked);
+    }
+    Ok(Value::Object(out))
+}
+
+fn truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+/// jq's ordering: null < false < true < numbers < strings < arrays < objects.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    fn rank(v: &Value) -> u8 {
+        match v {
+            Value::Null => 0,
+            Value::Bool(false) => 1,
+            Value::Bool(true) => 2,
+            Value::Number(_) => 3,
+            Value::String(_) => 4,
+            Value::Array(_) => 5,
+            Value::Object(_) => 6,
+        }
+    }
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => {
+            x.as_f64().unwrap_or(0.0).partial_cmp(&y.as_f64().unwrap_or(0.0)).unwrap_or(Ordering::Equal)
+        }
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::Array(x), Value::Array(y)) => {
+            x.iter().zip(y).map(|(x, y)| compare(x, y)).find(|o| o.is_ne()).unwrap_or(x.len().cmp(&y.len()))
+        }
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn orders() -> Value {
+        json!({
+            "orders": [
+                {"id": 1, "status": "open", "total": 30, "user": {"name": "Ana", "age": 31}},
+                {"id": 2, "status": "closed", "total": 12.5, "user": {"name": "Bo", "age": 25}},
+                {"id": 3, "status": "open", "total": 7, "user": {"name": "Cy", "age": 40}},
+            ],
+            "odd key": true,
+        })
+    }
+
+    fn run(expression: &str) -> Value {
+        apply(expression, &orders()).unwrap()
+    }
+
+    fn error(expression: &str, input: &Value) -> (usize, String) {
+        let e = apply(expression, input).unwrap_err();
+        (e.column, e.message)
+    }
+
+    #[test]
+    fn paths_select_fields_indexes_and_slices() {
+        assert_eq!(run("."), orders());
+        assert_eq!(run(".orders[0].user.name"), json!("Ana"));
+        assert_eq!(run(".[\"odd key\"]"), json!(true));
+        assert_eq!(run(".orders[-1].id"), json!(3));
+        assert_eq!(run(".orders[9]"), Value::Null);
+        assert_eq!(run(".missing.deeper[0]"), Value::Null);
+        let ids = |expression: &str| run(&format!("{} | map(.id)", expression));
+        assert_eq!(ids(".orders[1:3]"), json!([2, 3]));
+        assert_eq!(ids(".orders[-2:]"), json!([2, 3]));
+        assert_eq!(ids(".orders[:1]"), json!([1]));
+        assert_eq!(ids(".orders[2:1]"), json!([]));
+        assert_eq!(ids(".orders[-10:10]"), json!([1, 2, 3]));
+    }
+
+    #[test]
+    fn pick_keeps_the_nesting_of_its_paths() {
+        assert_eq!(run(".orders[0] | pick(.id, .user.name)"), json!({"id": 1, "user": {"name": "Ana"}}));
+        assert_eq!(run(".orders | pick(.user.age) | map(.user.age)"), json!([31, 25, 40]));
+        assert_eq!(run(".orders[0] | pick(.nope)"), json!({"nope": null}));
+    }
+
+    #[test]
+    fn select_and_comparisons_filter_elements() {
+        assert_eq!(run(".orders | select(.status == \"open\") | map(.id)"), json!([1, 3]));
+        assert_eq!(run(".orders | select(.total > 12.5) | map(.id)"), json!([1]));
+        assert_eq!(run(".orders | select(.total >= 12.5) | map(.id)"), json!([1, 2]));
+        assert_eq!(run(".orders | select(.total <= 12.5) | map(.id)"), json!([2, 3]));
+        assert_eq!(run(".orders | select(.user.age < 30) | map(.id)"), json!([2]));
+        assert_eq!(run(".orders | select(.status != .user.name) | length"), json!(3));
+        // Outside an array, select keeps or drops the value itself
+        assert_eq!(run(".orders[0] | select(.id == 1) | .id"), json!(1));
+        assert_eq!(run(".orders[0] | select(.id == 2)"), Value::Null);
+        // jq's order across types
+        assert_eq!(apply(". < false", &Value::Null).unwrap(), json!(true));
+        assert_eq!(apply(". > \"9\"", &json!([0])).unwrap(), json!(true));
+        assert_eq!(apply(".[0] < .[1]", &json!([[1, 2, 9], [1, 3]])).unwrap(), json!(true));
+        assert_eq!(apply(".[0] < .[1]", &json!([[1, 2], [1, 2, 0]])).unwrap(), json!(true));
+        assert_eq!(apply("(. | length) == 2", &json!("é!")).unwrap(), json!(true));
+    }
+
+    #[test]
+    fn sort_by_orders_by_key_across_types() {
+        assert_eq!(run(".orders | sort_by(.total) | map(.id)"), json!([3, 2, 1]));
+        assert_eq!(run(".orders | sort_by(.user.name) | reverse | map(.id)"), json!([3, 2, 1]));
+        let mixed = json!([{"k": "a"}, {"k": 2}, {"k": null}, {"k": true}, {"k": [1]}, {"k": false}]);
+        assert_eq!(apply("sort_by(.k) | map(.k)", &mixed).unwrap(), json!([null, false, true, 2, "a", [1]]));
+    }
+
+    #[test]
+    fn array_and_object_functions() {
+        assert_eq!(run(".orders | first | .id"), json!(1));
+        assert_eq!(run(".orders | last | .id"), json!(3));
+        assert_eq!(run(".orders | limit(2) | map(.id)"), json!([1, 2]));
+        assert_eq!(run(".orders | length"), json!(3));
+        assert_eq!(run("keys"), json!(["orders", "odd key"]));
+        assert_eq!(apply("first", &json!([])).unwrap(), Value::Null);
+        assert_eq!(apply("length", &json!("héllo")).unwrap(), json!(5));
+        assert_eq!(apply("length", &Value::Null).unwrap(), json!(0));
+    }
+
+    #[test]
+    fn tokenizer_errors_point_at_the_character() {
+        // Columns count characters, not bytes
+        assert_eq!(error(".\"héllo\" | @", &orders()), (12, "unexpected character '@'".to_string()));
+        assert_eq!(error(".é == \"é", &orders()), (7, "unterminated string".to_string()));
+        assert_eq!(error(".a = 1", &orders()), (4, "unknown operator '=' (comparisons use ==)".to_string()));
+        assert_eq!(error(".a == 1e", &orders()), (7, "invalid number '1e'".to_string()));
+    }
+
+    #[test]
+    fn parser_errors_point_at_the_token() {
+        assert_eq!(error(".é | pick(.a", &orders()), (13, "expected ')'".to_string()));
+        assert_eq!(error(".orders[1.5]", &orders()), (9, "indexes must be whole numbers".to_string()));
+        assert_eq!(error(".orders[]", &orders()), (9, "expected an index".to_string()));
+        assert_eq!(error(". ]", &orders()), (3, "unexpected input".to_string()));
+        assert_eq!(error("", &orders()), (1, "expression ends unexpectedly".to_string()));
+        assert_eq!(error("ü | ,", &orders()), (5, "expected a path, function or value".to_string()));
+    }
+
+    #[test]
+    fn evaluation_errors_point_at_the_stage() {
+        let input = json!({"ü": {"x": 5}});
+        assert_eq!(error(".\"ü\" | .x[0]", &input), (8, "cannot index a number".to_string()));
+        assert_eq!(error(".\"ü\".x.y", &input), (1, "cannot get field 'y' of a number".to_string()));
+        assert_eq!(error(".\"ü\" | frob", &input), (8, "unknown function 'frob'".to_string()));
+        assert_eq!(error(".\"ü\" | first", &input), (8, "first needs an array, got an object".to_string()));
+        assert_eq!(error(".\"ü\".x | length", &input), (10, "a number has no length".to_string()));
+        assert_eq!(error("map(.a, .b)", &input), (1, "map takes 1 argument".to_string()));
+        assert_eq!(error("limit(-1)", &json!([])), (1, "limit needs a non-negative whole number".to_string()));
+        assert_eq!(error("pick(.ü[0])", &input), (6, "pick only supports field names".to_string()));
+        assert_eq!(error("pick(1)", &input), (1, "pick takes paths such as .id or .user.name".to_string()));
+        assert_eq!(error("pick(.)", &input), (6, "pick needs a field name".to_string()));
+        assert_eq!(apply(".a | frob", &input).unwrap_err().to_string(), "Column 6: unknown function 'frob'");
+    }
+}
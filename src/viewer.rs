@@ -0,0 +1,616 @@
+use std::cmp::Ordering;
+use base64::Engine;
+use dioxus::prelude::*;
+use serde_json::Value;
+use crate::client::HttpResponse;
+use crate::soap;
+
+/// A way of showing a response body. Viewers are listed in [`VIEWERS`]; the
+/// view switcher offers every one that can render the response shown.
+pub trait ResponseView: Sync {
+    /// Stable name, stored in `# body-view:` lines and settings.
+    fn id(&self) -> &'static str;
+    fn label(&self) -> &'static str;
+    /// Whether the body can be shown this way. `content_type` is the MIME
+    /// type without parameters, lowercased, and empty when there is none.
+    fn can_render(&self, content_type: &str, res: &HttpResponse) -> bool;
+    /// Whether responses of `content_type` open in this viewer by default.
+    fn prefers(&self, _content_type: &str) -> bool {
+        false
+    }
+    fn render(&self, res: &HttpResponse, context: &ViewContext) -> Element;
+}
+
+/// Display preferences and callbacks a viewer may use.
+pub struct ViewContext {
+    pub linkify: bool,
+    /// Show only the contents of a SOAP envelope's Body.
+    pub envelope_body_only: bool,
+    pub on_follow: EventHandler<String>,
+}
+
+/// Every viewer, in the order the switcher lists them.
+pub static VIEWERS: &[&dyn ResponseView] = &[&JsonView, &PrettyJsonView, &XmlView, &TableView, &NdjsonView, &ImageView, &HexView, &TextView];
+
+pub fn find(id: &str) -> Option<&'static dyn ResponseView> {
+    VIEWERS.iter().copied().find(|viewer| viewer.id() == id)
+}
+
+/// The viewers that can show `res`.
+pub fn available(res: &HttpResponse) -> Vec<&'static dyn ResponseView> {
+    let mime = mime_type(res).unwrap_or_default();
+    VIEWERS.iter().copied().filter(|viewer| viewer.can_render(&mime, res)).collect()
+}
+
+/// A content type pattern mapped to the id of the viewer it opens in. The
+/// pattern is a MIME type such as `application/vnd.api+json`, or a prefix
+/// ending in `*` such as `text/*`.
+pub type ViewOverride = (String, String);
+
+/// The `Content-Type` of `res` without parameters, lowercased.
+pub fn mime_type(res: &HttpResponse) -> Option<String> {
+    res.headers.iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+        .map(|(_, v)| v.split(';').next().unwrap_or("").trim().to_ascii_lowercase())
+        .filter(|mime| !mime.is_empty())
+}
+
+fn matches(pattern: &str, mime: &str) -> bool {
+    let pattern = pattern.trim().to_ascii_lowercase();
+    match pattern.strip_suffix('*') {
+        Some(prefix) => mime.starts_with(prefix),
+        None => pattern == mime,
+    }
+}
+
+/// The viewer a response opens in: the first of `overrides` matching its
+/// content type, else the viewer preferring that type. Bodies served as
+/// plain text or without a type are sniffed.
+pub fn default_view(res: &HttpResponse, overrides: &[ViewOverride]) -> &'static dyn ResponseView {
+    let mime = mime_type(res).unwrap_or_default();
+    let chosen = overrides.iter()
+        .filter(|(pattern, _)| matches(pattern, &mime))
+        .find_map(|(_, id)| find(id))
+        .or_else(|| VIEWERS.iter().copied().find(|viewer| viewer.prefers(&mime)));
+    if let Some(viewer) = chosen.filter(|viewer| viewer.can_render(&mime, res)) {
+        return viewer;
+    }
+    let sniffed: &'static dyn ResponseView = if JsonView.can_render(&mime, res) {
+        &JsonView
+    } else if res.body.trim_start().starts_with("<?xml") {
+        &XmlView
+    } else if TableView.can_render(&mime, res) {
+        &TableView
+    } else if res.raw_body.iter().take(1024).any(|&b| b == 0) {
+        &HexView
+    } else {
+        &TextView
+    };
+    sniffed
+}
+
+fn json_body(res: &HttpResponse) -> Option<Value> {
+    let body = res.body.trim_start();
+    if !body.starts_with(['{', '[']) {
+        return None;
+    }
+    serde_json::from_str(body).ok()
+}
+
+/// The body as received, with links when enabled.
+pub struct TextView;
+
+impl ResponseView for TextView {
+    fn id(&self) -> &'static str {
+        "text"
+    }
+
+    fn label(&self) -> &'static str {
+        "Raw text"
+    }
+
+    fn can_render(&self, _content_type: &str, _res: &HttpResponse) -> bool {
+        true
+    }
+
+    fn render(&self, res: &HttpResponse, context: &ViewContext) -> Element {
+        let on_follow = context.on_follow;
+        rsx! {
+            if context.linkify {
+                crate::LinkedBody { text: res.body.clone(), on_follow }
+            } else {
+                pre { class: "result-body", "{res.body}" }
+            }
+        }
+    }
+}
+
+/// Collapsible tree of a JSON document.
+pub struct JsonView;
+
+impl ResponseView for JsonView {
+    fn id(&self) -> &'static str {
+        "json"
+    }
+
+    fn label(&self) -> &'static str {
+        "JSON tree"
+    }
+
+    fn can_render(&self, _content_type: &str, res: &HttpResponse) -> bool {
+        json_body(res).is_some()
+    }
+
+    fn prefers(&self, content_type: &str) -> bool {
+        content_type.ends_with("/json") || content_type.ends_with("+json")
+    }
+
+    fn render(&self, res: &HttpResponse, _context: &ViewContext) -> Element {
+        let Some(value) = json_body(res) else {
+            return rsx! { pre { class: "result-body", "{res.body}" } };
+        };
+        rsx! {
+            div { class: "result-body json-tree",
+                JsonNode { name: None, value, depth: 0 }
+            }
+        }
+    }
+}
+
+/// One value of the JSON tree; objects and arrays below the second level
+/// start collapsed.
+#[component]
+fn JsonNode(name: Option<String>, value: Value, depth: usize) -> Element {
+    let mut open = use_signal(|| depth < 2);
+    let key = name.map(|name| format!("{}: ", name));
+    let children: Vec<(String, Value)> = match &value {
+        Value::Object(map) => map.iter().map(|(k, v)| (format!("{:?}", k), v.clone())).collect(),
+        Value::Array(items) => items.iter().enumerate().map(|(i, v)| (i.to_string(), v.clone())).collect(),
+        _ => Vec::new(),
+    };
+    let (start, end) = if value.is_object() { ("{", "}") } else { ("[", "]") };
+
+    rsx! {
+        div { class: "json-node",
+            if value.is_object() || value.is_array() {
+                span { class: "json-toggle", onclick: move |_| open.toggle(),
+                    if open() { "▾ " } else { "▸ " }
+                    if let Some(key) = &key { span { class: "json-key", "{key}" } }
+                    if open() {
+                        "{start}"
+                    } else {
+                        "{start} {children.len()} {end}"
+                    }
+                }
+                if open() {
+                    div { class: "json-children",
+                        for (child, value) in children {
+                            JsonNode { key: "{child}", name: Some(child.clone()), value, depth: depth + 1 }
+                        }
+                    }
+                    "{end}"
+                }
+            } else {
+                if let Some(key) = &key { span { class: "json-key", "{key}" } }
+                span { class: json_class(&value), "{value}" }
+            }
+        }
+    }
+}
+
+fn json_class(value: &Value) -> &'static str {
+    match value {
+        Value::String(_) => "json-string",
+        Value::Number(_) => "json-number",
+        _ => "json-literal",
+    }
+}
+
+/// `body` indented by two spaces, or `None` when it is not JSON.
+pub fn pretty_json(body: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(body.trim_start()).ok()?;
+    serde_json::to_string_pretty(&value).ok()
+}
+
+/// Largest text highlighted as JSON; bigger bodies are shown plain so typing
+/// and scrolling stay responsive.
+pub const HIGHLIGHT_MAX_BYTES: usize = 256 * 1024;
+
+/// A kind of run in highlighted JSON.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JsonPart {
+    Key,
+    String,
+    Number,
+    /// `true`, `false` and `null`.
+    Literal,
+    Punctuation,
+    /// Whitespace and anything that is not JSON, such as `{{placeholders}}`.
+    Other,
+}
+
+impl JsonPart {
+    pub fn class(&self) -> &'static str {
+        match self {
+            JsonPart::Key => "json-key",
+            JsonPart::String => "json-string",
+            JsonPart::Number => "json-number",
+            JsonPart::Literal => "json-literal",
+            JsonPart::Punctuation => "json-punctuation",
+            JsonPart::Other => "json-other",
+        }
+    }
+}
+
+/// Splits `text` into highlighted runs whose concatenation is `text`. It
+/// never fails, so text being typed highlights as far as it makes sense; a
+/// string followed by `:` is a key.
+pub fn highlight_json(text: &str) -> Vec<(JsonPart, String)> {
+    let mut parts: Vec<(JsonPart, String)> = Vec::new();
+    let mut push = |part: JsonPart, run: &str| {
+        match parts.last_mut() {
+            Some((last, joined)) if *last == part => joined.push_str(run),
+            _ => parts.push((part, run.to_string())),
+        }
+    };
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let end = match c {
+            '"' => {
+                // Up to the closing quote, or the line end while it is being typed
+                let mut escaped = false;
+                let close = rest[1..].char_indices().find(|&(_, c)| {
+                    let found = (c == '"' && !escaped) || c == '\n';
+                    escaped = c == '\\' && !escaped;
+                    found
+                });
+                let end = match close {
+                    Some((i, '"')) => i + 2,
+                    Some((i, _)) => i + 1,
+                    None => rest.len(),
+                };
+                let is_key = rest[end..].trim_start().starts_with(':');
+                push(if is_key { JsonPart::Key } else { JsonPart::String }, &rest[..end]);
+                end
+            }
+            '{' | '}' | '[' | ']' | ',' | ':' => {
+                push(JsonPart::Punctuation, &rest[..1]);
+                1
+            }
+            '-' | '0'..='9' => {
+                let end = rest.find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c))).unwrap_or(rest.len());
+                push(JsonPart::Number, &rest[..end]);
+                end
+            }
+            c if c.is_ascii_alphabetic() => {
+                let end = rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+                let word = &rest[..end];
+                push(if ["true", "false", "null"].contains(&word) { JsonPart::Literal } else { JsonPart::Other }, word);
+                end
+            }
+            c => {
+                push(JsonPart::Other, &rest[..c.len_utf8()]);
+                c.len_utf8()
+            }
+        };
+        rest = &rest[end..];
+    }
+    parts
+}
+
+/// JSON as indented text, for minified bodies that are easier to read,
+/// search and copy as text than as a tree. Bodies that turn out not to be
+/// JSON are shown as received.
+pub struct PrettyJsonView;
+
+impl ResponseView for PrettyJsonView {
+    fn id(&self) -> &'static str {
+        "pretty"
+    }
+
+    fn label(&self) -> &'static str {
+        "Pretty JSON"
+    }
+
+    fn can_render(&self, content_type: &str, res: &HttpResponse) -> bool {
+        // The content type is trusted first; parsing is the fallback
+        JsonView.prefers(content_type) || json_body(res).is_some()
+    }
+
+    fn render(&self, res: &HttpResponse, context: &ViewContext) -> Element {
+        let Some(text) = pretty_json(&res.body) else {
+            return TextView.render(res, context);
+        };
+        let on_follow = context.on_follow;
+        rsx! {
+            if context.linkify {
+                crate::LinkedBody { text, on_follow }
+            } else if text.len() <= HIGHLIGHT_MAX_BYTES {
+                pre { class: "result-body",
+                    for (part, run) in highlight_json(&text) {
+                        span { class: part.class(), "{run}" }
+                    }
+                }
+            } else {
+                pre { class: "result-body", "{text}" }
+            }
+        }
+    }
+}
+
+/// Indented, highlighted XML.
+pub struct XmlView;
+
+impl ResponseView for XmlView {
+    fn id(&self) -> &'static str {
+        "xml"
+    }
+
+    fn label(&self) -> &'static str {
+        "XML"
+    }
+
+    fn can_render(&self, content_type: &str, res: &HttpResponse) -> bool {
+        content_type.contains("xml") || res.body.trim_start().starts_with('<')
+    }
+
+    fn prefers(&self, content_type: &str) -> bool {
+        content_type.ends_with("/xml") || content_type.ends_with("+xml")
+    }
+
+    fn render(&self, res: &HttpResponse, context: &ViewContext) -> Element {
+        let xml = match soap::envelope_body(&res.body) {
+            Some(body) if context.envelope_body_only => body.to_string(),
+            _ => res.body.clone(),
+        };
+        rsx! { crate::XmlBody { xml } }
+    }
+}
+
+/// CSV or TSV as a table whose columns sort when their heading is clicked.
+pub struct TableView;
+
+impl TableView {
+    /// The delimiter of a CSV or TSV body: from the content type, else the
+    /// one giving the first lines the same number of columns, at least two.
+    fn delimiter(content_type: &str, body: &str) -> Option<char> {
+        match content_type {
+            "text/csv" | "application/csv" => return Some(','),
+            "text/tab-separated-values" => return Some('\t'),
+            "" | "text/plain" => {}
+            _ => return None,
+        }
+        let lines: Vec<&str> = body.lines().filter(|l| !l.trim().is_empty()).take(5).collect();
+        if lines.len() < 2 {
+            return None;
+        }
+        ['\t', ',', ';'].into_iter().find(|&d| {
+            let columns = split_record(lines[0], d).len();
+            columns > 1 && lines.iter().all(|line| split_record(line, d).len() == columns)
+        })
+    }
+}
+
+impl ResponseView for TableView {
+    fn id(&self) -> &'static str {
+        "table"
+    }
+
+    fn label(&self) -> &'static str {
+        "Table"
+    }
+
+    fn can_render(&self, content_type: &str, res: &HttpResponse) -> bool {
+        TableView::delimiter(content_type, &res.body).is_some()
+    }
+
+    fn prefers(&self, content_type: &str) -> bool {
+        matches!(content_type, "text/csv" | "application/csv" | "text/tab-separated-values")
+    }
+
+    fn render(&self, res: &HttpResponse, _context: &ViewContext) -> Element {
+        let mime = mime_type(res).unwrap_or_default();
+        let delimiter = TableView::delimiter(&mime, &res.body).unwrap_or(',');
+        let mut rows = parse_delimited(&res.body, delimiter);
+        if rows.is_empty() {
+            return rsx! { pre { class: "result-body", "{res.body}" } };
+        }
+        let header = rows.remove(0);
+        rsx! { SortableTable { header, rows } }
+    }
+}
+
+#[component]
+fn SortableTable(header: Vec<String>, rows: Vec<Vec<String>>) -> Element {
+    // Column and whether it sorts descending
+    let mut sort = use_signal(|| None::<(usize, bool)>);
+    let mut sorted = rows.clone();
+    if let Some((column, descending)) = sort() {
+        sorted.sort_by(|a, b| {
+            let order = compare_cells(a.get(column), b.get(column));
+            if descending { order.reverse() } else { order }
+        });
+    }
+
+    rsx! {
+        div { class: "result-body table-view",
+            table {
+                tr {
+                    for (i, name) in header.iter().enumerate() {
+                        th {
+                            title: "Sort by this column",
+                            onclick: move |_| sort.set(match sort() {
+                                Some((column, false)) if column == i => Some((i, true)),
+                                Some((column, true)) if column == i => None,
+                                _ => Some((i, false)),
+                            }),
+                            "{name}"
+                            match sort() {
+                                Some((column, false)) if column == i => " ▲",
+                                Some((column, true)) if column == i => " ▼",
+                                _ => "",
+                            }
+                        }
+                    }
+                }
+                for row in sorted {
+                    tr {
+                        for cell in row {
+                            td { "{cell}" }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Numbers sort by value, other cells as text; missing cells first.
+fn compare_cells(a: Option<&String>, b: Option<&String>) -> Ordering {
+    match (a.and_then(|a| a.trim().parse::<f64>().ok()), b.and_then(|b| b.trim().parse::<f64>().ok())) {
+        (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+        _ => a.cmp(&b),
+    }
+}
+
+/// Splits one line into fields, honouring double quotes.
+fn split_record(line: &str, delimiter: char) -> Vec<String> {
+    parse_delimited(line, delimiter).into_iter().next().unwrap_or_default()
+}
+
+/// Parses delimited text into records. Quoted fields may contain the
+/// delimiter, doubled quotes and line breaks.
+fn parse_delimited(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            c if c == delimiter && !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                let done = std::mem::take(&mut record);
+                if done.iter().any(|f| !f.is_empty()) {
+                    records.push(done);
+                }
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+/// Newline-delimited JSON, one numbered row per record.
+pub struct NdjsonView;
+
+impl ResponseView for NdjsonView {
+    fn id(&self) -> &'static str {
+        "ndjson"
+    }
+
+    fn label(&self) -> &'static str {
+        "NDJSON"
+    }
+
+    fn can_render(&self, _content_type: &str, res: &HttpResponse) -> bool {
+        // A single line is plain JSON
+        let lines: Vec<&str> = res.body.lines().filter(|line| !line.trim().is_empty()).collect();
+        lines.len() > 1
+            && lines.iter().all(|line| line.trim_start().starts_with(['{', '[']) && serde_json::from_str::<Value>(line).is_ok())
+    }
+
+    fn prefers(&self, content_type: &str) -> bool {
+        matches!(content_type, "application/x-ndjson" | "application/ndjson" | "application/jsonl" | "application/x-jsonlines")
+    }
+
+    fn render(&self, res: &HttpResponse, _context: &ViewContext) -> Element {
+        let records: Vec<String> = res.body.lines().filter(|line| !line.trim().is_empty()).map(str::to_string).collect();
+        rsx! {
+            div { class: "result-body ndjson-view",
+                for (i, record) in records.into_iter().enumerate() {
+                    div { class: "ndjson-row",
+                        span { class: "ndjson-index", "{i + 1}" }
+                        span { "{record}" }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub struct ImageView;
+
+impl ResponseView for ImageView {
+    fn id(&self) -> &'static str {
+        "image"
+    }
+
+    fn label(&self) -> &'static str {
+        "Image"
+    }
+
+    fn can_render(&self, content_type: &str, _res: &HttpResponse) -> bool {
+        content_type.starts_with("image/")
+    }
+
+    fn prefers(&self, content_type: &str) -> bool {
+        content_type.starts_with("image/")
+    }
+
+    fn render(&self, res: &HttpResponse, _context: &ViewContext) -> Element {
+        // A data URL, so the webview can show the bytes without a server
+        let mime = mime_type(res).unwrap_or_default();
+        let src = format!("data:{};base64,{}", mime, base64::engine::general_purpose::STANDARD.encode(&res.raw_body));
+        rsx! { img { class: "result-image", src } }
+    }
+}
+
+pub struct HexView;
+
+impl ResponseView for HexView {
+    fn id(&self) -> &'static str {
+        "hex"
+    }
+
+    fn label(&self) -> &'static str {
+        "Hex"
+    }
+
+    fn can_render(&self, _content_type: &str, _res: &HttpResponse) -> bool {
+        true
+    }
+
+    fn prefers(&self, content_type: &str) -> bool {
+        content_type == "application/octet-stream"
+    }
+
+    fn render(&self, res: &HttpResponse, _context: &ViewContext) -> Element {
+        rsx! { pre { class: "result-body hex", "{crate::hex_dump(&res.raw_body)}" } }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pretty_json_indents_valid_json_only() {
+        assert_eq!(pretty_json("\n{\"b\":1,\"a\":[true]}").as_deref(), Some("{\n  \"b\": 1,\n  \"a\": [\n    true\n  ]\n}"));
+        assert_eq!(pretty_json("{\"a\":"), None);
+        assert_eq!(pretty_json("<a/>"), None);
+        assert_eq!(pretty_json(""), None);
+    }
+}
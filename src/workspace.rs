@@ -0,0 +1,116 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::storage::get_base_dir;
+
+// The lock is held through an OS advisory lock on `.lock`, which the OS
+// releases when the owning process exits or crashes, so a stale lock can
+// simply be re-acquired. The file also records the owner's PID for display.
+static LOCK_FILE: Mutex<Option<File>> = Mutex::new(None);
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Result of trying to become the single writer of the base dir.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LockState {
+    Acquired,
+    /// Another live instance holds the lock; its PID if it could be read.
+    ReadOnly { holder_pid: Option<u32> },
+}
+
+/// Acquires the workspace lock, switching the app to read-only mode if
+/// another instance already holds it. Safe to call again to retry.
+pub fn acquire() -> LockState {
+    let state = try_acquire();
+    READ_ONLY.store(state != LockState::Acquired, Ordering::Relaxed);
+    state
+}
+
+fn try_acquire() -> LockState {
+    let mut guard = LOCK_FILE.lock().unwrap_or_else(|e| e.into_inner());
+    if guard.is_some() {
+        return LockState::Acquired;
+    }
+    let dir = get_base_dir();
+    let _ = std::fs::create_dir_all(&dir);
+    let Ok(mut file) = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(dir.join(".lock")) else {
+        // Without a lock file there is nothing to coordinate on; keep working normally
+        return LockState::Acquired;
+    };
+    if file.try_lock().is_err() {
+        let mut content = String::new();
+        let _ = file.read_to_string(&mut content);
+        return LockState::ReadOnly { holder_pid: content.trim().parse().ok() };
+    }
+    let _ = file.set_len(0);
+    let _ = file.rewind();
+    let _ = write!(file, "{}", std::process::id());
+    *guard = Some(file);
+    LockState::Acquired
+}
+
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+/// Fails with a descriptive error when writes to shared stores are not allowed.
+pub fn ensure_writable() -> std::io::Result<()> {
+    if is_read_only() {
+        Err(std::io::Error::other("Workspace is open read-only in another instance"))
+    } else {
+        Ok(())
+    }
+}
+
+// Trust is recorded outside the workspace, so a collection cannot mark
+// itself trusted. Paths are stored canonical, so links and `..` do not matter.
+fn trust_file() -> Option<PathBuf> {
+    directories::ProjectDirs::from("", "", "requester").map(|dirs| dirs.config_dir().join("trusted-workspaces.json"))
+}
+
+/// Workspaces the user trusts to run hooks and read the OS environment.
+pub fn trusted_paths() -> Vec<PathBuf> {
+    trust_file()
+        .and_then(|file| std::fs::read_to_string(file).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_trusted(paths: &[PathBuf]) -> std::io::Result<()> {
+    let file = trust_file().ok_or_else(|| std::io::Error::other("No config directory"))?;
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(file, serde_json::to_string_pretty(paths)?)
+}
+
+pub fn is_trusted(path: &Path) -> bool {
+    let Ok(path) = path.canonicalize() else {
+        return false;
+    };
+    trusted_paths().contains(&path)
+}
+
+/// Whether the workspace the app works in is trusted.
+pub fn workspace_trusted() -> bool {
+    is_trusted(&get_base_dir())
+}
+
+pub fn trust(path: &Path) -> std::io::Result<()> {
+    let path = path.canonicalize()?;
+    let mut paths = trusted_paths();
+    if !paths.contains(&path) {
+        paths.push(path);
+        save_trusted(&paths)?;
+    }
+    Ok(())
+}
+
+/// Removes `path` as stored by [`trust`], so it is matched exactly even when
+/// the directory no longer exists.
+pub fn revoke(path: &Path) -> std::io::Result<()> {
+    let mut paths = trusted_paths();
+    paths.retain(|p| p != path);
+    save_trusted(&paths)
+}